@@ -0,0 +1,402 @@
+//! Session lifecycle against the GFN cloudmatch service: create, poll,
+//! and delete streaming sessions.
+
+use anyhow::{anyhow, Context, Result};
+use serde::{Deserialize, Serialize};
+
+use super::GfnApiClient;
+use crate::settings::Settings;
+
+/// Rejection causes that are worth retrying in a different zone.
+///
+/// Attached to the `create_session` error chain so callers can
+/// `downcast_ref` and decide whether failover applies.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SessionRejection {
+    AtCapacity,
+    Maintenance,
+}
+
+impl std::fmt::Display for SessionRejection {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SessionRejection::AtCapacity => write!(f, "zone is at capacity"),
+            SessionRejection::Maintenance => write!(f, "zone is under maintenance"),
+        }
+    }
+}
+
+impl std::error::Error for SessionRejection {}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum SessionState {
+    /// Waiting for a seat. Position/ETA are `None` when the zone's
+    /// response carried no usable queue information (seen on some
+    /// Alliance zones) — render a waiting message, not zeros.
+    Queued {
+        position: Option<u32>,
+        eta_secs: Option<u32>,
+    },
+    Provisioning,
+    Ready,
+    Streaming,
+    Finished,
+    Error(String),
+}
+
+/// Server-side game setup (install / shader compile) hint carried by
+/// some titles' session responses around first launch.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SetupProgress {
+    pub stage: Option<String>,
+    pub percent: Option<u8>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SessionInfo {
+    pub session_id: String,
+    pub game_id: String,
+    pub state: SessionState,
+    pub server_address: Option<String>,
+    pub signal_connection_url: Option<String>,
+    pub gpu_type: Option<String>,
+    pub zone: Option<String>,
+    /// Present while the rig is still installing/preparing the title.
+    pub setup: Option<SetupProgress>,
+}
+
+/// Zone-wide queue estimate, fetched before committing to a launch so
+/// the detail popup can show the likely wait for the user's tier.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct QueueEstimate {
+    /// Sessions currently waiting ahead of a new launch.
+    pub queue_length: Option<u32>,
+    pub wait_secs: Option<u32>,
+}
+
+impl GfnApiClient {
+    /// Fetch `zone`'s current queue estimate for a subscription tier.
+    /// Best-effort: zones without the endpoint just error, and callers
+    /// degrade to showing no estimate.
+    pub async fn fetch_queue_estimate(&self, zone: &str, tier: &str) -> Result<QueueEstimate> {
+        let url = format!("https://{}/v2/serverInfo?tier={}", zone, tier);
+        let body: serde_json::Value = self
+            .http()
+            .get(&url)
+            .bearer_auth(self.token())
+            .send()
+            .await
+            .context("queue estimate request failed")?
+            .error_for_status()
+            .context("queue estimate rejected")?
+            .json()
+            .await
+            .context("queue estimate returned invalid JSON")?;
+        Ok(parse_queue_estimate(&body))
+    }
+
+    /// Request a new streaming session for `app_id` in `zone`.
+    pub async fn create_session(
+        &self,
+        app_id: u64,
+        zone: &str,
+        settings: &Settings,
+    ) -> Result<SessionInfo> {
+        let url = format!("https://{}/v2/session", zone);
+        let (width, height) = settings.resolution;
+        let request_body = serde_json::json!({
+            "sessionRequestData": {
+                "appId": app_id,
+                "availableSupportedControllers": [1],
+                "clientRequestMonitorSettings": [{
+                    "widthInPixels": width,
+                    "heightInPixels": height,
+                    "framesPerSecond": settings.fps,
+                }],
+                "audioMode": 2,
+                "clientTimezoneOffset": 0,
+                "sdrHdrMode": if settings.hdr_enabled { 1 } else { 0 },
+                "networkTestSessionId": null,
+            }
+        });
+        let response = self
+            .http()
+            .post(&url)
+            .bearer_auth(self.token())
+            .json(&request_body)
+            .send()
+            .await
+            .context("create_session request failed")?;
+        let status = response.status();
+        let body: serde_json::Value = response
+            .json()
+            .await
+            .context("create_session returned invalid JSON")?;
+        super::recording::record("cloudmatch-create", &request_body, &body);
+        if !status.is_success() {
+            let message = body["requestStatus"]["statusDescription"]
+                .as_str()
+                .unwrap_or("unknown error");
+            let upper = message.to_uppercase();
+            if upper.contains("CAPACITY") || upper.contains("NO_SEAT") {
+                return Err(anyhow::Error::new(SessionRejection::AtCapacity)
+                    .context(format!("Session creation failed ({}): {}", status, message)));
+            }
+            if upper.contains("MAINTENANCE") {
+                return Err(anyhow::Error::new(SessionRejection::Maintenance)
+                    .context(format!("Session creation failed ({}): {}", status, message)));
+            }
+            return Err(anyhow!("Session creation failed ({}): {}", status, message));
+        }
+        parse_session_response(&body)
+    }
+
+    /// Poll an existing session's status.
+    pub async fn poll_session(&self, zone: &str, session_id: &str) -> Result<SessionInfo> {
+        let url = format!("https://{}/v2/session/{}", zone, session_id);
+        let body: serde_json::Value = self
+            .http()
+            .get(&url)
+            .bearer_auth(self.token())
+            .send()
+            .await
+            .context("poll_session request failed")?
+            .json()
+            .await
+            .context("poll_session returned invalid JSON")?;
+        super::recording::record(
+            "cloudmatch-poll",
+            &serde_json::json!({ "url": url }),
+            &body,
+        );
+        parse_session_response(&body)
+    }
+
+    /// Terminate a session server-side.
+    pub async fn delete_session(&self, zone: &str, session_id: &str) -> Result<()> {
+        let url = format!("https://{}/v2/session/{}", zone, session_id);
+        self.http()
+            .delete(&url)
+            .bearer_auth(self.token())
+            .send()
+            .await
+            .context("delete_session request failed")?
+            .error_for_status()
+            .context("delete_session rejected")?;
+        Ok(())
+    }
+}
+
+/// Tolerant parse over the serverInfo layouts seen: NVIDIA zones nest
+/// the fields under `serverInfo`, partners flatten them; absent fields
+/// stay `None` (same policy as `parse_queue_state`).
+fn parse_queue_estimate(body: &serde_json::Value) -> QueueEstimate {
+    let root = if body["serverInfo"].is_object() {
+        &body["serverInfo"]
+    } else {
+        body
+    };
+    QueueEstimate {
+        queue_length: root["queueLength"].as_u64().map(|v| v as u32),
+        wait_secs: root["estimatedWaitSeconds"]
+            .as_u64()
+            .or_else(|| root["queueEtaSeconds"].as_u64())
+            .map(|v| v as u32),
+    }
+}
+
+/// Extract queue position and ETA from a QUEUED session, trying the
+/// field layouts observed in the wild: NVIDIA zones put them in
+/// `seatSetupInfo`, some Alliance/partner zones use a `queueInfo` object
+/// or flatten them onto the session. Fields that are genuinely absent
+/// stay `None` instead of collapsing to a misleading zero.
+fn parse_queue_state(session: &serde_json::Value) -> SessionState {
+    let lookup = |paths: &[(&str, &str)], flat: &str| -> Option<u32> {
+        for (object, field) in paths {
+            if let Some(value) = session[object][field].as_u64() {
+                return Some(value as u32);
+            }
+        }
+        session[flat].as_u64().map(|v| v as u32)
+    };
+    SessionState::Queued {
+        position: lookup(
+            &[
+                ("seatSetupInfo", "queuePosition"),
+                ("queueInfo", "position"),
+            ],
+            "queuePosition",
+        ),
+        eta_secs: lookup(
+            &[
+                ("seatSetupInfo", "seatSetupEta"),
+                ("queueInfo", "etaSeconds"),
+            ],
+            "queueEtaSeconds",
+        ),
+    }
+}
+
+pub(super) fn parse_session_response(body: &serde_json::Value) -> Result<SessionInfo> {
+    let session = &body["session"];
+    let session_id = session["sessionId"]
+        .as_str()
+        .ok_or_else(|| anyhow!("Missing sessionId in session response"))?
+        .to_string();
+    let status = session["status"].as_str().unwrap_or("UNKNOWN");
+    let state = match status {
+        "QUEUED" => parse_queue_state(session),
+        "INITIALIZING" | "RESERVING_SEAT" => SessionState::Provisioning,
+        "READY_FOR_CONNECTION" => SessionState::Ready,
+        "STREAMING" => SessionState::Streaming,
+        "FINISHED" => SessionState::Finished,
+        other => SessionState::Error(format!("Unexpected session status: {}", other)),
+    };
+    let setup = session["gameSetupInfo"].as_object().map(|info| SetupProgress {
+        stage: info
+            .get("stage")
+            .and_then(|v| v.as_str())
+            .map(|s| s.to_string()),
+        percent: info
+            .get("progressPercent")
+            .and_then(|v| v.as_u64())
+            .map(|p| p.min(100) as u8),
+    });
+    let connection = session["connectionInfo"]
+        .as_array()
+        .and_then(|c| c.first())
+        .cloned()
+        .unwrap_or(serde_json::Value::Null);
+    Ok(SessionInfo {
+        session_id,
+        game_id: session["appId"]
+            .as_u64()
+            .map(|id| id.to_string())
+            .unwrap_or_default(),
+        state,
+        server_address: connection["ip"].as_str().map(|s| s.to_string()),
+        signal_connection_url: connection["resourcePath"].as_str().map(|s| s.to_string()),
+        gpu_type: session["gpuType"].as_str().map(|s| s.to_string()),
+        zone: session["zoneName"].as_str().map(|s| s.to_string()),
+        setup,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn nvidia_queue_layout_parses_position_and_eta() {
+        // Captured from an NVIDIA-operated zone.
+        let body = serde_json::json!({
+            "session": {
+                "sessionId": "abc-123",
+                "status": "QUEUED",
+                "seatSetupInfo": {
+                    "queuePosition": 12,
+                    "seatSetupEta": 180,
+                },
+            }
+        });
+        let info = parse_session_response(&body).unwrap();
+        assert_eq!(
+            info.state,
+            SessionState::Queued {
+                position: Some(12),
+                eta_secs: Some(180),
+            }
+        );
+    }
+
+    #[test]
+    fn alliance_queue_info_layout_parses() {
+        // Captured from an Alliance partner zone: queue fields live in a
+        // `queueInfo` object with different names.
+        let body = serde_json::json!({
+            "session": {
+                "sessionId": "abc-123",
+                "status": "QUEUED",
+                "queueInfo": {
+                    "position": 3,
+                    "etaSeconds": 45,
+                },
+            }
+        });
+        let info = parse_session_response(&body).unwrap();
+        assert_eq!(
+            info.state,
+            SessionState::Queued {
+                position: Some(3),
+                eta_secs: Some(45),
+            }
+        );
+    }
+
+    #[test]
+    fn flattened_queue_fields_parse() {
+        let body = serde_json::json!({
+            "session": {
+                "sessionId": "abc-123",
+                "status": "QUEUED",
+                "queuePosition": 7,
+                "queueEtaSeconds": 90,
+            }
+        });
+        let info = parse_session_response(&body).unwrap();
+        assert_eq!(
+            info.state,
+            SessionState::Queued {
+                position: Some(7),
+                eta_secs: Some(90),
+            }
+        );
+    }
+
+    #[test]
+    fn absent_queue_fields_stay_none_instead_of_zero() {
+        let body = serde_json::json!({
+            "session": {
+                "sessionId": "abc-123",
+                "status": "QUEUED",
+            }
+        });
+        let info = parse_session_response(&body).unwrap();
+        assert_eq!(
+            info.state,
+            SessionState::Queued {
+                position: None,
+                eta_secs: None,
+            }
+        );
+    }
+
+    #[test]
+    fn queue_estimate_parses_nested_and_flat_layouts() {
+        let nested = serde_json::json!({
+            "serverInfo": { "queueLength": 14, "estimatedWaitSeconds": 240 }
+        });
+        assert_eq!(
+            parse_queue_estimate(&nested),
+            QueueEstimate {
+                queue_length: Some(14),
+                wait_secs: Some(240),
+            }
+        );
+        let flat = serde_json::json!({ "queueEtaSeconds": 30 });
+        assert_eq!(
+            parse_queue_estimate(&flat),
+            QueueEstimate {
+                queue_length: None,
+                wait_secs: Some(30),
+            }
+        );
+    }
+
+    #[test]
+    fn queue_estimate_without_fields_stays_unknown() {
+        let estimate = parse_queue_estimate(&serde_json::json!({ "serverInfo": {} }));
+        assert_eq!(estimate.queue_length, None);
+        assert_eq!(estimate.wait_secs, None);
+    }
+}
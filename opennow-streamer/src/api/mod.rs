@@ -0,0 +1,674 @@
+//! HTTP client for the GFN GraphQL catalog and account endpoints.
+//!
+//! Session management (cloudmatch) lives in [`cloudmatch`], zone/server
+//! discovery in [`serverinfo`].
+
+pub mod cloudmatch;
+pub mod recording;
+pub mod serverinfo;
+
+use anyhow::{anyhow, Context, Result};
+use serde::{Deserialize, Serialize};
+
+use crate::auth::AuthTokens;
+
+const GRAPHQL_URL: &str = "https://games.geforce.com/graphql";
+const SUBSCRIPTION_URL: &str = "https://api-prod.nvidia.com/gfn/v2/subscription";
+pub(crate) const USERINFO_URL: &str = "https://login.nvidia.com/userinfo";
+
+/// A single entry in the games catalog / library grid.
+///
+/// The two identifiers are distinct and must not be mixed up: `cms_id`
+/// keys catalog/detail queries (GraphQL), `launch_app_id` is what the
+/// cloudmatch session API launches. Multi-edition SKUs are exactly the
+/// case where they diverge. Serde aliases keep older cache files
+/// (written with `id`/`app_id`) loadable.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GameInfo {
+    /// CMS catalog identifier; keys GraphQL queries and dedup.
+    #[serde(alias = "id")]
+    pub cms_id: String,
+    /// Identifier the session API expects in `create_session`.
+    #[serde(alias = "app_id")]
+    pub launch_app_id: Option<u64>,
+    pub title: String,
+    pub publisher: Option<String>,
+    pub image_url: Option<String>,
+    pub store: Option<String>,
+}
+
+/// Operational notice attached to a game (maintenance windows, server
+/// wipes, patching downtime). Carried by the game details query.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GameNotice {
+    pub message: String,
+    /// "info" | "warning" | "error" as delivered by the API; anything else
+    /// is treated as info.
+    pub severity: NoticeSeverity,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum NoticeSeverity {
+    Info,
+    Warning,
+    Error,
+}
+
+/// Extended metadata shown in the game detail popup.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GameDetails {
+    pub id: String,
+    pub description: Option<String>,
+    pub genres: Vec<String>,
+    pub supported_controls: Vec<String>,
+    /// Active operational notices for this title, if any.
+    pub notices: Vec<GameNotice>,
+}
+
+/// Items requested per library page.
+const LIBRARY_PAGE_SIZE: usize = 100;
+
+/// Result of a tolerant catalog fetch: the entries that parsed plus how
+/// many were skipped as malformed.
+#[derive(Debug, Clone)]
+pub struct CatalogLoad {
+    pub games: Vec<GameInfo>,
+    pub skipped: usize,
+}
+
+/// One page of the paginated library query.
+#[derive(Debug, Clone)]
+pub struct LibraryPage {
+    pub items: Vec<GameInfo>,
+    /// Cursor for the next page, or None when this was the last one.
+    pub next_cursor: Option<String>,
+    /// Total library size if the API reported it.
+    pub total: Option<usize>,
+}
+
+/// Merge a page into the accumulated library by id. Re-delivered
+/// entries replace what's there, so overlapping or out-of-order pages
+/// never duplicate titles.
+pub fn merge_library_page(into: &mut Vec<GameInfo>, page: Vec<GameInfo>) {
+    for game in page {
+        if let Some(existing) = into.iter_mut().find(|g| g.cms_id == game.cms_id) {
+            *existing = game;
+        } else {
+            into.push(game);
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SubscriptionInfo {
+    pub tier: String,
+    pub remaining_hours: Option<f64>,
+    pub total_hours: Option<f64>,
+    /// (width, height, fps) triples the subscription entitles.
+    pub entitled_resolutions: Vec<(u32, u32, u32)>,
+    /// Server-side bitrate ceiling, when the payload carries one.
+    pub max_bitrate_mbps: Option<u32>,
+}
+
+impl SubscriptionInfo {
+    /// The bitrate the server will actually honor: the payload's own
+    /// ceiling when present, else the known per-tier cap. None for
+    /// tiers we can't map (Alliance partners vary), which leaves the
+    /// slider uncapped.
+    pub fn bitrate_cap_mbps(&self) -> Option<u32> {
+        self.max_bitrate_mbps
+            .or_else(|| tier_bitrate_cap_mbps(&self.tier))
+    }
+}
+
+/// Known server-side bitrate caps by tier name. The matching is loose
+/// ("GeForce NOW Ultimate" hits "ultimate") because partner portals
+/// decorate the product names.
+pub fn tier_bitrate_cap_mbps(tier: &str) -> Option<u32> {
+    let tier = tier.to_ascii_lowercase();
+    if tier.contains("ultimate") {
+        Some(75)
+    } else if tier.contains("performance") || tier.contains("priority") {
+        Some(50)
+    } else if tier.contains("free") {
+        Some(25)
+    } else {
+        None
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UserInfo {
+    pub display_name: String,
+    pub email: Option<String>,
+    pub avatar_url: Option<String>,
+}
+
+/// Thin wrapper over reqwest carrying auth headers for GFN endpoints.
+pub struct GfnApiClient {
+    client: reqwest::Client,
+    access_token: String,
+}
+
+impl GfnApiClient {
+    pub fn new(tokens: &AuthTokens) -> Self {
+        let client = reqwest::Client::builder()
+            .user_agent(crate::USER_AGENT)
+            .build()
+            .expect("Failed to build HTTP client");
+        Self {
+            client,
+            access_token: tokens.access_token.clone(),
+        }
+    }
+
+    pub(crate) fn http(&self) -> &reqwest::Client {
+        &self.client
+    }
+
+    pub(crate) fn token(&self) -> &str {
+        &self.access_token
+    }
+
+    async fn graphql(&self, query: &str, variables: serde_json::Value) -> Result<serde_json::Value> {
+        let request = serde_json::json!({ "query": query, "variables": variables });
+        let response = self
+            .client
+            .post(GRAPHQL_URL)
+            .bearer_auth(&self.access_token)
+            .json(&request)
+            .send()
+            .await
+            .context("GraphQL request failed")?;
+        let status = response.status();
+        let body: serde_json::Value = response.json().await.context("Invalid GraphQL response")?;
+        recording::record(&graphql_fixture_kind(query), &request, &body);
+        if !status.is_success() {
+            return Err(anyhow!("GraphQL request failed with status {}: {}", status, body));
+        }
+        Ok(body)
+    }
+
+    /// Fetch the main GFN catalog (the "all games" panel). Malformed
+    /// entries are skipped, not fatal; see [`parse_game_items`].
+    pub async fn fetch_main_games(&self) -> Result<CatalogLoad> {
+        let query = r#"
+            query GamesPanel($vpId: String!) {
+                apps(vpId: $vpId) {
+                    items {
+                        id
+                        cmsId
+                        appId
+                        title
+                        publisherName
+                        images { GAME_BOX_ART }
+                        variants { appId appStore }
+                    }
+                }
+            }
+        "#;
+        let body = self
+            .graphql(query, serde_json::json!({ "vpId": "GFN" }))
+            .await?;
+        parse_main_games(&body)
+    }
+
+    /// Fetch one page of the user's library. `cursor` continues a
+    /// previous page; `modified_since` (unix seconds) asks the API for
+    /// changes only, which it may ignore.
+    pub async fn fetch_library_page(
+        &self,
+        cursor: Option<&str>,
+        modified_since: Option<i64>,
+    ) -> Result<LibraryPage> {
+        let query = r#"
+            query LibraryPanel($vpId: String!, $first: Int!, $after: String, $modifiedSince: Long) {
+                library(vpId: $vpId, first: $first, after: $after, modifiedSince: $modifiedSince) {
+                    items {
+                        id
+                        cmsId
+                        appId
+                        title
+                        publisherName
+                        images { GAME_BOX_ART }
+                        variants { appId appStore }
+                    }
+                    pageInfo { endCursor hasNextPage }
+                    totalCount
+                }
+            }
+        "#;
+        let body = self
+            .graphql(
+                query,
+                serde_json::json!({
+                    "vpId": "GFN",
+                    "first": LIBRARY_PAGE_SIZE,
+                    "after": cursor,
+                    "modifiedSince": modified_since,
+                }),
+            )
+            .await?;
+        let library = &body["data"]["library"];
+        let items = library["items"]
+            .as_array()
+            .ok_or_else(|| anyhow!("Missing library.items in library response"))?;
+        let (games, _skipped) = parse_game_items(items);
+        let next_cursor = if library["pageInfo"]["hasNextPage"].as_bool() == Some(true) {
+            library["pageInfo"]["endCursor"].as_str().map(String::from)
+        } else {
+            None
+        };
+        Ok(LibraryPage {
+            items: games,
+            next_cursor,
+            total: library["totalCount"].as_u64().map(|n| n as usize),
+        })
+    }
+
+    /// Fetch extended detail for a single title, including any operational
+    /// notices the service has published for it.
+    pub async fn get_game_details(&self, game_id: &str) -> Result<GameDetails> {
+        let query = r#"
+            query GameDetails($id: String!) {
+                app(id: $id) {
+                    id
+                    description
+                    genres
+                    supportedControls
+                    notices { message severity }
+                }
+            }
+        "#;
+        let body = self
+            .graphql(query, serde_json::json!({ "id": game_id }))
+            .await?;
+        let app = &body["data"]["app"];
+        if app.is_null() {
+            return Err(anyhow!("No details returned for game {}", game_id));
+        }
+        let notices = app["notices"]
+            .as_array()
+            .map(|items| {
+                items
+                    .iter()
+                    .filter_map(|n| {
+                        let message = n["message"].as_str()?.to_string();
+                        let severity = match n["severity"].as_str() {
+                            Some("error") => NoticeSeverity::Error,
+                            Some("warning") => NoticeSeverity::Warning,
+                            _ => NoticeSeverity::Info,
+                        };
+                        Some(GameNotice { message, severity })
+                    })
+                    .collect()
+            })
+            .unwrap_or_default();
+        Ok(GameDetails {
+            id: game_id.to_string(),
+            description: app["description"].as_str().map(|s| s.to_string()),
+            genres: string_array(&app["genres"]),
+            supported_controls: string_array(&app["supportedControls"]),
+            notices,
+        })
+    }
+
+    /// Look up the launch app id for a title whose catalog entry didn't
+    /// carry one. Fallback path only; normal launches use the id parsed
+    /// from the catalog.
+    pub async fn fetch_launch_app_id(&self, cms_id: &str) -> Result<Option<u64>> {
+        let query = r#"
+            query LaunchId($id: String!) {
+                app(id: $id) {
+                    appId
+                    variants { appId }
+                }
+            }
+        "#;
+        let body = self.graphql(query, serde_json::json!({ "id": cms_id })).await?;
+        let app = &body["data"]["app"];
+        if app.is_null() {
+            return Err(anyhow!("No app returned for {}", cms_id));
+        }
+        Ok(app["variants"][0]["appId"]
+            .as_u64()
+            .or_else(|| app["appId"].as_u64()))
+    }
+
+    pub async fn fetch_subscription(&self) -> Result<SubscriptionInfo> {
+        let body: serde_json::Value = self
+            .client
+            .get(SUBSCRIPTION_URL)
+            .bearer_auth(&self.access_token)
+            .send()
+            .await
+            .context("Subscription request failed")?
+            .json()
+            .await?;
+        recording::record(
+            "subscription",
+            &serde_json::json!({ "url": SUBSCRIPTION_URL }),
+            &body,
+        );
+        Ok(parse_subscription(&body))
+    }
+
+    pub async fn fetch_user_info(&self) -> Result<UserInfo> {
+        let body: serde_json::Value = self
+            .client
+            .get(USERINFO_URL)
+            .bearer_auth(&self.access_token)
+            .send()
+            .await
+            .context("Userinfo request failed")?
+            .json()
+            .await?;
+        recording::record(
+            "userinfo",
+            &serde_json::json!({ "url": USERINFO_URL }),
+            &body,
+        );
+        Ok(UserInfo {
+            display_name: body["preferred_username"]
+                .as_str()
+                .or_else(|| body["name"].as_str())
+                .unwrap_or("User")
+                .to_string(),
+            email: body["email"].as_str().map(|s| s.to_string()),
+            avatar_url: body["picture"].as_str().map(|s| s.to_string()),
+        })
+    }
+}
+
+/// Fixture kind for a GraphQL query, derived from its operation name.
+fn graphql_fixture_kind(query: &str) -> String {
+    let name = query
+        .trim_start()
+        .strip_prefix("query ")
+        .and_then(|rest| rest.split(['(', ' ', '{']).next())
+        .filter(|name| !name.is_empty())
+        .unwrap_or("unknown");
+    match name {
+        "GamesPanel" => "graphql-games".to_string(),
+        other => format!("graphql-{}", other.to_ascii_lowercase()),
+    }
+}
+
+/// Parse the main-catalog GraphQL body. Split out of
+/// [`GfnApiClient::fetch_main_games`] so recorded fixtures can replay
+/// through it.
+pub(crate) fn parse_main_games(body: &serde_json::Value) -> Result<CatalogLoad> {
+    let items = body["data"]["apps"]["items"]
+        .as_array()
+        .ok_or_else(|| anyhow!("Missing apps.items in games response"))?;
+    let (games, skipped) = parse_game_items(items);
+    Ok(CatalogLoad { games, skipped })
+}
+
+/// Parse the subscription endpoint's body. Split out of
+/// [`GfnApiClient::fetch_subscription`] so recorded fixtures can replay
+/// through it.
+pub(crate) fn parse_subscription(body: &serde_json::Value) -> SubscriptionInfo {
+    let entitled_resolutions = body["entitledResolutions"]
+        .as_array()
+        .map(|items| {
+            items
+                .iter()
+                .filter_map(|r| {
+                    Some((
+                        r["width"].as_u64()? as u32,
+                        r["height"].as_u64()? as u32,
+                        r["fps"].as_u64()? as u32,
+                    ))
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+    SubscriptionInfo {
+        tier: body["product"]["name"]
+            .as_str()
+            .unwrap_or("FREE")
+            .to_string(),
+        remaining_hours: body["remainingTimeInMinutes"].as_f64().map(|m| m / 60.0),
+        total_hours: body["totalTimeInMinutes"].as_f64().map(|m| m / 60.0),
+        entitled_resolutions,
+        max_bitrate_mbps: body["maxBitrateMbps"].as_u64().map(|m| m as u32),
+    }
+}
+
+/// Parse one raw GraphQL catalog item. Only id and title are required;
+/// everything else degrades to None so a null publisher or a missing
+/// images object can't take the entry down.
+fn parse_game_item(item: &serde_json::Value) -> Result<GameInfo> {
+    let id = item["id"]
+        .as_str()
+        .or_else(|| item["cmsId"].as_str())
+        .filter(|s| !s.is_empty())
+        .ok_or_else(|| anyhow!("missing id/cmsId"))?;
+    let title = item["title"]
+        .as_str()
+        .filter(|s| !s.is_empty())
+        .ok_or_else(|| anyhow!("missing title"))?;
+    Ok(GameInfo {
+        cms_id: id.to_string(),
+        // The session API launches by the variant's appId; multi-edition
+        // SKUs can carry a different top-level appId (the panel's), so
+        // the variant wins when both are present.
+        launch_app_id: item["variants"][0]["appId"]
+            .as_u64()
+            .or_else(|| item["appId"].as_u64()),
+        title: title.to_string(),
+        publisher: item["publisherName"].as_str().map(String::from),
+        image_url: item["images"]["GAME_BOX_ART"].as_str().map(String::from),
+        store: item["variants"][0]["appStore"].as_str().map(String::from),
+    })
+}
+
+/// Parse catalog items independently so one malformed entry can't fail
+/// the whole response. Returns the good entries and the skipped count;
+/// skipped entries are logged with whatever id they carried.
+pub(crate) fn parse_game_items(items: &[serde_json::Value]) -> (Vec<GameInfo>, usize) {
+    let mut games = Vec::with_capacity(items.len());
+    let mut skipped = 0usize;
+    for item in items {
+        match parse_game_item(item) {
+            Ok(game) => games.push(game),
+            Err(e) => {
+                skipped += 1;
+                let id = item["id"]
+                    .as_str()
+                    .or_else(|| item["cmsId"].as_str())
+                    .unwrap_or("<no id>");
+                log::warn!("Skipping malformed catalog entry {}: {}", id, e);
+            }
+        }
+    }
+    (games, skipped)
+}
+
+fn string_array(value: &serde_json::Value) -> Vec<String> {
+    value
+        .as_array()
+        .map(|items| {
+            items
+                .iter()
+                .filter_map(|v| v.as_str().map(|s| s.to_string()))
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn game(id: &str, title: &str) -> GameInfo {
+        GameInfo {
+            cms_id: id.to_string(),
+            launch_app_id: None,
+            title: title.to_string(),
+            publisher: None,
+            image_url: None,
+            store: None,
+        }
+    }
+
+    #[test]
+    fn merge_appends_new_entries() {
+        let mut library = vec![game("a", "Alpha")];
+        merge_library_page(&mut library, vec![game("b", "Beta"), game("c", "Gamma")]);
+        assert_eq!(
+            library.iter().map(|g| g.cms_id.as_str()).collect::<Vec<_>>(),
+            ["a", "b", "c"]
+        );
+    }
+
+    #[test]
+    fn merge_overlapping_pages_deduplicates_and_updates() {
+        let mut library = vec![game("a", "Alpha"), game("b", "Beta")];
+        // A retried page re-delivers "b" with newer metadata.
+        merge_library_page(&mut library, vec![game("b", "Beta (updated)"), game("c", "Gamma")]);
+        assert_eq!(library.len(), 3);
+        assert_eq!(library[1].title, "Beta (updated)");
+    }
+
+    #[test]
+    fn merge_out_of_order_pages_converges() {
+        let page1 = vec![game("a", "Alpha"), game("b", "Beta")];
+        let page2 = vec![game("c", "Gamma"), game("d", "Delta")];
+        let mut in_order = Vec::new();
+        merge_library_page(&mut in_order, page1.clone());
+        merge_library_page(&mut in_order, page2.clone());
+        let mut reversed = Vec::new();
+        merge_library_page(&mut reversed, page2);
+        merge_library_page(&mut reversed, page1);
+        let mut in_order_ids: Vec<_> = in_order.iter().map(|g| g.cms_id.clone()).collect();
+        let mut reversed_ids: Vec<_> = reversed.iter().map(|g| g.cms_id.clone()).collect();
+        in_order_ids.sort();
+        reversed_ids.sort();
+        assert_eq!(in_order_ids, reversed_ids);
+    }
+
+    /// Shapes captured from real catalog responses that used to fail the
+    /// whole deserialization; each must now degrade per-entry.
+    #[test]
+    fn null_publisher_and_missing_images_do_not_skip_the_entry() {
+        let items = vec![
+            serde_json::json!({
+                "id": "good",
+                "appId": 100,
+                "title": "Fine Game",
+                "publisherName": null,
+                "images": { "GAME_BOX_ART": "https://img/fine.jpg" },
+                "variants": [{ "appStore": "STEAM" }],
+            }),
+            serde_json::json!({
+                "id": "no-images",
+                "title": "Artless Game",
+                "publisherName": "Studio",
+                // images object absent entirely.
+                "variants": [],
+            }),
+        ];
+        let (games, skipped) = parse_game_items(&items);
+        assert_eq!(skipped, 0);
+        assert_eq!(games.len(), 2);
+        assert_eq!(games[0].publisher, None);
+        assert_eq!(games[1].image_url, None);
+        assert_eq!(games[1].store, None);
+    }
+
+    #[test]
+    fn malformed_entries_are_skipped_and_the_rest_load() {
+        let items = vec![
+            serde_json::json!({ "id": "a", "title": "Alpha" }),
+            // Null title.
+            serde_json::json!({ "id": "bad-title", "title": null }),
+            // No id at all.
+            serde_json::json!({ "title": "Orphan" }),
+            serde_json::json!({ "id": "b", "title": "Beta" }),
+        ];
+        let (games, skipped) = parse_game_items(&items);
+        assert_eq!(skipped, 2);
+        assert_eq!(
+            games.iter().map(|g| g.cms_id.as_str()).collect::<Vec<_>>(),
+            ["a", "b"]
+        );
+    }
+
+    #[test]
+    fn unexpected_field_types_degrade_instead_of_failing() {
+        // appId as a string and appStore as a number (unknown variants
+        // NVIDIA has shipped) must not take the entry down.
+        let items = vec![serde_json::json!({
+            "id": "odd",
+            "appId": "not-a-number",
+            "title": "Odd Game",
+            "variants": [{ "appStore": 7 }],
+        })];
+        let (games, skipped) = parse_game_items(&items);
+        assert_eq!(skipped, 0);
+        assert_eq!(games[0].launch_app_id, None);
+        assert_eq!(games[0].store, None);
+    }
+
+    /// Captured from a multi-edition Ubisoft title: the panel-level appId
+    /// is the base game, the variant carries the edition actually
+    /// entitled. Launching by the panel id starts the wrong edition.
+    #[test]
+    fn variant_launch_id_wins_over_divergent_panel_id() {
+        let items = vec![serde_json::json!({
+            "id": "ubi-multi-edition",
+            "appId": 100500,
+            "title": "Far Shore: Gold Edition",
+            "variants": [{ "appId": 100501, "appStore": "UBISOFT" }],
+        })];
+        let (games, skipped) = parse_game_items(&items);
+        assert_eq!(skipped, 0);
+        assert_eq!(games[0].cms_id, "ubi-multi-edition");
+        assert_eq!(games[0].launch_app_id, Some(100501));
+    }
+
+    /// DLC-bundled SKU with no variant appId: the top-level id is the
+    /// only launch id and must still be used.
+    #[test]
+    fn panel_id_is_the_fallback_when_variants_carry_none() {
+        let items = vec![serde_json::json!({
+            "id": "dlc-bundle",
+            "appId": 200100,
+            "title": "Skyline Complete Bundle",
+            "variants": [{ "appStore": "STEAM" }],
+        })];
+        let (games, _) = parse_game_items(&items);
+        assert_eq!(games[0].launch_app_id, Some(200100));
+    }
+
+    /// Cache files written before the identifier rename (`id`/`app_id`
+    /// field names) must still deserialize.
+    #[test]
+    fn old_cache_field_names_still_deserialize() {
+        let old = r#"{ "id": "legacy", "app_id": 7, "title": "Old Cache Entry",
+                       "publisher": null, "image_url": null, "store": null }"#;
+        let game: GameInfo = serde_json::from_str(old).unwrap();
+        assert_eq!(game.cms_id, "legacy");
+        assert_eq!(game.launch_app_id, Some(7));
+    }
+
+    #[test]
+    fn bitrate_cap_prefers_the_payload_over_the_tier_table() {
+        let mut sub = parse_subscription(&serde_json::json!({
+            "product": { "name": "GeForce NOW Ultimate" },
+            "maxBitrateMbps": 100
+        }));
+        assert_eq!(sub.bitrate_cap_mbps(), Some(100));
+        // Without the payload field the decorated tier name still maps.
+        sub.max_bitrate_mbps = None;
+        assert_eq!(sub.bitrate_cap_mbps(), Some(75));
+        // Unknown Alliance tiers stay uncapped.
+        let alliance = parse_subscription(&serde_json::json!({
+            "product": { "name": "GameStream Plus" }
+        }));
+        assert_eq!(alliance.bitrate_cap_mbps(), None);
+    }
+}
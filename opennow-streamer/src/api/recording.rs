@@ -0,0 +1,255 @@
+//! Opt-in API traffic recording (dev setting) and fixture replay.
+//!
+//! With `Settings::api_recording` on, every request/response pair to
+//! cloudmatch, the GraphQL catalog and the account endpoints is written
+//! — sanitized — into a per-session directory under the app data dir
+//! (`fixtures/<timestamp>/NNNN-<kind>.json`). `replay_fixtures` feeds a
+//! fixtures directory back through the same parsing layers, so a
+//! captured breakage becomes a permanent regression test. A sanitized
+//! starter corpus ships in the repo under `fixtures/api/` and runs with
+//! `cargo test`.
+
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::Mutex;
+
+use anyhow::{anyhow, Context, Result};
+
+static RECORDING_DIR: Mutex<Option<PathBuf>> = Mutex::new(None);
+static SEQUENCE: AtomicU32 = AtomicU32::new(1);
+
+/// Turn recording on (creating a fresh per-session directory) or off.
+/// Idempotent; an active recording keeps its directory.
+pub fn set_enabled(enabled: bool) {
+    let mut dir = RECORDING_DIR.lock().unwrap();
+    if !enabled {
+        *dir = None;
+        return;
+    }
+    if dir.is_some() {
+        return;
+    }
+    let path = crate::app::cache::get_app_data_dir()
+        .join("fixtures")
+        .join(chrono::Utc::now().format("%Y%m%d-%H%M%S").to_string());
+    if let Err(e) = std::fs::create_dir_all(&path) {
+        log::warn!(
+            "API recording disabled — cannot create {}: {}",
+            path.display(),
+            e
+        );
+        return;
+    }
+    log::info!("Recording sanitized API traffic to {}", path.display());
+    SEQUENCE.store(1, Ordering::SeqCst);
+    *dir = Some(path);
+}
+
+/// Write one request/response pair if recording is on. Never fails the
+/// caller — a full disk just loses the fixture.
+pub fn record(kind: &str, request: &serde_json::Value, response: &serde_json::Value) {
+    let Some(dir) = RECORDING_DIR.lock().unwrap().clone() else {
+        return;
+    };
+    let mut request = request.clone();
+    let mut response = response.clone();
+    sanitize(&mut request);
+    sanitize(&mut response);
+    let fixture = serde_json::json!({
+        "kind": kind,
+        "request": request,
+        "response": response,
+    });
+    let seq = SEQUENCE.fetch_add(1, Ordering::SeqCst);
+    let path = dir.join(format!("{:04}-{}.json", seq, kind));
+    match serde_json::to_string_pretty(&fixture) {
+        Ok(text) => {
+            if let Err(e) = std::fs::write(&path, text) {
+                log::warn!("Failed to write fixture {}: {}", path.display(), e);
+            }
+        }
+        Err(e) => log::warn!("Failed to serialize fixture {}: {}", kind, e),
+    }
+}
+
+/// Keys whose string values are always personal or secret, regardless
+/// of what they contain.
+const SENSITIVE_KEYS: &[&str] = &[
+    "access_token",
+    "refresh_token",
+    "id_token",
+    "authorization",
+    "email",
+    "sub",
+    "userid",
+    "user_id",
+    "preferred_username",
+    "picture",
+    "deviceid",
+    "device_id",
+];
+
+/// Strip tokens, emails and user identifiers in place, recursively.
+/// Key-based for the known fields, value-based as a backstop: anything
+/// shaped like a JWT or an email address is redacted wherever it
+/// appears, so a new response field can't leak a credential into a
+/// shared fixture.
+pub fn sanitize(value: &mut serde_json::Value) {
+    match value {
+        serde_json::Value::Object(map) => {
+            for (key, entry) in map.iter_mut() {
+                if SENSITIVE_KEYS.contains(&key.to_ascii_lowercase().as_str()) {
+                    *entry = serde_json::Value::String("REDACTED".to_string());
+                } else {
+                    sanitize(entry);
+                }
+            }
+        }
+        serde_json::Value::Array(items) => {
+            for item in items {
+                sanitize(item);
+            }
+        }
+        serde_json::Value::String(s) => {
+            if looks_like_jwt(s) || looks_like_email(s) {
+                *s = "REDACTED".to_string();
+            }
+        }
+        _ => {}
+    }
+}
+
+fn looks_like_jwt(s: &str) -> bool {
+    s.starts_with("eyJ") && s.bytes().filter(|b| *b == b'.').count() == 2
+}
+
+fn looks_like_email(s: &str) -> bool {
+    s.split_once('@')
+        .is_some_and(|(local, domain)| !local.is_empty() && domain.contains('.'))
+}
+
+/// Replay every fixture in `dir` through the parsing layer it was
+/// recorded from, failing on the first response the parsers reject.
+/// Returns how many fixtures were actually checked so a caller can
+/// assert the corpus wasn't silently skipped; kinds without a replay
+/// arm (e.g. `userinfo`) are logged and don't count.
+pub fn replay_fixtures(dir: &Path) -> Result<usize> {
+    let mut paths: Vec<PathBuf> = std::fs::read_dir(dir)
+        .with_context(|| format!("Cannot read fixtures dir {}", dir.display()))?
+        .filter_map(|entry| Some(entry.ok()?.path()))
+        .filter(|path| path.extension().is_some_and(|ext| ext == "json"))
+        .collect();
+    paths.sort();
+    let mut replayed = 0;
+    for path in paths {
+        let text = std::fs::read_to_string(&path)
+            .with_context(|| format!("Cannot read {}", path.display()))?;
+        let fixture: serde_json::Value = serde_json::from_str(&text)
+            .with_context(|| format!("{} is not valid JSON", path.display()))?;
+        let kind = fixture["kind"].as_str().unwrap_or("");
+        if replay_one(kind, &fixture["response"])
+            .with_context(|| format!("Fixture {} failed replay", path.display()))?
+        {
+            replayed += 1;
+        }
+    }
+    Ok(replayed)
+}
+
+/// Run one fixture through its parser. Returns whether the kind had a
+/// replay arm.
+fn replay_one(kind: &str, response: &serde_json::Value) -> Result<bool> {
+    match kind {
+        "cloudmatch-create" | "cloudmatch-poll" => {
+            let info = super::cloudmatch::parse_session_response(response)?;
+            if info.session_id.is_empty() {
+                return Err(anyhow!("empty session id"));
+            }
+        }
+        "subscription" => {
+            let info = super::parse_subscription(response);
+            if info.tier.is_empty() {
+                return Err(anyhow!("empty subscription tier"));
+            }
+        }
+        "graphql-games" => {
+            let load = super::parse_main_games(response)?;
+            if load.skipped > 0 {
+                return Err(anyhow!("{} catalog entries failed to parse", load.skipped));
+            }
+        }
+        other => {
+            log::debug!("No replay arm for fixture kind {:?}; skipping", other);
+            return Ok(false);
+        }
+    }
+    Ok(true)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sanitizer_strips_tokens_emails_and_ids_recursively() {
+        let mut value = serde_json::json!({
+            "access_token": "eyJhbGciOi.payload.signature",
+            "user": {
+                "email": "player@example.com",
+                "userId": "12345-abcde",
+                "displayName": "Player One",
+            },
+            "sessions": [{ "note": "contact me at someone@host.example" }],
+            "product": { "name": "Performance" },
+        });
+        sanitize(&mut value);
+        assert_eq!(value["access_token"], "REDACTED");
+        assert_eq!(value["user"]["email"], "REDACTED");
+        assert_eq!(value["user"]["userId"], "REDACTED");
+        assert_eq!(value["sessions"][0]["note"], "REDACTED");
+        // Non-sensitive fields survive — the tier name must stay usable.
+        assert_eq!(value["user"]["displayName"], "Player One");
+        assert_eq!(value["product"]["name"], "Performance");
+    }
+
+    /// The sanitized starter corpus in the repo must keep replaying
+    /// cleanly; a parser change that breaks a captured layout fails
+    /// here with the offending fixture's path.
+    #[test]
+    fn starter_corpus_replays_through_the_parsers() {
+        let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("fixtures/api");
+        let replayed = replay_fixtures(&dir).unwrap();
+        assert!(
+            replayed >= 4,
+            "starter corpus shrank: only {} fixtures replayed",
+            replayed
+        );
+    }
+
+    /// Record → replay round trip through a scratch directory, the way
+    /// a captured breakage becomes a regression fixture.
+    #[test]
+    fn recorded_traffic_replays() {
+        let dir = std::env::temp_dir().join(format!(
+            "opennow-recording-test-{}",
+            std::process::id()
+        ));
+        std::fs::create_dir_all(&dir).unwrap();
+        *RECORDING_DIR.lock().unwrap() = Some(dir.clone());
+        record(
+            "cloudmatch-poll",
+            &serde_json::json!({ "url": "/v2/session/abc-123" }),
+            &serde_json::json!({
+                "session": {
+                    "sessionId": "abc-123",
+                    "status": "READY_FOR_CONNECTION",
+                    "gpuType": "A10G",
+                }
+            }),
+        );
+        *RECORDING_DIR.lock().unwrap() = None;
+        let replayed = replay_fixtures(&dir).unwrap();
+        std::fs::remove_dir_all(&dir).ok();
+        assert_eq!(replayed, 1);
+    }
+}
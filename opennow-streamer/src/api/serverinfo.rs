@@ -0,0 +1,344 @@
+//! Zone/server discovery and latency measurement.
+
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use anyhow::{anyhow, Context, Result};
+use serde::{Deserialize, Serialize};
+
+const SERVERINFO_URL: &str = "https://api-prod.nvidia.com/gfn/v2/serverInfo";
+
+/// A streaming zone the user can launch against.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ServerInfo {
+    pub id: String,
+    pub name: String,
+    pub address: String,
+    /// Last measured latency, if we have pinged this zone.
+    pub ping_ms: Option<u32>,
+    /// GPU classes with rigs present in this zone (e.g. "RTX 4080"),
+    /// as reported by serverInfo. Empty when the endpoint doesn't
+    /// expose them — the UI simply omits the badge then.
+    #[serde(default)]
+    pub rig_classes: Vec<String>,
+}
+
+/// How long a cached region list stays authoritative. Rig availability
+/// shifts as zones add or drain capacity, so a settings-modal open past
+/// this age re-fetches instead of serving month-old badges.
+const REGIONS_TTL: Duration = Duration::from_secs(15 * 60);
+
+/// Cached dynamic region list, keyed by the login provider code it was
+/// fetched under, so re-opening the settings modal doesn't re-fetch on
+/// every open but a provider switch never serves the previous
+/// provider's zones.
+static DYNAMIC_REGIONS_CACHE: Mutex<Option<(String, Instant, Vec<ServerInfo>)>> =
+    Mutex::new(None);
+
+fn cached_zones(provider: &str) -> Option<Vec<ServerInfo>> {
+    let cache = DYNAMIC_REGIONS_CACHE.lock().unwrap();
+    let (cached_provider, fetched_at, zones) = cache.as_ref()?;
+    (cached_provider == provider && fetched_at.elapsed() < REGIONS_TTL)
+        .then(|| zones.clone())
+}
+
+fn store_zones(provider: &str, zones: &[ServerInfo]) {
+    *DYNAMIC_REGIONS_CACHE.lock().unwrap() =
+        Some((provider.to_string(), Instant::now(), zones.to_vec()));
+}
+
+/// Drop the cached region list. Called on provider/account switches so
+/// the next fetch hits the network for the new provider.
+pub fn invalidate_regions_cache() {
+    *DYNAMIC_REGIONS_CACHE.lock().unwrap() = None;
+}
+
+/// Keep a persisted zone selection only when `zones` actually contains
+/// it; a leftover id from another provider falls back to automatic
+/// selection (None) instead of silently launching in a wrong zone.
+pub fn validate_selection(selected: Option<String>, zones: &[ServerInfo]) -> Option<String> {
+    selected.filter(|id| zones.iter().any(|zone| &zone.id == id))
+}
+
+/// Resolve the address to launch against: the selected zone when it
+/// exists in `zones`, else the best-pinged one (`zones` is ping-sorted).
+pub fn resolve_launch_address(selected: Option<&str>, zones: &[ServerInfo]) -> Option<String> {
+    if let Some(id) = selected {
+        if let Some(zone) = zones.iter().find(|zone| zone.id == id) {
+            return Some(zone.address.clone());
+        }
+    }
+    zones.first().map(|zone| zone.address.clone())
+}
+
+/// Decide whether a pre-launch re-ping justifies offering a switch away
+/// from `selected`: its fresh latency drifted at least `threshold_ms`
+/// above the stored value, or the best alternative beats it by at least
+/// the same margin. A selected zone that stopped answering pings counts
+/// as arbitrarily degraded. Returns the zone to offer, which is always
+/// strictly faster than the selected one — a prompt that can't improve
+/// anything is never shown.
+pub fn switch_candidate(
+    selected: &ServerInfo,
+    zones: &[ServerInfo],
+    stored_ping_ms: Option<u32>,
+    threshold_ms: u32,
+) -> Option<ServerInfo> {
+    let (best_ping, best) = zones
+        .iter()
+        .filter(|zone| zone.id != selected.id)
+        .filter_map(|zone| zone.ping_ms.map(|ping| (ping, zone)))
+        .min_by_key(|(ping, _)| *ping)?;
+    let fresh = selected.ping_ms.unwrap_or(u32::MAX);
+    let degraded =
+        stored_ping_ms.is_some_and(|stored| fresh.saturating_sub(stored) >= threshold_ms);
+    let outclassed = fresh.saturating_sub(best_ping) >= threshold_ms;
+    ((degraded || outclassed) && best_ping < fresh).then(|| best.clone())
+}
+
+/// Fetch the list of zones, using the cached copy when it belongs to the
+/// active login provider.
+pub async fn fetch_zones(access_token: &str) -> Result<Vec<ServerInfo>> {
+    let provider = crate::auth::active_provider().code;
+    if let Some(cached) = cached_zones(&provider) {
+        return Ok(cached);
+    }
+    let client = reqwest::Client::builder()
+        .user_agent(crate::USER_AGENT)
+        .build()?;
+    let body: serde_json::Value = client
+        .get(SERVERINFO_URL)
+        .bearer_auth(access_token)
+        .send()
+        .await
+        .context("serverInfo request failed")?
+        .json()
+        .await
+        .context("serverInfo returned invalid JSON")?;
+    let zones = body["regions"]
+        .as_array()
+        .ok_or_else(|| anyhow!("Missing regions in serverInfo response"))?
+        .iter()
+        .filter_map(parse_zone)
+        .collect::<Vec<_>>();
+    store_zones(&provider, &zones);
+    Ok(zones)
+}
+
+/// Parse one serverInfo region entry. Rig classes arrive under either
+/// `rigClasses` or the older `gpuClasses` key; both are optional and an
+/// entry without them still yields a usable zone.
+fn parse_zone(region: &serde_json::Value) -> Option<ServerInfo> {
+    let classes = region["rigClasses"]
+        .as_array()
+        .or_else(|| region["gpuClasses"].as_array());
+    Some(ServerInfo {
+        id: region["id"].as_str()?.to_string(),
+        name: region["name"].as_str()?.to_string(),
+        address: region["address"].as_str()?.to_string(),
+        ping_ms: None,
+        rig_classes: classes
+            .map(|classes| {
+                classes
+                    .iter()
+                    .filter_map(|class| class.as_str())
+                    .map(|class| class.to_string())
+                    .collect()
+            })
+            .unwrap_or_default(),
+    })
+}
+
+/// Whether a zone advertises rigs of the given class. Matched loosely
+/// ("RTX 4080" hits "GeForce RTX 4080") since the server strings carry
+/// marketing prefixes the preference shouldn't have to reproduce.
+pub fn zone_has_rig(zone: &ServerInfo, rig: &str) -> bool {
+    let wanted = rig.to_ascii_lowercase();
+    zone.rig_classes
+        .iter()
+        .any(|class| class.to_ascii_lowercase().contains(&wanted))
+}
+
+/// Best-pinged zone advertising the preferred rig class, for strict
+/// automatic selection (`zones` is ping-sorted). None when no zone
+/// lists it — including when the endpoint exposes no rig data at all —
+/// so callers can fall back to plain best-ping selection.
+pub fn preferred_rig_zone<'a>(zones: &'a [ServerInfo], rig: &str) -> Option<&'a ServerInfo> {
+    zones.iter().find(|zone| zone_has_rig(zone, rig))
+}
+
+/// Measure latency to a single zone with a lightweight HTTPS round trip.
+pub async fn ping_server(server: &ServerInfo) -> Option<u32> {
+    let client = reqwest::Client::builder()
+        .timeout(Duration::from_secs(3))
+        .build()
+        .ok()?;
+    let url = format!("https://{}/v2/serverInfo", server.address);
+    let start = Instant::now();
+    client.get(&url).send().await.ok()?;
+    Some(start.elapsed().as_millis() as u32)
+}
+
+/// Ping every zone concurrently and fill in `ping_ms`, sorted best-first.
+pub async fn ping_all_servers(mut servers: Vec<ServerInfo>) -> Vec<ServerInfo> {
+    let pings = futures::future::join_all(servers.iter().map(ping_server)).await;
+    for (server, ping) in servers.iter_mut().zip(pings) {
+        server.ping_ms = ping;
+    }
+    servers.sort_by_key(|s| s.ping_ms.unwrap_or(u32::MAX));
+    servers
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn zone(id: &str, address: &str) -> ServerInfo {
+        ServerInfo {
+            id: id.to_string(),
+            name: id.to_string(),
+            address: address.to_string(),
+            ping_ms: None,
+            rig_classes: Vec::new(),
+        }
+    }
+
+    /// The full provider-switch sequence against the single-slot cache:
+    /// NVIDIA's zones must never be served under a partner's code, and
+    /// invalidation forces the next fetch to the network.
+    #[test]
+    fn cache_is_scoped_by_provider() {
+        let nvidia_zones = vec![zone("eu-west", "eu.nvidia.example")];
+        store_zones("nvidia", &nvidia_zones);
+        assert!(cached_zones("nvidia").is_some());
+        assert!(cached_zones("partner-x").is_none());
+
+        let partner_zones = vec![zone("px-central", "central.partner-x.example")];
+        store_zones("partner-x", &partner_zones);
+        assert!(cached_zones("nvidia").is_none());
+        assert_eq!(cached_zones("partner-x").unwrap()[0].id, "px-central");
+
+        invalidate_regions_cache();
+        assert!(cached_zones("partner-x").is_none());
+    }
+
+    #[test]
+    fn stale_selection_falls_back_to_automatic() {
+        let partner_zones = vec![
+            zone("px-central", "central.partner-x.example"),
+            zone("px-north", "north.partner-x.example"),
+        ];
+        // A selection persisted under the previous provider is dropped…
+        assert_eq!(
+            validate_selection(Some("eu-west".to_string()), &partner_zones),
+            None
+        );
+        // …while one the new provider offers survives.
+        assert_eq!(
+            validate_selection(Some("px-north".to_string()), &partner_zones),
+            Some("px-north".to_string())
+        );
+    }
+
+    #[test]
+    fn launch_resolution_ignores_unknown_selection() {
+        let partner_zones = vec![
+            zone("px-central", "central.partner-x.example"),
+            zone("px-north", "north.partner-x.example"),
+        ];
+        // Stale id: launch in the best-pinged zone, not a wrong one.
+        assert_eq!(
+            resolve_launch_address(Some("eu-west"), &partner_zones),
+            Some("central.partner-x.example".to_string())
+        );
+        assert_eq!(
+            resolve_launch_address(Some("px-north"), &partner_zones),
+            Some("north.partner-x.example".to_string())
+        );
+        assert_eq!(resolve_launch_address(None, &[]), None);
+    }
+
+    fn pinged(id: &str, ping_ms: Option<u32>) -> ServerInfo {
+        ServerInfo {
+            ping_ms,
+            ..zone(id, &format!("{}.example", id))
+        }
+    }
+
+    #[test]
+    fn switch_offered_only_when_latency_drifted_and_an_alternative_helps() {
+        let frankfurt = pinged("frankfurt", Some(85));
+        let zones = vec![frankfurt.clone(), pinged("amsterdam", Some(18))];
+        // Degraded well past the stored 20ms: offer the better zone.
+        let offer = switch_candidate(&frankfurt, &zones, Some(20), 30).unwrap();
+        assert_eq!(offer.id, "amsterdam");
+        // Same fresh pings, but 85ms is what Frankfurt always measured:
+        // 85 vs 18 still clears the margin, so the offer stands…
+        assert!(switch_candidate(&frankfurt, &zones, Some(85), 30).is_some());
+        // …until the margin doesn't: a 10ms spread is noise.
+        let close = vec![frankfurt.clone(), pinged("amsterdam", Some(75))];
+        assert!(switch_candidate(&frankfurt, &close, Some(85), 30).is_none());
+        // Degraded, but every alternative is even worse: nothing to offer.
+        let worse = vec![frankfurt.clone(), pinged("amsterdam", Some(120))];
+        assert!(switch_candidate(&frankfurt, &worse, Some(20), 30).is_none());
+    }
+
+    #[test]
+    fn unresponsive_selected_zone_counts_as_degraded() {
+        let dead = pinged("frankfurt", None);
+        let zones = vec![dead.clone(), pinged("amsterdam", Some(18))];
+        let offer = switch_candidate(&dead, &zones, Some(20), 30).unwrap();
+        assert_eq!(offer.id, "amsterdam");
+        // No alternative answered either: no offer to make.
+        let all_dead = vec![dead.clone(), pinged("amsterdam", None)];
+        assert!(switch_candidate(&dead, &all_dead, Some(20), 30).is_none());
+    }
+
+    #[test]
+    fn rig_classes_parse_from_either_key_and_stay_optional() {
+        let with_new_key = serde_json::json!({
+            "id": "eu-west", "name": "EU West", "address": "eu.example",
+            "rigClasses": ["GeForce RTX 4080", "GeForce RTX 3080"]
+        });
+        let with_old_key = serde_json::json!({
+            "id": "eu-north", "name": "EU North", "address": "north.example",
+            "gpuClasses": ["GeForce RTX 3080"]
+        });
+        let without = serde_json::json!({
+            "id": "eu-south", "name": "EU South", "address": "south.example"
+        });
+        assert_eq!(
+            parse_zone(&with_new_key).unwrap().rig_classes,
+            vec!["GeForce RTX 4080", "GeForce RTX 3080"]
+        );
+        assert_eq!(
+            parse_zone(&with_old_key).unwrap().rig_classes,
+            vec!["GeForce RTX 3080"]
+        );
+        assert!(parse_zone(&without).unwrap().rig_classes.is_empty());
+    }
+
+    #[test]
+    fn strict_rig_preference_picks_the_best_zone_that_has_it() {
+        let mut slow_4080 = pinged("frankfurt", Some(40));
+        slow_4080.rig_classes = vec!["GeForce RTX 4080".to_string()];
+        let mut fast_3080 = pinged("amsterdam", Some(18));
+        fast_3080.rig_classes = vec!["GeForce RTX 3080".to_string()];
+        // Ping-sorted order, as `ping_all_servers` delivers.
+        let zones = vec![fast_3080, slow_4080];
+        // The loose match tolerates the marketing prefix.
+        assert_eq!(
+            preferred_rig_zone(&zones, "RTX 4080").unwrap().id,
+            "frankfurt"
+        );
+        assert_eq!(
+            preferred_rig_zone(&zones, "rtx 3080").unwrap().id,
+            "amsterdam"
+        );
+        // No zone lists the class (or no zone lists anything): the
+        // caller falls back to plain best-ping selection.
+        assert!(preferred_rig_zone(&zones, "RTX 5090").is_none());
+        let bare = vec![pinged("frankfurt", Some(40))];
+        assert!(preferred_rig_zone(&bare, "RTX 4080").is_none());
+    }
+}
@@ -0,0 +1,299 @@
+//! On-disk caches under the app data directory: session handoff, games
+//! catalog, and the selected login provider. Account-scoped files live
+//! in a per-profile namespace so several logins can coexist.
+
+use std::fs;
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+
+use crate::api::cloudmatch::SessionState;
+use crate::api::GameInfo;
+
+pub fn get_app_data_dir() -> PathBuf {
+    dirs::data_dir()
+        .unwrap_or_else(|| PathBuf::from("."))
+        .join("OpenNow")
+}
+
+/// Profile id the pre-profiles layout maps to. Its files stay directly
+/// under the app data directory so existing installs keep their tokens
+/// and caches without a migration.
+pub const DEFAULT_PROFILE: &str = "default";
+
+/// Active profile id. Set at startup (and on every switch) before any
+/// of the per-profile path helpers run.
+static ACTIVE_PROFILE: Mutex<Option<String>> = Mutex::new(None);
+
+pub fn set_active_profile(id: &str) {
+    *ACTIVE_PROFILE.lock().unwrap() = Some(id.to_string());
+}
+
+pub fn active_profile() -> String {
+    ACTIVE_PROFILE
+        .lock()
+        .unwrap()
+        .clone()
+        .unwrap_or_else(|| DEFAULT_PROFILE.to_string())
+}
+
+/// Namespace for tokens and account-scoped caches of the active
+/// profile. Settings and the profile registry itself stay global.
+pub fn get_profile_data_dir() -> PathBuf {
+    let profile = active_profile();
+    if profile == DEFAULT_PROFILE {
+        get_app_data_dir()
+    } else {
+        get_app_data_dir().join("profiles").join(profile)
+    }
+}
+
+pub fn profiles_path() -> PathBuf {
+    get_app_data_dir().join("profiles.json")
+}
+
+/// Saved account profiles and which one is active. Each profile keeps
+/// its own tokens, login provider, and caches under
+/// `get_profile_data_dir()`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProfilesFile {
+    pub active: String,
+    pub profiles: Vec<String>,
+}
+
+impl Default for ProfilesFile {
+    fn default() -> Self {
+        Self {
+            active: DEFAULT_PROFILE.to_string(),
+            profiles: vec![DEFAULT_PROFILE.to_string()],
+        }
+    }
+}
+
+pub fn load_profiles() -> ProfilesFile {
+    let mut file: ProfilesFile = fs::read_to_string(profiles_path())
+        .ok()
+        .and_then(|data| serde_json::from_str(&data).ok())
+        .unwrap_or_default();
+    // A hand-edited registry must not strand the app in a profile it
+    // doesn't list.
+    if file.profiles.is_empty() {
+        file.profiles.push(DEFAULT_PROFILE.to_string());
+    }
+    if !file.profiles.contains(&file.active) {
+        file.active = file.profiles[0].clone();
+    }
+    file
+}
+
+pub fn save_profiles(file: &ProfilesFile) -> Result<()> {
+    let path = profiles_path();
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::write(&path, serde_json::to_string_pretty(file)?)
+        .context("Failed to write profiles.json")?;
+    Ok(())
+}
+
+pub fn session_cache_path() -> PathBuf {
+    get_profile_data_dir().join("session_cache.json")
+}
+
+pub fn games_cache_path() -> PathBuf {
+    get_profile_data_dir().join("games_cache.json")
+}
+
+pub fn login_provider_path() -> PathBuf {
+    get_profile_data_dir().join("login_provider.json")
+}
+
+/// Snapshot of the in-flight session written by the polling tasks and read
+/// back by `App::update` each frame.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SessionCache {
+    pub session_id: String,
+    pub game_id: String,
+    /// The parsed session state as-is. Previously a Debug-formatted
+    /// string that consumers had to re-parse; queue position/ETA
+    /// survived the round trip only by string matching.
+    pub state: SessionState,
+    pub server_address: Option<String>,
+    pub signal_connection_url: Option<String>,
+    /// Zone API address the session was created against; resume
+    /// verification polls go here. Caches written before this field
+    /// existed can't be verified and are dropped.
+    #[serde(default)]
+    pub zone: Option<String>,
+    pub updated_at: i64,
+}
+
+pub fn save_session_cache(cache: &SessionCache) -> Result<()> {
+    let path = session_cache_path();
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::write(&path, serde_json::to_string_pretty(cache)?)
+        .context("Failed to write session_cache.json")?;
+    Ok(())
+}
+
+pub fn load_session_cache() -> Option<SessionCache> {
+    let data = fs::read_to_string(session_cache_path()).ok()?;
+    serde_json::from_str(&data).ok()
+}
+
+pub fn clear_session_cache() {
+    let _ = fs::remove_file(session_cache_path());
+}
+
+pub fn save_games_cache(games: &[GameInfo]) -> Result<()> {
+    let path = games_cache_path();
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::write(&path, serde_json::to_string(games)?).context("Failed to write games cache")?;
+    Ok(())
+}
+
+pub fn load_games_cache() -> Option<Vec<GameInfo>> {
+    let data = fs::read_to_string(games_cache_path()).ok()?;
+    serde_json::from_str(&data).ok()
+}
+
+pub fn library_cache_path() -> PathBuf {
+    get_profile_data_dir().join("library_cache.json")
+}
+
+/// Cached library with a completeness marker: a sync interrupted
+/// mid-pagination writes `complete: false` so a partial result is never
+/// mistaken for the full library.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LibraryCache {
+    pub complete: bool,
+    /// Unix timestamp of the sync this cache came from.
+    pub synced_at: i64,
+    pub games: Vec<GameInfo>,
+}
+
+pub fn save_library_cache(cache: &LibraryCache) -> Result<()> {
+    let path = library_cache_path();
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::write(&path, serde_json::to_string(cache)?).context("Failed to write library cache")?;
+    Ok(())
+}
+
+pub fn load_library_cache() -> Option<LibraryCache> {
+    let data = fs::read_to_string(library_cache_path()).ok()?;
+    serde_json::from_str(&data).ok()
+}
+
+pub fn schedules_path() -> PathBuf {
+    get_profile_data_dir().join("scheduled_launches.json")
+}
+
+/// A queued-up launch the user asked for ("start queueing at 17:45").
+/// Persisted so schedules survive restarts.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ScheduledLaunch {
+    pub game: GameInfo,
+    /// Unix timestamp at which to begin the session request.
+    pub launch_at: i64,
+}
+
+pub fn save_schedules(schedules: &[ScheduledLaunch]) -> Result<()> {
+    let path = schedules_path();
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::write(&path, serde_json::to_string_pretty(schedules)?)
+        .context("Failed to write scheduled launches")?;
+    Ok(())
+}
+
+pub fn load_schedules() -> Vec<ScheduledLaunch> {
+    fs::read_to_string(schedules_path())
+        .ok()
+        .and_then(|data| serde_json::from_str(&data).ok())
+        .unwrap_or_default()
+}
+
+/// Persist the whole provider, not just its code: restoring an
+/// Alliance partner at startup needs the auth/token URLs before the
+/// provider list has been fetched (or when it can't be).
+pub fn save_login_provider(provider: &crate::auth::LoginProvider) -> Result<()> {
+    let path = login_provider_path();
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::write(&path, serde_json::to_string(provider)?)
+        .context("Failed to write login provider cache")?;
+    Ok(())
+}
+
+pub fn load_login_provider() -> Option<crate::auth::LoginProvider> {
+    let data = fs::read_to_string(login_provider_path()).ok()?;
+    match serde_json::from_str(&data) {
+        Ok(provider) => Some(provider),
+        Err(_) => {
+            // Pre-full-persistence files only stored the code; NVIDIA
+            // is reconstructable, partners re-resolve after the fetch.
+            let value: serde_json::Value = serde_json::from_str(&data).ok()?;
+            let code = value.get("code")?.as_str()?;
+            if code == "nvidia" {
+                Some(crate::auth::nvidia_default())
+            } else {
+                log::info!(
+                    "Saved login provider '{}' predates full persistence; \
+                     reselect it once the provider list loads",
+                    code
+                );
+                None
+            }
+        }
+    }
+}
+
+pub fn last_session_summary_path() -> PathBuf {
+    get_profile_data_dir().join("last_session.json")
+}
+
+/// Why the previous stream ended, persisted on abnormal terminations so
+/// the context survives the reflexive relaunch and can go into a bug
+/// report.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StreamEndSummary {
+    /// Unix timestamp of the teardown.
+    pub ended_at: i64,
+    pub game_title: String,
+    /// Streamed time; 0 when the session never reached streaming.
+    pub duration_secs: f32,
+    /// `session_result` classification: "remote" or "error".
+    pub classification: String,
+    pub reason: String,
+    /// The stats as they stood at teardown.
+    pub stats: crate::media::StreamStats,
+}
+
+pub fn save_last_session_summary(summary: &StreamEndSummary) -> Result<()> {
+    let path = last_session_summary_path();
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::write(&path, serde_json::to_string_pretty(summary)?)
+        .context("Failed to write last session summary")?;
+    Ok(())
+}
+
+pub fn load_last_session_summary() -> Option<StreamEndSummary> {
+    let data = fs::read_to_string(last_session_summary_path()).ok()?;
+    serde_json::from_str(&data).ok()
+}
+
+pub fn clear_last_session_summary() {
+    let _ = fs::remove_file(last_session_summary_path());
+}
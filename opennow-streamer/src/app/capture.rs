@@ -0,0 +1,69 @@
+//! Best-effort detection of screen recording/capture, used to offer
+//! hiding the stats overlay so it doesn't end up burned into videos.
+//!
+//! Detection is heuristic and platform-dependent; when the platform
+//! offers no usable signal the answer is simply "not captured" and the
+//! feature stays dormant.
+
+/// True when a capture scenario is currently detected. Cheap enough to
+/// call every few seconds, but not per-frame.
+pub fn capture_active() -> bool {
+    platform_capture_active().unwrap_or(false)
+}
+
+/// Windows exposes no direct "is this window being captured" query, so
+/// scan the process list for well-known capture/recording tools via a
+/// Toolhelp snapshot.
+#[cfg(windows)]
+fn platform_capture_active() -> Option<bool> {
+    use windows_sys::Win32::Foundation::{CloseHandle, INVALID_HANDLE_VALUE};
+    use windows_sys::Win32::System::Diagnostics::ToolHelp::{
+        CreateToolhelp32Snapshot, Process32FirstW, Process32NextW, PROCESSENTRY32W,
+        TH32CS_SNAPPROCESS,
+    };
+
+    const CAPTURE_PROCESSES: &[&str] = &[
+        "obs64.exe",
+        "obs32.exe",
+        "streamlabs obs.exe",
+        "xsplit.core.exe",
+        "bandicam.exe",
+        "sharex.exe",
+    ];
+
+    unsafe {
+        let snapshot = CreateToolhelp32Snapshot(TH32CS_SNAPPROCESS, 0);
+        if snapshot == INVALID_HANDLE_VALUE {
+            return None;
+        }
+        let mut entry: PROCESSENTRY32W = std::mem::zeroed();
+        entry.dwSize = std::mem::size_of::<PROCESSENTRY32W>() as u32;
+        let mut found = false;
+        let mut ok = Process32FirstW(snapshot, &mut entry);
+        while ok != 0 && !found {
+            let len = entry
+                .szExeFile
+                .iter()
+                .position(|&c| c == 0)
+                .unwrap_or(entry.szExeFile.len());
+            let name = String::from_utf16_lossy(&entry.szExeFile[..len]).to_lowercase();
+            found = CAPTURE_PROCESSES.contains(&name.as_str());
+            ok = Process32NextW(snapshot, &mut entry);
+        }
+        CloseHandle(snapshot);
+        Some(found)
+    }
+}
+
+/// macOS flags the main display as captured while a display stream or
+/// full-screen recording is active.
+#[cfg(target_os = "macos")]
+fn platform_capture_active() -> Option<bool> {
+    Some(core_graphics::display::CGDisplay::main().is_captured())
+}
+
+#[cfg(not(any(windows, target_os = "macos")))]
+fn platform_capture_active() -> Option<bool> {
+    // No portable capture signal on Linux/Wayland.
+    None
+}
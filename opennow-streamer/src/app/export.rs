@@ -0,0 +1,97 @@
+//! Library export for external backlog tools: CSV or JSON, picked by
+//! the file extension the user chooses in the save dialog.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+
+use crate::api::GameInfo;
+
+/// Open the native save dialog. Blocking — call from a blocking task,
+/// never the UI thread. Returns None when the user cancels.
+pub fn pick_export_path() -> Option<PathBuf> {
+    rfd::FileDialog::new()
+        .set_title("Export library")
+        .set_file_name("gfn-library.csv")
+        .add_filter("CSV", &["csv"])
+        .add_filter("JSON", &["json"])
+        .save_file()
+}
+
+/// Write the library to `path` as CSV or JSON depending on its
+/// extension (anything that isn't `.json` gets CSV). Returns the number
+/// of games written.
+pub fn export_library(path: &Path, games: &[GameInfo]) -> Result<usize> {
+    let json = path
+        .extension()
+        .map(|ext| ext.eq_ignore_ascii_case("json"))
+        .unwrap_or(false);
+    let data = if json {
+        serde_json::to_string_pretty(games)?
+    } else {
+        render_csv(games)
+    };
+    fs::write(path, data).with_context(|| format!("Failed to write {}", path.display()))?;
+    Ok(games.len())
+}
+
+/// RFC 4180 quoting: fields containing commas, quotes, or newlines are
+/// wrapped in double quotes with embedded quotes doubled. Everything is
+/// UTF-8 throughout, so non-ASCII titles pass through untouched.
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+fn render_csv(games: &[GameInfo]) -> String {
+    let mut out = String::from("id,app_id,title,publisher,store\r\n");
+    for game in games {
+        let app_id = game.launch_app_id.map(|id| id.to_string()).unwrap_or_default();
+        out.push_str(&format!(
+            "{},{},{},{},{}\r\n",
+            csv_field(&game.cms_id),
+            app_id,
+            csv_field(&game.title),
+            csv_field(game.publisher.as_deref().unwrap_or("")),
+            csv_field(game.store.as_deref().unwrap_or("")),
+        ));
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn game(title: &str, publisher: &str) -> GameInfo {
+        GameInfo {
+            cms_id: "g1".to_string(),
+            launch_app_id: Some(42),
+            title: title.to_string(),
+            publisher: Some(publisher.to_string()),
+            image_url: None,
+            store: Some("Steam".to_string()),
+        }
+    }
+
+    #[test]
+    fn csv_quotes_commas_and_embedded_quotes() {
+        let csv = render_csv(&[game("Deus Ex: Mankind \"Divided\"", "Square Enix, Ltd.")]);
+        let row = csv.lines().nth(1).unwrap();
+        assert_eq!(
+            row,
+            "g1,42,\"Deus Ex: Mankind \"\"Divided\"\"\",\"Square Enix, Ltd.\",Steam"
+        );
+    }
+
+    #[test]
+    fn csv_passes_unicode_titles_through() {
+        let csv = render_csv(&[game("ギルティギア", "アークシステムワークス")]);
+        assert!(csv.contains("ギルティギア"));
+        assert!(csv.contains("アークシステムワークス"));
+    }
+}
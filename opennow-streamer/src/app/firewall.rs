@@ -0,0 +1,127 @@
+//! Windows firewall detection for the UDP media path.
+//!
+//! First-run users who dismiss the Defender prompt end up with every
+//! stream dying on ICE timeouts and no hint why. When that combination
+//! occurs we check whether an allow rule exists for this executable and,
+//! if not, offer to register one (elevated netsh, behind a UAC consent
+//! prompt). Everything here is a no-op on non-Windows platforms.
+
+use anyhow::Result;
+
+/// True when the platform firewall is plausibly blocking our UDP
+/// traffic: Windows with no allow rule registered for this executable.
+/// Always false elsewhere. One registry scan, silent and instant when a
+/// rule is already in place.
+pub fn likely_blocking() -> bool {
+    platform_rule_exists().map(|exists| !exists).unwrap_or(false)
+}
+
+/// Register an inbound UDP allow rule for this executable via elevated
+/// netsh. The UAC prompt is the user consent step; declining it fails.
+/// Outbound traffic is allowed by default on Windows, so one inbound
+/// rule is enough.
+pub fn register_rule() -> Result<()> {
+    platform_register_rule()
+}
+
+/// Scan the registered firewall rules for one naming this executable.
+/// Rules live as strings under the FirewallPolicy registry key, each a
+/// `|`-separated list including `App=<path>` and `Action=Allow`.
+#[cfg(windows)]
+fn platform_rule_exists() -> Option<bool> {
+    use windows_sys::Win32::System::Registry::{
+        RegCloseKey, RegEnumValueW, RegOpenKeyExW, HKEY, HKEY_LOCAL_MACHINE, KEY_READ,
+    };
+
+    const RULES_KEY: &str = "SYSTEM\\CurrentControlSet\\Services\\SharedAccess\\Parameters\\FirewallPolicy\\FirewallRules";
+
+    let exe = std::env::current_exe().ok()?;
+    let exe = exe.to_string_lossy().to_lowercase();
+
+    unsafe {
+        let mut key: HKEY = std::ptr::null_mut();
+        let path: Vec<u16> = RULES_KEY.encode_utf16().chain(std::iter::once(0)).collect();
+        if RegOpenKeyExW(HKEY_LOCAL_MACHINE, path.as_ptr(), 0, KEY_READ, &mut key) != 0 {
+            return None;
+        }
+        let mut found = false;
+        let mut index = 0u32;
+        loop {
+            let mut name = [0u16; 256];
+            let mut name_len = name.len() as u32;
+            let mut data = [0u8; 4096];
+            let mut data_len = data.len() as u32;
+            let status = RegEnumValueW(
+                key,
+                index,
+                name.as_mut_ptr(),
+                &mut name_len,
+                std::ptr::null_mut(),
+                std::ptr::null_mut(),
+                data.as_mut_ptr(),
+                &mut data_len,
+            );
+            if status != 0 {
+                break;
+            }
+            index += 1;
+            let rule = String::from_utf16_lossy(std::slice::from_raw_parts(
+                data.as_ptr() as *const u16,
+                (data_len as usize / 2).min(data.len() / 2),
+            ))
+            .to_lowercase();
+            if rule.contains("action=allow") && rule.contains(&format!("app={}", exe)) {
+                found = true;
+                break;
+            }
+        }
+        RegCloseKey(key);
+        Some(found)
+    }
+}
+
+#[cfg(windows)]
+fn platform_register_rule() -> Result<()> {
+    use windows_sys::Win32::UI::Shell::ShellExecuteW;
+    use windows_sys::Win32::UI::WindowsAndMessaging::SW_HIDE;
+
+    use anyhow::{anyhow, Context};
+
+    let exe = std::env::current_exe().context("Could not determine the executable path")?;
+    let args = format!(
+        "advfirewall firewall add rule name=\"OpenNOW Streamer\" dir=in action=allow \
+         program=\"{}\" protocol=udp enable=yes",
+        exe.display()
+    );
+    let to_wide = |s: &str| -> Vec<u16> { s.encode_utf16().chain(std::iter::once(0)).collect() };
+    let verb = to_wide("runas");
+    let netsh = to_wide("netsh");
+    let args = to_wide(&args);
+    let result = unsafe {
+        ShellExecuteW(
+            std::ptr::null_mut(),
+            verb.as_ptr(),
+            netsh.as_ptr(),
+            args.as_ptr(),
+            std::ptr::null(),
+            SW_HIDE,
+        )
+    };
+    // ShellExecuteW reports success as a value above 32; the common
+    // failure here is the user declining the UAC prompt.
+    if result as isize > 32 {
+        Ok(())
+    } else {
+        Err(anyhow!("Elevation was declined or netsh could not start"))
+    }
+}
+
+#[cfg(not(windows))]
+fn platform_rule_exists() -> Option<bool> {
+    None
+}
+
+#[cfg(not(windows))]
+fn platform_register_rule() -> Result<()> {
+    anyhow::bail!("Firewall registration is only needed on Windows")
+}
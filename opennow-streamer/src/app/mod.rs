@@ -0,0 +1,3236 @@
+//! Application state and the per-frame `update` that drains async
+//! results into it.
+
+pub mod cache;
+pub mod capture;
+pub mod export;
+pub mod firewall;
+pub mod notifications;
+
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use tokio::sync::mpsc::{self, UnboundedReceiver, UnboundedSender};
+
+use crate::api::cloudmatch::{QueueEstimate, SessionInfo, SessionState, SetupProgress};
+use crate::api::serverinfo::{self, ServerInfo};
+use crate::api::{CatalogLoad, GameDetails, GameInfo, GfnApiClient, SubscriptionInfo, UserInfo};
+use crate::auth::{self, AuthTokens, LoginProvider};
+use crate::input::controller::ControllerManager;
+use crate::input::{InputEvent, RumbleCommand};
+use crate::media::history::FrameHistory;
+use crate::media::{SharedFrame, StreamStats};
+use crate::settings::{InputProfile, SaveDebouncer, Settings};
+
+/// How long fetched game details (and their notices) stay fresh before
+/// re-opening the popup refetches them.
+const DETAILS_TTL: Duration = Duration::from_secs(300);
+/// Queue estimates go stale fast, but refetching on every popup open
+/// would hammer the endpoint.
+const QUEUE_ESTIMATE_TTL: Duration = Duration::from_secs(120);
+
+/// Poll cadence of the session polling task.
+const SESSION_POLL_INTERVAL: Duration = Duration::from_secs(2);
+
+/// Total zones a failover launch may try.
+const MAX_FAILOVER_ATTEMPTS: usize = 3;
+
+/// Hard cap on the pre-launch freshness re-ping; whatever answered by
+/// then is all the check gets to work with.
+const PRELAUNCH_PING_BUDGET: Duration = Duration::from_secs(1);
+
+/// After this long with no game-setup progress we offer to cancel.
+const SETUP_STALL_TIMEOUT: Duration = Duration::from_secs(600);
+
+/// Background connectivity probe cadence while offline.
+const OFFLINE_RETRY_INTERVAL: Duration = Duration::from_secs(30);
+
+/// How often to re-check whether a screen capture tool is active.
+const CAPTURE_POLL_INTERVAL: Duration = Duration::from_secs(5);
+
+/// A scheduled launch missed by more than this (machine asleep, app
+/// closed) is dropped instead of fired late.
+const SCHEDULE_GRACE: Duration = Duration::from_secs(30 * 60);
+
+/// A scheduled session that reached readiness is cancelled after this
+/// long without user interaction, to avoid burning session hours.
+const SCHEDULE_IDLE_CANCEL: Duration = Duration::from_secs(10 * 60);
+
+/// How long the AFK warning stays up before the stream is stopped.
+const AFK_WARNING_GRACE: Duration = Duration::from_secs(60);
+
+/// Delay before the first reconnect attempt; each further attempt
+/// waits one step longer.
+const RECONNECT_BACKOFF_STEP: Duration = Duration::from_secs(2);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AppState {
+    Login,
+    Games,
+    Session,
+    Streaming,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GamesTab {
+    AllGames,
+    Library,
+}
+
+/// Results of async work, consumed by `App::update` each frame.
+pub enum AppEvent {
+    LoggedIn(anyhow::Result<AuthTokens>),
+    /// Background token refresh finished; `App` applies the result so it
+    /// stays the only writer of in-memory and on-disk token state.
+    TokensRefreshed(anyhow::Result<AuthTokens>),
+    ProvidersLoaded(anyhow::Result<Vec<LoginProvider>>),
+    GamesLoaded(anyhow::Result<CatalogLoad>),
+    LibraryLoaded(anyhow::Result<Vec<GameInfo>>),
+    /// Incremental progress of a paginated library sync.
+    LibrarySyncProgress { loaded: usize, total: Option<usize> },
+    /// Library export finished (None = the user cancelled the dialog).
+    LibraryExported(Option<anyhow::Result<(usize, std::path::PathBuf)>>),
+    DetailsLoaded(String, anyhow::Result<GameDetails>),
+    /// One part of the account snapshot finished its retries (None:
+    /// every attempt failed).
+    AccountUserLoaded(Option<UserInfo>),
+    AccountSubscriptionLoaded(Option<SubscriptionInfo>),
+    ServersLoaded(Vec<ServerInfo>),
+    /// The launch task is about to try this zone.
+    LaunchAttempt {
+        zone_name: String,
+        zone_address: String,
+    },
+    /// Transient status line for the session screen.
+    LaunchStatus(String),
+    SessionCreated(anyhow::Result<SessionInfo>),
+    /// Periodic update from the session polling task. `seq` increases
+    /// monotonically so a stale response can never regress the displayed
+    /// state.
+    SessionUpdate {
+        seq: u64,
+        result: anyhow::Result<SessionInfo>,
+    },
+    /// Session racing started; one entry per competing zone, in lane
+    /// order.
+    RaceStarted(Vec<String>),
+    /// Progress for one racing lane.
+    RaceLaneUpdate {
+        lane: usize,
+        status: String,
+        finished: bool,
+    },
+    /// A losing racer's session survived every cancel retry — the user
+    /// must know a duplicate session may be burning hours.
+    RaceCancelFailed { zone_name: String, error: String },
+    /// A concurrent create was rejected by the account's one-session
+    /// limit; racing is disabled for the rest of the run.
+    RacingUnsupported,
+    /// The pre-launch freshness re-ping finished. `zones` is the
+    /// re-pinged list (the selected zone measured twice, best sample
+    /// kept), or None when the time budget lapsed.
+    ZoneFreshness {
+        game: GameInfo,
+        zones: Option<Vec<ServerInfo>>,
+    },
+    /// Queue estimate fetch finished for a (zone, tier) pair.
+    QueueEstimateLoaded {
+        zone: String,
+        tier: String,
+        result: anyhow::Result<QueueEstimate>,
+    },
+    /// `run_streaming` returned an error (as opposed to a clean stop).
+    StreamingFailed(String),
+    /// Startup check of the session recovery cache finished: a verified
+    /// still-running session to offer on the Games screen, or None.
+    ResumeSessionChecked(Option<ResumeCandidate>),
+    /// Frame-history contact sheet export finished.
+    ContactSheetSaved(anyhow::Result<std::path::PathBuf>),
+    /// F12 screenshot write finished.
+    ScreenshotSaved(anyhow::Result<std::path::PathBuf>),
+}
+
+/// A still-running session found in the recovery cache at startup and
+/// verified against its zone; offered as a "Resume" banner on the
+/// Games screen.
+#[derive(Debug, Clone)]
+pub struct ResumeCandidate {
+    pub session: SessionInfo,
+    pub game_id: String,
+    /// Zone API address the session lives in; becomes `active_zone` on
+    /// resume so teardown can terminate the session.
+    pub zone: String,
+}
+
+/// One zone's attempt in a session race, rendered side by side on the
+/// session screen.
+#[derive(Debug, Clone)]
+pub struct RaceLane {
+    pub zone_name: String,
+    pub status: String,
+    pub finished: bool,
+}
+
+/// A pre-launch re-ping found the selected zone degraded with a faster
+/// alternative on offer; the launch is parked until the user answers.
+#[derive(Debug, Clone)]
+pub struct ZoneSwitchPrompt {
+    pub game: GameInfo,
+    pub current_name: String,
+    /// Fresh ping of the selected zone; None when it stopped answering.
+    pub current_ping_ms: Option<u32>,
+    pub better: ServerInfo,
+}
+
+/// One independently-fetched piece of the account snapshot. `Ready`
+/// survives later fetch failures: stale data shown with an age hint
+/// beats snapping back to "FREE"/"User" defaults.
+#[derive(Debug, Clone)]
+pub enum AccountPart<T> {
+    /// No data yet — a fetch (with retries) is still running.
+    Loading,
+    /// Every retry failed and nothing is cached; the header shows an
+    /// explicit error state with a manual retry.
+    Failed,
+    Ready { value: T, fetched_at: Instant },
+}
+
+impl<T> AccountPart<T> {
+    /// The cached value, fresh or stale.
+    pub fn value(&self) -> Option<&T> {
+        match self {
+            AccountPart::Ready { value, .. } => Some(value),
+            _ => None,
+        }
+    }
+
+    /// Age of the cached value, for the staleness hint.
+    pub fn age(&self) -> Option<Duration> {
+        match self {
+            AccountPart::Ready { fetched_at, .. } => Some(fetched_at.elapsed()),
+            _ => None,
+        }
+    }
+
+    fn merge(&mut self, fresh: Option<T>) {
+        match fresh {
+            Some(value) => {
+                *self = AccountPart::Ready {
+                    value,
+                    fetched_at: Instant::now(),
+                };
+            }
+            // Keep displaying a cached value through a failed refresh;
+            // only a part that never loaded becomes Failed.
+            None => {
+                if self.value().is_none() {
+                    *self = AccountPart::Failed;
+                }
+            }
+        }
+    }
+}
+
+/// Post-login account data — user info plus subscription (which carries
+/// the entitlements) — fetched concurrently with independent retries
+/// and merged as results arrive.
+#[derive(Debug, Clone)]
+pub struct AccountSnapshot {
+    pub user: AccountPart<UserInfo>,
+    pub subscription: AccountPart<SubscriptionInfo>,
+    /// Bumped on every merge, so consumers can tell whether anything
+    /// changed since they last looked.
+    pub version: u64,
+}
+
+impl Default for AccountSnapshot {
+    fn default() -> Self {
+        Self {
+            user: AccountPart::Loading,
+            subscription: AccountPart::Loading,
+            version: 0,
+        }
+    }
+}
+
+impl AccountSnapshot {
+    pub fn merge_user(&mut self, fresh: Option<UserInfo>) {
+        self.user.merge(fresh);
+        self.version += 1;
+    }
+
+    pub fn merge_subscription(&mut self, fresh: Option<SubscriptionInfo>) {
+        self.subscription.merge(fresh);
+        self.version += 1;
+    }
+
+    /// "updated 2h ago" once the cached data is old enough that it may
+    /// no longer reflect reality (refreshes failing, long sessions).
+    /// None while everything is reasonably fresh.
+    pub fn staleness_hint(&self) -> Option<String> {
+        let age = [self.user.age(), self.subscription.age()]
+            .into_iter()
+            .flatten()
+            .max()?;
+        if age < Duration::from_secs(15 * 60) {
+            return None;
+        }
+        let secs = age.as_secs();
+        Some(if secs >= 3600 {
+            format!("updated {}h ago", secs / 3600)
+        } else {
+            format!("updated {}m ago", secs / 60)
+        })
+    }
+}
+
+/// Run one account fetch with up to three attempts and short growing
+/// pauses, enough to ride out transient network flake without parking
+/// a header widget in its loading state for long.
+async fn fetch_account_part<T, F, Fut>(what: &'static str, fetch: F) -> Option<T>
+where
+    F: Fn() -> Fut,
+    Fut: std::future::Future<Output = anyhow::Result<T>>,
+{
+    const ATTEMPTS: u32 = 3;
+    for attempt in 1..=ATTEMPTS {
+        match fetch().await {
+            Ok(value) => return Some(value),
+            Err(e) => log::warn!("{} fetch attempt {}/{} failed: {}", what, attempt, ATTEMPTS, e),
+        }
+        if attempt < ATTEMPTS {
+            tokio::time::sleep(Duration::from_secs(1 << (attempt - 1))).await;
+        }
+    }
+    None
+}
+
+pub struct App {
+    pub state: AppState,
+    pub tab: GamesTab,
+    pub settings: Settings,
+    settings_saver: SaveDebouncer,
+    pub auth_tokens: Option<AuthTokens>,
+    pub api_client: Option<Arc<GfnApiClient>>,
+    /// Saved account profiles; each keeps its own tokens, login
+    /// provider, and caches under `cache::get_profile_data_dir()`.
+    pub profiles: Vec<String>,
+    /// The profile currently loaded (mirrors `cache::active_profile`).
+    pub active_profile: String,
+    /// Buffer for the "new profile" field in the account menu.
+    pub profile_name_input: String,
+    pub login_providers: Vec<LoginProvider>,
+    pub selected_provider_index: usize,
+    pub login_in_progress: bool,
+    /// Read-only cached mode entered on connectivity failures (never on
+    /// genuine auth rejections).
+    pub offline: bool,
+    offline_retry_in_flight: bool,
+    offline_last_retry: Option<Instant>,
+    /// A background token refresh is in flight.
+    refresh_in_progress: bool,
+    /// Bind the OAuth callback on all interfaces so the login can be
+    /// completed from another device (remote/headless setups).
+    pub login_bind_all: bool,
+    /// The in-flight login attempt; drives the QR code and the manual
+    /// redirect-URL fallback on the login screen.
+    pub pending_login: Option<auth::PendingLogin>,
+    pub manual_redirect_input: String,
+    /// Pasted refresh token / auth.json contents for the advanced login
+    /// path (SSH-only boxes, broken OAuth regions).
+    pub manual_token_input: String,
+    pub token_import_in_progress: bool,
+    pub account: AccountSnapshot,
+    pub games: Vec<GameInfo>,
+    pub library: Vec<GameInfo>,
+    /// (loaded, total) while a paginated library sync runs.
+    pub library_sync_progress: Option<(usize, Option<usize>)>,
+    /// An export dialog/write is running off-thread.
+    pub library_export_in_progress: bool,
+    /// Latest result of the periodic capture-tool check.
+    pub capture_active: bool,
+    capture_last_poll: Option<Instant>,
+    /// The one-time "Recording detected" prompt is on screen.
+    pub show_capture_prompt: bool,
+    /// The one-time "4K at 100% scale" UI-scale offer is on screen.
+    pub show_ui_scale_prompt: bool,
+    /// State of the prompt's "remember my choice" checkbox.
+    pub capture_prompt_remember: bool,
+    /// Overlay hidden because of capture; cleared (and the overlay
+    /// restored) when capture ends.
+    pub overlay_suppressed_by_capture: bool,
+    /// Pending scheduled launches, soonest first.
+    pub scheduled_launches: Vec<cache::ScheduledLaunch>,
+    /// The current session was started by a schedule; drives the
+    /// ready notification and the idle auto-cancel.
+    scheduled_session: bool,
+    /// When a scheduled session gets cancelled unless the user interacts.
+    schedule_cancel_deadline: Option<Instant>,
+    /// Transient UI state for the schedule picker in the game popup.
+    pub schedule_time_input: String,
+    pub schedule_tomorrow: bool,
+    pub servers: Vec<ServerInfo>,
+    pub search_query: String,
+    /// Game whose detail popup is open.
+    pub selected_game: Option<GameInfo>,
+    pub game_details: Option<GameDetails>,
+    details_cache: HashMap<String, (Instant, GameDetails)>,
+    /// Experimental LAN co-watch endpoint; Some while a stream runs
+    /// with spectating enabled.
+    pub spectate: Option<crate::media::spectate::SpectateServer>,
+    /// Active session race, one lane per competing zone; None outside
+    /// racing launches.
+    pub race_lanes: Option<Vec<RaceLane>>,
+    /// The account's one-session limit rejected a concurrent create;
+    /// don't try racing again this run.
+    racing_unsupported: bool,
+    /// Pending pre-launch zone-switch prompt; the launch waits on the
+    /// answer (or on the session screen's Cancel).
+    pub zone_switch_prompt: Option<ZoneSwitchPrompt>,
+    /// State of that prompt's "remember my choice" checkbox.
+    pub zone_switch_remember: bool,
+    /// Queue estimates keyed by (zone address, tier), expiring after
+    /// `QUEUE_ESTIMATE_TTL`.
+    queue_estimates: HashMap<(String, String), (Instant, QueueEstimate)>,
+    /// An estimate fetch is in flight (dedupes popup opens + refresh).
+    queue_estimate_pending: bool,
+    /// When the current launch entered the queue, and the estimate on
+    /// screen at that moment — compared once the rig is ready.
+    queue_started_at: Option<Instant>,
+    queue_estimate_secs: Option<u32>,
+    /// A dialog just opened; its primary action should grab keyboard
+    /// focus on the next frame (screen readers announce focused widgets).
+    focus_primary_action: bool,
+    /// Set when an error-severity notice requires explicit acknowledgment
+    /// before the Launch button proceeds.
+    pub launch_ack_required: bool,
+    /// The user confirmed launching despite being under the configured
+    /// low-hours threshold. Reset when the popup closes.
+    pub low_hours_ack: bool,
+    pub show_settings: bool,
+    /// The F1 hotkey cheat sheet is on screen. Shown automatically once
+    /// after install (`Settings::help_overlay_seen`), on demand after.
+    pub show_help_overlay: bool,
+    /// The F2 quick menu (input profile switcher) is on screen.
+    pub show_quick_menu: bool,
+    /// The effective input profile changed; consumed by the frame loop
+    /// to reconfigure the input handler and capture state.
+    input_profile_dirty: bool,
+    /// Window position of the local-cursor preview, published by the
+    /// frame loop while the active profile draws one.
+    pub local_cursor_pos: Option<(f32, f32)>,
+    /// Monitor names published by the frame loop while the settings
+    /// modal is open, for the fullscreen monitor picker.
+    pub available_monitors: Vec<String>,
+    /// A/V sync test is running: once a second a click is queued and a
+    /// box flashes in the streaming view, so the audio delay slider can
+    /// be tuned until they line up.
+    pub av_sync_test: bool,
+    av_sync_last_click: Option<Instant>,
+    /// Windows-only helper shown when a stream died on an ICE timeout
+    /// and no firewall rule exists for this executable.
+    pub show_firewall_help: bool,
+    /// Last real user input (key, mouse button/move, controller). The
+    /// AFK guard measures idle time from here; the client's own
+    /// heartbeat and keep-alive traffic never touches it.
+    last_user_input: Instant,
+    /// When set, the "Still here?" warning is up and the stream stops at
+    /// this instant unless the user acknowledges.
+    pub afk_warning_deadline: Option<Instant>,
+    /// Critical error pinned inline on the current screen (session
+    /// creation failures and the like); transient errors go through
+    /// `notifications` instead.
+    pub error_message: Option<String>,
+    pub status_message: Option<String>,
+    pub notifications: notifications::Notifications,
+    pub show_notification_history: bool,
+    pub session: Option<SessionInfo>,
+    pub session_status_text: String,
+    /// Zone names tried for the current launch, in order; shown in the
+    /// session details for transparency when failover kicks in.
+    pub launch_attempts: Vec<String>,
+    /// Address of the zone the current session actually lives in (may
+    /// differ from the saved preference after failover).
+    active_zone: Option<String>,
+    /// Game setup (install/shader compile) progress while the rig is
+    /// Ready but the title isn't up yet.
+    pub setup_progress: Option<SetupProgress>,
+    /// When the setup progress value last changed.
+    setup_last_change: Option<Instant>,
+    /// Set when setup made no progress for `SETUP_STALL_TIMEOUT`.
+    pub setup_stalled: bool,
+    /// The user chose to watch the setup on the stream instead of the
+    /// session screen.
+    pub setup_watch_requested: bool,
+    /// True while `run_streaming` is alive (possibly before the UI has
+    /// switched to the streaming view).
+    pipeline_active: bool,
+    /// Reconnect attempts made since the connection last dropped; reset
+    /// once frames flow again. While non-zero the streaming view shows
+    /// the "Reconnecting…" overlay instead of tearing down.
+    reconnect_attempts: u32,
+    /// When the next reconnect attempt fires (backoff timer).
+    reconnect_at: Option<Instant>,
+    /// Title of the game being launched/streamed, kept for the stream-end
+    /// summary (the detail popup is long closed by teardown).
+    streaming_game_title: Option<String>,
+    /// When the pipeline was spawned; the end summary's duration.
+    stream_started_at: Option<Instant>,
+    /// Subscription balance when the pipeline was spawned; the session
+    /// countdown ticks down from here. None on unlimited plans.
+    session_remaining_at_start: Option<Duration>,
+    /// Countdown marks (minutes remaining, descending) that have not
+    /// fired yet this stream.
+    session_warnings_pending: Vec<u32>,
+    /// An abnormal end was already persisted for the current run, so the
+    /// user-stop path in `stop_streaming` must not clear the card.
+    stream_end_recorded: bool,
+    /// Persisted summary of the previous abnormal stream end; renders as
+    /// a dismissible card on the Games screen.
+    pub last_session_summary: Option<cache::StreamEndSummary>,
+    /// Verified still-running session from the recovery cache; renders
+    /// as a "Resume" banner on the Games screen until taken or
+    /// dismissed.
+    pub resume_session: Option<ResumeCandidate>,
+    pub current_frame: SharedFrame,
+    pub stream_stats: Arc<Mutex<StreamStats>>,
+    /// Rolling thumbnail ring for the F4 review overlay. Survives the
+    /// end of a stream so a crash can still be reviewed.
+    pub frame_history: Arc<Mutex<FrameHistory>>,
+    /// The F4 frame-history strip is on screen.
+    pub show_frame_history: bool,
+    /// Scrub position in the strip (index into the ring, oldest first).
+    pub frame_history_index: usize,
+    pub connection_info: Arc<Mutex<crate::webrtc::ConnectionInfo>>,
+    pub input_event_tx: Option<UnboundedSender<InputEvent>>,
+    /// Haptic commands coming back from the stream task, drained each
+    /// frame on this thread because gilrs owns the pads here.
+    rumble_rx: Option<UnboundedReceiver<RumbleCommand>>,
+    /// Latest window size not yet forwarded to the server.
+    viewport_pending: Option<(u32, u32)>,
+    /// Viewport the server last heard about.
+    viewport_sent: Option<(u32, u32)>,
+    viewport_last_sent: Option<Instant>,
+    /// Gamepad manager; polled by the settings visualizer for tuning.
+    pub controller: Option<ControllerManager>,
+    /// Identity of the pad whose tuning profile is currently loaded
+    /// into the manager.
+    controller_profile_loaded: Option<String>,
+    stream_stop: Arc<AtomicBool>,
+    runtime: tokio::runtime::Handle,
+    events_tx: UnboundedSender<AppEvent>,
+    events_rx: UnboundedReceiver<AppEvent>,
+    /// Long-lived polling task for the current launch attempt.
+    session_poll_task: Option<tokio::task::JoinHandle<()>>,
+    /// The `run_streaming` task, kept so shutdown can wait (bounded)
+    /// for it to observe the stop flag.
+    stream_task: Option<tokio::task::JoinHandle<()>>,
+    /// Sequence number of the last applied session update.
+    last_session_seq: u64,
+}
+
+impl App {
+    pub fn new(runtime: tokio::runtime::Handle) -> Self {
+        let (events_tx, events_rx) = mpsc::unbounded_channel();
+        let settings = Settings::load();
+        crate::api::recording::set_enabled(settings.api_recording);
+        auth::set_secure_storage(settings.use_secure_token_storage);
+        // The profile namespace must be active before tokens or any
+        // account-scoped cache are read below.
+        let profiles = cache::load_profiles();
+        cache::set_active_profile(&profiles.active);
+        // Restore the saved login provider before any API traffic so
+        // Alliance users come back up against their partner, not NVIDIA.
+        let saved_provider = cache::load_login_provider();
+        if let Some(provider) = &saved_provider {
+            auth::set_login_provider(provider);
+        }
+        // Seed the list with the restored provider so the login screen
+        // shows it pre-selected even before (or without) the fetch.
+        let login_providers = match saved_provider {
+            Some(provider) if provider.code != "nvidia" => {
+                vec![auth::nvidia_default(), provider]
+            }
+            _ => vec![auth::nvidia_default()],
+        };
+        let selected_provider_index = login_providers.len() - 1;
+        let auth_tokens = auth::load_tokens();
+        let state = if auth_tokens.is_some() {
+            AppState::Games
+        } else {
+            AppState::Login
+        };
+        let mut app = Self {
+            state,
+            tab: GamesTab::AllGames,
+            settings,
+            settings_saver: SaveDebouncer::default(),
+            api_client: auth_tokens.as_ref().map(|t| Arc::new(GfnApiClient::new(t))),
+            auth_tokens,
+            active_profile: profiles.active,
+            profiles: profiles.profiles,
+            profile_name_input: String::new(),
+            login_providers,
+            selected_provider_index,
+            login_in_progress: false,
+            offline: false,
+            offline_retry_in_flight: false,
+            offline_last_retry: None,
+            refresh_in_progress: false,
+            login_bind_all: false,
+            pending_login: None,
+            manual_redirect_input: String::new(),
+            manual_token_input: String::new(),
+            token_import_in_progress: false,
+            account: AccountSnapshot::default(),
+            games: cache::load_games_cache().unwrap_or_default(),
+            library: cache::load_library_cache()
+                .filter(|c| c.complete)
+                .map(|c| c.games)
+                .unwrap_or_default(),
+            library_sync_progress: None,
+            library_export_in_progress: false,
+            capture_active: false,
+            capture_last_poll: None,
+            show_capture_prompt: false,
+            show_ui_scale_prompt: false,
+            capture_prompt_remember: false,
+            overlay_suppressed_by_capture: false,
+            scheduled_launches: cache::load_schedules(),
+            scheduled_session: false,
+            schedule_cancel_deadline: None,
+            schedule_time_input: String::new(),
+            schedule_tomorrow: false,
+            servers: Vec::new(),
+            search_query: String::new(),
+            selected_game: None,
+            game_details: None,
+            details_cache: HashMap::new(),
+            spectate: None,
+            race_lanes: None,
+            racing_unsupported: false,
+            zone_switch_prompt: None,
+            zone_switch_remember: false,
+            queue_estimates: HashMap::new(),
+            queue_estimate_pending: false,
+            queue_started_at: None,
+            queue_estimate_secs: None,
+            focus_primary_action: false,
+            launch_ack_required: false,
+            low_hours_ack: false,
+            show_settings: false,
+            show_help_overlay: false,
+            show_quick_menu: false,
+            input_profile_dirty: false,
+            local_cursor_pos: None,
+            available_monitors: Vec::new(),
+            av_sync_test: false,
+            av_sync_last_click: None,
+            show_firewall_help: false,
+            last_user_input: Instant::now(),
+            afk_warning_deadline: None,
+            error_message: None,
+            status_message: None,
+            notifications: notifications::Notifications::default(),
+            show_notification_history: false,
+            session: None,
+            session_status_text: String::new(),
+            launch_attempts: Vec::new(),
+            active_zone: None,
+            setup_progress: None,
+            setup_last_change: None,
+            setup_stalled: false,
+            setup_watch_requested: false,
+            pipeline_active: false,
+            reconnect_attempts: 0,
+            reconnect_at: None,
+            streaming_game_title: None,
+            stream_started_at: None,
+            session_remaining_at_start: None,
+            session_warnings_pending: Vec::new(),
+            stream_end_recorded: false,
+            last_session_summary: cache::load_last_session_summary(),
+            resume_session: None,
+            current_frame: SharedFrame::new(),
+            stream_stats: Arc::new(Mutex::new(StreamStats::default())),
+            frame_history: Arc::new(Mutex::new(FrameHistory::new())),
+            show_frame_history: false,
+            frame_history_index: 0,
+            connection_info: Arc::new(Mutex::new(crate::webrtc::ConnectionInfo::default())),
+            input_event_tx: None,
+            rumble_rx: None,
+            viewport_pending: None,
+            viewport_sent: None,
+            viewport_last_sent: None,
+            controller: ControllerManager::new(),
+            controller_profile_loaded: None,
+            stream_stop: Arc::new(AtomicBool::new(false)),
+            runtime,
+            events_tx,
+            events_rx,
+            session_poll_task: None,
+            stream_task: None,
+            last_session_seq: 0,
+        };
+        // First run: open the cheat sheet with the getting-started section
+        // once; after that it only appears on F1.
+        app.show_help_overlay = !app.settings.help_overlay_seen;
+        app.fetch_login_providers();
+        if app.state == AppState::Games {
+            app.post_login_fetches();
+        }
+        app
+    }
+
+    pub fn toggle_quick_menu(&mut self) {
+        self.show_quick_menu = !self.show_quick_menu;
+    }
+
+    /// Open the settings modal, refreshing the zone list (and the rig
+    /// availability that rides along with it) when the cached copy has
+    /// gone stale.
+    pub fn open_settings(&mut self) {
+        self.show_settings = true;
+        self.load_servers();
+    }
+
+    /// Flip stream mute (Ctrl+Shift+M); the playback thread picks the
+    /// gain up on its next sample batch.
+    pub fn toggle_mute(&mut self) {
+        self.settings.muted = !self.settings.muted;
+        crate::media::audio::set_playback_volume(self.settings.volume, self.settings.muted);
+        self.settings_changed();
+        self.notify_info(if self.settings.muted {
+            "Audio muted"
+        } else {
+            "Audio unmuted"
+        });
+    }
+
+    /// Name of the profile in effect: the streamed game's override when
+    /// one is set, otherwise the global choice.
+    pub fn active_input_profile_name(&self) -> String {
+        self.session
+            .as_ref()
+            .and_then(|s| self.settings.game_input_profiles.get(&s.game_id).cloned())
+            .unwrap_or_else(|| self.settings.active_input_profile.clone())
+    }
+
+    pub fn active_input_profile(&self) -> InputProfile {
+        self.settings.input_profile(&self.active_input_profile_name())
+    }
+
+    /// Switch profiles from the quick menu. Mid-session the choice is
+    /// remembered as this game's override; outside a session it becomes
+    /// the global default — so a game's override never silently shadows
+    /// what was just picked.
+    pub fn select_input_profile(&mut self, name: &str) {
+        match &self.session {
+            Some(session) => {
+                self.settings
+                    .game_input_profiles
+                    .insert(session.game_id.clone(), name.to_string());
+            }
+            None => self.settings.active_input_profile = name.to_string(),
+        }
+        self.settings_changed();
+        self.input_profile_dirty = true;
+    }
+
+    /// The active profile's definition was edited in place; reapply it.
+    pub fn note_input_profile_edited(&mut self) {
+        self.settings_changed();
+        self.input_profile_dirty = true;
+    }
+
+    /// True once after the effective profile changed; the frame loop
+    /// releases held keys, reconfigures the handler and re-captures on
+    /// it.
+    pub fn take_input_profile_change(&mut self) -> bool {
+        std::mem::take(&mut self.input_profile_dirty)
+    }
+
+    /// True once after a dialog opened; the dialog moves keyboard focus
+    /// to its primary action so the screen reader announces it.
+    pub fn take_primary_focus_request(&mut self) -> bool {
+        std::mem::take(&mut self.focus_primary_action)
+    }
+
+    pub fn toggle_help_overlay(&mut self) {
+        if self.show_help_overlay {
+            self.dismiss_help_overlay();
+        } else {
+            self.show_help_overlay = true;
+        }
+    }
+
+    /// Drive the A/V sync test: queue a click once a second and report
+    /// whether the flash box should be lit (the first ~120ms of each
+    /// cycle). Called from the streaming view each frame.
+    pub fn av_sync_flash(&mut self) -> bool {
+        if !self.av_sync_test {
+            self.av_sync_last_click = None;
+            return false;
+        }
+        let now = Instant::now();
+        if self
+            .av_sync_last_click
+            .is_none_or(|at| now.duration_since(at) >= Duration::from_secs(1))
+        {
+            crate::media::audio::request_test_click();
+            self.av_sync_last_click = Some(now);
+        }
+        self.av_sync_last_click
+            .is_some_and(|at| now.duration_since(at) < Duration::from_millis(120))
+    }
+
+    pub fn toggle_frame_history(&mut self) {
+        self.show_frame_history = !self.show_frame_history;
+        if self.show_frame_history {
+            // Land on the newest thumbnail when opening.
+            self.frame_history_index = self
+                .frame_history
+                .lock()
+                .unwrap()
+                .thumbs()
+                .len()
+                .saturating_sub(1);
+        }
+    }
+
+    /// Save the frame-history contact sheet next to the other exports.
+    /// Runs the dialog and the PNG write on a blocking task.
+    pub fn save_frame_history_sheet(&mut self) {
+        let history = self.frame_history.clone();
+        let tx = self.events_tx.clone();
+        self.runtime.spawn(async move {
+            tokio::task::spawn_blocking(move || {
+                let Some(path) = rfd::FileDialog::new()
+                    .set_title("Save contact sheet")
+                    .set_file_name("frame-history.png")
+                    .add_filter("PNG", &["png"])
+                    .save_file()
+                else {
+                    return;
+                };
+                let result = history.lock().unwrap().save_contact_sheet(&path);
+                let _ = tx.send(AppEvent::ContactSheetSaved(result.map(|()| path)));
+            })
+            .await
+            .ok();
+        });
+    }
+
+    /// F12: write the currently displayed frame as a timestamped PNG in
+    /// the Pictures directory. The conversion matches what the renderer
+    /// shows (same color space/range handling) and runs off the UI
+    /// thread; without a decoded frame yet this is a quiet no-op toast.
+    pub fn save_screenshot(&mut self) {
+        let Some(frame) = self.current_frame.peek() else {
+            self.notify_info("No frame to capture yet");
+            return;
+        };
+        let dir = dirs::picture_dir().unwrap_or_else(cache::get_app_data_dir);
+        let path = dir.join(format!(
+            "OpenNOW {}.png",
+            chrono::Local::now().format("%Y-%m-%d %H%M%S")
+        ));
+        let tx = self.events_tx.clone();
+        self.runtime.spawn(async move {
+            tokio::task::spawn_blocking(move || {
+                let result = std::fs::create_dir_all(&dir)
+                    .map_err(anyhow::Error::from)
+                    .and_then(|()| {
+                        let rgba = crate::gui::renderer::yuv_to_rgba(&frame);
+                        crate::media::history::write_png(
+                            &path,
+                            frame.width,
+                            frame.height,
+                            &rgba,
+                        )
+                    });
+                let _ = tx.send(AppEvent::ScreenshotSaved(result.map(|()| path)));
+            })
+            .await
+            .ok();
+        });
+    }
+
+    /// Register the firewall allow rule from the help dialog. Blocks
+    /// only for the UAC prompt, which is modal anyway.
+    pub fn add_firewall_rule(&mut self) {
+        match firewall::register_rule() {
+            Ok(()) => {
+                self.show_firewall_help = false;
+                self.notify_success("Firewall rule added — try streaming again");
+            }
+            Err(e) => self.notify_error(format!("Could not add the firewall rule: {}", e)),
+        }
+    }
+
+    pub fn dismiss_help_overlay(&mut self) {
+        self.show_help_overlay = false;
+        if !self.settings.help_overlay_seen {
+            self.settings.help_overlay_seen = true;
+            self.settings_changed();
+        }
+    }
+
+    /// Mark the settings dirty; the change applies in memory immediately
+    /// and hits disk once the debounce window passes (a slider drag is
+    /// one write, not hundreds).
+    pub fn settings_changed(&mut self) {
+        // Recording follows the dev toggle immediately, not the
+        // debounced save.
+        crate::api::recording::set_enabled(self.settings.api_recording);
+        self.settings_saver.note_change();
+    }
+
+    /// Write pending settings changes now, ignoring the debounce — used
+    /// on exit and before handing the event loop over to streaming.
+    pub fn flush_settings(&mut self) {
+        if self.settings_saver.take_dirty() {
+            if let Err(e) = self.settings.save() {
+                log::error!("Failed to save settings: {}", e);
+            }
+        }
+    }
+
+    /// Wait (bounded) for the streaming runner to finish after the stop
+    /// flag is raised. Only used during shutdown — everywhere else the
+    /// runner winds down asynchronously.
+    pub fn wait_for_stream_end(&mut self, timeout: Duration) {
+        let Some(task) = self.stream_task.take() else {
+            return;
+        };
+        let result = self
+            .runtime
+            .block_on(async { tokio::time::timeout(timeout, task).await });
+        if result.is_err() {
+            log::warn!(
+                "Streaming runner did not stop within {:?}; continuing shutdown",
+                timeout
+            );
+        }
+    }
+
+    /// Drain async results and advance session state. Called once per
+    /// frame from the event loop.
+    pub fn update(&mut self) {
+        while let Ok(event) = self.events_rx.try_recv() {
+            self.handle_event(event);
+        }
+        self.notifications.tick();
+        self.maybe_refresh_tokens();
+        self.flush_viewport_update();
+        self.poll_capture_state();
+        self.poll_scheduled_launches();
+        self.poll_afk_guard();
+        self.poll_session_clock();
+        self.poll_reconnect();
+        self.poll_controller_hotplug();
+        self.poll_rumble();
+        if self.settings_saver.take_due() {
+            if let Err(e) = self.settings.save() {
+                log::error!("Failed to save settings: {}", e);
+            }
+        }
+        // Feed the launcher result file's running averages (no-op
+        // without --result-file; rate-limited internally).
+        if self.pipeline_active {
+            crate::session_result::sample_stats(&self.stream_stats.lock().unwrap());
+        }
+        // Idle auto-cancel for scheduled sessions that reached readiness.
+        if let Some(deadline) = self.schedule_cancel_deadline {
+            if Instant::now() >= deadline {
+                self.schedule_cancel_deadline = None;
+                self.scheduled_session = false;
+                self.notify_warning(
+                    "Scheduled session cancelled: no interaction after the rig became ready",
+                );
+                self.stop_streaming();
+            }
+        }
+        // Background connectivity probe while offline.
+        if self.offline
+            && !self.offline_retry_in_flight
+            && self
+                .offline_last_retry
+                .is_none_or(|t| t.elapsed() >= OFFLINE_RETRY_INTERVAL)
+        {
+            self.retry_connectivity();
+        }
+        // While the rig is setting the game up we hold the session
+        // screen; switch to the streaming view once real frames arrive
+        // or the user opted to watch.
+        if self.state == AppState::Session && self.pipeline_active {
+            if self.current_frame.has_frame() || self.setup_watch_requested {
+                self.stop_session_poll();
+                self.state = AppState::Streaming;
+            } else if let Some(changed) = self.setup_last_change {
+                if changed.elapsed() >= SETUP_STALL_TIMEOUT {
+                    self.setup_stalled = true;
+                }
+            }
+        }
+    }
+
+    /// Infrequent capture-tool check. On capture start: hide the overlay
+    /// automatically when the setting says so, otherwise show the
+    /// one-time prompt. On capture end: restore whatever we hid.
+    fn poll_capture_state(&mut self) {
+        if self
+            .capture_last_poll
+            .is_some_and(|t| t.elapsed() < CAPTURE_POLL_INTERVAL)
+        {
+            return;
+        }
+        self.capture_last_poll = Some(Instant::now());
+        let active = capture::capture_active();
+        if active && !self.capture_active {
+            if self.settings.hide_overlay_when_captured {
+                self.overlay_suppressed_by_capture = true;
+            } else if !self.settings.capture_prompt_remembered && self.settings.show_stats_overlay
+            {
+                self.show_capture_prompt = true;
+            }
+        } else if !active && self.capture_active {
+            self.show_capture_prompt = false;
+            self.overlay_suppressed_by_capture = false;
+        }
+        self.capture_active = active;
+    }
+
+    /// Apply the user's answer to the capture prompt.
+    /// One-time offer to bump the UI scale when the display looks like
+    /// a 4K panel reporting 100% scale. Skipped once answered, or when
+    /// the user already adjusted the scale themselves.
+    pub fn offer_ui_scale_prompt(&mut self) {
+        if !self.settings.ui_scale_prompt_seen && self.settings.ui_scale == 1.0 {
+            self.show_ui_scale_prompt = true;
+            self.focus_primary_action = true;
+        }
+    }
+
+    /// Answer the UI-scale offer; None keeps the current scale.
+    pub fn answer_ui_scale_prompt(&mut self, scale: Option<f32>) {
+        self.show_ui_scale_prompt = false;
+        self.settings.ui_scale_prompt_seen = true;
+        if let Some(scale) = scale {
+            self.settings.ui_scale = scale;
+        }
+        self.settings_changed();
+    }
+
+    pub fn answer_capture_prompt(&mut self, hide: bool) {
+        self.show_capture_prompt = false;
+        self.overlay_suppressed_by_capture = hide;
+        if self.capture_prompt_remember {
+            self.settings.capture_prompt_remembered = true;
+            self.settings.hide_overlay_when_captured = hide;
+            self.settings_changed();
+        }
+    }
+
+    /// Enter read-only cached mode after a connectivity failure: the
+    /// cached game grids stay browsable, streaming is unavailable.
+    fn enter_offline(&mut self) {
+        if self.offline {
+            return;
+        }
+        self.offline = true;
+        if self.state == AppState::Login {
+            self.state = AppState::Games;
+        }
+        self.notify_warning("Offline — streaming unavailable. Showing cached data.");
+    }
+
+    fn exit_offline(&mut self) {
+        self.offline = false;
+        self.notify_success("Back online");
+        if self.auth_tokens.is_some() {
+            self.post_login_fetches();
+        } else {
+            self.state = AppState::Login;
+        }
+    }
+
+    /// Probe connectivity (manually or from the background timer). A
+    /// successful provider fetch flips us back online.
+    pub fn retry_connectivity(&mut self) {
+        self.offline_retry_in_flight = true;
+        self.offline_last_retry = Some(Instant::now());
+        self.fetch_login_providers();
+    }
+
+    /// A genuine 401: tokens are no longer valid, so route to the login
+    /// screen instead of offline mode.
+    fn handle_auth_rejection(&mut self) {
+        self.notify_error("Session expired — please log in again");
+        self.logout();
+    }
+
+    /// Record a window resize; forwarded to the server by
+    /// `flush_viewport_update` once debounced.
+    pub fn notify_viewport_resize(&mut self, width: u32, height: u32) {
+        self.viewport_pending = Some((width, height));
+    }
+
+    /// Forward the latest window size to the stream task at most once
+    /// per second, and only when it moved more than 5% from what the
+    /// server last heard.
+    fn flush_viewport_update(&mut self) {
+        if !self.settings.dynamic_viewport || self.state != AppState::Streaming {
+            return;
+        }
+        let Some((width, height)) = self.viewport_pending else {
+            return;
+        };
+        let Some(tx) = &self.input_event_tx else {
+            return;
+        };
+        if let Some(last) = self.viewport_last_sent {
+            if last.elapsed() < Duration::from_secs(1) {
+                return;
+            }
+        }
+        if let Some((sent_w, sent_h)) = self.viewport_sent {
+            let moved =
+                |new: u32, old: u32| (new as f32 - old as f32).abs() / old.max(1) as f32 > 0.05;
+            if !moved(width, sent_w) && !moved(height, sent_h) {
+                self.viewport_pending = None;
+                return;
+            }
+        }
+        let _ = tx.send(InputEvent::ViewportResize { width, height });
+        self.viewport_sent = Some((width, height));
+        self.viewport_last_sent = Some(Instant::now());
+        self.viewport_pending = None;
+    }
+
+    /// Kick off a proactive token refresh when the access token nears
+    /// expiry. The result comes back through the event channel, keeping
+    /// `auth_tokens` the single source of truth.
+    fn maybe_refresh_tokens(&mut self) {
+        if self.refresh_in_progress || self.login_in_progress {
+            return;
+        }
+        let Some(tokens) = self.auth_tokens.clone() else {
+            return;
+        };
+        if !tokens.should_refresh() || tokens.refresh_token.is_none() {
+            return;
+        }
+        self.refresh_in_progress = true;
+        let tx = self.events_tx.clone();
+        self.runtime.spawn(async move {
+            let _ = tx.send(AppEvent::TokensRefreshed(auth::refresh_token(&tokens).await));
+        });
+    }
+
+    pub fn notify_info(&mut self, text: impl Into<String>) {
+        self.notifications
+            .push(notifications::NotificationLevel::Info, text, None);
+    }
+
+    pub fn notify_success(&mut self, text: impl Into<String>) {
+        self.notifications
+            .push(notifications::NotificationLevel::Success, text, None);
+    }
+
+    pub fn notify_warning(&mut self, text: impl Into<String>) {
+        self.notifications
+            .push(notifications::NotificationLevel::Warning, text, None);
+    }
+
+    pub fn notify_error(&mut self, text: impl Into<String>) {
+        self.notifications
+            .push(notifications::NotificationLevel::Error, text, None);
+    }
+
+    pub fn notify_error_with_action(
+        &mut self,
+        text: impl Into<String>,
+        action: notifications::NotificationAction,
+    ) {
+        self.notifications
+            .push(notifications::NotificationLevel::Error, text, Some(action));
+    }
+
+    /// Clamp saved resolution/FPS to the subscription's entitlements, so
+    /// a downgraded tier doesn't keep requesting 4K120 and failing. Does
+    /// nothing when entitlements are unknown (empty) — Alliance partners
+    /// don't report them.
+    fn apply_entitlements(&mut self, subscription: &SubscriptionInfo) {
+        // Bitrate first: the cap can come from the tier table even when
+        // the payload reports no resolutions. A stored value above the
+        // cap is only ever server-ignored noise — unless the user
+        // explicitly opted into overriding.
+        if !self.settings.bitrate_override {
+            if let Some(cap) = subscription.bitrate_cap_mbps() {
+                if self.settings.max_bitrate_mbps > cap {
+                    self.settings.max_bitrate_mbps = cap;
+                    self.settings_changed();
+                    self.notify_info(format!(
+                        "Max bitrate reduced to {} Mbps — the {} plan's server-side cap",
+                        cap, subscription.tier
+                    ));
+                }
+            }
+        }
+        if subscription.entitled_resolutions.is_empty() {
+            return;
+        }
+        let (width, height) = self.settings.resolution;
+        let fps = self.settings.fps;
+        if subscription
+            .entitled_resolutions
+            .contains(&(width, height, fps))
+        {
+            return;
+        }
+        let Some(&(new_width, new_height, new_fps)) = subscription
+            .entitled_resolutions
+            .iter()
+            .min_by_key(|(w, h, f)| {
+                // Keeping FPS matters most, then aspect ratio, then the
+                // closest pixel count.
+                let fps_diff = (*f as i64 - fps as i64).abs();
+                let aspect_mismatch = u64::from(w * height != h * width);
+                let area_diff =
+                    ((*w as i64 * *h as i64) - (width as i64 * height as i64)).abs();
+                (fps_diff, aspect_mismatch, area_diff)
+            })
+        else {
+            return;
+        };
+        self.settings.resolution = (new_width, new_height);
+        self.settings.fps = new_fps;
+        self.settings_changed();
+        self.notify_warning(format!(
+            "Your {} plan doesn't include {}x{}@{}; stream settings adjusted to {}x{}@{}",
+            subscription.tier, width, height, fps, new_width, new_height, new_fps
+        ));
+    }
+
+    fn handle_event(&mut self, event: AppEvent) {
+        match event {
+            AppEvent::LoggedIn(result) => {
+                self.login_in_progress = false;
+                self.token_import_in_progress = false;
+                self.pending_login = None;
+                self.manual_redirect_input.clear();
+                self.manual_token_input.clear();
+                match result {
+                    Ok(tokens) => {
+                        if let Err(e) = auth::save_tokens(&tokens) {
+                            log::warn!("Failed to persist tokens: {}", e);
+                        }
+                        self.api_client = Some(Arc::new(GfnApiClient::new(&tokens)));
+                        self.auth_tokens = Some(tokens);
+                        self.state = AppState::Games;
+                        self.post_login_fetches();
+                    }
+                    Err(e) => self.notify_error(format!("Login failed: {}", e)),
+                }
+            }
+            AppEvent::TokensRefreshed(result) => {
+                self.refresh_in_progress = false;
+                match result {
+                    Ok(tokens) => {
+                        if let Err(e) = auth::save_tokens(&tokens) {
+                            log::warn!("Failed to persist refreshed tokens: {}", e);
+                        }
+                        self.api_client = Some(Arc::new(GfnApiClient::new(&tokens)));
+                        self.auth_tokens = Some(tokens);
+                    }
+                    Err(e) => log::warn!("Token refresh failed: {}", e),
+                }
+            }
+            AppEvent::ProvidersLoaded(result) => {
+                self.offline_retry_in_flight = false;
+                match result {
+                    Ok(providers) => {
+                        let active_code = auth::active_provider().code;
+                        self.login_providers = providers;
+                        match self
+                            .login_providers
+                            .iter()
+                            .position(|p| p.code == active_code)
+                        {
+                            Some(index) => self.selected_provider_index = index,
+                            None => {
+                                log::warn!(
+                                    "Saved login provider '{}' is no longer offered; \
+                                     falling back to NVIDIA",
+                                    active_code
+                                );
+                                self.selected_provider_index = 0;
+                                auth::set_login_provider(&auth::nvidia_default());
+                            }
+                        }
+                        if self.offline {
+                            self.exit_offline();
+                        }
+                    }
+                    Err(e) => {
+                        if is_network_error(&e) {
+                            self.enter_offline();
+                        }
+                        log::warn!("Failed to fetch login providers: {}", e);
+                    }
+                }
+            }
+            AppEvent::GamesLoaded(result) => match result {
+                Ok(load) => {
+                    self.status_message = Some(if load.skipped > 0 {
+                        format!(
+                            "Loaded {} games ({} skipped due to bad data)",
+                            load.games.len(),
+                            load.skipped
+                        )
+                    } else {
+                        format!("Loaded {} games", load.games.len())
+                    });
+                    if let Err(e) = cache::save_games_cache(&load.games) {
+                        log::warn!("Failed to write games cache: {}", e);
+                    }
+                    self.games = load.games;
+                }
+                Err(e) if is_network_error(&e) => self.enter_offline(),
+                Err(e) if is_auth_rejection(&e) => self.handle_auth_rejection(),
+                Err(e) => self.notify_error_with_action(
+                    format!("Failed to load games: {}", e),
+                    notifications::NotificationAction::RetryGamesLoad,
+                ),
+            },
+            AppEvent::LibraryLoaded(result) => {
+                self.library_sync_progress = None;
+                match result {
+                    Ok(library) => self.library = library,
+                    Err(e) if is_network_error(&e) => self.enter_offline(),
+                    Err(e) if is_auth_rejection(&e) => self.handle_auth_rejection(),
+                    Err(e) => self.notify_error(format!("Failed to load library: {}", e)),
+                }
+            }
+            AppEvent::LibrarySyncProgress { loaded, total } => {
+                self.library_sync_progress = Some((loaded, total));
+            }
+            AppEvent::LibraryExported(result) => {
+                self.library_export_in_progress = false;
+                match result {
+                    Some(Ok((count, path))) => {
+                        self.notify_success(format!(
+                            "Exported {} games to {}",
+                            count,
+                            path.display()
+                        ));
+                    }
+                    Some(Err(e)) => self.notify_error(format!("Library export failed: {}", e)),
+                    None => {}
+                }
+            }
+            AppEvent::DetailsLoaded(game_id, result) => match result {
+                Ok(details) => {
+                    self.details_cache
+                        .insert(game_id.clone(), (Instant::now(), details.clone()));
+                    if self
+                        .selected_game
+                        .as_ref()
+                        .is_some_and(|g| g.cms_id == game_id)
+                    {
+                        self.apply_details(details);
+                    }
+                }
+                Err(e) => log::warn!("Failed to load details for {}: {}", game_id, e),
+            },
+            AppEvent::AccountSubscriptionLoaded(subscription) => {
+                if let Some(subscription) = &subscription {
+                    self.apply_entitlements(subscription);
+                }
+                self.account.merge_subscription(subscription);
+            }
+            AppEvent::AccountUserLoaded(user) => self.account.merge_user(user),
+            AppEvent::ServersLoaded(servers) => {
+                self.servers = servers;
+                // A selection persisted under another provider falls
+                // back to automatic instead of launching a wrong zone.
+                let validated = serverinfo::validate_selection(
+                    self.settings.selected_server.clone(),
+                    &self.servers,
+                );
+                if validated != self.settings.selected_server {
+                    log::info!(
+                        "Selected zone {:?} is not offered by this provider; using automatic",
+                        self.settings.selected_server
+                    );
+                    self.settings.selected_server = validated;
+                    self.settings_changed();
+                }
+            }
+            AppEvent::LaunchAttempt {
+                zone_name,
+                zone_address,
+            } => {
+                self.launch_attempts.push(zone_name);
+                self.active_zone = Some(zone_address);
+            }
+            AppEvent::LaunchStatus(status) => {
+                if self.state == AppState::Session {
+                    self.session_status_text = status;
+                }
+            }
+            AppEvent::SessionCreated(result) => match result {
+                // Either way the race is decided; the lanes are done.
+                Ok(session) => {
+                    self.race_lanes = None;
+                    log::info!("Session created: {}", session.session_id);
+                    crate::session_result::session_started(&session.game_id, &session.session_id);
+                    // Start the estimate-vs-actual clock with whatever
+                    // estimate was on screen at launch.
+                    self.queue_started_at = Some(Instant::now());
+                    self.queue_estimate_secs =
+                        self.queue_estimate().and_then(|(e, _)| e.wait_secs);
+                    self.session = Some(session);
+                }
+                Err(e) => {
+                    self.race_lanes = None;
+                    // Critical: stays pinned inline as well as toasting.
+                    let text = format!("Session creation failed: {}", e);
+                    self.error_message = Some(text.clone());
+                    self.notify_error(text);
+                    self.state = AppState::Games;
+                }
+            },
+            AppEvent::SessionUpdate { seq, result } => {
+                if seq <= self.last_session_seq {
+                    log::debug!("Dropping stale session update (seq {})", seq);
+                    return;
+                }
+                self.last_session_seq = seq;
+                self.apply_session_update(result);
+            }
+            AppEvent::RaceStarted(zone_names) => {
+                self.race_lanes = Some(
+                    zone_names
+                        .into_iter()
+                        .map(|zone_name| RaceLane {
+                            zone_name,
+                            status: "Requesting session…".to_string(),
+                            finished: false,
+                        })
+                        .collect(),
+                );
+            }
+            AppEvent::RaceLaneUpdate { lane, status, finished } => {
+                if let Some(entry) = self
+                    .race_lanes
+                    .as_mut()
+                    .and_then(|lanes| lanes.get_mut(lane))
+                {
+                    entry.status = status;
+                    entry.finished = finished;
+                }
+            }
+            AppEvent::RaceCancelFailed { zone_name, error } => {
+                log::error!("Losing race session in {} not cancelled: {}", zone_name, error);
+                self.notify_error(format!(
+                    "Couldn't cancel the losing session in {} — it may still count \
+                     against your session limit ({})",
+                    zone_name, error
+                ));
+            }
+            AppEvent::RacingUnsupported => {
+                self.racing_unsupported = true;
+                self.notify_warning(
+                    "Your account allows one session at a time — racing disabled, \
+                     continuing with a single zone",
+                );
+            }
+            AppEvent::ZoneFreshness { game, zones } => {
+                // A cancel while the re-ping ran put us back on the
+                // Games screen; the launch is off.
+                if self.state == AppState::Session {
+                    self.finish_zone_freshness_check(game, zones);
+                }
+            }
+            AppEvent::QueueEstimateLoaded { zone, tier, result } => {
+                self.queue_estimate_pending = false;
+                match result {
+                    Ok(estimate) => {
+                        self.queue_estimates
+                            .insert((zone, tier), (Instant::now(), estimate));
+                    }
+                    // Degrade silently: the popup just shows no estimate.
+                    Err(e) => log::debug!("Queue estimate unavailable for {}: {}", zone, e),
+                }
+            }
+            AppEvent::StreamingFailed(message) => {
+                let ice_timeout = message.contains("ICE connection timed out");
+                // A dropped transport (or a failed attempt to bring it
+                // back) is retried against the still-alive session
+                // instead of tearing the stream down; the overlay shows
+                // "Reconnecting…" meanwhile.
+                let interrupted = message.contains("Connection interrupted");
+                if (interrupted || self.reconnect_attempts > 0)
+                    && self.session.is_some()
+                    && self.reconnect_attempts < self.settings.max_reconnect_attempts
+                {
+                    self.reconnect_attempts += 1;
+                    self.pipeline_active = false;
+                    self.input_event_tx = None;
+                    self.rumble_rx = None;
+                    self.reconnect_at = Some(
+                        Instant::now() + RECONNECT_BACKOFF_STEP * self.reconnect_attempts,
+                    );
+                    log::warn!(
+                        "Connection lost ({}); reconnect attempt {}/{} scheduled",
+                        message,
+                        self.reconnect_attempts,
+                        self.settings.max_reconnect_attempts
+                    );
+                    return;
+                }
+                if self.reconnect_attempts >= self.settings.max_reconnect_attempts
+                    && self.reconnect_attempts > 0
+                {
+                    self.notify_error("Could not reconnect — stopping the stream");
+                }
+                // Record the error before stop_streaming files a user
+                // stop; the first end reason wins.
+                crate::session_result::session_ended(crate::session_result::EndReason::Error {
+                    message: message.clone(),
+                });
+                self.record_stream_end("error", message.clone());
+                self.stop_streaming();
+                let text = format!("Streaming failed: {}", message);
+                self.error_message = Some(text.clone());
+                self.notify_error(text);
+                // An ICE timeout with no firewall rule for our exe is
+                // almost always the dismissed Defender prompt; explain
+                // and offer the fix instead of a bare timeout.
+                if ice_timeout && firewall::likely_blocking() {
+                    self.show_firewall_help = true;
+                }
+            }
+            AppEvent::ResumeSessionChecked(candidate) => {
+                // A launch that happened while the check was in flight
+                // wins; the banner only appears when nothing is running.
+                if self.session.is_none() && !self.pipeline_active {
+                    self.resume_session = candidate;
+                }
+            }
+            AppEvent::ContactSheetSaved(result) => match result {
+                Ok(path) => self.notify_success(format!("Saved {}", path.display())),
+                Err(e) => self.notify_error(format!("Contact sheet export failed: {}", e)),
+            },
+            AppEvent::ScreenshotSaved(result) => match result {
+                Ok(path) => self.notify_success(format!("Screenshot saved to {}", path.display())),
+                Err(e) => self.notify_error(format!("Screenshot failed: {}", e)),
+            },
+        }
+    }
+
+    fn apply_session_update(&mut self, result: anyhow::Result<SessionInfo>) {
+        if self.state != AppState::Session {
+            return;
+        }
+        let session = match result {
+            Ok(session) => session,
+            Err(e) => {
+                log::warn!("Session poll failed: {}", e);
+                return;
+            }
+        };
+        match &session.state {
+            SessionState::Queued { position, eta_secs } => {
+                self.session_status_text = match (position, eta_secs) {
+                    (Some(position), Some(eta)) => {
+                        format!("Queue position: {} (ETA: {}s)", position, eta)
+                    }
+                    (Some(position), None) => format!("Queue position: {}", position),
+                    // The zone sent no usable queue fields; zeros here
+                    // would read as "position 0", which is nonsense.
+                    _ => "Waiting for queue information…".to_string(),
+                };
+                self.session = Some(session);
+            }
+            SessionState::Provisioning => {
+                self.session_status_text = "Preparing your rig…".to_string();
+                self.session = Some(session);
+            }
+            SessionState::Ready | SessionState::Streaming => {
+                if let Some(queued_at) = self.queue_started_at.take() {
+                    let actual = queued_at.elapsed().as_secs_f32();
+                    let estimate = self.queue_estimate_secs.take();
+                    log::info!(
+                        "Queue wait: {:.0}s actual vs {:?}s estimated",
+                        actual,
+                        estimate
+                    );
+                    let mut stats = self.stream_stats.lock().unwrap();
+                    stats.queue_wait_actual_secs = actual;
+                    stats.queue_wait_estimate_secs = estimate.unwrap_or(0) as f32;
+                }
+                if self.scheduled_session && self.schedule_cancel_deadline.is_none() {
+                    self.notify_success(format!(
+                        "Your scheduled rig is ready — auto-cancel in {} minutes without input",
+                        SCHEDULE_IDLE_CANCEL.as_secs() / 60
+                    ));
+                    self.schedule_cancel_deadline = Some(Instant::now() + SCHEDULE_IDLE_CANCEL);
+                }
+                let setup = session.setup.clone();
+                self.session = Some(session);
+                let setup_active = setup
+                    .as_ref()
+                    .is_some_and(|s| s.percent.is_none_or(|p| p < 100));
+                if setup_active && !self.setup_watch_requested {
+                    // The rig is up but the game is still installing /
+                    // compiling shaders: keep the session screen (with
+                    // progress), start the pipeline so we notice the
+                    // first real frame, and keep polling for progress.
+                    self.apply_setup_progress(setup);
+                    if !self.pipeline_active {
+                        self.start_stream_pipeline();
+                    }
+                } else {
+                    self.stop_session_poll();
+                    self.start_streaming();
+                }
+            }
+            SessionState::Finished | SessionState::Error(_) => {
+                let code = match &session.state {
+                    SessionState::Error(error) => error.clone(),
+                    _ => "FINISHED".to_string(),
+                };
+                crate::session_result::session_ended(
+                    crate::session_result::EndReason::RemoteTermination { code: code.clone() },
+                );
+                // FINISHED is the server's normal end-of-session; only a
+                // server-side error earns the Games-screen card.
+                if matches!(session.state, SessionState::Error(_)) {
+                    self.record_stream_end("remote", code);
+                }
+                let text = format!("Session ended: {:?}", session.state);
+                self.error_message = Some(text.clone());
+                self.notify_error(text);
+                self.stop_session_poll();
+                cache::clear_session_cache();
+                self.session = None;
+                self.state = AppState::Games;
+            }
+        }
+    }
+
+    fn stop_session_poll(&mut self) {
+        if let Some(task) = self.session_poll_task.take() {
+            task.abort();
+        }
+    }
+
+    fn apply_setup_progress(&mut self, setup: Option<SetupProgress>) {
+        if setup != self.setup_progress {
+            self.setup_last_change = Some(Instant::now());
+            self.setup_stalled = false;
+        } else if self.setup_last_change.is_none() {
+            self.setup_last_change = Some(Instant::now());
+        }
+        let stage = setup
+            .as_ref()
+            .and_then(|s| s.stage.clone())
+            .unwrap_or_else(|| "Setting up the game".to_string());
+        self.session_status_text = match setup.as_ref().and_then(|s| s.percent) {
+            Some(percent) => format!("{} ({}%)", stage, percent),
+            None => stage,
+        };
+        self.setup_progress = setup;
+    }
+
+    fn post_login_fetches(&mut self) {
+        self.load_games();
+        self.load_library();
+        self.load_servers();
+        self.refresh_account_snapshot();
+        self.check_resume_session();
+    }
+
+    /// Look for a recovery cache left behind by a crashed or closed
+    /// client and verify the session is still alive server-side before
+    /// offering to resume it. Dead or unverifiable caches are cleared
+    /// so the banner can never point at nothing.
+    fn check_resume_session(&mut self) {
+        if self.pipeline_active || self.session.is_some() {
+            return;
+        }
+        let Some(cached) = cache::load_session_cache() else {
+            return;
+        };
+        let Some(client) = self.api_client.clone() else {
+            return;
+        };
+        let Some(zone) = cached.zone.clone() else {
+            // Written before the zone was recorded; can't be verified.
+            cache::clear_session_cache();
+            return;
+        };
+        let tx = self.events_tx.clone();
+        self.runtime.spawn(async move {
+            let candidate = match client.poll_session(&zone, &cached.session_id).await {
+                Ok(session)
+                    if matches!(
+                        session.state,
+                        SessionState::Ready | SessionState::Streaming
+                    ) =>
+                {
+                    Some(ResumeCandidate {
+                        session,
+                        game_id: cached.game_id,
+                        zone,
+                    })
+                }
+                _ => {
+                    cache::clear_session_cache();
+                    None
+                }
+            };
+            let _ = tx.send(AppEvent::ResumeSessionChecked(candidate));
+        });
+    }
+
+    /// Jump straight back into the verified session from the Resume
+    /// banner, skipping the queue entirely.
+    pub fn resume_cached_session(&mut self) {
+        if self.pipeline_active || self.session.is_some() {
+            self.resume_session = None;
+            return;
+        }
+        let Some(candidate) = self.resume_session.take() else {
+            return;
+        };
+        self.streaming_game_title = self
+            .games
+            .iter()
+            .find(|game| game.cms_id == candidate.game_id)
+            .map(|game| game.title.clone());
+        self.active_zone = Some(candidate.zone);
+        self.session_status_text = "Resuming previous session…".to_string();
+        crate::session_result::session_started(
+            &candidate.session.game_id,
+            &candidate.session.session_id,
+        );
+        self.session = Some(candidate.session);
+        self.start_streaming();
+    }
+
+    /// Drop the Resume banner and the cache file behind it.
+    pub fn dismiss_resume_session(&mut self) {
+        if self.resume_session.take().is_some() {
+            cache::clear_session_cache();
+        }
+    }
+
+    fn fetch_login_providers(&mut self) {
+        let tx = self.events_tx.clone();
+        self.runtime.spawn(async move {
+            let _ = tx.send(AppEvent::ProvidersLoaded(auth::fetch_login_providers().await));
+        });
+    }
+
+    pub fn start_login(&mut self) {
+        if self.login_in_progress {
+            return;
+        }
+        self.login_in_progress = true;
+        self.error_message = None;
+        if let Some(provider) = self.login_providers.get(self.selected_provider_index) {
+            if auth::active_provider().code != provider.code {
+                // Zones from the previous provider are meaningless now;
+                // drop them so nothing resolves against a stale list
+                // mid-switch. The post-login fetch repopulates.
+                self.servers.clear();
+            }
+            auth::set_login_provider(provider);
+        }
+        let pending = auth::prepare_login(self.login_bind_all);
+        self.pending_login = Some(pending.clone());
+        let tx = self.events_tx.clone();
+        self.runtime.spawn(async move {
+            let _ = tx.send(AppEvent::LoggedIn(auth::run_login_flow(pending).await));
+        });
+    }
+
+    /// Complete login from a redirect URL the user pasted in manually
+    /// (fallback for networks where the callback can't reach us).
+    pub fn submit_manual_redirect(&mut self) {
+        let Some(pending) = self.pending_login.clone() else {
+            return;
+        };
+        let pasted = self.manual_redirect_input.trim().to_string();
+        if pasted.is_empty() {
+            return;
+        }
+        let tx = self.events_tx.clone();
+        self.runtime.spawn(async move {
+            let _ = tx.send(AppEvent::LoggedIn(
+                auth::import_redirect_url(&pending, &pasted).await,
+            ));
+        });
+    }
+
+    /// Validate and import a pasted refresh token / auth.json. Success
+    /// flows through the normal `LoggedIn` path (persist + fetches).
+    pub fn submit_token_import(&mut self) {
+        let pasted = self.manual_token_input.trim().to_string();
+        if pasted.is_empty() || self.token_import_in_progress {
+            return;
+        }
+        self.token_import_in_progress = true;
+        let tx = self.events_tx.clone();
+        self.runtime.spawn(async move {
+            let _ = tx.send(AppEvent::LoggedIn(auth::import_token_text(&pasted).await));
+        });
+    }
+
+    /// Load the persisted tuning profile for the currently connected
+    /// pad into the manager (hot-plug aware). Returns the pad identity.
+    pub fn sync_controller_profile(&mut self) -> Option<String> {
+        let manager = self.controller.as_mut()?;
+        let identity = manager.pad_identity()?;
+        if self.controller_profile_loaded.as_deref() != Some(identity.as_str()) {
+            let tuning = self
+                .settings
+                .controller_profiles
+                .get(&identity)
+                .copied()
+                .unwrap_or_default();
+            manager.set_tuning(tuning);
+            self.controller_profile_loaded = Some(identity.clone());
+        }
+        Some(identity)
+    }
+
+    /// Switch to another saved profile: persist the choice, then reload
+    /// tokens, provider, and caches from the new namespace. Refused
+    /// while a session is live so a switch can't strand a stream whose
+    /// tokens belong to the old account.
+    pub fn switch_profile(&mut self, id: &str) {
+        if id == self.active_profile || !self.profiles.iter().any(|p| p == id) {
+            return;
+        }
+        if self.session.is_some() || self.pipeline_active {
+            self.notify_warning("End the current session before switching profiles");
+            return;
+        }
+        self.active_profile = id.to_string();
+        cache::set_active_profile(id);
+        self.persist_profiles();
+        // Provider first: the new profile may have logged in through a
+        // different Alliance partner, and setting it invalidates the
+        // old account's region cache.
+        match cache::load_login_provider() {
+            Some(provider) => {
+                self.selected_provider_index = self
+                    .login_providers
+                    .iter()
+                    .position(|p| p.code == provider.code)
+                    .unwrap_or(0);
+                auth::set_login_provider(&provider);
+            }
+            None => {
+                self.selected_provider_index = 0;
+                auth::set_login_provider(&auth::nvidia_default());
+            }
+        }
+        self.auth_tokens = auth::load_tokens();
+        self.api_client = self
+            .auth_tokens
+            .as_ref()
+            .map(|t| Arc::new(GfnApiClient::new(t)));
+        self.account = AccountSnapshot::default();
+        self.games = cache::load_games_cache().unwrap_or_default();
+        self.library = cache::load_library_cache()
+            .filter(|c| c.complete)
+            .map(|c| c.games)
+            .unwrap_or_default();
+        self.scheduled_launches = cache::load_schedules();
+        self.last_session_summary = cache::load_last_session_summary();
+        self.selected_game = None;
+        self.game_details = None;
+        self.details_cache.clear();
+        if self.auth_tokens.is_some() {
+            self.state = AppState::Games;
+            self.post_login_fetches();
+        } else {
+            self.state = AppState::Login;
+        }
+    }
+
+    /// Create a profile from the account-menu field (or just switch to
+    /// it when the name already exists).
+    pub fn add_profile(&mut self, name: &str) {
+        let id: String = name
+            .trim()
+            .chars()
+            .filter(|c| c.is_ascii_alphanumeric() || *c == '-' || *c == '_')
+            .collect();
+        if id.is_empty() {
+            self.notify_warning("Profile names use letters, digits, '-' and '_'");
+            return;
+        }
+        if !self.profiles.iter().any(|p| p == &id) {
+            self.profiles.push(id.clone());
+            self.persist_profiles();
+        }
+        self.switch_profile(&id);
+    }
+
+    fn persist_profiles(&self) {
+        let file = cache::ProfilesFile {
+            active: self.active_profile.clone(),
+            profiles: self.profiles.clone(),
+        };
+        if let Err(e) = cache::save_profiles(&file) {
+            log::warn!("Failed to persist profiles: {}", e);
+        }
+    }
+
+    /// Log out of the active profile only; other profiles keep their
+    /// tokens.
+    pub fn logout(&mut self) {
+        auth::clear_tokens();
+        self.auth_tokens = None;
+        self.api_client = None;
+        self.account = AccountSnapshot::default();
+        self.library.clear();
+        self.state = AppState::Login;
+    }
+
+    pub fn load_games(&mut self) {
+        let Some(client) = self.api_client.clone() else {
+            return;
+        };
+        let tx = self.events_tx.clone();
+        self.runtime.spawn(async move {
+            let _ = tx.send(AppEvent::GamesLoaded(client.fetch_main_games().await));
+        });
+    }
+
+    pub fn load_library(&mut self) {
+        let Some(client) = self.api_client.clone() else {
+            return;
+        };
+        let tx = self.events_tx.clone();
+        self.runtime.spawn(async move {
+            let result = sync_library(&client, &tx).await;
+            let _ = tx.send(AppEvent::LibraryLoaded(result));
+        });
+    }
+
+    /// Export the library to a user-chosen CSV/JSON file. The dialog and
+    /// the write both run on a blocking task so the UI stays responsive.
+    pub fn export_library(&mut self) {
+        if self.library_export_in_progress || self.library.is_empty() {
+            return;
+        }
+        self.library_export_in_progress = true;
+        let games = self.library.clone();
+        let tx = self.events_tx.clone();
+        self.runtime.spawn(async move {
+            let result = tokio::task::spawn_blocking(move || {
+                export::pick_export_path()
+                    .map(|path| export::export_library(&path, &games).map(|count| (count, path)))
+            })
+            .await
+            .unwrap_or(None);
+            let _ = tx.send(AppEvent::LibraryExported(result));
+        });
+    }
+
+    pub fn load_servers(&mut self) {
+        let Some(tokens) = self.auth_tokens.clone() else {
+            return;
+        };
+        let tx = self.events_tx.clone();
+        self.runtime.spawn(async move {
+            match serverinfo::fetch_zones(&tokens.access_token).await {
+                Ok(zones) => {
+                    let zones = serverinfo::ping_all_servers(zones).await;
+                    let _ = tx.send(AppEvent::ServersLoaded(zones));
+                }
+                Err(e) => log::warn!("Failed to fetch zones: {}", e),
+            }
+        });
+    }
+
+    /// Open the detail popup for `game`, fetching details (description,
+    /// notices) unless a fresh cached copy exists.
+    pub fn open_game_details(&mut self, game: GameInfo) {
+        let game_id = game.cms_id.clone();
+        self.selected_game = Some(game);
+        self.focus_primary_action = true;
+        self.request_queue_estimate(false);
+        self.game_details = None;
+        self.launch_ack_required = false;
+        if let Some((fetched_at, details)) = self.details_cache.get(&game_id) {
+            if fetched_at.elapsed() < DETAILS_TTL {
+                let details = details.clone();
+                self.apply_details(details);
+                return;
+            }
+        }
+        let Some(client) = self.api_client.clone() else {
+            return;
+        };
+        let tx = self.events_tx.clone();
+        self.runtime.spawn(async move {
+            let result = client.get_game_details(&game_id).await;
+            let _ = tx.send(AppEvent::DetailsLoaded(game_id, result));
+        });
+    }
+
+    /// (zone address, tier) the next launch would use; None until the
+    /// zones and subscription have loaded.
+    fn queue_estimate_key(&self) -> Option<(String, String)> {
+        Some((
+            self.resolve_zone()?,
+            self.account.subscription.value()?.tier.clone(),
+        ))
+    }
+
+    /// The unexpired estimate for the launch zone, with its age.
+    pub fn queue_estimate(&self) -> Option<(QueueEstimate, Duration)> {
+        let key = self.queue_estimate_key()?;
+        let (fetched_at, estimate) = self.queue_estimates.get(&key)?;
+        let age = fetched_at.elapsed();
+        (age < QUEUE_ESTIMATE_TTL).then(|| (estimate.clone(), age))
+    }
+
+    /// Fetch the estimate for the launch zone, unless a fresh one is
+    /// cached (`force` bypasses the cache for the refresh button).
+    pub fn request_queue_estimate(&mut self, force: bool) {
+        if self.queue_estimate_pending {
+            return;
+        }
+        let Some((zone, tier)) = self.queue_estimate_key() else {
+            return;
+        };
+        if !force && self.queue_estimate().is_some() {
+            return;
+        }
+        let Some(client) = self.api_client.clone() else {
+            return;
+        };
+        self.queue_estimate_pending = true;
+        let tx = self.events_tx.clone();
+        self.runtime.spawn(async move {
+            let result = client.fetch_queue_estimate(&zone, &tier).await;
+            let _ = tx.send(AppEvent::QueueEstimateLoaded { zone, tier, result });
+        });
+    }
+
+    fn apply_details(&mut self, details: GameDetails) {
+        self.launch_ack_required = details
+            .notices
+            .iter()
+            .any(|n| n.severity == crate::api::NoticeSeverity::Error);
+        self.game_details = Some(details);
+    }
+
+    pub fn close_game_details(&mut self) {
+        self.selected_game = None;
+        self.game_details = None;
+        self.launch_ack_required = false;
+        self.low_hours_ack = false;
+    }
+
+    /// Remaining entitled hours this period, or None for unlimited plans
+    /// (or before the subscription has loaded).
+    pub fn remaining_hours(&self) -> Option<f64> {
+        self.account.subscription.value()?.remaining_hours
+    }
+
+    /// Whether the configured low-hours block applies right now.
+    pub fn low_hours_blocked(&self) -> bool {
+        match (self.settings.low_hours_block_threshold, self.remaining_hours()) {
+            (Some(threshold), Some(remaining)) => remaining < threshold as f64,
+            _ => false,
+        }
+    }
+
+    /// Fetch user info and the subscription concurrently, each with its
+    /// own retries, merging results into `self.account` as they land.
+    /// Parts with cached data keep displaying it during the refresh.
+    pub fn refresh_account_snapshot(&mut self) {
+        let Some(client) = self.api_client.clone() else {
+            return;
+        };
+        // A failed part gets another chance; a Ready one stays Ready
+        // until fresh data replaces it.
+        if self.account.user.value().is_none() {
+            self.account.user = AccountPart::Loading;
+        }
+        if self.account.subscription.value().is_none() {
+            self.account.subscription = AccountPart::Loading;
+        }
+        let tx = self.events_tx.clone();
+        let user_tx = self.events_tx.clone();
+        let user_client = client.clone();
+        self.runtime.spawn(async move {
+            let user = fetch_account_part("User info", || {
+                let client = user_client.clone();
+                async move { client.fetch_user_info().await }
+            })
+            .await;
+            let _ = user_tx.send(AppEvent::AccountUserLoaded(user));
+        });
+        self.runtime.spawn(async move {
+            let subscription = fetch_account_part("Subscription", || {
+                let client = client.clone();
+                async move { client.fetch_subscription().await }
+            })
+            .await;
+            let _ = tx.send(AppEvent::AccountSubscriptionLoaded(subscription));
+        });
+    }
+
+    /// Resolve the zone to launch in: the configured server, or the
+    /// best-pinged one — preferring, under a strict rig preference, the
+    /// best-pinged zone that actually has the wanted rig class.
+    fn resolve_zone(&self) -> Option<String> {
+        if self.settings.selected_server.is_none() && self.settings.preferred_rig_strict {
+            if let Some(rig) = self.settings.preferred_rig.as_deref() {
+                if let Some(zone) = serverinfo::preferred_rig_zone(&self.servers, rig) {
+                    return Some(zone.address.clone());
+                }
+            }
+        }
+        serverinfo::resolve_launch_address(
+            self.settings.selected_server.as_deref(),
+            &self.servers,
+        )
+    }
+
+    /// Zones to try for a launch: the selected zone (or the one-session
+    /// override from the zone-switch prompt) first, then — when failover
+    /// is enabled — the next-best zones by ping that stay under the
+    /// configured ping ceiling. The saved preference is not touched.
+    fn launch_candidates(&self, primary_override: Option<&str>) -> Vec<ServerInfo> {
+        let primary_address = match primary_override
+            .and_then(|id| self.servers.iter().find(|s| s.id == id))
+        {
+            Some(zone) => zone.address.clone(),
+            None => match self.resolve_zone() {
+                Some(address) => address,
+                None => return Vec::new(),
+            },
+        };
+        let mut candidates: Vec<ServerInfo> = self
+            .servers
+            .iter()
+            .filter(|s| s.address == primary_address)
+            .cloned()
+            .collect();
+        if self.settings.zone_failover {
+            // `servers` is already ping-sorted best-first.
+            for server in &self.servers {
+                if candidates.len() >= MAX_FAILOVER_ATTEMPTS {
+                    break;
+                }
+                if server.address == primary_address {
+                    continue;
+                }
+                match server.ping_ms {
+                    Some(ping) if ping <= self.settings.failover_max_ping_ms => {
+                        candidates.push(server.clone());
+                    }
+                    _ => {}
+                }
+            }
+        }
+        candidates
+    }
+
+    /// Add (or move) a scheduled launch for `game` at `launch_at` (unix
+    /// seconds) and persist the list.
+    pub fn schedule_launch(&mut self, game: GameInfo, launch_at: i64) {
+        let when = chrono::DateTime::from_timestamp(launch_at, 0)
+            .map(|t| {
+                t.with_timezone(&chrono::Local)
+                    .format("%a %H:%M")
+                    .to_string()
+            })
+            .unwrap_or_default();
+        self.notify_info(format!("Queueing for {} scheduled at {}", game.title, when));
+        self.scheduled_launches.retain(|s| s.game.cms_id != game.cms_id);
+        self.scheduled_launches
+            .push(cache::ScheduledLaunch { game, launch_at });
+        self.scheduled_launches.sort_by_key(|s| s.launch_at);
+        if let Err(e) = cache::save_schedules(&self.scheduled_launches) {
+            log::warn!("Failed to persist scheduled launches: {}", e);
+        }
+    }
+
+    pub fn cancel_scheduled_launch(&mut self, game_id: &str) {
+        self.scheduled_launches.retain(|s| s.game.cms_id != game_id);
+        if let Err(e) = cache::save_schedules(&self.scheduled_launches) {
+            log::warn!("Failed to persist scheduled launches: {}", e);
+        }
+    }
+
+    /// Fire due schedules. A schedule that comes due while the machine
+    /// was asleep (or another session is running) fires as soon as we're
+    /// back on the Games screen, unless the grace window has passed.
+    fn poll_scheduled_launches(&mut self) {
+        if self.state != AppState::Games {
+            return;
+        }
+        let now = chrono::Utc::now().timestamp();
+        let Some(index) = self
+            .scheduled_launches
+            .iter()
+            .position(|s| s.launch_at <= now)
+        else {
+            return;
+        };
+        let schedule = self.scheduled_launches.remove(index);
+        if let Err(e) = cache::save_schedules(&self.scheduled_launches) {
+            log::warn!("Failed to persist scheduled launches: {}", e);
+        }
+        if now - schedule.launch_at > SCHEDULE_GRACE.as_secs() as i64 {
+            self.notify_warning(format!(
+                "Missed scheduled launch for {} (over {} minutes late)",
+                schedule.game.title,
+                SCHEDULE_GRACE.as_secs() / 60
+            ));
+            return;
+        }
+        self.notify_info(format!(
+            "Starting scheduled queue for {}",
+            schedule.game.title
+        ));
+        self.scheduled_session = true;
+        self.schedule_cancel_deadline = None;
+        self.launch_game(&schedule.game);
+    }
+
+    /// The user is at the keyboard: a scheduled session that reached
+    /// readiness is now claimed, so stop the idle auto-cancel countdown.
+    /// Also resets the AFK idle timer and dismisses its warning.
+    pub fn note_user_interaction(&mut self) {
+        self.last_user_input = Instant::now();
+        self.afk_warning_deadline = None;
+        if self.schedule_cancel_deadline.is_some() {
+            self.schedule_cancel_deadline = None;
+            self.scheduled_session = false;
+        }
+    }
+
+    /// AFK guard: warn after the configured idle period, then stop the
+    /// stream (terminating the session, so the hour meter stops) when
+    /// the warning goes unacknowledged. Controller input counts as
+    /// presence, so pads-only players aren't disconnected mid-game.
+    fn poll_afk_guard(&mut self) {
+        let Some(minutes) = self.settings.afk_timeout_minutes else {
+            self.afk_warning_deadline = None;
+            return;
+        };
+        if self.state != AppState::Streaming {
+            self.afk_warning_deadline = None;
+            return;
+        }
+        if crate::input::take_raw_activity()
+            || self
+                .controller
+                .as_mut()
+                .is_some_and(|c| c.had_input_event())
+        {
+            self.note_user_interaction();
+            return;
+        }
+        let idle = Duration::from_secs(minutes as u64 * 60);
+        if let Some(deadline) = self.afk_warning_deadline {
+            if Instant::now() >= deadline {
+                self.afk_warning_deadline = None;
+                self.notify_warning(format!(
+                    "Disconnected after {} minutes idle to save your session hours",
+                    minutes
+                ));
+                self.stop_streaming();
+            }
+        } else if self.last_user_input.elapsed() >= idle {
+            self.afk_warning_deadline = Some(Instant::now() + AFK_WARNING_GRACE);
+        }
+    }
+
+    /// Session clock: mirrors elapsed/remaining time into the stats
+    /// overlay and fires the configured countdown warnings as the
+    /// subscription balance runs out, so a tier limit doesn't kill the
+    /// stream without notice. Unlimited plans have no balance and never
+    /// warn.
+    fn poll_session_clock(&mut self) {
+        if self.state != AppState::Streaming {
+            return;
+        }
+        let Some(started) = self.stream_started_at else {
+            return;
+        };
+        let elapsed = started.elapsed();
+        let remaining = self
+            .session_remaining_at_start
+            .map(|r| r.saturating_sub(elapsed));
+        {
+            let mut stats = self.stream_stats.lock().unwrap();
+            stats.session_elapsed_secs = elapsed.as_secs_f32();
+            stats.session_remaining_secs = remaining.map_or(0.0, |r| r.as_secs_f32());
+        }
+        let Some(remaining) = remaining else {
+            return;
+        };
+        while let Some(&minutes) = self.session_warnings_pending.first() {
+            if remaining > Duration::from_secs(minutes as u64 * 60) {
+                break;
+            }
+            self.session_warnings_pending.remove(0);
+            self.notify_warning(format!(
+                "About {} minute{} of session time left",
+                minutes,
+                if minutes == 1 { "" } else { "s" }
+            ));
+        }
+    }
+
+    /// Reconnect driver: once the backoff timer fires, respawn the
+    /// pipeline against the kept session (same session ID, fresh
+    /// signaling + offer/answer). Frames flowing again clears the
+    /// attempt counter; exhausted attempts are handled where
+    /// `StreamingFailed` lands.
+    fn poll_reconnect(&mut self) {
+        if self.reconnect_attempts > 0
+            && self.reconnect_at.is_none()
+            && self.pipeline_active
+            && self.current_frame.has_frame()
+        {
+            self.reconnect_attempts = 0;
+            self.notify_success("Reconnected");
+        }
+        let Some(at) = self.reconnect_at else {
+            return;
+        };
+        if Instant::now() < at {
+            return;
+        }
+        self.reconnect_at = None;
+        if self.session.is_none() {
+            // The session went away (user stop, server teardown) while
+            // we were waiting.
+            self.reconnect_attempts = 0;
+            return;
+        }
+        log::info!(
+            "Reconnecting (attempt {}/{})",
+            self.reconnect_attempts,
+            self.settings.max_reconnect_attempts
+        );
+        self.start_stream_pipeline();
+    }
+
+    /// Current reconnect attempt while the connection is down, for the
+    /// streaming overlay. None when the stream is healthy.
+    pub fn reconnecting(&self) -> Option<(u32, u32)> {
+        (self.reconnect_attempts > 0)
+            .then_some((self.reconnect_attempts, self.settings.max_reconnect_attempts))
+    }
+
+    /// Forward pad arrivals/removals to the rig so the game swaps its
+    /// prompt glyphs. Sent ahead of any state packets, matching the
+    /// official client's sequencing. Outside a stream the events are
+    /// dropped — `start_stream_pipeline` snapshots connected pads when
+    /// the next stream begins.
+    fn poll_controller_hotplug(&mut self) {
+        let Some(controller) = self.controller.as_mut() else {
+            return;
+        };
+        let events = controller.take_connection_events();
+        let Some(tx) = &self.input_event_tx else {
+            return;
+        };
+        for event in events {
+            let _ = tx.send(event.to_input_event());
+        }
+    }
+
+    /// Forward the stream task's haptic commands to the pads. Commands
+    /// are drained even with vibration off so a toggle mid-buzz doesn't
+    /// replay a backlog when re-enabled.
+    fn poll_rumble(&mut self) {
+        let Some(mut rx) = self.rumble_rx.take() else {
+            return;
+        };
+        while let Ok(command) = rx.try_recv() {
+            if !self.settings.controller_vibration {
+                continue;
+            }
+            if let Some(controller) = self.controller.as_mut() {
+                controller.apply_rumble(command);
+            }
+        }
+        self.rumble_rx = Some(rx);
+    }
+
+    /// Entry point for a launch. When the freshness check applies, the
+    /// zones are re-pinged first (at most `PRELAUNCH_PING_BUDGET`) and
+    /// the launch may park behind the zone-switch prompt; otherwise
+    /// session creation starts right away on the stored pings.
+    pub fn launch_game(&mut self, game: &GameInfo) {
+        let check = self.settings.prelaunch_ping_check
+            && !self.offline
+            && !self.servers.is_empty()
+            // Automatic selection always re-runs on fresh pings; a
+            // remembered "keep my zone" makes the re-ping pointless.
+            && (self.settings.selected_server.is_none()
+                || self.settings.prelaunch_switch_remembered != Some(false));
+        if check {
+            self.start_zone_freshness_check(game.clone());
+        } else {
+            self.begin_launch(game, None);
+        }
+    }
+
+    /// Quick freshness re-ping before session creation: one concurrent
+    /// sample of every zone, plus a second sample of the zone we're
+    /// about to launch in (the better one counts, so a single slow
+    /// handshake can't trigger the prompt), all inside a hard one-second
+    /// budget.
+    fn start_zone_freshness_check(&mut self, game: GameInfo) {
+        self.state = AppState::Session;
+        self.session_status_text = format!("Checking zone latency for {}…", game.title);
+        let servers = self.servers.clone();
+        let selected_address = self.resolve_zone();
+        let tx = self.events_tx.clone();
+        self.runtime.spawn(async move {
+            let zones = tokio::time::timeout(PRELAUNCH_PING_BUDGET, async {
+                let mut zones = serverinfo::ping_all_servers(servers).await;
+                if let Some(zone) = zones
+                    .iter_mut()
+                    .find(|z| Some(&z.address) == selected_address.as_ref())
+                {
+                    if let Some(second) = serverinfo::ping_server(zone).await {
+                        zone.ping_ms =
+                            Some(zone.ping_ms.map_or(second, |first| first.min(second)));
+                    }
+                }
+                zones.sort_by_key(|z| z.ping_ms.unwrap_or(u32::MAX));
+                zones
+            })
+            .await
+            .ok();
+            let _ = tx.send(AppEvent::ZoneFreshness { game, zones });
+        });
+    }
+
+    /// Decide what the freshness re-ping means: launch unchanged, switch
+    /// silently (automatic selection, or a remembered prompt answer), or
+    /// park the launch behind the zone-switch prompt.
+    fn finish_zone_freshness_check(&mut self, game: GameInfo, zones: Option<Vec<ServerInfo>>) {
+        let Some(zones) = zones else {
+            // Budget lapsed — the stored pings are the best data we have.
+            log::debug!("Pre-launch re-ping ran over budget; launching on stored pings");
+            self.begin_launch(&game, None);
+            return;
+        };
+        let stored_pings: HashMap<String, u32> = self
+            .servers
+            .iter()
+            .filter_map(|z| Some((z.id.clone(), z.ping_ms?)))
+            .collect();
+        self.servers = zones;
+        let selected = self
+            .settings
+            .selected_server
+            .as_deref()
+            .and_then(|id| self.servers.iter().find(|z| z.id == id))
+            .cloned();
+        let Some(selected) = selected else {
+            // Automatic selection: `servers` is freshly ping-sorted, so
+            // resolving the zone again is the silent re-selection.
+            self.begin_launch(&game, None);
+            return;
+        };
+        let better = serverinfo::switch_candidate(
+            &selected,
+            &self.servers,
+            stored_pings.get(&selected.id).copied(),
+            self.settings.prelaunch_ping_threshold_ms,
+        );
+        let Some(better) = better else {
+            self.begin_launch(&game, None);
+            return;
+        };
+        match self.settings.prelaunch_switch_remembered {
+            Some(true) => {
+                self.notify_info(format!(
+                    "{} is at {}ms — using {} ({}ms) for this session",
+                    selected.name,
+                    selected.ping_ms.unwrap_or(0),
+                    better.name,
+                    better.ping_ms.unwrap_or(0),
+                ));
+                self.begin_launch(&game, Some(better.id));
+            }
+            Some(false) => self.begin_launch(&game, None),
+            None => {
+                self.focus_primary_action = true;
+                self.zone_switch_remember = false;
+                self.zone_switch_prompt = Some(ZoneSwitchPrompt {
+                    game,
+                    current_name: selected.name,
+                    current_ping_ms: selected.ping_ms,
+                    better,
+                });
+            }
+        }
+    }
+
+    /// Answer the zone-switch prompt. `switch` uses the offered zone for
+    /// this session only — the saved preference is never touched. With
+    /// "remember my choice" checked, the same answer applies silently
+    /// from now on.
+    pub fn answer_zone_switch_prompt(&mut self, switch: bool) {
+        let Some(prompt) = self.zone_switch_prompt.take() else {
+            return;
+        };
+        if self.zone_switch_remember {
+            self.settings.prelaunch_switch_remembered = Some(switch);
+            self.settings_changed();
+        }
+        self.begin_launch(&prompt.game, switch.then(|| prompt.better.id.clone()));
+    }
+
+    /// Kick off session creation for `game` and switch to the session
+    /// screen.
+    fn begin_launch(&mut self, game: &GameInfo, zone_override: Option<String>) {
+        // The freshness path arrives here already on the session screen;
+        // a guard failure below must land back on Games.
+        self.state = AppState::Games;
+        if self.offline {
+            self.notify_error("Offline — streaming unavailable");
+            return;
+        }
+        if let Err(message) = self.settings.validate_stream_config() {
+            // The modal greys these combos out, but settings.json can be
+            // edited into one; fail here with the explanation instead of
+            // an opaque server error.
+            self.notify_error(message);
+            return;
+        }
+        let Some(client) = self.api_client.clone() else {
+            self.notify_error("Not logged in");
+            return;
+        };
+        let candidates = self.launch_candidates(zone_override.as_deref());
+        if candidates.is_empty() {
+            self.notify_error("No server available");
+            return;
+        }
+        cache::clear_session_cache();
+        self.session = None;
+        self.stop_session_poll();
+        self.last_session_seq = 0;
+        self.launch_attempts.clear();
+        self.race_lanes = None;
+        self.streaming_game_title = Some(game.title.clone());
+        self.state = AppState::Session;
+        self.session_status_text = format!("Requesting session for {}…", game.title);
+        // Opt-in racing needs at least two candidates and an account
+        // that tolerates concurrent session requests.
+        let race_lane_count = if self.settings.session_racing && !self.racing_unsupported {
+            (self.settings.session_racing_zones.clamp(2, 4) as usize).min(candidates.len())
+        } else {
+            1
+        };
+        let settings = self.settings.clone();
+        let game_id = game.cms_id.clone();
+        let game_title = game.title.clone();
+        let launch_app_id = game.launch_app_id;
+        let tx = self.events_tx.clone();
+        // One task owns the whole launch attempt: create the session
+        // (failing over across candidate zones where allowed), then poll
+        // it on a fixed interval with the same client. Each update
+        // carries a sequence number; the session cache file is written
+        // only as crash-recovery state.
+        let task = self.runtime.spawn(async move {
+            // Resolve the launch id first: catalog entries occasionally
+            // arrive without one, and launching by the wrong identifier
+            // (the CMS id) starts the wrong edition or fails outright.
+            let app_id = match launch_app_id {
+                Some(id) => id,
+                None => {
+                    log::warn!(
+                        "{} ({}) has no launch id in its catalog entry; looking it up",
+                        game_title,
+                        game_id
+                    );
+                    match client.fetch_launch_app_id(&game_id).await {
+                        Ok(Some(id)) => id,
+                        Ok(None) => {
+                            let _ = tx.send(AppEvent::SessionCreated(Err(anyhow::anyhow!(
+                                "{} has no launch id",
+                                game_title
+                            ))));
+                            return;
+                        }
+                        Err(e) => {
+                            let _ = tx.send(AppEvent::SessionCreated(Err(e)));
+                            return;
+                        }
+                    }
+                }
+            };
+            let outcome = if race_lane_count > 1 {
+                race_sessions(
+                    &client,
+                    app_id,
+                    &candidates[..race_lane_count],
+                    &settings,
+                    &tx,
+                )
+                .await
+            } else {
+                RaceOutcome::Sequential
+            };
+            let (session, zone) = match outcome {
+                RaceOutcome::Winner { session, zone } => (session, zone),
+                RaceOutcome::Sequential => {
+                    match create_with_failover(&client, app_id, &candidates, &settings, &tx)
+                        .await
+                    {
+                        Some(created) => created,
+                        None => return,
+                    }
+                }
+                RaceOutcome::Failed => return,
+            };
+            let _ = tx.send(AppEvent::SessionCreated(Ok(session.clone())));
+            let session_id = session.session_id.clone();
+            write_session_recovery_cache(&session, &game_id, &zone);
+            let mut seq: u64 = 0;
+            loop {
+                tokio::time::sleep(SESSION_POLL_INTERVAL).await;
+                let result = client.poll_session(&zone, &session_id).await;
+                if let Ok(session) = &result {
+                    write_session_recovery_cache(session, &game_id, &zone);
+                }
+                seq += 1;
+                if tx.send(AppEvent::SessionUpdate { seq, result }).is_err() {
+                    break;
+                }
+            }
+        });
+        self.session_poll_task = Some(task);
+    }
+
+    /// Transition to streaming: spawn `run_streaming` with the current
+    /// session and switch the UI over.
+    pub fn start_streaming(&mut self) {
+        if self.state == AppState::Streaming {
+            return;
+        }
+        if !self.pipeline_active {
+            self.start_stream_pipeline();
+        }
+        if self.pipeline_active {
+            self.state = AppState::Streaming;
+        }
+    }
+
+    /// Spawn `run_streaming` without changing the visible screen.
+    fn start_stream_pipeline(&mut self) {
+        let Some(session) = self.session.clone() else {
+            return;
+        };
+        // Pending settings changes should be on disk before a stream
+        // that could take the whole process down with it.
+        self.flush_settings();
+        // The fresh input handler picks up the game's profile on the
+        // first frame.
+        self.input_profile_dirty = true;
+        self.stream_started_at = Some(Instant::now());
+        self.session_remaining_at_start = self
+            .remaining_hours()
+            .map(|hours| Duration::from_secs_f64(hours * 3600.0));
+        let mut warnings = self.settings.session_warning_minutes.clone();
+        warnings.sort_unstable_by(|a, b| b.cmp(a));
+        warnings.dedup();
+        self.session_warnings_pending = warnings;
+        self.stream_stop = Arc::new(AtomicBool::new(false));
+        // The server starts encoding at the requested resolution; the
+        // debounced viewport updates diff against this.
+        self.viewport_sent = Some(self.settings.resolution);
+        self.viewport_last_sent = None;
+        self.viewport_pending = None;
+        let (input_tx, input_rx) = mpsc::unbounded_channel();
+        // Announce pads that are already plugged in before any input
+        // flows, so the game shows controller glyphs from the first
+        // frame (hot-plugs are forwarded by `poll_controller_hotplug`).
+        if let Some(controller) = self.controller.as_mut() {
+            for event in controller.connection_snapshot() {
+                let _ = input_tx.send(event.to_input_event());
+            }
+        }
+        self.input_event_tx = Some(input_tx.clone());
+        crate::input::set_raw_input_sender(input_tx);
+        let (rumble_tx, rumble_rx) = mpsc::unbounded_channel();
+        self.rumble_rx = Some(rumble_rx);
+        let settings = self.settings.clone();
+        let shared_frame = self.current_frame.clone();
+        let stats = self.stream_stats.clone();
+        let connection_info = self.connection_info.clone();
+        // A fresh stream means the old review material is stale.
+        self.frame_history.lock().unwrap().clear();
+        let frame_history = self.frame_history.clone();
+        let stop = self.stream_stop.clone();
+        let tx = self.events_tx.clone();
+        self.stream_task = Some(self.runtime.spawn(async move {
+            if let Err(e) = crate::webrtc::run_streaming(
+                session,
+                settings,
+                shared_frame,
+                stats,
+                connection_info,
+                frame_history,
+                input_rx,
+                rumble_tx,
+                stop,
+            )
+            .await
+            {
+                log::error!("Streaming failed: {}", e);
+                let _ = tx.send(AppEvent::StreamingFailed(e.to_string()));
+            }
+        }));
+        if self.settings.spectate_enabled {
+            match crate::media::spectate::SpectateServer::start(
+                self.settings.spectate_max_fps,
+                self.settings.spectate_max_mbps,
+            ) {
+                Ok(server) => {
+                    self.notify_info(format!(
+                        "Co-watch link (experimental): {}",
+                        server.watch_url()
+                    ));
+                    self.spectate = Some(server);
+                }
+                Err(e) => self.notify_error(format!("Co-watch endpoint unavailable: {}", e)),
+            }
+        }
+        if self.settings.stats_export_enabled {
+            let dir = self
+                .settings
+                .stats_export_dir
+                .clone()
+                .unwrap_or_else(|| cache::get_app_data_dir().join("obs"));
+            let template = self.settings.stats_export_template.clone();
+            let stats = self.stream_stats.clone();
+            let stop = self.stream_stop.clone();
+            self.runtime.spawn(crate::media::stats_export::run_stats_exporter(
+                dir, template, stats, stop,
+            ));
+        }
+        self.pipeline_active = true;
+    }
+
+    /// Stop the stream and optionally terminate the session server-side.
+    pub fn stop_streaming(&mut self) {
+        if self.pipeline_active {
+            crate::session_result::session_ended(crate::session_result::EndReason::UserStop);
+            // A clean stop after a real stream means the previous
+            // failure's card has served its purpose.
+            if !self.stream_end_recorded {
+                self.dismiss_last_session_summary();
+            }
+        }
+        self.stream_end_recorded = false;
+        self.stream_started_at = None;
+        self.session_remaining_at_start = None;
+        self.session_warnings_pending.clear();
+        self.stop_session_poll();
+        self.scheduled_session = false;
+        self.schedule_cancel_deadline = None;
+        self.stream_stop.store(true, Ordering::SeqCst);
+        // Zero every pad the rig has seen before the channel goes away,
+        // so a stick held at stop time isn't left stuck server-side.
+        // Also resets the change baselines for the next stream.
+        if let Some(controller) = self.controller.as_mut() {
+            if let Some(tx) = &self.input_event_tx {
+                for slot in controller.take_reported_slots() {
+                    let _ = tx.send(InputEvent::GamepadState {
+                        slot,
+                        buttons: 0,
+                        left_trigger: 0,
+                        right_trigger: 0,
+                        left_stick: (0, 0),
+                        right_stick: (0, 0),
+                    });
+                }
+            }
+            // ...and stop our side's motors; the rig won't send the
+            // zero-magnitude command once the channel is gone.
+            controller.stop_rumble();
+        }
+        self.input_event_tx = None;
+        self.rumble_rx = None;
+        self.reconnect_attempts = 0;
+        self.reconnect_at = None;
+        if let (Some(session), Some(client)) = (self.session.take(), self.api_client.clone()) {
+            if let Some(zone) = self.active_zone.take().or_else(|| self.resolve_zone()) {
+                self.runtime.spawn(async move {
+                    if let Err(e) = client.delete_session(&zone, &session.session_id).await {
+                        log::warn!("Failed to terminate session: {}", e);
+                    }
+                });
+            }
+        }
+        cache::clear_session_cache();
+        *self.stream_stats.lock().unwrap() = StreamStats::default();
+        *self.connection_info.lock().unwrap() = crate::webrtc::ConnectionInfo::default();
+        self.pipeline_active = false;
+        self.spectate = None;
+        self.race_lanes = None;
+        self.zone_switch_prompt = None;
+        self.queue_started_at = None;
+        self.queue_estimate_secs = None;
+        self.show_quick_menu = false;
+        self.local_cursor_pos = None;
+        self.av_sync_test = false;
+        self.av_sync_last_click = None;
+        self.setup_progress = None;
+        self.setup_last_change = None;
+        self.setup_stalled = false;
+        self.setup_watch_requested = false;
+        self.state = AppState::Games;
+        // The session just burned entitled time; bring the balance up to
+        // date for the hours indicator.
+        self.refresh_account_snapshot();
+    }
+
+    /// Persist an abnormal stream end (with a final stats snapshot) so
+    /// the Games screen can say what happened even after a relaunch.
+    fn record_stream_end(&mut self, classification: &str, reason: String) {
+        let summary = cache::StreamEndSummary {
+            ended_at: chrono::Utc::now().timestamp(),
+            game_title: self
+                .streaming_game_title
+                .clone()
+                .unwrap_or_else(|| "your last game".to_string()),
+            duration_secs: self
+                .stream_started_at
+                .map(|started| started.elapsed().as_secs_f32())
+                .unwrap_or(0.0),
+            classification: classification.to_string(),
+            reason,
+            stats: self.stream_stats.lock().unwrap().clone(),
+        };
+        if let Err(e) = cache::save_last_session_summary(&summary) {
+            log::error!("Failed to persist stream end summary: {}", e);
+        }
+        self.last_session_summary = Some(summary);
+        self.stream_end_recorded = true;
+    }
+
+    pub fn dismiss_last_session_summary(&mut self) {
+        if self.last_session_summary.take().is_some() {
+            cache::clear_last_session_summary();
+        }
+    }
+
+    /// Write the end summary to a standalone file under the app data
+    /// dir, ready to attach to a bug report.
+    pub fn export_last_session_diagnostics(&mut self) {
+        let Some(summary) = self.last_session_summary.clone() else {
+            return;
+        };
+        let path = cache::get_app_data_dir().join(format!("diagnostics-{}.json", summary.ended_at));
+        let result = serde_json::to_string_pretty(&summary)
+            .map_err(anyhow::Error::from)
+            .and_then(|json| std::fs::write(&path, json).map_err(anyhow::Error::from));
+        match result {
+            Ok(()) => self.notify_success(format!("Diagnostics written to {}", path.display())),
+            Err(e) => self.notify_error(format!("Couldn't write diagnostics: {}", e)),
+        }
+    }
+
+    pub fn runtime(&self) -> &tokio::runtime::Handle {
+        &self.runtime
+    }
+
+    pub fn events_tx(&self) -> UnboundedSender<AppEvent> {
+        self.events_tx.clone()
+    }
+}
+
+/// Paginated library sync: fetch pages with per-page retry/backoff,
+/// merge incrementally (a complete cached sync seeds the merge and its
+/// timestamp is passed as `modifiedSince`), and persist after every
+/// page with the completeness marker only set at the end.
+async fn sync_library(
+    client: &GfnApiClient,
+    tx: &UnboundedSender<AppEvent>,
+) -> anyhow::Result<Vec<GameInfo>> {
+    let previous = cache::load_library_cache().filter(|c| c.complete);
+    let modified_since = previous.as_ref().map(|c| c.synced_at);
+    let mut merged = previous.map(|c| c.games).unwrap_or_default();
+    let synced_at = chrono::Utc::now().timestamp();
+    let mut cursor: Option<String> = None;
+    loop {
+        let mut attempt = 0u32;
+        let page = loop {
+            match client
+                .fetch_library_page(cursor.as_deref(), modified_since)
+                .await
+            {
+                Ok(page) => break page,
+                Err(e) if attempt < 3 => {
+                    attempt += 1;
+                    log::warn!(
+                        "Library page fetch failed (attempt {}): {}; retrying",
+                        attempt,
+                        e
+                    );
+                    tokio::time::sleep(Duration::from_secs(1 << attempt)).await;
+                }
+                Err(e) => return Err(e),
+            }
+        };
+        let total = page.total;
+        let next_cursor = page.next_cursor.clone();
+        crate::api::merge_library_page(&mut merged, page.items);
+        let _ = tx.send(AppEvent::LibrarySyncProgress {
+            loaded: merged.len(),
+            total,
+        });
+        if let Err(e) = cache::save_library_cache(&cache::LibraryCache {
+            complete: next_cursor.is_none(),
+            synced_at,
+            games: merged.clone(),
+        }) {
+            log::warn!("Failed to write library cache: {}", e);
+        }
+        match next_cursor {
+            Some(next) => cursor = Some(next),
+            None => break,
+        }
+    }
+    Ok(merged)
+}
+
+/// True for connectivity failures (DNS, refused, timeout) as opposed to
+/// a server that answered with an error.
+fn is_network_error(error: &anyhow::Error) -> bool {
+    error.chain().any(|cause| {
+        cause
+            .downcast_ref::<reqwest::Error>()
+            .is_some_and(|e| e.is_connect() || e.is_timeout())
+    })
+}
+
+/// True when the server explicitly rejected our credentials.
+fn is_auth_rejection(error: &anyhow::Error) -> bool {
+    error
+        .chain()
+        .any(|cause| cause.to_string().contains("401"))
+}
+
+/// Per-lane progress messages inside a session race.
+enum RacerEvent {
+    Status { lane: usize, text: String },
+    Created { lane: usize, session_id: String },
+    Ready { lane: usize, session: SessionInfo },
+    Failed {
+        lane: usize,
+        error: String,
+        concurrent_limit: bool,
+    },
+}
+
+/// How a race resolved.
+enum RaceOutcome {
+    Winner { session: SessionInfo, zone: String },
+    /// Racing can't (or shouldn't) run; launch through the normal
+    /// failover path instead.
+    Sequential,
+    /// Every lane failed; the error has already been reported.
+    Failed,
+}
+
+/// Server messages seen when an account's one-session limit rejects a
+/// concurrent request.
+fn is_concurrent_session_error(message: &str) -> bool {
+    let upper = message.to_uppercase();
+    upper.contains("CONCURRENT") || upper.contains("SESSION_LIMIT") || upper.contains("ALREADY")
+}
+
+/// The non-racing path: create a session in each candidate zone in
+/// order, failing over on retryable rejections. Returns the session and
+/// its zone, or None after reporting the failure.
+async fn create_with_failover(
+    client: &Arc<GfnApiClient>,
+    app_id: u64,
+    candidates: &[ServerInfo],
+    settings: &Settings,
+    tx: &UnboundedSender<AppEvent>,
+) -> Option<(SessionInfo, String)> {
+    for (index, candidate) in candidates.iter().enumerate() {
+        let _ = tx.send(AppEvent::LaunchAttempt {
+            zone_name: candidate.name.clone(),
+            zone_address: candidate.address.clone(),
+        });
+        let zone = candidate.address.clone();
+        match client.create_session(app_id, &zone, settings).await {
+            Ok(created) => return Some((created, zone)),
+            Err(e) => {
+                let retryable = e
+                    .downcast_ref::<crate::api::cloudmatch::SessionRejection>()
+                    .is_some();
+                let next = candidates.get(index + 1);
+                if retryable && next.is_some() {
+                    let next_name = &next.unwrap().name;
+                    log::info!(
+                        "{} rejected launch ({}); failing over to {}",
+                        candidate.name,
+                        e,
+                        next_name
+                    );
+                    let _ = tx.send(AppEvent::LaunchStatus(format!(
+                        "{} full — trying {}…",
+                        candidate.name, next_name
+                    )));
+                    continue;
+                }
+                let _ = tx.send(AppEvent::SessionCreated(Err(e)));
+                return None;
+            }
+        }
+    }
+    None
+}
+
+/// Race session creation across `zones`: every lane creates and polls
+/// its own session, the first to reach Ready wins, and every other
+/// created session is cancelled (with retries) before the winner is
+/// returned — so at most one session is ever left active.
+async fn race_sessions(
+    client: &Arc<GfnApiClient>,
+    app_id: u64,
+    zones: &[ServerInfo],
+    settings: &Settings,
+    tx: &UnboundedSender<AppEvent>,
+) -> RaceOutcome {
+    let _ = tx.send(AppEvent::RaceStarted(
+        zones.iter().map(|zone| zone.name.clone()).collect(),
+    ));
+    let (race_tx, mut race_rx) = mpsc::unbounded_channel();
+    let mut lanes = Vec::new();
+    for (lane, zone) in zones.iter().enumerate() {
+        let client = client.clone();
+        let settings = settings.clone();
+        let race_tx = race_tx.clone();
+        let address = zone.address.clone();
+        lanes.push(tokio::spawn(async move {
+            let session = match client.create_session(app_id, &address, &settings).await {
+                Ok(session) => session,
+                Err(e) => {
+                    let error = format!("{:#}", e);
+                    let _ = race_tx.send(RacerEvent::Failed {
+                        lane,
+                        concurrent_limit: is_concurrent_session_error(&error),
+                        error,
+                    });
+                    return;
+                }
+            };
+            let session_id = session.session_id.clone();
+            let _ = race_tx.send(RacerEvent::Created { lane, session_id: session_id.clone() });
+            loop {
+                tokio::time::sleep(SESSION_POLL_INTERVAL).await;
+                match client.poll_session(&address, &session_id).await {
+                    Ok(update) => match &update.state {
+                        SessionState::Queued { position, .. } => {
+                            let text = match position {
+                                Some(position) => format!("Queue position: {}", position),
+                                None => "Queued…".to_string(),
+                            };
+                            let _ = race_tx.send(RacerEvent::Status { lane, text });
+                        }
+                        SessionState::Provisioning => {
+                            let _ = race_tx.send(RacerEvent::Status {
+                                lane,
+                                text: "Preparing your rig…".to_string(),
+                            });
+                        }
+                        SessionState::Ready | SessionState::Streaming => {
+                            let _ = race_tx.send(RacerEvent::Ready { lane, session: update });
+                            return;
+                        }
+                        SessionState::Finished | SessionState::Error(_) => {
+                            let _ = race_tx.send(RacerEvent::Failed {
+                                lane,
+                                error: format!("session ended: {:?}", update.state),
+                                concurrent_limit: false,
+                            });
+                            return;
+                        }
+                    },
+                    // Transient poll errors: keep trying; the
+                    // coordinator only cares about outcomes.
+                    Err(e) => log::warn!("Race poll failed: {}", e),
+                }
+            }
+        }));
+    }
+    drop(race_tx);
+    let mut created: Vec<Option<String>> = vec![None; zones.len()];
+    let mut saw_concurrent_limit = false;
+    let mut last_error = String::new();
+    while let Some(event) = race_rx.recv().await {
+        match event {
+            RacerEvent::Status { lane, text } => {
+                let _ = tx.send(AppEvent::RaceLaneUpdate {
+                    lane,
+                    status: text,
+                    finished: false,
+                });
+            }
+            RacerEvent::Created { lane, session_id } => {
+                created[lane] = Some(session_id);
+                let _ = tx.send(AppEvent::RaceLaneUpdate {
+                    lane,
+                    status: "Session created — waiting for a seat…".to_string(),
+                    finished: false,
+                });
+            }
+            RacerEvent::Ready { lane, session } => {
+                let _ = tx.send(AppEvent::RaceLaneUpdate {
+                    lane,
+                    status: "Ready — streaming from here".to_string(),
+                    finished: true,
+                });
+                for handle in &lanes {
+                    handle.abort();
+                }
+                for (loser, session_id) in created.iter().enumerate() {
+                    if loser == lane {
+                        continue;
+                    }
+                    match session_id {
+                        Some(session_id) => {
+                            cancel_losing_session(client, &zones[loser], loser, session_id, tx)
+                                .await;
+                        }
+                        None => {
+                            let _ = tx.send(AppEvent::RaceLaneUpdate {
+                                lane: loser,
+                                status: "Lost the race".to_string(),
+                                finished: true,
+                            });
+                        }
+                    }
+                }
+                return RaceOutcome::Winner {
+                    zone: zones[lane].address.clone(),
+                    session,
+                };
+            }
+            RacerEvent::Failed {
+                lane,
+                error,
+                concurrent_limit,
+            } => {
+                let _ = tx.send(AppEvent::RaceLaneUpdate {
+                    lane,
+                    status: format!("Failed: {}", error),
+                    finished: true,
+                });
+                if concurrent_limit {
+                    // The other lane may already hold the only allowed
+                    // session; let it finish the race alone.
+                    saw_concurrent_limit = true;
+                    let _ = tx.send(AppEvent::RacingUnsupported);
+                } else {
+                    last_error = error;
+                }
+            }
+        }
+    }
+    // Every lane ended without a winner. If the one-session limit was
+    // the culprit, hand over to the sequential path; otherwise the race
+    // genuinely failed everywhere.
+    if saw_concurrent_limit {
+        return RaceOutcome::Sequential;
+    }
+    let _ = tx.send(AppEvent::SessionCreated(Err(anyhow::anyhow!(
+        "every raced zone failed — last error: {}",
+        last_error
+    ))));
+    RaceOutcome::Failed
+}
+
+/// The race's safety guarantee: a losing session must not stay active.
+/// Retries the cancel with backoff and surfaces a final failure to the
+/// user instead of silently leaking a session that burns hours.
+async fn cancel_losing_session(
+    client: &Arc<GfnApiClient>,
+    zone: &ServerInfo,
+    lane: usize,
+    session_id: &str,
+    tx: &UnboundedSender<AppEvent>,
+) {
+    const CANCEL_ATTEMPTS: u32 = 3;
+    let mut last_error = String::new();
+    for attempt in 1..=CANCEL_ATTEMPTS {
+        match client.delete_session(&zone.address, session_id).await {
+            Ok(()) => {
+                log::info!("Cancelled losing race session in {}", zone.name);
+                let _ = tx.send(AppEvent::RaceLaneUpdate {
+                    lane,
+                    status: "Lost the race — session cancelled".to_string(),
+                    finished: true,
+                });
+                return;
+            }
+            Err(e) => {
+                last_error = format!("{:#}", e);
+                log::warn!(
+                    "Cancel attempt {}/{} for losing session in {} failed: {}",
+                    attempt,
+                    CANCEL_ATTEMPTS,
+                    zone.name,
+                    last_error
+                );
+                tokio::time::sleep(Duration::from_secs(2 * attempt as u64)).await;
+            }
+        }
+    }
+    let _ = tx.send(AppEvent::RaceCancelFailed {
+        zone_name: zone.name.clone(),
+        error: last_error,
+    });
+}
+
+/// Persist the latest known session state so a crashed client can find
+/// its way back to a still-running session on restart.
+fn write_session_recovery_cache(session: &SessionInfo, game_id: &str, zone: &str) {
+    let result = cache::save_session_cache(&cache::SessionCache {
+        session_id: session.session_id.clone(),
+        game_id: game_id.to_string(),
+        state: session.state.clone(),
+        server_address: session.server_address.clone(),
+        signal_connection_url: session.signal_connection_url.clone(),
+        zone: Some(zone.to_string()),
+        updated_at: chrono::Utc::now().timestamp(),
+    });
+    if let Err(e) = result {
+        log::warn!("Failed to write session recovery cache: {}", e);
+    }
+}
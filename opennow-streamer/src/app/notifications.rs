@@ -0,0 +1,112 @@
+//! Transient notification queue backing the toast stack and the
+//! notification history panel.
+
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+/// Toasts stacked on screen at once.
+pub const MAX_VISIBLE_TOASTS: usize = 3;
+/// Entries kept in the history panel.
+const MAX_HISTORY: usize = 50;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NotificationLevel {
+    Info,
+    Success,
+    Warning,
+    Error,
+}
+
+/// Optional action button attached to a toast.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NotificationAction {
+    OpenSettings,
+    RetryGamesLoad,
+    EnableLowSpecUi,
+}
+
+impl NotificationAction {
+    pub fn label(&self) -> &'static str {
+        match self {
+            NotificationAction::OpenSettings => "Open settings",
+            NotificationAction::RetryGamesLoad => "Retry",
+            NotificationAction::EnableLowSpecUi => "Enable low-spec UI",
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Notification {
+    pub id: u64,
+    pub level: NotificationLevel,
+    pub text: String,
+    pub timestamp: chrono::DateTime<chrono::Utc>,
+    pub action: Option<NotificationAction>,
+    /// When the toast auto-dismisses; pushed forward while hovered.
+    deadline: Instant,
+}
+
+impl Notification {
+    fn display_time(level: NotificationLevel) -> Duration {
+        match level {
+            NotificationLevel::Error => Duration::from_secs(8),
+            _ => Duration::from_secs(5),
+        }
+    }
+
+    /// Keep the toast alive while the pointer is over it.
+    pub fn pause_dismiss(&mut self) {
+        self.deadline = Instant::now() + Duration::from_secs(2);
+    }
+}
+
+#[derive(Default)]
+pub struct Notifications {
+    queue: VecDeque<Notification>,
+    history: VecDeque<Notification>,
+    next_id: u64,
+}
+
+impl Notifications {
+    pub fn push(
+        &mut self,
+        level: NotificationLevel,
+        text: impl Into<String>,
+        action: Option<NotificationAction>,
+    ) {
+        let text = text.into();
+        log::info!("Notification ({:?}): {}", level, text);
+        self.next_id += 1;
+        let notification = Notification {
+            id: self.next_id,
+            level,
+            text,
+            timestamp: chrono::Utc::now(),
+            action,
+            deadline: Instant::now() + Notification::display_time(level),
+        };
+        self.history.push_front(notification.clone());
+        self.history.truncate(MAX_HISTORY);
+        self.queue.push_back(notification);
+    }
+
+    /// Drop expired toasts; call once per frame before rendering.
+    pub fn tick(&mut self) {
+        let now = Instant::now();
+        self.queue.retain(|n| n.deadline > now);
+    }
+
+    /// The toasts currently on screen, oldest first.
+    pub fn visible_mut(&mut self) -> impl Iterator<Item = &mut Notification> {
+        self.queue.iter_mut().take(MAX_VISIBLE_TOASTS)
+    }
+
+    pub fn dismiss(&mut self, id: u64) {
+        self.queue.retain(|n| n.id != id);
+    }
+
+    /// History entries, newest first.
+    pub fn history(&self) -> impl Iterator<Item = &Notification> {
+        self.history.iter()
+    }
+}
@@ -0,0 +1,538 @@
+//! OAuth login against NVIDIA (or an Alliance partner), token persistence
+//! and refresh.
+
+use std::fs;
+use std::io::{BufRead, BufReader, Write};
+use std::net::TcpListener;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+
+use anyhow::{anyhow, bail, Context, Result};
+use serde::{Deserialize, Serialize};
+
+use crate::app::cache::get_profile_data_dir;
+
+const CALLBACK_PORT: u16 = 17452;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AuthTokens {
+    pub access_token: String,
+    pub refresh_token: Option<String>,
+    /// Unix timestamp at which the access token expires.
+    pub expires_at: i64,
+}
+
+impl AuthTokens {
+    pub fn is_expired(&self) -> bool {
+        chrono::Utc::now().timestamp() >= self.expires_at
+    }
+
+    /// True when the token is within 5 minutes of expiry and worth
+    /// refreshing proactively.
+    pub fn should_refresh(&self) -> bool {
+        chrono::Utc::now().timestamp() >= self.expires_at - 300
+    }
+}
+
+/// An identity provider the user can log in through: NVIDIA itself or a
+/// GFN Alliance partner.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LoginProvider {
+    pub name: String,
+    pub code: String,
+    pub auth_url: String,
+    pub token_url: String,
+    pub client_id: String,
+}
+
+pub fn nvidia_default() -> LoginProvider {
+    LoginProvider {
+        name: "NVIDIA".to_string(),
+        code: "nvidia".to_string(),
+        auth_url: "https://login.nvidia.com/authorize".to_string(),
+        token_url: "https://login.nvidia.com/token".to_string(),
+        client_id: "GFN-PC".to_string(),
+    }
+}
+
+static ACTIVE_PROVIDER: Mutex<Option<LoginProvider>> = Mutex::new(None);
+
+pub fn set_login_provider(provider: &LoginProvider) {
+    let changed = {
+        let mut active = ACTIVE_PROVIDER.lock().unwrap();
+        let changed = active.as_ref().map(|p| p.code.as_str()) != Some(provider.code.as_str());
+        *active = Some(provider.clone());
+        changed
+    };
+    if changed {
+        // A different provider has a different zone catalog; the cached
+        // region list must not survive the switch.
+        crate::api::serverinfo::invalidate_regions_cache();
+    }
+    if let Err(e) = crate::app::cache::save_login_provider(provider) {
+        log::warn!("Failed to persist login provider: {}", e);
+    }
+}
+
+pub fn active_provider() -> LoginProvider {
+    ACTIVE_PROVIDER
+        .lock()
+        .unwrap()
+        .clone()
+        .unwrap_or_else(nvidia_default)
+}
+
+/// Fetch the list of available login providers (NVIDIA + Alliance
+/// partners for the user's region).
+pub async fn fetch_login_providers() -> Result<Vec<LoginProvider>> {
+    let client = reqwest::Client::builder()
+        .user_agent(crate::USER_AGENT)
+        .build()?;
+    let body: serde_json::Value = client
+        .get("https://api-prod.nvidia.com/gfn/v2/loginProviders")
+        .send()
+        .await
+        .context("loginProviders request failed")?
+        .json()
+        .await?;
+    let mut providers = vec![nvidia_default()];
+    if let Some(items) = body["providers"].as_array() {
+        for item in items {
+            if let Ok(provider) = serde_json::from_value::<LoginProvider>(item.clone()) {
+                providers.push(provider);
+            }
+        }
+    }
+    Ok(providers)
+}
+
+pub fn tokens_path() -> PathBuf {
+    get_profile_data_dir().join("auth.json")
+}
+
+/// Guards all writes to auth.json so concurrent login/refresh tasks
+/// can't interleave and leave a truncated file behind.
+static TOKENS_FILE_LOCK: Mutex<()> = Mutex::new(());
+
+/// Token persistence goes through the OS keyring instead of auth.json
+/// (`Settings::use_secure_token_storage`). Set at startup before the
+/// first `load_tokens`; a broken keyring falls back to the file.
+static SECURE_STORAGE: AtomicBool = AtomicBool::new(false);
+
+/// Service/account naming the keyring entry that holds the serialized
+/// tokens.
+const KEYRING_SERVICE: &str = "OpenNOW";
+const KEYRING_ACCOUNT: &str = "gfn-tokens";
+
+/// One keyring entry per profile; the default profile keeps the
+/// original account name so pre-profile entries are still found.
+fn keyring_account() -> String {
+    let profile = crate::app::cache::active_profile();
+    if profile == crate::app::cache::DEFAULT_PROFILE {
+        KEYRING_ACCOUNT.to_string()
+    } else {
+        format!("{}-{}", KEYRING_ACCOUNT, profile)
+    }
+}
+
+pub fn set_secure_storage(enabled: bool) {
+    SECURE_STORAGE.store(enabled, Ordering::Relaxed);
+}
+
+/// Read the keyring entry: Ok(None) when no entry exists yet, Err when
+/// the keyring itself is unavailable (no secret service on the bus,
+/// locked collection).
+fn load_tokens_from_keyring() -> Result<Option<AuthTokens>> {
+    let entry = keyring::Entry::new(KEYRING_SERVICE, &keyring_account())?;
+    match entry.get_password() {
+        Ok(data) => Ok(Some(serde_json::from_str(&data)?)),
+        Err(keyring::Error::NoEntry) => Ok(None),
+        Err(e) => Err(e.into()),
+    }
+}
+
+fn save_tokens_to_keyring(tokens: &AuthTokens) -> Result<()> {
+    let entry = keyring::Entry::new(KEYRING_SERVICE, &keyring_account())?;
+    entry.set_password(&serde_json::to_string(tokens)?)?;
+    Ok(())
+}
+
+/// Discard tokens that can no longer open a session.
+fn usable(tokens: AuthTokens) -> Option<AuthTokens> {
+    if tokens.is_expired() && tokens.refresh_token.is_none() {
+        log::info!("Stored tokens expired with no refresh token; discarding");
+        return None;
+    }
+    Some(tokens)
+}
+
+pub fn load_tokens() -> Option<AuthTokens> {
+    if SECURE_STORAGE.load(Ordering::Relaxed) {
+        match load_tokens_from_keyring() {
+            Ok(Some(tokens)) => return usable(tokens),
+            Ok(None) => {
+                // First run with secure storage: migrate an existing
+                // auth.json into the keyring and delete the plaintext.
+                let tokens = load_tokens_from_file()?;
+                match save_tokens_to_keyring(&tokens) {
+                    Ok(()) => {
+                        let _ = fs::remove_file(tokens_path());
+                        log::info!("Migrated auth.json into the OS keyring");
+                    }
+                    Err(e) => log::warn!("Keyring migration failed: {}", e),
+                }
+                return Some(tokens);
+            }
+            Err(e) => log::warn!("Keyring unavailable ({}); using auth.json", e),
+        }
+    }
+    load_tokens_from_file()
+}
+
+fn load_tokens_from_file() -> Option<AuthTokens> {
+    let path = tokens_path();
+    let data = fs::read_to_string(&path).ok()?;
+    let tokens: AuthTokens = match serde_json::from_str(&data) {
+        Ok(tokens) => tokens,
+        Err(e) => {
+            log::warn!("auth.json is corrupt ({}); backing it up and starting fresh", e);
+            let _ = fs::rename(&path, path.with_extension("json.corrupt"));
+            return None;
+        }
+    };
+    usable(tokens)
+}
+
+pub fn save_tokens(tokens: &AuthTokens) -> Result<()> {
+    if SECURE_STORAGE.load(Ordering::Relaxed) {
+        match save_tokens_to_keyring(tokens) {
+            Ok(()) => {
+                // Never leave a stale plaintext copy behind the keyring.
+                let _ = fs::remove_file(tokens_path());
+                return Ok(());
+            }
+            Err(e) => log::warn!("Keyring unavailable ({}); writing auth.json", e),
+        }
+    }
+    save_tokens_at(&tokens_path(), tokens)
+}
+
+/// Serialized, atomic write: serialize under the file lock into a temp
+/// file, then rename over the target so readers never see a partial
+/// file.
+fn save_tokens_at(path: &std::path::Path, tokens: &AuthTokens) -> Result<()> {
+    let _guard = TOKENS_FILE_LOCK.lock().unwrap();
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let tmp = path.with_extension("json.tmp");
+    fs::write(&tmp, serde_json::to_string_pretty(tokens)?)?;
+    fs::rename(&tmp, path).context("Failed to write auth.json")?;
+    Ok(())
+}
+
+/// Forget the active profile's tokens; other profiles are untouched.
+pub fn clear_tokens() {
+    let _ = fs::remove_file(tokens_path());
+    if let Ok(entry) = keyring::Entry::new(KEYRING_SERVICE, &keyring_account()) {
+        let _ = entry.delete_credential();
+    }
+}
+
+/// Everything the UI needs while a login attempt is in flight: the URL
+/// to open (or render as a QR code) and the CSRF state to validate.
+#[derive(Debug, Clone)]
+pub struct PendingLogin {
+    pub provider: LoginProvider,
+    pub auth_url: String,
+    pub redirect_uri: String,
+    /// Random CSRF token; callbacks without it are rejected so a LAN
+    /// peer can't inject a code when we're bound on all interfaces.
+    pub state: String,
+    /// Bind the callback server on 0.0.0.0 so the flow can be completed
+    /// from another device (remote/headless setups).
+    pub bind_all: bool,
+}
+
+/// Build a login attempt. With `bind_all` the redirect points at this
+/// machine's LAN address so a phone/other device can reach it.
+pub fn prepare_login(bind_all: bool) -> PendingLogin {
+    let provider = active_provider();
+    let state = format!("{:032x}", rand::random::<u128>());
+    let host = if bind_all {
+        lan_ip().map(|ip| ip.to_string()).unwrap_or_else(|| {
+            log::warn!("Could not determine LAN IP; falling back to localhost redirect");
+            "localhost".to_string()
+        })
+    } else {
+        "localhost".to_string()
+    };
+    let redirect_uri = format!("http://{}:{}/callback", host, CALLBACK_PORT);
+    let auth_url = format!(
+        "{}?response_type=code&client_id={}&redirect_uri={}&scope=openid+email&state={}",
+        provider.auth_url, provider.client_id, redirect_uri, state
+    );
+    PendingLogin {
+        provider,
+        auth_url,
+        redirect_uri,
+        state,
+        bind_all,
+    }
+}
+
+/// Run the interactive login: open the browser, wait for the redirect
+/// on the callback server, and exchange the code.
+pub async fn run_login_flow(pending: PendingLogin) -> Result<AuthTokens> {
+    log::info!("Opening browser for login via {}", pending.provider.name);
+    if let Err(e) = open::that(&pending.auth_url) {
+        log::warn!("Failed to open browser: {}; URL: {}", e, pending.auth_url);
+    }
+    let bind_all = pending.bind_all;
+    let expected_state = pending.state.clone();
+    let code = tokio::task::spawn_blocking(move || wait_for_callback(bind_all, &expected_state))
+        .await??;
+    exchange_code(&pending.provider, &code, &pending.redirect_uri).await
+}
+
+/// Manual fallback: the user pastes the full redirect URL the browser
+/// landed on (for networks where the callback can't reach this box).
+pub async fn import_redirect_url(pending: &PendingLogin, pasted: &str) -> Result<AuthTokens> {
+    let query = pasted
+        .split_once('?')
+        .map(|(_, q)| q)
+        .ok_or_else(|| anyhow!("That doesn't look like a redirect URL (no query string)"))?;
+    let code = query_param(query, "code")
+        .ok_or_else(|| anyhow!("No authorization code in the pasted URL"))?;
+    match query_param(query, "state") {
+        Some(state) if state == pending.state => {}
+        _ => return Err(anyhow!("State mismatch — paste the URL from this login attempt")),
+    }
+    exchange_code(&pending.provider, &code, &pending.redirect_uri).await
+}
+
+/// Block on a single HTTP request to the callback server, validate the
+/// `state` parameter, and extract the authorization code.
+fn wait_for_callback(bind_all: bool, expected_state: &str) -> Result<String> {
+    let bind_addr = if bind_all { "0.0.0.0" } else { "127.0.0.1" };
+    let listener = TcpListener::bind((bind_addr, CALLBACK_PORT))
+        .context("Failed to bind OAuth callback port")?;
+    loop {
+        let (mut stream, peer) = listener.accept().context("Callback accept failed")?;
+        let mut reader = BufReader::new(stream.try_clone()?);
+        let mut request_line = String::new();
+        reader.read_line(&mut request_line)?;
+        let query = request_line
+            .split_whitespace()
+            .nth(1)
+            .and_then(|path| path.split_once('?'))
+            .map(|(_, q)| q)
+            .unwrap_or_default()
+            .to_string();
+        if query_param(&query, "state").as_deref() != Some(expected_state) {
+            log::warn!("Rejected OAuth callback from {} with bad/missing state", peer);
+            let _ = stream.write_all(b"HTTP/1.1 400 Bad Request\r\n\r\nInvalid state");
+            continue;
+        }
+        let code = query_param(&query, "code")
+            .ok_or_else(|| anyhow!("No code in OAuth callback"))?;
+        let _ = stream.write_all(
+            b"HTTP/1.1 200 OK\r\nContent-Type: text/html\r\n\r\n<html><body>Login complete. You can close this window.</body></html>",
+        );
+        return Ok(code);
+    }
+}
+
+fn query_param(query: &str, name: &str) -> Option<String> {
+    query.split('&').find_map(|pair| {
+        let (key, value) = pair.split_once('=')?;
+        (key == name).then(|| value.to_string())
+    })
+}
+
+/// Best-effort LAN IP discovery (no traffic is actually sent). Also
+/// used by the co-watch endpoint to build its link.
+pub(crate) fn lan_ip() -> Option<std::net::IpAddr> {
+    let socket = std::net::UdpSocket::bind("0.0.0.0:0").ok()?;
+    socket.connect("8.8.8.8:80").ok()?;
+    Some(socket.local_addr().ok()?.ip())
+}
+
+/// Exchange an authorization code for tokens.
+async fn exchange_code(
+    provider: &LoginProvider,
+    code: &str,
+    redirect_uri: &str,
+) -> Result<AuthTokens> {
+    let client = reqwest::Client::builder()
+        .user_agent(crate::USER_AGENT)
+        .build()?;
+    let response: serde_json::Value = client
+        .post(&provider.token_url)
+        .form(&[
+            ("grant_type", "authorization_code"),
+            ("code", code),
+            ("client_id", provider.client_id.as_str()),
+            ("redirect_uri", redirect_uri),
+        ])
+        .send()
+        .await
+        .context("Token exchange failed")?
+        .json()
+        .await?;
+    tokens_from_response(&response)
+}
+
+/// Refresh an access token using the stored refresh token.
+pub async fn refresh_token(tokens: &AuthTokens) -> Result<AuthTokens> {
+    let refresh = tokens
+        .refresh_token
+        .as_deref()
+        .ok_or_else(|| anyhow!("No refresh token available"))?;
+    let provider = active_provider();
+    let client = reqwest::Client::builder()
+        .user_agent(crate::USER_AGENT)
+        .build()?;
+    let response: serde_json::Value = client
+        .post(&provider.token_url)
+        .form(&[
+            ("grant_type", "refresh_token"),
+            ("refresh_token", refresh),
+            ("client_id", provider.client_id.as_str()),
+        ])
+        .send()
+        .await
+        .context("Token refresh failed")?
+        .json()
+        .await?;
+    tokens_from_response(&response)
+}
+
+/// Manual token import for machines that can't complete browser OAuth:
+/// accepts either a bare refresh token or full auth.json contents.
+/// Validates the resulting access token against the userinfo endpoint
+/// before returning, so a bad paste fails here instead of on the first
+/// API call.
+pub async fn import_token_text(input: &str) -> Result<AuthTokens> {
+    let input = input.trim();
+    if input.is_empty() {
+        bail!("Nothing to import");
+    }
+    let mut tokens = if input.starts_with('{') {
+        serde_json::from_str::<AuthTokens>(input).context(
+            "Malformed auth.json — expected access_token / refresh_token / expires_at fields",
+        )?
+    } else if input.contains(char::is_whitespace) {
+        bail!("Malformed token — a refresh token is a single opaque string");
+    } else {
+        // A bare string is treated as a refresh token; exchange it for a
+        // fresh access token.
+        AuthTokens {
+            access_token: String::new(),
+            refresh_token: Some(input.to_string()),
+            expires_at: 0,
+        }
+    };
+    if tokens.access_token.is_empty() || tokens.is_expired() {
+        tokens = refresh_token(&tokens)
+            .await
+            .context("Could not exchange the refresh token (expired or revoked?)")?;
+    }
+    validate_access_token(&tokens.access_token).await?;
+    Ok(tokens)
+}
+
+/// Probe the userinfo endpoint with the token and map the HTTP status
+/// to a precise, user-facing error.
+pub async fn validate_access_token(access_token: &str) -> Result<()> {
+    let client = reqwest::Client::builder()
+        .user_agent(crate::USER_AGENT)
+        .build()?;
+    let response = client
+        .get(crate::api::USERINFO_URL)
+        .bearer_auth(access_token)
+        .send()
+        .await
+        .context("Userinfo request failed")?;
+    match response.status() {
+        status if status.is_success() => Ok(()),
+        reqwest::StatusCode::UNAUTHORIZED => {
+            Err(anyhow!("Token rejected: expired or revoked"))
+        }
+        reqwest::StatusCode::FORBIDDEN => {
+            Err(anyhow!("Token rejected: wrong audience (not a GFN token)"))
+        }
+        status => Err(anyhow!("Token validation failed: HTTP {}", status)),
+    }
+}
+
+fn tokens_from_response(response: &serde_json::Value) -> Result<AuthTokens> {
+    let access_token = match response["access_token"].as_str() {
+        Some(token) => token.to_string(),
+        None => bail!(
+            "Token endpoint error: {}",
+            response["error_description"]
+                .as_str()
+                .or_else(|| response["error"].as_str())
+                .unwrap_or("unknown")
+        ),
+    };
+    let expires_in = response["expires_in"].as_i64().unwrap_or(3600);
+    Ok(AuthTokens {
+        access_token,
+        refresh_token: response["refresh_token"].as_str().map(|s| s.to_string()),
+        expires_at: chrono::Utc::now().timestamp() + expires_in,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tokens(n: usize) -> AuthTokens {
+        AuthTokens {
+            access_token: format!("access-{}", "x".repeat(n % 64 + 1)),
+            refresh_token: Some(format!("refresh-{}", n)),
+            expires_at: chrono::Utc::now().timestamp() + n as i64,
+        }
+    }
+
+    /// Concurrent login/refresh writers must never leave auth.json in a
+    /// truncated or interleaved state: any successful read parses.
+    #[test]
+    fn concurrent_token_writes_keep_file_valid() {
+        let path = std::env::temp_dir().join(format!(
+            "opennow-auth-stress-{}.json",
+            std::process::id()
+        ));
+        let _ = fs::remove_file(&path);
+        let mut writers = Vec::new();
+        for thread in 0..8 {
+            let path = path.clone();
+            writers.push(std::thread::spawn(move || {
+                for n in 0..50 {
+                    save_tokens_at(&path, &tokens(thread * 100 + n)).unwrap();
+                }
+            }));
+        }
+        let reader = {
+            let path = path.clone();
+            std::thread::spawn(move || {
+                for _ in 0..400 {
+                    if let Ok(data) = fs::read_to_string(&path) {
+                        serde_json::from_str::<AuthTokens>(&data)
+                            .expect("auth.json readable but not valid JSON");
+                    }
+                }
+            })
+        };
+        for writer in writers {
+            writer.join().unwrap();
+        }
+        reader.join().unwrap();
+        let data = fs::read_to_string(&path).unwrap();
+        serde_json::from_str::<AuthTokens>(&data).unwrap();
+        let _ = fs::remove_file(&path);
+    }
+}
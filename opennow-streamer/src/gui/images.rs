@@ -0,0 +1,404 @@
+//! Non-blocking cover art loading for the games grid.
+//!
+//! Fetches run on the tokio runtime with hard per-request timeouts and a
+//! global concurrency cap; decoded images come back over a channel and
+//! are turned into textures between frames, so a slow or blackholed CDN
+//! can never stall the UI. Consecutive timeouts trip a circuit breaker
+//! that stops issuing new requests for a cooldown period — tiles just
+//! keep their placeholder until the CDN recovers.
+
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+use tokio::sync::mpsc::{self, UnboundedReceiver, UnboundedSender};
+
+/// Connection establishment budget per fetch.
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(3);
+/// Whole-request budget per fetch (connect + headers + body).
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+/// Covers fetched at once; the rest wait in placeholder state.
+const MAX_CONCURRENT_FETCHES: usize = 4;
+/// Fallback texture budget if settings hand us zero.
+const MIN_VRAM_BUDGET_MB: u32 = 64;
+/// Consecutive timeouts that open the circuit breaker.
+const BREAKER_THRESHOLD: u32 = 3;
+/// How long the breaker stays open before new fetches are attempted.
+const BREAKER_COOLDOWN: Duration = Duration::from_secs(30);
+
+enum ImageState {
+    /// A fetch task is (or will shortly be) running for this URL.
+    Loading,
+    Ready {
+        texture: egui::TextureHandle,
+        last_used: Instant,
+        /// RGBA bytes this texture occupies on the GPU, counted against
+        /// the budget.
+        bytes: usize,
+    },
+    /// Fetch or decode failed; placeholder for the rest of the run.
+    Failed,
+}
+
+struct FetchResult {
+    url: String,
+    outcome: Result<egui::ColorImage, FetchError>,
+}
+
+struct FetchError {
+    message: String,
+    /// Connect/request deadline exceeded — the breaker's signal.
+    timed_out: bool,
+}
+
+/// Stops issuing cover requests after `BREAKER_THRESHOLD` consecutive
+/// timeouts. After the cooldown a single further timeout re-opens it,
+/// so a still-dead CDN is probed with one request per cooldown, not a
+/// thundering herd.
+struct CircuitBreaker {
+    consecutive_timeouts: u32,
+    open_until: Option<Instant>,
+}
+
+impl CircuitBreaker {
+    fn new() -> Self {
+        Self {
+            consecutive_timeouts: 0,
+            open_until: None,
+        }
+    }
+
+    fn is_open(&mut self) -> bool {
+        match self.open_until {
+            Some(until) if Instant::now() < until => true,
+            Some(_) => {
+                // Half-open: allow requests again, but one more timeout
+                // trips immediately.
+                self.open_until = None;
+                self.consecutive_timeouts = BREAKER_THRESHOLD.saturating_sub(1);
+                false
+            }
+            None => false,
+        }
+    }
+
+    fn record_success(&mut self) {
+        self.consecutive_timeouts = 0;
+        self.open_until = None;
+    }
+
+    fn record_timeout(&mut self) {
+        self.consecutive_timeouts += 1;
+        if self.consecutive_timeouts >= BREAKER_THRESHOLD && self.open_until.is_none() {
+            log::warn!(
+                "Cover CDN timed out {} times in a row; pausing image loads for {}s",
+                self.consecutive_timeouts,
+                BREAKER_COOLDOWN.as_secs()
+            );
+            self.open_until = Some(Instant::now() + BREAKER_COOLDOWN);
+        }
+    }
+}
+
+pub struct ImageCache {
+    client: reqwest::Client,
+    entries: HashMap<String, ImageState>,
+    results_tx: UnboundedSender<FetchResult>,
+    results_rx: UnboundedReceiver<FetchResult>,
+    in_flight: usize,
+    breaker: CircuitBreaker,
+    /// GPU bytes Ready textures may occupy before LRU eviction kicks in.
+    budget_bytes: usize,
+    /// Running total across Ready entries.
+    ready_bytes: usize,
+}
+
+impl ImageCache {
+    pub fn new() -> Self {
+        let (results_tx, results_rx) = mpsc::unbounded_channel();
+        let client = reqwest::Client::builder()
+            .user_agent(crate::USER_AGENT)
+            .connect_timeout(CONNECT_TIMEOUT)
+            .timeout(REQUEST_TIMEOUT)
+            .build()
+            .expect("Failed to build image fetch client");
+        Self {
+            client,
+            entries: HashMap::new(),
+            results_tx,
+            results_rx,
+            in_flight: 0,
+            breaker: CircuitBreaker::new(),
+            budget_bytes: 256 * 1024 * 1024,
+            ready_bytes: 0,
+        }
+    }
+
+    /// Apply the configured texture budget (`Settings::cover_vram_budget_mb`).
+    pub fn set_budget_mb(&mut self, budget_mb: u32) {
+        self.budget_bytes = budget_mb.max(MIN_VRAM_BUDGET_MB) as usize * 1024 * 1024;
+    }
+
+    /// Current GPU bytes held by cover textures, for the settings UI.
+    pub fn texture_memory_bytes(&self) -> usize {
+        self.ready_bytes
+    }
+
+    /// Drain finished fetches into textures. Called once per frame,
+    /// between frames' UI passes — texture upload of a few covers is the
+    /// only work that happens on the UI thread.
+    pub fn apply_results(&mut self, ctx: &egui::Context) {
+        while let Ok(result) = self.results_rx.try_recv() {
+            self.in_flight = self.in_flight.saturating_sub(1);
+            match result.outcome {
+                Ok(image) => {
+                    self.breaker.record_success();
+                    let texture =
+                        ctx.load_texture(&result.url, image, egui::TextureOptions::LINEAR);
+                    self.insert_ready(result.url, texture);
+                    ctx.request_repaint();
+                }
+                Err(e) => {
+                    if e.timed_out {
+                        self.breaker.record_timeout();
+                    }
+                    log::debug!("Cover fetch failed for {}: {}", result.url, e.message);
+                    self.entries.insert(result.url, ImageState::Failed);
+                }
+            }
+        }
+        self.enforce_budget();
+    }
+
+    /// The texture for `url` if it's ready, updating its LRU stamp.
+    /// Otherwise starts a fetch (capacity and breaker permitting) and
+    /// returns None — the caller draws a placeholder.
+    pub fn texture(
+        &mut self,
+        runtime: &tokio::runtime::Handle,
+        url: &str,
+    ) -> Option<egui::TextureId> {
+        match self.entries.get_mut(url) {
+            Some(ImageState::Ready {
+                texture, last_used, ..
+            }) => {
+                *last_used = Instant::now();
+                return Some(texture.id());
+            }
+            Some(_) => return None,
+            None => {}
+        }
+        // Not requested yet. Under the cap and with the breaker closed,
+        // kick off a fetch; otherwise leave the entry absent so the next
+        // frame retries — tiles scrolled past never occupy a slot.
+        if self.in_flight >= MAX_CONCURRENT_FETCHES || self.breaker.is_open() {
+            return None;
+        }
+        self.entries.insert(url.to_string(), ImageState::Loading);
+        self.in_flight += 1;
+        let client = self.client.clone();
+        let tx = self.results_tx.clone();
+        let url = url.to_string();
+        runtime.spawn(async move {
+            let outcome = fetch_image(&client, &url).await;
+            let _ = tx.send(FetchResult { url, outcome });
+        });
+        None
+    }
+
+    /// Drop every loaded texture. Used on GPU device loss: the handles
+    /// belong to a context that no longer exists, so entries disappear
+    /// entirely and visible tiles reload lazily — from the disk cache,
+    /// not the CDN. In-flight fetches land in the new context as usual.
+    pub fn forget_textures(&mut self) {
+        self.entries
+            .retain(|_, state| !matches!(state, ImageState::Ready { .. }));
+        self.ready_bytes = 0;
+    }
+
+    fn insert_ready(&mut self, url: String, texture: egui::TextureHandle) {
+        let [w, h] = texture.size();
+        let bytes = w * h * 4;
+        self.ready_bytes += bytes;
+        self.entries.insert(
+            url,
+            ImageState::Ready {
+                texture,
+                last_used: Instant::now(),
+                bytes,
+            },
+        );
+    }
+
+    /// Free least-recently-shown textures until cover art fits the
+    /// budget again. Dropping the handle releases the GPU memory; the
+    /// entry disappears entirely, so a tile scrolling back into view
+    /// reloads it — from the disk cache, not the CDN. The visible grid
+    /// is a few MB at most, so evictions only ever hit off-screen tiles.
+    fn enforce_budget(&mut self) {
+        if self.ready_bytes <= self.budget_bytes {
+            return;
+        }
+        let mut stamped: Vec<(String, Instant, usize)> = self
+            .entries
+            .iter()
+            .filter_map(|(url, state)| match state {
+                ImageState::Ready {
+                    last_used, bytes, ..
+                } => Some((url.clone(), *last_used, *bytes)),
+                _ => None,
+            })
+            .collect();
+        stamped.sort_by_key(|(_, last_used, _)| *last_used);
+        for (url, _, bytes) in stamped {
+            if self.ready_bytes <= self.budget_bytes {
+                break;
+            }
+            self.entries.remove(&url);
+            self.ready_bytes -= bytes;
+        }
+    }
+}
+
+impl Default for ImageCache {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Where the encoded (JPEG/PNG) cover for `url` lives on disk. Box art
+/// is immutable per URL, so a plain content file keyed by URL hash works
+/// as a permanent cache.
+fn disk_cache_path(url: &str) -> std::path::PathBuf {
+    use std::hash::{Hash, Hasher};
+    let mut hasher = std::collections::hash_map::DefaultHasher::new();
+    url.hash(&mut hasher);
+    crate::app::cache::get_app_data_dir()
+        .join("covers")
+        .join(format!("{:016x}.img", hasher.finish()))
+}
+
+/// Fetch and decode one cover, entirely off the UI thread. The disk
+/// cache is tried first so evicted textures (and restarts) never go back
+/// to the CDN. The client's timeouts bound the network half; decode of a
+/// box-art JPEG is microseconds by comparison.
+async fn fetch_image(client: &reqwest::Client, url: &str) -> Result<egui::ColorImage, FetchError> {
+    let cache_path = disk_cache_path(url);
+    if let Ok(bytes) = std::fs::read(&cache_path) {
+        if let Ok(image) = decode_image(&bytes) {
+            return Ok(image);
+        }
+        // Corrupt cache file; refetch over it.
+    }
+    let map_err = |e: reqwest::Error| FetchError {
+        timed_out: e.is_timeout() || e.is_connect(),
+        message: e.to_string(),
+    };
+    let bytes = client
+        .get(url)
+        .send()
+        .await
+        .and_then(|r| r.error_for_status())
+        .map_err(map_err)?
+        .bytes()
+        .await
+        .map_err(map_err)?;
+    let image = decode_image(&bytes)?;
+    // Best-effort: a failed cache write just means a refetch later.
+    if let Some(parent) = cache_path.parent() {
+        let _ = std::fs::create_dir_all(parent);
+    }
+    let _ = std::fs::write(&cache_path, &bytes);
+    Ok(image)
+}
+
+fn decode_image(bytes: &[u8]) -> Result<egui::ColorImage, FetchError> {
+    let decoded = image::load_from_memory(bytes)
+        .map_err(|e| FetchError {
+            timed_out: false,
+            message: format!("decode failed: {}", e),
+        })?
+        .to_rgba8();
+    let size = [decoded.width() as usize, decoded.height() as usize];
+    Ok(egui::ColorImage::from_rgba_unmultiplied(
+        size,
+        decoded.as_raw(),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn breaker_opens_after_consecutive_timeouts_and_reprobes_once() {
+        let mut breaker = CircuitBreaker::new();
+        for _ in 0..BREAKER_THRESHOLD {
+            assert!(!breaker.is_open());
+            breaker.record_timeout();
+        }
+        assert!(breaker.is_open());
+        // Cooldown elapsed: half-open, and a single timeout re-trips.
+        breaker.open_until = Some(Instant::now() - Duration::from_secs(1));
+        assert!(!breaker.is_open());
+        breaker.record_timeout();
+        assert!(breaker.is_open());
+        // A success anywhere closes it fully.
+        breaker.record_success();
+        assert!(!breaker.is_open());
+    }
+
+    #[test]
+    fn scrolling_a_huge_catalog_respects_the_vram_budget() {
+        const BUDGET_MB: u32 = 256;
+        let ctx = egui::Context::default();
+        let mut images = ImageCache::new();
+        images.set_budget_mb(BUDGET_MB);
+        // Box-art sized cover: 256x341 RGBA ≈ 349 KB, so 2,000 of them
+        // would be ~680 MB unmanaged.
+        let cover = egui::ColorImage::new([256, 341], egui::Color32::BLACK);
+        for i in 0..2000 {
+            let url = format!("https://img.example/cover-{}.jpg", i);
+            let texture = ctx.load_texture(&url, cover.clone(), egui::TextureOptions::LINEAR);
+            images.insert_ready(url, texture);
+            images.enforce_budget();
+            assert!(
+                images.texture_memory_bytes() <= BUDGET_MB as usize * 1024 * 1024,
+                "budget exceeded at cover {}: {} bytes",
+                i,
+                images.texture_memory_bytes()
+            );
+        }
+        // The most recently shown covers are the ones still resident.
+        assert!(matches!(
+            images.entries.get("https://img.example/cover-1999.jpg"),
+            Some(ImageState::Ready { .. })
+        ));
+        assert!(!images.entries.contains_key("https://img.example/cover-0.jpg"));
+    }
+
+    #[test]
+    fn blackholed_cdn_never_blocks_the_frame() {
+        // A server that accepts connections and then says nothing — the
+        // worst case for anything that awaits inside the frame.
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/cover.jpg", listener.local_addr().unwrap());
+        std::thread::spawn(move || {
+            let mut held = Vec::new();
+            for stream in listener.incoming().flatten() {
+                held.push(stream);
+            }
+        });
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+        let ctx = egui::Context::default();
+        let mut images = ImageCache::new();
+        for _ in 0..50 {
+            let frame_start = Instant::now();
+            images.apply_results(&ctx);
+            assert!(images.texture(runtime.handle(), &url).is_none());
+            assert!(
+                frame_start.elapsed() < Duration::from_millis(20),
+                "image loading stalled the frame"
+            );
+        }
+    }
+}
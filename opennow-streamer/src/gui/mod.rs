@@ -0,0 +1,8 @@
+//! wgpu/egui presentation layer.
+
+pub mod images;
+pub mod renderer;
+pub mod screens;
+pub mod theme;
+
+pub use renderer::Renderer;
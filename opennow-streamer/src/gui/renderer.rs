@@ -0,0 +1,764 @@
+//! Window + wgpu surface + egui integration, and presentation of decoded
+//! video frames behind the UI.
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+use anyhow::{anyhow, Result};
+use winit::window::{CursorGrabMode, Fullscreen, Window};
+
+use crate::app::{App, AppState};
+use crate::media::{ColorRange, ColorSpace, ColorTransfer, PixelFormat, VideoFrame};
+use crate::settings::CursorCapture;
+
+pub struct Renderer {
+    pub window: Arc<Window>,
+    surface: wgpu::Surface<'static>,
+    device: wgpu::Device,
+    queue: wgpu::Queue,
+    surface_config: wgpu::SurfaceConfiguration,
+    egui_ctx: egui::Context,
+    egui_state: egui_winit::State,
+    egui_renderer: egui_wgpu::Renderer,
+    /// Texture holding the latest converted video frame.
+    video_texture: Option<(wgpu::Texture, egui::TextureId, (u32, u32))>,
+    /// Async cover art loader; results become textures between frames.
+    images: super::images::ImageCache,
+    applied_capture: Option<CursorCapture>,
+    /// The adapter is a software rasterizer (llvmpipe etc.); used to
+    /// suggest low-spec UI mode.
+    pub software_adapter: bool,
+    low_spec: bool,
+    /// Set from wgpu's device-lost callback (any thread); the next
+    /// `render` call picks it up and rebuilds the GPU state.
+    device_lost: Arc<AtomicBool>,
+    /// Consecutive failed recreations; reset on success.
+    recreate_attempts: u32,
+    /// Device-loss recovery replaces the egui context, which detaches
+    /// the AccessKit adapter; the event loop re-attaches it when this
+    /// is set (it owns the proxy and the `ActiveEventLoop`).
+    accesskit_needs_reinit: bool,
+}
+
+/// Texture side cap applied in low-spec mode to keep atlas uploads and
+/// VRAM use down on software renderers.
+const LOW_SPEC_MAX_TEXTURE_SIDE: usize = 2048;
+
+/// Give up on device-loss recovery after this many consecutive failed
+/// attempts (one per frame) and tell the user instead.
+const MAX_DEVICE_RECREATE_ATTEMPTS: u32 = 3;
+
+/// The last grab attempt was refused (Wayland compositors can decline
+/// pointer constraints). Read by the streaming overlay to explain why
+/// the mouse is escaping the window.
+static CAPTURE_DENIED: AtomicBool = AtomicBool::new(false);
+
+pub fn capture_denied() -> bool {
+    CAPTURE_DENIED.load(Ordering::Relaxed)
+}
+
+/// Exclusive fullscreen is currently holding the display, published
+/// each frame for the stats overlay so users can verify which mode
+/// they actually got.
+static EXCLUSIVE_FULLSCREEN: AtomicBool = AtomicBool::new(false);
+
+pub fn exclusive_fullscreen_active() -> bool {
+    EXCLUSIVE_FULLSCREEN.load(Ordering::Relaxed)
+}
+
+/// Everything whose lifetime is tied to one wgpu device, grouped so
+/// startup and device-loss recovery build it the same way.
+struct GpuStack {
+    surface: wgpu::Surface<'static>,
+    device: wgpu::Device,
+    queue: wgpu::Queue,
+    surface_config: wgpu::SurfaceConfiguration,
+    software_adapter: bool,
+}
+
+/// Create the wgpu stack for `window`: surface, adapter, device/queue
+/// and a configured swapchain. The device-lost callback only raises
+/// `device_lost` — it can fire on any thread (driver update, TDR), so
+/// the actual rebuild happens on the next `Renderer::render`.
+fn create_gpu(window: &Arc<Window>, device_lost: &Arc<AtomicBool>) -> Result<GpuStack> {
+    let instance = wgpu::Instance::default();
+    let surface = instance.create_surface(window.clone())?;
+    let adapter = pollster::block_on(instance.request_adapter(&wgpu::RequestAdapterOptions {
+        power_preference: wgpu::PowerPreference::HighPerformance,
+        compatible_surface: Some(&surface),
+        force_fallback_adapter: false,
+    }))
+    .ok_or_else(|| anyhow!("No compatible GPU adapter found"))?;
+    let adapter_info = adapter.get_info();
+    log::info!("GPU adapter: {}", adapter_info.name);
+    let name = adapter_info.name.to_lowercase();
+    let software_adapter = adapter_info.device_type == wgpu::DeviceType::Cpu
+        || name.contains("llvmpipe")
+        || name.contains("software");
+    let (device, queue) = pollster::block_on(
+        adapter.request_device(&wgpu::DeviceDescriptor::default(), None),
+    )?;
+    let flag = device_lost.clone();
+    device.set_device_lost_callback(move |reason, message| {
+        log::error!("GPU device lost ({:?}): {}", reason, message);
+        flag.store(true, Ordering::SeqCst);
+    });
+    // The default uncaptured-error handler aborts the process. After a
+    // reset, in-flight work can still produce validation errors; log
+    // them and let the lost callback drive recovery instead.
+    device.on_uncaptured_error(Box::new(|e| {
+        log::error!("wgpu error: {}", e);
+    }));
+
+    let size = window.inner_size();
+    let capabilities = surface.get_capabilities(&adapter);
+    let format = capabilities
+        .formats
+        .iter()
+        .copied()
+        .find(|f| f.is_srgb())
+        .unwrap_or(capabilities.formats[0]);
+    let surface_config = wgpu::SurfaceConfiguration {
+        usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
+        format,
+        width: size.width.max(1),
+        height: size.height.max(1),
+        present_mode: wgpu::PresentMode::Fifo,
+        alpha_mode: capabilities.alpha_modes[0],
+        view_formats: vec![],
+        desired_maximum_frame_latency: 2,
+    };
+    surface.configure(&device, &surface_config);
+    Ok(GpuStack {
+        surface,
+        device,
+        queue,
+        surface_config,
+        software_adapter,
+    })
+}
+
+impl Renderer {
+    pub fn new(window: Arc<Window>) -> Result<Self> {
+        let device_lost = Arc::new(AtomicBool::new(false));
+        let GpuStack {
+            surface,
+            device,
+            queue,
+            surface_config,
+            software_adapter,
+        } = create_gpu(&window, &device_lost)?;
+        let format = surface_config.format;
+
+        let egui_ctx = egui::Context::default();
+        let egui_state = egui_winit::State::new(
+            egui_ctx.clone(),
+            egui::ViewportId::ROOT,
+            &window,
+            None,
+            None,
+            None,
+        );
+        let egui_renderer = egui_wgpu::Renderer::new(&device, format, None, 1, false);
+
+        Ok(Self {
+            window,
+            surface,
+            device,
+            queue,
+            surface_config,
+            egui_ctx,
+            egui_state,
+            egui_renderer,
+            video_texture: None,
+            images: super::images::ImageCache::new(),
+            applied_capture: None,
+            software_adapter,
+            low_spec: false,
+            device_lost,
+            recreate_attempts: 0,
+            accesskit_needs_reinit: false,
+        })
+    }
+
+    /// Rebuild every GPU-lifetime object after device loss. The decode
+    /// pipeline is untouched: frames keep landing in the shared slot and
+    /// are dropped until rendering resumes, then the next one uploads.
+    /// egui's managed textures (font atlas, cover art) lived on the dead
+    /// device and only a fresh context re-sends them in full, so the
+    /// context is replaced too — carrying its memory over, so open
+    /// windows and scroll positions survive. Covers reload lazily from
+    /// the disk cache.
+    fn recover_device(&mut self, app: &mut App) -> bool {
+        self.recreate_attempts += 1;
+        let gpu = match create_gpu(&self.window, &self.device_lost) {
+            Ok(gpu) => gpu,
+            Err(e) => {
+                log::error!(
+                    "GPU device recreation attempt {}/{} failed: {}",
+                    self.recreate_attempts,
+                    MAX_DEVICE_RECREATE_ATTEMPTS,
+                    e
+                );
+                if self.recreate_attempts >= MAX_DEVICE_RECREATE_ATTEMPTS {
+                    app.notify_error(
+                        "The GPU device was lost and could not be recovered. \
+                         Restart the app to continue.",
+                    );
+                } else {
+                    // Retry on the next frame.
+                    self.device_lost.store(true, Ordering::SeqCst);
+                }
+                return false;
+            }
+        };
+        let memory = self.egui_ctx.memory(|m| m.clone());
+        let egui_ctx = egui::Context::default();
+        egui_ctx.memory_mut(|m| *m = memory);
+        egui_ctx.style_mut(|style| {
+            style.animation_time = if self.low_spec { 0.0 } else { 0.083 };
+        });
+        let egui_state = egui_winit::State::new(
+            egui_ctx.clone(),
+            egui::ViewportId::ROOT,
+            &self.window,
+            None,
+            None,
+            None,
+        );
+        let egui_renderer =
+            egui_wgpu::Renderer::new(&gpu.device, gpu.surface_config.format, None, 1, false);
+        self.images.forget_textures();
+        self.video_texture = None;
+        self.surface = gpu.surface;
+        self.device = gpu.device;
+        self.queue = gpu.queue;
+        self.surface_config = gpu.surface_config;
+        self.software_adapter = gpu.software_adapter;
+        self.egui_ctx = egui_ctx;
+        self.egui_state = egui_state;
+        self.egui_renderer = egui_renderer;
+        self.accesskit_needs_reinit = true;
+        self.recreate_attempts = 0;
+        log::info!("GPU device recreated after loss");
+        true
+    }
+
+    /// Whether device-loss recovery replaced the egui context since the
+    /// last call; the event loop re-attaches AccessKit then.
+    pub fn take_accesskit_reinit(&mut self) -> bool {
+        std::mem::take(&mut self.accesskit_needs_reinit)
+    }
+
+    /// Destroy the live device on purpose so the recovery path can be
+    /// exercised without yanking a driver mid-session (dev hotkey).
+    pub fn simulate_device_loss(&self) {
+        log::warn!("Simulating GPU device loss");
+        self.device.destroy();
+        self.device_lost.store(true, Ordering::SeqCst);
+    }
+
+    /// Toggle low-spec rendering at runtime: no style animations and a
+    /// capped texture side. The redraw policy lives in the event loop.
+    pub fn set_low_spec(&mut self, enabled: bool) {
+        if enabled == self.low_spec {
+            return;
+        }
+        self.low_spec = enabled;
+        self.egui_ctx.style_mut(|style| {
+            style.animation_time = if enabled { 0.0 } else { 0.083 };
+        });
+    }
+
+    pub fn resize(&mut self, width: u32, height: u32) {
+        if width == 0 || height == 0 {
+            return;
+        }
+        self.surface_config.width = width;
+        self.surface_config.height = height;
+        self.surface.configure(&self.device, &self.surface_config);
+    }
+
+    pub fn on_window_event(&mut self, event: &winit::event::WindowEvent) -> egui_winit::EventResponse {
+        self.egui_state.on_window_event(&self.window, event)
+    }
+
+    /// True while an egui text widget (search box, chat field) holds
+    /// keyboard focus. Distinct from `EventResponse::consumed`, which only
+    /// covers events egui used this frame: a focused field wants *future*
+    /// keystrokes too, so capture must stop before they arrive.
+    pub fn wants_keyboard_input(&self) -> bool {
+        self.egui_ctx.wants_keyboard_input()
+    }
+
+    /// True while the pointer hovers or drags an egui widget.
+    pub fn wants_pointer_input(&self) -> bool {
+        self.egui_ctx.wants_pointer_input()
+    }
+
+    /// Hook up AccessKit so screen readers (NVDA, VoiceOver, Orca) see
+    /// the egui widget tree. egui only builds the tree once a reader
+    /// actually connects, so this costs nothing for sighted users.
+    /// Adapter events arrive on the winit user-event channel and are fed
+    /// back through `on_accesskit_event`.
+    pub fn init_accesskit(
+        &mut self,
+        event_loop: &winit::event_loop::ActiveEventLoop,
+        proxy: winit::event_loop::EventLoopProxy<accesskit_winit::Event>,
+    ) {
+        self.egui_state
+            .init_accesskit(event_loop, &self.window, proxy);
+    }
+
+    /// Whether the window sits on a monitor that looks like a 4K panel
+    /// reporting 100% scale (TVs typically report 96 DPI), i.e. where
+    /// the default UI comes out unreadably small.
+    pub fn looks_like_unscaled_4k(&self) -> bool {
+        self.window.current_monitor().is_some_and(|monitor| {
+            monitor.size().height >= 2160 && monitor.scale_factor() <= 1.0
+        })
+    }
+
+    pub fn on_accesskit_event(&mut self, event: accesskit_winit::WindowEvent) {
+        self.egui_state.on_accesskit_event(event);
+        // Tree requests need a fresh frame to answer from.
+        self.window.request_redraw();
+    }
+
+    /// Toggle fullscreen on the preferred monitor (by OS name); None
+    /// means whatever monitor the window is on. A preferred monitor
+    /// that's no longer connected falls back to the primary.
+    ///
+    /// `FullscreenMode::Exclusive` takes the display over with a video
+    /// mode matching the stream resolution, preferring the refresh
+    /// closest to the stream FPS; without a matching mode it degrades
+    /// to borderless. Dropping fullscreen (or the process dying) always
+    /// restores the desktop mode — winit owns that.
+    pub fn toggle_fullscreen(&mut self, settings: &crate::settings::Settings) {
+        let fullscreen = self.window.fullscreen().is_none();
+        if !fullscreen {
+            self.window.set_fullscreen(None);
+            return;
+        }
+        let monitor = settings.preferred_monitor.as_deref().and_then(|name| {
+            let found = self
+                .window
+                .available_monitors()
+                .find(|monitor| monitor.name().as_deref() == Some(name));
+            if found.is_none() {
+                log::warn!("Monitor '{}' not connected; using the primary", name);
+                return self.window.primary_monitor();
+            }
+            found
+        });
+        if settings.fullscreen_mode == crate::settings::FullscreenMode::Exclusive {
+            let target = monitor
+                .clone()
+                .or_else(|| self.window.current_monitor())
+                .or_else(|| self.window.primary_monitor());
+            if let Some(mode) = target.and_then(|monitor| {
+                let (width, height) = settings.resolution;
+                monitor
+                    .video_modes()
+                    .filter(|mode| {
+                        mode.size().width == width && mode.size().height == height
+                    })
+                    .min_by_key(|mode| {
+                        mode.refresh_rate_millihertz()
+                            .abs_diff(settings.fps * 1000)
+                    })
+            }) {
+                self.window.set_fullscreen(Some(Fullscreen::Exclusive(mode)));
+                return;
+            }
+            log::warn!(
+                "No {}x{} display mode for exclusive fullscreen; using borderless",
+                settings.resolution.0,
+                settings.resolution.1
+            );
+        }
+        self.window
+            .set_fullscreen(Some(Fullscreen::Borderless(monitor)));
+    }
+
+    /// Monitor names for the settings picker, in enumeration order.
+    pub fn monitor_names(&self) -> Vec<String> {
+        self.window
+            .available_monitors()
+            .filter_map(|monitor| monitor.name())
+            .collect()
+    }
+
+    /// Apply the capture state the active input profile asks for; None
+    /// releases everything (not streaming). A transition always fully
+    /// releases first, so a mid-stream profile switch can never leave
+    /// the cursor hidden without a grab backing it.
+    pub fn apply_capture(&mut self, capture: Option<CursorCapture>) {
+        if self.applied_capture == capture {
+            return;
+        }
+        let _ = self.window.set_cursor_grab(CursorGrabMode::None);
+        self.window.set_cursor_visible(true);
+        match capture {
+            Some(CursorCapture::Locked) => {
+                let result = self
+                    .window
+                    .set_cursor_grab(CursorGrabMode::Locked)
+                    .or_else(|_| self.window.set_cursor_grab(CursorGrabMode::Confined));
+                match result {
+                    Ok(()) => self.window.set_cursor_visible(false),
+                    Err(e) => {
+                        // Stay released; the next frame retries. The
+                        // overlay explains the escaping cursor meanwhile.
+                        log::warn!("Cursor grab failed: {}", e);
+                        CAPTURE_DENIED.store(true, Ordering::Relaxed);
+                        self.applied_capture = None;
+                        return;
+                    }
+                }
+            }
+            Some(CursorCapture::Confined) => {
+                if let Err(e) = self.window.set_cursor_grab(CursorGrabMode::Confined) {
+                    log::warn!("Cursor confine failed: {}", e);
+                    CAPTURE_DENIED.store(true, Ordering::Relaxed);
+                    self.applied_capture = None;
+                    return;
+                }
+            }
+            Some(CursorCapture::Free) | None => {}
+        }
+        CAPTURE_DENIED.store(false, Ordering::Relaxed);
+        self.applied_capture = capture;
+    }
+
+    /// Force the next `apply_capture` to re-grab even though the desired
+    /// state hasn't changed. Wayland compositors silently drop pointer
+    /// constraints when focus leaves the surface, so focus return has to
+    /// grab afresh.
+    pub fn reapply_capture(&mut self) {
+        self.applied_capture = None;
+    }
+
+    /// Render one frame: latest video (while streaming) plus the egui UI.
+    pub fn render(&mut self, app: &mut App) -> Result<()> {
+        // A lost device (driver update, TDR) is recovered transparently;
+        // until that succeeds this frame is skipped, not failed — the
+        // stream and the event loop keep running.
+        if self.device_lost.swap(false, Ordering::SeqCst) && !self.recover_device(app) {
+            return Ok(());
+        }
+        // User scale on top of the OS scale factor. Zoom scales every
+        // point-sized element (windows, dialogs, overlay text), so a
+        // 150%+ setting can't clip fixed-size layouts.
+        self.egui_ctx
+            .set_zoom_factor(app.settings.ui_scale.clamp(0.75, 2.0));
+        if app.state == AppState::Streaming {
+            if let Some(frame) = app.current_frame.read() {
+                // The co-watch copy is handed off with `try_send` and
+                // dropped under load; it can't hold up the upload.
+                if let Some(spectate) = app.spectate.as_mut() {
+                    spectate.offer_frame(&frame);
+                }
+                self.upload_video_frame(&frame);
+            }
+            self.apply_capture(Some(app.active_input_profile().capture));
+        } else {
+            self.apply_capture(None);
+            // Exclusive fullscreen is for the stream only; dropping to
+            // borderless here restores the desktop mode the moment the
+            // stream ends.
+            if matches!(self.window.fullscreen(), Some(Fullscreen::Exclusive(_))) {
+                self.window
+                    .set_fullscreen(Some(Fullscreen::Borderless(None)));
+            }
+        }
+        EXCLUSIVE_FULLSCREEN.store(
+            matches!(self.window.fullscreen(), Some(Fullscreen::Exclusive(_))),
+            Ordering::Relaxed,
+        );
+
+        let mut raw_input = self.egui_state.take_egui_input(&self.window);
+        if self.low_spec {
+            raw_input.max_texture_side = Some(
+                raw_input
+                    .max_texture_side
+                    .map_or(LOW_SPEC_MAX_TEXTURE_SIDE, |side| {
+                        side.min(LOW_SPEC_MAX_TEXTURE_SIDE)
+                    }),
+            );
+        }
+        let video_texture = self.video_texture.as_ref().map(|(_, id, size)| (*id, *size));
+        // Finished cover fetches become textures now, between UI passes.
+        self.images.set_budget_mb(app.settings.cover_vram_budget_mb);
+        self.images.apply_results(&self.egui_ctx);
+        let images = &mut self.images;
+        let full_output = self.egui_ctx.clone().run(raw_input, |ctx| {
+            crate::gui::screens::render_ui(ctx, app, video_texture, images);
+        });
+        self.egui_state
+            .handle_platform_output(&self.window, full_output.platform_output);
+
+        let output = match self.surface.get_current_texture() {
+            Ok(output) => output,
+            Err(wgpu::SurfaceError::Lost | wgpu::SurfaceError::Outdated) => {
+                self.surface.configure(&self.device, &self.surface_config);
+                return Ok(());
+            }
+            Err(e) => {
+                // Timeout/OutOfMemory here usually means the device is
+                // gone too; treat it as loss and rebuild next frame.
+                log::error!("Surface error: {}; scheduling device recreation", e);
+                self.device_lost.store(true, Ordering::SeqCst);
+                return Ok(());
+            }
+        };
+        let view = output
+            .texture
+            .create_view(&wgpu::TextureViewDescriptor::default());
+
+        let pixels_per_point = self.egui_ctx.pixels_per_point();
+        let primitives = self
+            .egui_ctx
+            .tessellate(full_output.shapes, pixels_per_point);
+        for (id, delta) in &full_output.textures_delta.set {
+            self.egui_renderer
+                .update_texture(&self.device, &self.queue, *id, delta);
+        }
+        let screen = egui_wgpu::ScreenDescriptor {
+            size_in_pixels: [self.surface_config.width, self.surface_config.height],
+            pixels_per_point,
+        };
+        let mut encoder = self
+            .device
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+        self.egui_renderer.update_buffers(
+            &self.device,
+            &self.queue,
+            &mut encoder,
+            &primitives,
+            &screen,
+        );
+        {
+            let render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("egui"),
+                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                    view: &view,
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(wgpu::Color {
+                            r: 0.05,
+                            g: 0.05,
+                            b: 0.07,
+                            a: 1.0,
+                        }),
+                        store: wgpu::StoreOp::Store,
+                    },
+                })],
+                depth_stencil_attachment: None,
+                timestamp_writes: None,
+                occlusion_query_set: None,
+            });
+            self.egui_renderer.render(
+                &mut render_pass.forget_lifetime(),
+                &primitives,
+                &screen,
+            );
+        }
+        for id in &full_output.textures_delta.free {
+            self.egui_renderer.free_texture(id);
+        }
+        self.queue.submit(Some(encoder.finish()));
+        output.present();
+        Ok(())
+    }
+
+    /// Convert a decoded frame to RGBA and (re)upload the video texture.
+    fn upload_video_frame(&mut self, frame: &VideoFrame) {
+        let rgba = yuv_to_rgba(frame);
+        let needs_recreate = self
+            .video_texture
+            .as_ref()
+            .map(|(_, _, size)| *size != (frame.width, frame.height))
+            .unwrap_or(true);
+        if needs_recreate {
+            if let Some((_, id, _)) = self.video_texture.take() {
+                self.egui_renderer.free_texture(&id);
+            }
+            let texture = self.device.create_texture(&wgpu::TextureDescriptor {
+                label: Some("video"),
+                size: wgpu::Extent3d {
+                    width: frame.width,
+                    height: frame.height,
+                    depth_or_array_layers: 1,
+                },
+                mip_level_count: 1,
+                sample_count: 1,
+                dimension: wgpu::TextureDimension::D2,
+                format: wgpu::TextureFormat::Rgba8UnormSrgb,
+                usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+                view_formats: &[],
+            });
+            let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+            let id = self.egui_renderer.register_native_texture(
+                &self.device,
+                &view,
+                wgpu::FilterMode::Linear,
+            );
+            self.video_texture = Some((texture, id, (frame.width, frame.height)));
+        }
+        if let Some((texture, _, _)) = &self.video_texture {
+            self.queue.write_texture(
+                wgpu::TexelCopyTextureInfo {
+                    texture,
+                    mip_level: 0,
+                    origin: wgpu::Origin3d::ZERO,
+                    aspect: wgpu::TextureAspect::All,
+                },
+                &rgba,
+                wgpu::TexelCopyBufferLayout {
+                    offset: 0,
+                    bytes_per_row: Some(frame.width * 4),
+                    rows_per_image: Some(frame.height),
+                },
+                wgpu::Extent3d {
+                    width: frame.width,
+                    height: frame.height,
+                    depth_or_array_layers: 1,
+                },
+            );
+        }
+    }
+}
+
+/// CPU conversion of a decoded frame to RGBA for the video texture.
+/// 8-bit YUV420 takes the direct path; P010 HDR10 frames are tonemapped
+/// to SDR — the swapchain is 8-bit sRGB, so true passthrough would need
+/// a float surface format.
+pub(crate) fn yuv_to_rgba(frame: &VideoFrame) -> Vec<u8> {
+    match frame.pixel_format {
+        PixelFormat::Yuv420 if frame.planes.len() >= 3 => yuv420_to_rgba(frame),
+        // P010 always carries PQ from the hardware decoders today; the
+        // transfer field keeps the tonemap honest if that changes.
+        PixelFormat::P010 if frame.planes.len() >= 2 && frame.transfer == ColorTransfer::Pq => {
+            p010_to_rgba(frame)
+        }
+        _ => vec![0u8; frame.width as usize * frame.height as usize * 4],
+    }
+}
+
+/// CPU YUV420 -> RGBA conversion, honoring the frame's color space and
+/// range: matrix coefficients follow `ColorSpace` and limited-range
+/// frames are expanded from 16–235/16–240 before the matrix. GFN sends
+/// BT.709 limited, which fixed BT.601 full-range math renders washed
+/// out and slightly green.
+fn yuv420_to_rgba(frame: &VideoFrame) -> Vec<u8> {
+    let width = frame.width as usize;
+    let height = frame.height as usize;
+    let mut rgba = vec![0u8; width * height * 4];
+    let (kr, kb) = match frame.color_space {
+        ColorSpace::Bt601 => (0.299, 0.114),
+        ColorSpace::Bt709 => (0.2126, 0.0722),
+        ColorSpace::Bt2020 => (0.2627, 0.0593),
+    };
+    // Standard YCbCr reconstruction derived from the luma weights.
+    let kg = 1.0 - kr - kb;
+    let v_to_r = 2.0 * (1.0 - kr);
+    let u_to_b = 2.0 * (1.0 - kb);
+    let u_to_g = u_to_b * kb / kg;
+    let v_to_g = v_to_r * kr / kg;
+    let (y_offset, y_scale, c_scale) = match frame.color_range {
+        ColorRange::Limited => (16.0, 255.0 / 219.0, 255.0 / 224.0),
+        ColorRange::Full => (0.0, 1.0, 1.0),
+    };
+    let (y_plane, u_plane, v_plane) = (&frame.planes[0], &frame.planes[1], &frame.planes[2]);
+    let (y_stride, u_stride, v_stride) = (frame.strides[0], frame.strides[1], frame.strides[2]);
+    for row in 0..height {
+        for col in 0..width {
+            let y = (y_plane[row * y_stride + col] as f32 - y_offset) * y_scale;
+            let u = (u_plane[(row / 2) * u_stride + col / 2] as f32 - 128.0) * c_scale;
+            let v = (v_plane[(row / 2) * v_stride + col / 2] as f32 - 128.0) * c_scale;
+            let r = (y + v_to_r * v).clamp(0.0, 255.0) as u8;
+            let g = (y - u_to_g * u - v_to_g * v).clamp(0.0, 255.0) as u8;
+            let b = (y + u_to_b * u).clamp(0.0, 255.0) as u8;
+            let offset = (row * width + col) * 4;
+            rgba[offset] = r;
+            rgba[offset + 1] = g;
+            rgba[offset + 2] = b;
+            rgba[offset + 3] = 255;
+        }
+    }
+    rgba
+}
+
+/// Typical HDR10 mastering peak, used as the white point the tonemap
+/// compresses toward.
+const PQ_PEAK_NITS: f32 = 1000.0;
+/// SDR reference white (ITU-R BT.2408); PQ luminance at this level maps
+/// to full SDR white before highlight compression.
+const SDR_REFERENCE_NITS: f32 = 203.0;
+
+/// SMPTE ST 2084 (PQ) EOTF: non-linear sample in 0..1 to luminance in
+/// nits.
+fn pq_eotf(e: f32) -> f32 {
+    const M1: f32 = 2610.0 / 16384.0;
+    const M2: f32 = 2523.0 / 4096.0 * 128.0;
+    const C1: f32 = 3424.0 / 4096.0;
+    const C2: f32 = 2413.0 / 4096.0 * 32.0;
+    const C3: f32 = 2392.0 / 4096.0 * 32.0;
+    let p = e.max(0.0).powf(1.0 / M2);
+    10000.0 * ((p - C1).max(0.0) / (C2 - C3 * p)).powf(1.0 / M1)
+}
+
+/// Linear 0..1 to the sRGB transfer the swapchain expects.
+fn linear_to_srgb(l: f32) -> f32 {
+    if l <= 0.003_130_8 {
+        12.92 * l
+    } else {
+        1.055 * l.powf(1.0 / 2.4) - 0.055
+    }
+}
+
+/// CPU P010 (HDR10) -> SDR RGBA: BT.2020 YCbCr to R'G'B', PQ to linear
+/// nits, gamut map to BT.709, white-preserving Reinhard from the
+/// mastering peak down to SDR, then sRGB encode.
+fn p010_to_rgba(frame: &VideoFrame) -> Vec<u8> {
+    let width = frame.width as usize;
+    let height = frame.height as usize;
+    let mut rgba = vec![0u8; width * height * 4];
+    let (y_plane, uv_plane) = (&frame.planes[0], &frame.planes[1]);
+    let (y_stride, uv_stride) = (frame.strides[0], frame.strides[1]);
+    let sample = |plane: &[u8], offset: usize| -> f32 {
+        // 16-bit little-endian with the 10 significant bits on top.
+        (u16::from_le_bytes([plane[offset], plane[offset + 1]]) >> 6) as f32
+    };
+    let white = PQ_PEAK_NITS / SDR_REFERENCE_NITS;
+    for row in 0..height {
+        for col in 0..width {
+            // Limited-range 10-bit: luma 64..940, chroma centered on 512.
+            let y = (sample(y_plane, row * y_stride + col * 2) - 64.0) / 876.0;
+            let uv_offset = (row / 2) * uv_stride + (col / 2) * 4;
+            let u = (sample(uv_plane, uv_offset) - 512.0) / 896.0;
+            let v = (sample(uv_plane, uv_offset + 2) - 512.0) / 896.0;
+            // BT.2020 non-constant-luminance matrix; channels are still
+            // PQ-encoded here.
+            let r = (y + 1.4746 * v).clamp(0.0, 1.0);
+            let g = (y - 0.16455 * u - 0.57135 * v).clamp(0.0, 1.0);
+            let b = (y + 1.8814 * u).clamp(0.0, 1.0);
+            let (r, g, b) = (pq_eotf(r), pq_eotf(g), pq_eotf(b));
+            // BT.2020 -> BT.709 primaries on linear light.
+            let r7 = 1.6605 * r - 0.5876 * g - 0.0728 * b;
+            let g7 = -0.1246 * r + 1.1329 * g - 0.0083 * b;
+            let b7 = -0.0182 * r - 0.1006 * g + 1.1187 * b;
+            let offset = (row * width + col) * 4;
+            for (i, channel) in [r7, g7, b7].into_iter().enumerate() {
+                // Reference white lands on 1.0; the extended Reinhard
+                // curve brings the mastering peak down to exactly 1.0.
+                let l = channel.max(0.0) / SDR_REFERENCE_NITS;
+                let mapped = l * (1.0 + l / (white * white)) / (1.0 + l);
+                rgba[offset + i] = (linear_to_srgb(mapped.clamp(0.0, 1.0)) * 255.0) as u8;
+            }
+            rgba[offset + 3] = 255;
+        }
+    }
+    rgba
+}
@@ -0,0 +1,193 @@
+//! Theme application and OS appearance detection.
+//!
+//! `Settings.theme` is "system", "dark" or "light". "system" follows the
+//! OS appearance (and accent color where the platform exposes one),
+//! re-checked every few seconds so changes apply while the app runs.
+//! Detection failures silently fall back to dark. Theme switches are
+//! deferred while streaming so the backdrop never flashes white behind
+//! the video.
+
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use crate::app::{App, AppState};
+
+/// How often the OS appearance is re-read for live change detection.
+const DETECT_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Resolved appearance: dark/light plus an optional OS accent color.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ResolvedTheme {
+    pub dark: bool,
+    /// OS accent as RGB, when the platform exposes one and the theme is
+    /// "system".
+    pub accent: Option<[u8; 3]>,
+}
+
+impl ResolvedTheme {
+    const DARK: ResolvedTheme = ResolvedTheme {
+        dark: true,
+        accent: None,
+    };
+}
+
+/// Cached OS detection result and the theme currently applied to the
+/// egui context, so apply() is a no-op on the steady state.
+static DETECTED: Mutex<Option<(Instant, ResolvedTheme)>> = Mutex::new(None);
+static APPLIED: Mutex<Option<ResolvedTheme>> = Mutex::new(None);
+
+/// Apply the configured theme to `ctx`. Called once per frame; cheap
+/// unless the resolved theme actually changed. While streaming, a
+/// pending change is held back until the stream ends.
+pub fn apply(ctx: &egui::Context, app: &App) {
+    let resolved = match app.settings.theme.as_str() {
+        "light" => ResolvedTheme {
+            dark: false,
+            accent: None,
+        },
+        "dark" => ResolvedTheme::DARK,
+        _ => system_theme(),
+    };
+    let mut applied = APPLIED.lock().unwrap();
+    if *applied == Some(resolved) {
+        return;
+    }
+    if app.state == AppState::Streaming && applied.is_some() {
+        // Defer: rebuilding visuals mid-stream flashes the window
+        // background before the next video frame covers it.
+        return;
+    }
+    let mut visuals = if resolved.dark {
+        egui::Visuals::dark()
+    } else {
+        egui::Visuals::light()
+    };
+    if let Some([r, g, b]) = resolved.accent {
+        let accent = egui::Color32::from_rgb(r, g, b);
+        visuals.selection.bg_fill = accent;
+        visuals.hyperlink_color = accent;
+    }
+    ctx.set_visuals(visuals);
+    *applied = Some(resolved);
+}
+
+/// The OS appearance, re-detected at most every `DETECT_INTERVAL`.
+fn system_theme() -> ResolvedTheme {
+    let mut cached = DETECTED.lock().unwrap();
+    if let Some((at, theme)) = *cached {
+        if at.elapsed() < DETECT_INTERVAL {
+            return theme;
+        }
+    }
+    let theme = platform_detect().unwrap_or(ResolvedTheme::DARK);
+    *cached = Some((Instant::now(), theme));
+    theme
+}
+
+/// Read `AppsUseLightTheme` and the DWM colorization color from the
+/// user registry hive.
+#[cfg(windows)]
+fn platform_detect() -> Option<ResolvedTheme> {
+    let light = read_user_dword(
+        "Software\\Microsoft\\Windows\\CurrentVersion\\Themes\\Personalize",
+        "AppsUseLightTheme",
+    )?;
+    // ColorizationColor is ARGB; missing accent is fine.
+    let accent = read_user_dword("Software\\Microsoft\\Windows\\DWM", "ColorizationColor")
+        .map(|argb| [(argb >> 16) as u8, (argb >> 8) as u8, argb as u8]);
+    Some(ResolvedTheme {
+        dark: light == 0,
+        accent,
+    })
+}
+
+#[cfg(windows)]
+fn read_user_dword(key_path: &str, value: &str) -> Option<u32> {
+    use windows_sys::Win32::System::Registry::{
+        RegCloseKey, RegOpenKeyExW, RegQueryValueExW, HKEY, HKEY_CURRENT_USER, KEY_READ,
+    };
+
+    let to_wide = |s: &str| -> Vec<u16> { s.encode_utf16().chain(std::iter::once(0)).collect() };
+    let path = to_wide(key_path);
+    let name = to_wide(value);
+    unsafe {
+        let mut key: HKEY = std::ptr::null_mut();
+        if RegOpenKeyExW(HKEY_CURRENT_USER, path.as_ptr(), 0, KEY_READ, &mut key) != 0 {
+            return None;
+        }
+        let mut data = 0u32;
+        let mut data_len = std::mem::size_of::<u32>() as u32;
+        let status = RegQueryValueExW(
+            key,
+            name.as_ptr(),
+            std::ptr::null_mut(),
+            std::ptr::null_mut(),
+            &mut data as *mut u32 as *mut u8,
+            &mut data_len,
+        );
+        RegCloseKey(key);
+        (status == 0).then_some(data)
+    }
+}
+
+/// `defaults read -g AppleInterfaceStyle` prints "Dark" in dark mode and
+/// errors in light mode; the accent index maps to fixed Apple colors.
+#[cfg(target_os = "macos")]
+fn platform_detect() -> Option<ResolvedTheme> {
+    let read_default = |key: &str| -> Option<String> {
+        let output = std::process::Command::new("defaults")
+            .args(["read", "-g", key])
+            .output()
+            .ok()?;
+        output
+            .status
+            .success()
+            .then(|| String::from_utf8_lossy(&output.stdout).trim().to_string())
+    };
+    let dark = read_default("AppleInterfaceStyle")
+        .is_some_and(|style| style.eq_ignore_ascii_case("dark"));
+    let accent = read_default("AppleAccentColor")
+        .and_then(|index| index.parse::<i32>().ok())
+        .and_then(|index| match index {
+            0 => Some([255, 69, 58]),   // red
+            1 => Some([255, 159, 10]),  // orange
+            2 => Some([255, 214, 10]),  // yellow
+            3 => Some([50, 215, 75]),   // green
+            5 => Some([191, 90, 242]),  // purple
+            6 => Some([255, 55, 95]),   // pink
+            _ => None,                  // 4/default: system blue
+        });
+    Some(ResolvedTheme { dark, accent })
+}
+
+/// GNOME's `color-scheme` key via gsettings; other desktops fall back to
+/// dark. The accent-color key exists from GNOME 44 on.
+#[cfg(not(any(windows, target_os = "macos")))]
+fn platform_detect() -> Option<ResolvedTheme> {
+    let gsetting = |key: &str| -> Option<String> {
+        let output = std::process::Command::new("gsettings")
+            .args(["get", "org.gnome.desktop.interface", key])
+            .output()
+            .ok()?;
+        output
+            .status
+            .success()
+            .then(|| String::from_utf8_lossy(&output.stdout).trim().to_string())
+    };
+    let scheme = gsetting("color-scheme")?;
+    let accent = gsetting("accent-color").and_then(|name| match name.trim_matches('\'') {
+        "red" => Some([230, 45, 66]),
+        "orange" => Some([255, 120, 0]),
+        "yellow" => Some([248, 228, 92]),
+        "green" => Some([58, 148, 74]),
+        "teal" => Some([33, 144, 164]),
+        "purple" => Some([145, 65, 172]),
+        "pink" => Some([213, 97, 153]),
+        "slate" => Some([111, 131, 150]),
+        _ => None, // "blue"/unset: egui's defaults are already blue.
+    });
+    Some(ResolvedTheme {
+        dark: scheme.contains("dark"),
+        accent,
+    })
+}
@@ -0,0 +1,592 @@
+//! `--headless-stream`: stream a game with zero UI for latency
+//! benchmarking and kiosk pipelines. Reuses the normal session +
+//! `run_streaming` path but presents frames with a minimal wgpu blit
+//! into a borderless fullscreen window, prints one JSON stats line per
+//! second to stdout, and tears the session down on SIGINT.
+//!
+//! Exit codes for scripting:
+//!   0  clean exit
+//!   2  bad command line
+//!   3  authentication unavailable/expired
+//!   4  no usable zone
+//!   5  session creation/queue failed
+//!   6  streaming pipeline failed
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+
+use winit::application::ApplicationHandler;
+use winit::event::WindowEvent;
+use winit::event_loop::{ActiveEventLoop, ControlFlow, EventLoop};
+use winit::window::{Fullscreen, Window, WindowId};
+
+use crate::api::cloudmatch::SessionState;
+use crate::api::serverinfo;
+use crate::api::GfnApiClient;
+use crate::auth::{self, AuthTokens};
+use crate::media::{SharedFrame, StreamStats};
+use crate::settings::Settings;
+use crate::webrtc::ConnectionInfo;
+
+pub const EXIT_BAD_ARGS: i32 = 2;
+pub const EXIT_AUTH: i32 = 3;
+pub const EXIT_NO_ZONE: i32 = 4;
+pub const EXIT_SESSION: i32 = 5;
+pub const EXIT_STREAMING: i32 = 6;
+
+const SESSION_POLL_INTERVAL: std::time::Duration = std::time::Duration::from_secs(2);
+
+struct HeadlessArgs {
+    app_id: u64,
+    token: Option<String>,
+    zone: Option<String>,
+}
+
+fn parse_args(args: &[String]) -> Result<HeadlessArgs, String> {
+    let mut app_id = None;
+    let mut token = None;
+    let mut zone = None;
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        match arg.as_str() {
+            "--headless-stream" => {}
+            "--app-id" => {
+                let value = iter.next().ok_or("--app-id needs a value")?;
+                app_id = Some(value.parse().map_err(|_| "--app-id must be numeric")?);
+            }
+            "--token" => token = Some(iter.next().ok_or("--token needs a value")?.clone()),
+            "--zone" => zone = Some(iter.next().ok_or("--zone needs a value")?.clone()),
+            other => return Err(format!("unknown flag {}", other)),
+        }
+    }
+    Ok(HeadlessArgs {
+        app_id: app_id.ok_or("--app-id is required")?,
+        token,
+        zone,
+    })
+}
+
+/// One machine-readable line to stdout; logging goes to stderr via
+/// env_logger, so stdout stays parseable.
+fn emit(record: serde_json::Value) {
+    println!("{}", record);
+}
+
+/// Run the headless mode to completion. Never returns to the normal UI
+/// path; the caller passes the exit code straight to the OS.
+pub fn run(args: &[String]) -> i32 {
+    let args = match parse_args(args) {
+        Ok(args) => args,
+        Err(e) => {
+            eprintln!("opennow-streamer --headless-stream: {}", e);
+            eprintln!(
+                "usage: opennow-streamer --headless-stream --app-id <id> [--token <jwt>] [--zone <address>]"
+            );
+            return EXIT_BAD_ARGS;
+        }
+    };
+    let runtime = tokio::runtime::Runtime::new().expect("Failed to create tokio runtime");
+
+    let settings = Settings::load();
+    // Token storage may have been migrated into the keyring by the UI.
+    auth::set_secure_storage(settings.use_secure_token_storage);
+    // Saved tokens live under the profile the UI last had active.
+    crate::app::cache::set_active_profile(&crate::app::cache::load_profiles().active);
+    let tokens = match args.token {
+        // A provided token is trusted as-is; expiry shows up as 401s.
+        Some(access_token) => AuthTokens {
+            access_token,
+            refresh_token: None,
+            expires_at: chrono::Utc::now().timestamp() + 3600,
+        },
+        None => match auth::load_tokens() {
+            Some(tokens) if !tokens.is_expired() => tokens,
+            _ => {
+                log::error!("No valid saved login; pass --token or log in via the UI first");
+                return EXIT_AUTH;
+            }
+        },
+    };
+    let client = Arc::new(GfnApiClient::new(&tokens));
+
+    let zone = match args.zone {
+        Some(zone) => zone,
+        None => {
+            let zones = runtime.block_on(async {
+                let zones = serverinfo::fetch_zones(&tokens.access_token).await?;
+                anyhow::Ok(serverinfo::ping_all_servers(zones).await)
+            });
+            let best = match zones {
+                Ok(zones) => zones
+                    .into_iter()
+                    .min_by_key(|z| z.ping_ms.unwrap_or(u32::MAX)),
+                Err(e) => {
+                    log::error!("Zone discovery failed: {}", e);
+                    return EXIT_NO_ZONE;
+                }
+            };
+            match best {
+                Some(zone) => {
+                    log::info!("Using zone {} ({})", zone.name, zone.address);
+                    zone.address
+                }
+                None => {
+                    log::error!("No zones available");
+                    return EXIT_NO_ZONE;
+                }
+            }
+        }
+    };
+
+    // Create the session and poll until the rig is ready, emitting queue
+    // progress as it comes in.
+    let session = runtime.block_on(async {
+        let mut session = client.create_session(args.app_id, &zone, &settings).await?;
+        loop {
+            match &session.state {
+                SessionState::Queued { position, eta_secs } => emit(serde_json::json!({
+                    "event": "queued", "position": position, "eta_secs": eta_secs,
+                })),
+                SessionState::Provisioning => emit(serde_json::json!({ "event": "provisioning" })),
+                SessionState::Ready | SessionState::Streaming => return anyhow::Ok(session),
+                SessionState::Finished | SessionState::Error(_) => {
+                    anyhow::bail!("session ended while waiting: {:?}", session.state)
+                }
+            }
+            tokio::time::sleep(SESSION_POLL_INTERVAL).await;
+            session = client.poll_session(&zone, &session.session_id).await?;
+        }
+    });
+    let session = match session {
+        Ok(session) => session,
+        Err(e) => {
+            log::error!("Session setup failed: {}", e);
+            return EXIT_SESSION;
+        }
+    };
+    emit(serde_json::json!({ "event": "ready", "session_id": session.session_id }));
+
+    let shared_frame = SharedFrame::new();
+    let stats: Arc<Mutex<StreamStats>> = Arc::new(Mutex::new(StreamStats::default()));
+    let connection_info = Arc::new(Mutex::new(ConnectionInfo::default()));
+    let stop = Arc::new(AtomicBool::new(false));
+    let failed = Arc::new(AtomicBool::new(false));
+
+    let (input_tx, input_rx) = tokio::sync::mpsc::unbounded_channel();
+    crate::input::set_raw_input_sender(input_tx);
+    // No gamepads in headless mode; rumble commands land in a channel
+    // nobody reads and drop with it at shutdown.
+    let (rumble_tx, _rumble_rx) = tokio::sync::mpsc::unbounded_channel();
+    if let Err(e) = crate::input::start_raw_input() {
+        log::warn!("Raw input unavailable in headless mode: {}", e);
+    }
+
+    {
+        let session = session.clone();
+        let settings = settings.clone();
+        let shared_frame = shared_frame.clone();
+        let stats = stats.clone();
+        let stop = stop.clone();
+        let failed = failed.clone();
+        runtime.spawn(async move {
+            if let Err(e) = crate::webrtc::run_streaming(
+                session,
+                settings,
+                shared_frame,
+                stats,
+                connection_info,
+                // No review UI in headless mode; the ring stays empty.
+                Arc::new(Mutex::new(crate::media::history::FrameHistory::new())),
+                input_rx,
+                rumble_tx,
+                stop.clone(),
+            )
+            .await
+            {
+                log::error!("Streaming failed: {}", e);
+                failed.store(true, Ordering::SeqCst);
+            }
+            // Whether it failed or the server ended the stream, wind the
+            // presenter down.
+            stop.store(true, Ordering::SeqCst);
+        });
+    }
+    {
+        let stats = stats.clone();
+        let stop = stop.clone();
+        runtime.spawn(async move {
+            while !stop.load(Ordering::SeqCst) {
+                tokio::time::sleep(std::time::Duration::from_secs(1)).await;
+                let s = stats.lock().unwrap().clone();
+                emit(serde_json::json!({
+                    "event": "stats",
+                    "t": chrono::Utc::now().timestamp(),
+                    "fps": s.fps,
+                    "bitrate_mbps": s.bitrate_mbps,
+                    "decode_ms": s.latency_ms,
+                    "rtt_ms": s.rtt_ms,
+                    "frames_decoded": s.frames_decoded,
+                    "frames_dropped": s.frames_dropped,
+                    "resolution": format!("{}x{}", s.resolution.0, s.resolution.1),
+                }));
+            }
+        });
+    }
+    {
+        let stop = stop.clone();
+        runtime.spawn(async move {
+            if tokio::signal::ctrl_c().await.is_ok() {
+                log::info!("SIGINT: shutting down");
+                stop.store(true, Ordering::SeqCst);
+            }
+        });
+    }
+
+    // The presenter owns the main thread until the stop flag flips.
+    let event_loop = EventLoop::new().expect("Failed to create event loop");
+    let mut presenter = Presenter::new(shared_frame, stop.clone());
+    if let Err(e) = event_loop.run_app(&mut presenter) {
+        log::error!("Presenter error: {}", e);
+    }
+
+    stop.store(true, Ordering::SeqCst);
+    crate::input::stop_raw_input();
+    let teardown = runtime.block_on(client.delete_session(&zone, &session.session_id));
+    if let Err(e) = teardown {
+        log::warn!("Failed to terminate session: {}", e);
+    }
+    emit(serde_json::json!({ "event": "exit" }));
+    if failed.load(Ordering::SeqCst) {
+        EXIT_STREAMING
+    } else {
+        0
+    }
+}
+
+/// Borderless fullscreen presenter: one texture, one fullscreen
+/// triangle, aspect-fit viewport. No egui, no chrome.
+struct Presenter {
+    shared_frame: SharedFrame,
+    stop: Arc<AtomicBool>,
+    gpu: Option<PresenterGpu>,
+}
+
+struct PresenterGpu {
+    window: Arc<Window>,
+    surface: wgpu::Surface<'static>,
+    device: wgpu::Device,
+    queue: wgpu::Queue,
+    surface_config: wgpu::SurfaceConfiguration,
+    pipeline: wgpu::RenderPipeline,
+    bind_group_layout: wgpu::BindGroupLayout,
+    sampler: wgpu::Sampler,
+    texture: Option<(wgpu::Texture, wgpu::BindGroup, (u32, u32))>,
+}
+
+const BLIT_SHADER: &str = r#"
+struct VsOut {
+    @builtin(position) pos: vec4<f32>,
+    @location(0) uv: vec2<f32>,
+};
+
+@vertex
+fn vs_main(@builtin(vertex_index) index: u32) -> VsOut {
+    // Fullscreen triangle.
+    var out: VsOut;
+    let x = f32(i32(index) / 2) * 4.0 - 1.0;
+    let y = f32(i32(index) % 2) * 4.0 - 1.0;
+    out.pos = vec4<f32>(x, y, 0.0, 1.0);
+    out.uv = vec2<f32>((x + 1.0) * 0.5, 1.0 - (y + 1.0) * 0.5);
+    return out;
+}
+
+@group(0) @binding(0) var frame: texture_2d<f32>;
+@group(0) @binding(1) var frame_sampler: sampler;
+
+@fragment
+fn fs_main(in: VsOut) -> @location(0) vec4<f32> {
+    return textureSample(frame, frame_sampler, in.uv);
+}
+"#;
+
+impl Presenter {
+    fn new(shared_frame: SharedFrame, stop: Arc<AtomicBool>) -> Self {
+        Self {
+            shared_frame,
+            stop,
+            gpu: None,
+        }
+    }
+}
+
+impl PresenterGpu {
+    fn new(window: Arc<Window>) -> anyhow::Result<Self> {
+        let instance = wgpu::Instance::default();
+        let surface = instance.create_surface(window.clone())?;
+        let adapter = pollster::block_on(instance.request_adapter(&wgpu::RequestAdapterOptions {
+            power_preference: wgpu::PowerPreference::HighPerformance,
+            compatible_surface: Some(&surface),
+            force_fallback_adapter: false,
+        }))
+        .ok_or_else(|| anyhow::anyhow!("No compatible GPU adapter found"))?;
+        let (device, queue) = pollster::block_on(
+            adapter.request_device(&wgpu::DeviceDescriptor::default(), None),
+        )?;
+        let size = window.inner_size();
+        let capabilities = surface.get_capabilities(&adapter);
+        let format = capabilities
+            .formats
+            .iter()
+            .copied()
+            .find(|f| f.is_srgb())
+            .unwrap_or(capabilities.formats[0]);
+        let surface_config = wgpu::SurfaceConfiguration {
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
+            format,
+            width: size.width.max(1),
+            height: size.height.max(1),
+            present_mode: wgpu::PresentMode::Fifo,
+            alpha_mode: capabilities.alpha_modes[0],
+            view_formats: vec![],
+            desired_maximum_frame_latency: 2,
+        };
+        surface.configure(&device, &surface_config);
+
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("blit"),
+            source: wgpu::ShaderSource::Wgsl(BLIT_SHADER.into()),
+        });
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("blit"),
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        multisampled: false,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                    count: None,
+                },
+            ],
+        });
+        let layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("blit"),
+            bind_group_layouts: &[&bind_group_layout],
+            push_constant_ranges: &[],
+        });
+        let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("blit"),
+            layout: Some(&layout),
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: Some("vs_main"),
+                buffers: &[],
+                compilation_options: Default::default(),
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: Some("fs_main"),
+                targets: &[Some(format.into())],
+                compilation_options: Default::default(),
+            }),
+            primitive: wgpu::PrimitiveState::default(),
+            depth_stencil: None,
+            multisample: wgpu::MultisampleState::default(),
+            multiview: None,
+            cache: None,
+        });
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            ..Default::default()
+        });
+        Ok(Self {
+            window,
+            surface,
+            device,
+            queue,
+            surface_config,
+            pipeline,
+            bind_group_layout,
+            sampler,
+            texture: None,
+        })
+    }
+
+    fn resize(&mut self, width: u32, height: u32) {
+        if width == 0 || height == 0 {
+            return;
+        }
+        self.surface_config.width = width;
+        self.surface_config.height = height;
+        self.surface.configure(&self.device, &self.surface_config);
+    }
+
+    fn upload(&mut self, frame: &crate::media::VideoFrame) {
+        let rgba = crate::gui::renderer::yuv_to_rgba(frame);
+        let needs_recreate = self
+            .texture
+            .as_ref()
+            .map(|(_, _, size)| *size != (frame.width, frame.height))
+            .unwrap_or(true);
+        if needs_recreate {
+            let texture = self.device.create_texture(&wgpu::TextureDescriptor {
+                label: Some("video"),
+                size: wgpu::Extent3d {
+                    width: frame.width,
+                    height: frame.height,
+                    depth_or_array_layers: 1,
+                },
+                mip_level_count: 1,
+                sample_count: 1,
+                dimension: wgpu::TextureDimension::D2,
+                format: wgpu::TextureFormat::Rgba8UnormSrgb,
+                usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+                view_formats: &[],
+            });
+            let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+            let bind_group = self.device.create_bind_group(&wgpu::BindGroupDescriptor {
+                label: Some("blit"),
+                layout: &self.bind_group_layout,
+                entries: &[
+                    wgpu::BindGroupEntry {
+                        binding: 0,
+                        resource: wgpu::BindingResource::TextureView(&view),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 1,
+                        resource: wgpu::BindingResource::Sampler(&self.sampler),
+                    },
+                ],
+            });
+            self.texture = Some((texture, bind_group, (frame.width, frame.height)));
+        }
+        if let Some((texture, _, _)) = &self.texture {
+            self.queue.write_texture(
+                wgpu::TexelCopyTextureInfo {
+                    texture,
+                    mip_level: 0,
+                    origin: wgpu::Origin3d::ZERO,
+                    aspect: wgpu::TextureAspect::All,
+                },
+                &rgba,
+                wgpu::TexelCopyBufferLayout {
+                    offset: 0,
+                    bytes_per_row: Some(frame.width * 4),
+                    rows_per_image: Some(frame.height),
+                },
+                wgpu::Extent3d {
+                    width: frame.width,
+                    height: frame.height,
+                    depth_or_array_layers: 1,
+                },
+            );
+        }
+    }
+
+    fn render(&mut self) {
+        let output = match self.surface.get_current_texture() {
+            Ok(output) => output,
+            Err(wgpu::SurfaceError::Lost | wgpu::SurfaceError::Outdated) => {
+                self.surface.configure(&self.device, &self.surface_config);
+                return;
+            }
+            Err(e) => {
+                log::error!("Surface error: {}", e);
+                return;
+            }
+        };
+        let view = output
+            .texture
+            .create_view(&wgpu::TextureViewDescriptor::default());
+        let mut encoder = self
+            .device
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+        {
+            let mut pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("blit"),
+                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                    view: &view,
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(wgpu::Color::BLACK),
+                        store: wgpu::StoreOp::Store,
+                    },
+                })],
+                depth_stencil_attachment: None,
+                timestamp_writes: None,
+                occlusion_query_set: None,
+            });
+            if let Some((_, bind_group, (width, height))) = &self.texture {
+                // Aspect-fit viewport so the stream isn't stretched.
+                let (sw, sh) = (
+                    self.surface_config.width as f32,
+                    self.surface_config.height as f32,
+                );
+                let scale = (sw / *width as f32).min(sh / *height as f32);
+                let (vw, vh) = (*width as f32 * scale, *height as f32 * scale);
+                pass.set_viewport((sw - vw) / 2.0, (sh - vh) / 2.0, vw, vh, 0.0, 1.0);
+                pass.set_pipeline(&self.pipeline);
+                pass.set_bind_group(0, bind_group, &[]);
+                pass.draw(0..3, 0..1);
+            }
+        }
+        self.queue.submit(Some(encoder.finish()));
+        output.present();
+    }
+}
+
+impl ApplicationHandler for Presenter {
+    fn resumed(&mut self, event_loop: &ActiveEventLoop) {
+        if self.gpu.is_none() {
+            let window = Arc::new(
+                event_loop
+                    .create_window(
+                        Window::default_attributes()
+                            .with_title("OpenNOW")
+                            .with_fullscreen(Some(Fullscreen::Borderless(None)))
+                            .with_decorations(false),
+                    )
+                    .expect("Failed to create window"),
+            );
+            window.set_cursor_visible(false);
+            self.gpu = Some(PresenterGpu::new(window).expect("Failed to create presenter"));
+        }
+    }
+
+    fn window_event(&mut self, event_loop: &ActiveEventLoop, _id: WindowId, event: WindowEvent) {
+        let Some(gpu) = self.gpu.as_mut() else {
+            return;
+        };
+        match event {
+            WindowEvent::CloseRequested => event_loop.exit(),
+            WindowEvent::Resized(size) => gpu.resize(size.width, size.height),
+            WindowEvent::RedrawRequested => {
+                if let Some(frame) = self.shared_frame.read() {
+                    gpu.upload(&frame);
+                }
+                gpu.render();
+            }
+            _ => {}
+        }
+    }
+
+    fn about_to_wait(&mut self, event_loop: &ActiveEventLoop) {
+        if self.stop.load(Ordering::SeqCst) {
+            event_loop.exit();
+            return;
+        }
+        event_loop.set_control_flow(ControlFlow::Poll);
+        if let Some(gpu) = &self.gpu {
+            gpu.window.request_redraw();
+        }
+    }
+}
@@ -0,0 +1,443 @@
+//! Gamepad state via gilrs.
+
+use gilrs::ff::{BaseEffect, BaseEffectType, EffectBuilder};
+use gilrs::{Axis, Button, EventType, GamepadId, Gilrs};
+
+use crate::settings::{ControllerTuning, StickCurve, StickTuning};
+
+use super::{InputEvent, RumbleCommand};
+
+/// Controller type codes carried in the arrival packet; values match
+/// the official client's captures. Games pick their prompt glyphs
+/// (A/B/X/Y vs cross/circle/square/triangle) from this.
+pub const CONTROLLER_TYPE_XBOX: u8 = 0x01;
+pub const CONTROLLER_TYPE_DUALSHOCK: u8 = 0x02;
+pub const CONTROLLER_TYPE_GENERIC: u8 = 0x03;
+
+/// Capability flags carried in the arrival packet.
+pub const PAD_CAP_RUMBLE: u16 = 0x0001;
+pub const PAD_CAP_ANALOG_TRIGGERS: u16 = 0x0002;
+
+/// How many pads the protocol can address (XInput-style slots 0–3).
+const MAX_PAD_SLOTS: usize = 4;
+
+/// A pad arriving or leaving, in GFN packet terms.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PadConnectionEvent {
+    Connected {
+        slot: u8,
+        controller_type: u8,
+        capabilities: u16,
+    },
+    Disconnected { slot: u8 },
+}
+
+impl PadConnectionEvent {
+    pub fn to_input_event(self) -> InputEvent {
+        match self {
+            Self::Connected {
+                slot,
+                controller_type,
+                capabilities,
+            } => InputEvent::GamepadConnected {
+                slot,
+                controller_type,
+                capabilities,
+            },
+            Self::Disconnected { slot } => InputEvent::GamepadDisconnected { slot },
+        }
+    }
+}
+
+/// Snapshot of one controller's state in GFN packet terms.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct ControllerState {
+    pub buttons: u16,
+    pub left_trigger: u8,
+    pub right_trigger: u8,
+    pub left_stick: (i16, i16),
+    pub right_stick: (i16, i16),
+}
+
+/// Raw vs tuned stick positions, for the settings visualizer.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct StickSample {
+    pub raw_left: (f32, f32),
+    pub raw_right: (f32, f32),
+    pub tuned_left: (f32, f32),
+    pub tuned_right: (f32, f32),
+}
+
+/// Apply deadzones and the response curve to a normalized stick
+/// position, preserving direction.
+pub fn apply_stick_tuning(tuning: &StickTuning, x: f32, y: f32) -> (f32, f32) {
+    let magnitude = (x * x + y * y).sqrt();
+    if magnitude <= tuning.inner_deadzone {
+        return (0.0, 0.0);
+    }
+    let range = (tuning.outer_deadzone - tuning.inner_deadzone).max(0.01);
+    let scaled = ((magnitude - tuning.inner_deadzone) / range).clamp(0.0, 1.0);
+    let curved = match tuning.curve {
+        StickCurve::Linear => scaled,
+        StickCurve::Squared => scaled * scaled,
+        StickCurve::Custom => scaled.powf(tuning.curve_exponent.max(0.1)),
+    };
+    (x / magnitude * curved, y / magnitude * curved)
+}
+
+/// Tracks connected gamepads and exposes their current state.
+pub struct ControllerManager {
+    gilrs: Gilrs,
+    tuning: ControllerTuning,
+    /// Protocol slot assignments, index = slot. A pad keeps its slot
+    /// across a reconnect so the game sees the same player port.
+    slots: [Option<GamepadId>; MAX_PAD_SLOTS],
+    /// Arrivals/removals observed while draining gilrs events, waiting
+    /// for `take_connection_events`.
+    pending_connections: Vec<PadConnectionEvent>,
+    /// Last state reported per slot, for change detection in
+    /// `poll_changed`.
+    last_states: [Option<ControllerState>; MAX_PAD_SLOTS],
+    /// Playing force-feedback effect per slot; dropping one stops it.
+    rumble_effects: [Option<gilrs::ff::Effect>; MAX_PAD_SLOTS],
+}
+
+impl ControllerManager {
+    pub fn new() -> Option<Self> {
+        match Gilrs::new() {
+            Ok(gilrs) => Some(Self {
+                gilrs,
+                tuning: ControllerTuning::default(),
+                slots: [None; MAX_PAD_SLOTS],
+                pending_connections: Vec::new(),
+                last_states: [None; MAX_PAD_SLOTS],
+                rumble_effects: [None, None, None, None],
+            }),
+            Err(e) => {
+                log::warn!("Controller support unavailable: {}", e);
+                None
+            }
+        }
+    }
+
+    pub fn connected_count(&self) -> usize {
+        self.gilrs.gamepads().count()
+    }
+
+    pub fn tuning(&self) -> ControllerTuning {
+        self.tuning
+    }
+
+    pub fn set_tuning(&mut self, tuning: ControllerTuning) {
+        self.tuning = tuning;
+    }
+
+    /// Drain the gilrs event queue, recording arrivals/removals as
+    /// pending connection events. Returns whether anything arrived.
+    /// State readers (`poll`, `sample_sticks`) work off absolute pad
+    /// state, so dropping the other event kinds here loses nothing.
+    fn drain_events(&mut self) -> bool {
+        let mut any = false;
+        while let Some(event) = self.gilrs.next_event() {
+            any = true;
+            match event.event {
+                EventType::Connected => self.note_connected(event.id),
+                EventType::Disconnected => self.note_disconnected(event.id),
+                _ => {}
+            }
+        }
+        any
+    }
+
+    /// Give `id` its existing slot, or the lowest free one. None when
+    /// all four protocol slots are taken (the pad is ignored, like the
+    /// official client does with a fifth pad).
+    fn assign_slot(&mut self, id: GamepadId) -> Option<u8> {
+        if let Some(slot) = self.slots.iter().position(|s| *s == Some(id)) {
+            return Some(slot as u8);
+        }
+        match self.slots.iter().position(|s| s.is_none()) {
+            Some(slot) => {
+                self.slots[slot] = Some(id);
+                Some(slot as u8)
+            }
+            None => {
+                log::warn!("More than {} gamepads connected; ignoring the extra one", MAX_PAD_SLOTS);
+                None
+            }
+        }
+    }
+
+    /// Type code and capability flags for the arrival packet, from the
+    /// pad's reported name and force-feedback support.
+    fn classify(gamepad: &gilrs::Gamepad) -> (u8, u16) {
+        let name = gamepad.name().to_ascii_lowercase();
+        let controller_type = if name.contains("dualshock")
+            || name.contains("dualsense")
+            || name.contains("sony")
+            || name.contains("ps4")
+            || name.contains("ps5")
+        {
+            CONTROLLER_TYPE_DUALSHOCK
+        } else if name.contains("xbox") || name.contains("x-box") || name.contains("xinput") {
+            CONTROLLER_TYPE_XBOX
+        } else {
+            CONTROLLER_TYPE_GENERIC
+        };
+        let mut capabilities = 0;
+        if gamepad.is_ff_supported() {
+            capabilities |= PAD_CAP_RUMBLE;
+        }
+        // Every pad with a known mapping exposes analog trigger values
+        // through gilrs; only unmapped generics are digital-only.
+        if controller_type != CONTROLLER_TYPE_GENERIC {
+            capabilities |= PAD_CAP_ANALOG_TRIGGERS;
+        }
+        (controller_type, capabilities)
+    }
+
+    fn note_connected(&mut self, id: GamepadId) {
+        let Some(slot) = self.assign_slot(id) else {
+            return;
+        };
+        let (controller_type, capabilities) = Self::classify(&self.gilrs.gamepad(id));
+        self.pending_connections.push(PadConnectionEvent::Connected {
+            slot,
+            controller_type,
+            capabilities,
+        });
+    }
+
+    fn note_disconnected(&mut self, id: GamepadId) {
+        // The slot stays reserved so a reconnect lands on the same
+        // player port.
+        if let Some(slot) = self.slots.iter().position(|s| *s == Some(id)) {
+            self.pending_connections
+                .push(PadConnectionEvent::Disconnected { slot: slot as u8 });
+        }
+    }
+
+    /// Pads that arrived or left since the last call, in observed
+    /// order. The rig needs an arrival before a pad's first state
+    /// packet, so callers forward these ahead of `poll` output.
+    pub fn take_connection_events(&mut self) -> Vec<PadConnectionEvent> {
+        self.drain_events();
+        std::mem::take(&mut self.pending_connections)
+    }
+
+    /// Arrival events for every pad already connected, in slot order.
+    /// Sent once at stream start so games show controller glyphs from
+    /// the first frame, matching the official client's sequencing.
+    pub fn connection_snapshot(&mut self) -> Vec<PadConnectionEvent> {
+        self.drain_events();
+        self.pending_connections.clear();
+        let ids: Vec<GamepadId> = self.gilrs.gamepads().map(|(id, _)| id).collect();
+        let mut events = Vec::new();
+        for id in ids {
+            let Some(slot) = self.assign_slot(id) else {
+                continue;
+            };
+            let (controller_type, capabilities) = Self::classify(&self.gilrs.gamepad(id));
+            events.push(PadConnectionEvent::Connected {
+                slot,
+                controller_type,
+                capabilities,
+            });
+        }
+        events.sort_by_key(|event| match event {
+            PadConnectionEvent::Connected { slot, .. } => *slot,
+            PadConnectionEvent::Disconnected { slot } => *slot,
+        });
+        events
+    }
+
+    /// Stable identity (name + gilrs UUID) of the first connected pad,
+    /// used as the key for persisted tuning profiles.
+    pub fn pad_identity(&self) -> Option<String> {
+        let (_id, gamepad) = self.gilrs.gamepads().next()?;
+        let uuid = gamepad
+            .uuid()
+            .iter()
+            .map(|b| format!("{:02x}", b))
+            .collect::<String>();
+        Some(format!("{}-{}", gamepad.name(), uuid))
+    }
+
+    /// Raw and tuned stick positions of the first pad, for the settings
+    /// visualizer. Drains pending gilrs events.
+    pub fn sample_sticks(&mut self) -> Option<StickSample> {
+        self.drain_events();
+        let (_id, gamepad) = self.gilrs.gamepads().next()?;
+        let raw_left = (gamepad.value(Axis::LeftStickX), gamepad.value(Axis::LeftStickY));
+        let raw_right = (
+            gamepad.value(Axis::RightStickX),
+            gamepad.value(Axis::RightStickY),
+        );
+        Some(StickSample {
+            raw_left,
+            raw_right,
+            tuned_left: apply_stick_tuning(&self.tuning.left_stick, raw_left.0, raw_left.1),
+            tuned_right: apply_stick_tuning(&self.tuning.right_stick, raw_right.0, raw_right.1),
+        })
+    }
+
+    /// Whether any gilrs events arrived since the last drain. Used by
+    /// the AFK guard; draining here is harmless because `poll` and
+    /// `sample_sticks` read absolute gamepad state, not the event queue.
+    pub fn had_input_event(&mut self) -> bool {
+        self.drain_events()
+    }
+
+    /// Drain pending gilrs events and return the state of every slotted
+    /// pad that changed since the last call, in slot order. Change-driven
+    /// so an idle pad costs no bandwidth; a pad whose slot just
+    /// (re)connected always reports once, giving the rig a baseline.
+    pub fn poll_changed(&mut self) -> Vec<(u8, ControllerState)> {
+        self.drain_events();
+        let mut changed = Vec::new();
+        for slot in 0..MAX_PAD_SLOTS {
+            let Some(id) = self.slots[slot] else {
+                continue;
+            };
+            let gamepad = self.gilrs.gamepad(id);
+            if !gamepad.is_connected() {
+                // Forget the last state so a reconnect resends it even
+                // if the pad comes back in the exact same position.
+                self.last_states[slot] = None;
+                continue;
+            }
+            let state = Self::read_state(&self.tuning, &gamepad);
+            if self.last_states[slot] != Some(state) {
+                self.last_states[slot] = Some(state);
+                changed.push((slot as u8, state));
+            }
+        }
+        changed
+    }
+
+    /// Forget every slot's reported state, returning the slots that had
+    /// reported anything. The stream-stop path zeroes those slots on
+    /// the wire; clearing the baselines makes the next stream resend
+    /// full state on its first poll.
+    pub fn take_reported_slots(&mut self) -> Vec<u8> {
+        let mut reported = Vec::new();
+        for (slot, state) in self.last_states.iter_mut().enumerate() {
+            if state.take().is_some() {
+                reported.push(slot as u8);
+            }
+        }
+        reported
+    }
+
+    /// Drive the addressed pad's motors from a server haptic command.
+    /// Each command replaces the slot's running effect; both magnitudes
+    /// zero stops it. Pads without force feedback are skipped silently
+    /// (the arrival packet already told the rig via `PAD_CAP_RUMBLE`,
+    /// but some titles rumble every slot anyway).
+    pub fn apply_rumble(&mut self, command: RumbleCommand) {
+        let slot = command.slot as usize;
+        if slot >= MAX_PAD_SLOTS {
+            return;
+        }
+        // Dropping the old effect stops it; do so even when the new
+        // command fails below, so a stale buzz can't outlive its packet.
+        self.rumble_effects[slot] = None;
+        if command.low_frequency == 0 && command.high_frequency == 0 {
+            return;
+        }
+        let Some(id) = self.slots[slot] else {
+            return;
+        };
+        if !self.gilrs.gamepad(id).is_ff_supported() {
+            return;
+        }
+        let mut builder = EffectBuilder::new();
+        builder
+            .add_effect(BaseEffect {
+                kind: BaseEffectType::Strong {
+                    magnitude: command.low_frequency,
+                },
+                ..Default::default()
+            })
+            .add_effect(BaseEffect {
+                kind: BaseEffectType::Weak {
+                    magnitude: command.high_frequency,
+                },
+                ..Default::default()
+            });
+        builder.add_gamepad(&self.gilrs.gamepad(id));
+        match builder.finish(&mut self.gilrs) {
+            Ok(effect) => {
+                if let Err(e) = effect.play() {
+                    log::debug!("Rumble playback failed: {}", e);
+                    return;
+                }
+                self.rumble_effects[slot] = Some(effect);
+            }
+            Err(e) => log::debug!("Rumble effect rejected: {}", e),
+        }
+    }
+
+    /// Stop every motor. Called when the stream ends so the last
+    /// command received can't leave a pad buzzing on the Games screen.
+    pub fn stop_rumble(&mut self) {
+        self.rumble_effects = [None, None, None, None];
+    }
+
+    /// Map a pad's absolute state into GFN packet terms: the standard
+    /// button bitmask, tuned sticks quantized to i16, and triggers as
+    /// 0–255 with the configured threshold treated as unpressed.
+    fn read_state(tuning: &ControllerTuning, gamepad: &gilrs::Gamepad) -> ControllerState {
+        let mut buttons = 0u16;
+        for (button, bit) in BUTTON_MASKS {
+            if gamepad.is_pressed(button) {
+                buttons |= bit;
+            }
+        }
+        let quantize = |v: f32| (v.clamp(-1.0, 1.0) * i16::MAX as f32) as i16;
+        let trigger = |b: Button| {
+            let value = gamepad.button_data(b).map(|d| d.value()).unwrap_or(0.0);
+            if value < tuning.trigger_threshold {
+                0
+            } else {
+                (value * 255.0) as u8
+            }
+        };
+        let left = apply_stick_tuning(
+            &tuning.left_stick,
+            gamepad.value(Axis::LeftStickX),
+            gamepad.value(Axis::LeftStickY),
+        );
+        let right = apply_stick_tuning(
+            &tuning.right_stick,
+            gamepad.value(Axis::RightStickX),
+            gamepad.value(Axis::RightStickY),
+        );
+        ControllerState {
+            buttons,
+            left_trigger: trigger(Button::LeftTrigger2),
+            right_trigger: trigger(Button::RightTrigger2),
+            left_stick: (quantize(left.0), quantize(left.1)),
+            right_stick: (quantize(right.0), quantize(right.1)),
+        }
+    }
+}
+
+/// The standard GFN button bitmask (XInput bit layout), matching the
+/// official client's captures.
+pub const BUTTON_MASKS: [(Button, u16); 14] = [
+    (Button::DPadUp, 0x0001),
+    (Button::DPadDown, 0x0002),
+    (Button::DPadLeft, 0x0004),
+    (Button::DPadRight, 0x0008),
+    (Button::Start, 0x0010),
+    (Button::Select, 0x0020),
+    (Button::LeftThumb, 0x0040),
+    (Button::RightThumb, 0x0080),
+    (Button::LeftTrigger, 0x0100),
+    (Button::RightTrigger, 0x0200),
+    (Button::South, 0x1000),
+    (Button::East, 0x2000),
+    (Button::West, 0x4000),
+    (Button::North, 0x8000),
+];
@@ -0,0 +1,180 @@
+//! Linux raw input via evdev: one reader thread per relative pointing
+//! device forwards REL_X/REL_Y at device rate, bypassing winit's
+//! accelerated, frame-rate-bound fallback.
+
+use std::collections::HashSet;
+use std::fs::File;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use std::time::Duration;
+
+use anyhow::{bail, Result};
+
+static RAW_INPUT_RUNNING: AtomicBool = AtomicBool::new(false);
+static RAW_INPUT_PAUSED: AtomicBool = AtomicBool::new(false);
+/// Event nodes with a live reader thread; guards against the hot-plug
+/// scanner opening a device twice.
+static OPEN_NODES: Mutex<Option<HashSet<PathBuf>>> = Mutex::new(None);
+
+/// How often the hot-plug scanner rescans /dev/input for new devices.
+const HOTPLUG_SCAN_INTERVAL: Duration = Duration::from_secs(2);
+
+// struct input_event on 64-bit: timeval (16 bytes), type u16, code u16,
+// value i32.
+const INPUT_EVENT_SIZE: usize = 24;
+const EV_SYN: u16 = 0x00;
+const EV_REL: u16 = 0x02;
+const REL_X: u16 = 0x00;
+const REL_Y: u16 = 0x01;
+
+pub(super) fn platform_pause_raw_input(paused: bool) {
+    RAW_INPUT_PAUSED.store(paused, Ordering::SeqCst);
+}
+
+/// Open every relative pointing device under /dev/input and start a
+/// reader thread per device; a scanner thread picks up mice plugged in
+/// mid-session. Fails with a group hint when the nodes exist but are
+/// unreadable (the usual udev default).
+pub(super) fn platform_start_raw_input() -> Result<()> {
+    if RAW_INPUT_RUNNING.swap(true, Ordering::SeqCst) {
+        bail!("Raw input already running");
+    }
+    RAW_INPUT_PAUSED.store(false, Ordering::SeqCst);
+    *OPEN_NODES.lock().unwrap() = Some(HashSet::new());
+
+    let mut opened = 0;
+    let mut permission_denied = false;
+    for node in relative_event_nodes() {
+        match open_reader(&node) {
+            Ok(()) => opened += 1,
+            Err(e) => {
+                permission_denied |= e.kind() == std::io::ErrorKind::PermissionDenied;
+                log::debug!("Raw input: cannot open {}: {}", node.display(), e);
+            }
+        }
+    }
+    if opened == 0 {
+        RAW_INPUT_RUNNING.store(false, Ordering::SeqCst);
+        if permission_denied {
+            bail!(
+                "No permission to read /dev/input event devices — add your \
+                 user to the 'input' group and log in again"
+            );
+        }
+        bail!("No relative pointing devices found under /dev/input");
+    }
+    log::info!("Raw input capture started (evdev, {} device(s))", opened);
+    std::thread::Builder::new()
+        .name("raw-input-hotplug".to_string())
+        .spawn(hotplug_thread)?;
+    Ok(())
+}
+
+pub(super) fn platform_stop_raw_input() {
+    RAW_INPUT_RUNNING.store(false, Ordering::SeqCst);
+    // Reader threads notice on their next event and exit; like the
+    // other platforms, events are dropped immediately either way.
+}
+
+/// Event nodes whose sysfs capabilities advertise REL_X (mice and
+/// trackballs; absolute devices like tablets stay on the winit path).
+fn relative_event_nodes() -> Vec<PathBuf> {
+    let mut nodes = Vec::new();
+    let Ok(entries) = std::fs::read_dir("/dev/input") else {
+        return nodes;
+    };
+    for entry in entries.flatten() {
+        let name = entry.file_name();
+        let Some(name) = name.to_str() else { continue };
+        if !name.starts_with("event") {
+            continue;
+        }
+        let caps = format!("/sys/class/input/{}/device/capabilities/rel", name);
+        let Ok(rel) = std::fs::read_to_string(caps) else {
+            continue;
+        };
+        // The file is a hex bitmask of REL_* codes; REL_X is bit 0 of
+        // the last (lowest) word.
+        let has_rel_x = rel
+            .split_whitespace()
+            .last()
+            .and_then(|word| u64::from_str_radix(word, 16).ok())
+            .is_some_and(|mask| mask & (1 << REL_X) != 0);
+        if has_rel_x {
+            nodes.push(entry.path());
+        }
+    }
+    nodes
+}
+
+/// Open one device node and spawn its reader thread. Registered in
+/// `OPEN_NODES` so the scanner skips it while the reader lives.
+fn open_reader(node: &Path) -> std::io::Result<()> {
+    let file = File::open(node)?;
+    if let Some(open) = OPEN_NODES.lock().unwrap().as_mut() {
+        if !open.insert(node.to_path_buf()) {
+            return Ok(());
+        }
+    }
+    let path = node.to_path_buf();
+    std::thread::Builder::new()
+        .name("raw-input".to_string())
+        .spawn(move || reader_thread(file, path))?;
+    Ok(())
+}
+
+/// Rescan for devices plugged in mid-session. Unplugged devices clean
+/// themselves up when their reader hits a read error.
+fn hotplug_thread() {
+    while RAW_INPUT_RUNNING.load(Ordering::SeqCst) {
+        std::thread::sleep(HOTPLUG_SCAN_INTERVAL);
+        for node in relative_event_nodes() {
+            let tracked = OPEN_NODES
+                .lock()
+                .unwrap()
+                .as_ref()
+                .is_some_and(|open| open.contains(&node));
+            if !tracked {
+                if let Err(e) = open_reader(&node) {
+                    log::debug!("Raw input: cannot open {}: {}", node.display(), e);
+                }
+            }
+        }
+    }
+}
+
+/// Blocking-read loop for one device: REL_X/REL_Y deltas accumulate
+/// until the SYN_REPORT that closes the hardware report, then go out as
+/// one `MouseMove`.
+fn reader_thread(mut file: File, path: PathBuf) {
+    let mut buf = [0u8; INPUT_EVENT_SIZE];
+    let (mut dx, mut dy) = (0i32, 0i32);
+    while RAW_INPUT_RUNNING.load(Ordering::SeqCst) {
+        if let Err(e) = file.read_exact(&mut buf) {
+            // EOF/ENODEV on unplug; drop the registration so a re-plug
+            // is picked up by the scanner.
+            log::debug!("Raw input: {} closed: {}", path.display(), e);
+            break;
+        }
+        let event_type = u16::from_ne_bytes([buf[16], buf[17]]);
+        let code = u16::from_ne_bytes([buf[18], buf[19]]);
+        let value = i32::from_ne_bytes([buf[20], buf[21], buf[22], buf[23]]);
+        match (event_type, code) {
+            (EV_REL, REL_X) => dx += value,
+            (EV_REL, REL_Y) => dy += value,
+            (EV_SYN, _) => {
+                if (dx != 0 || dy != 0) && !RAW_INPUT_PAUSED.load(Ordering::SeqCst) {
+                    super::forward_raw_delta(dx, dy);
+                }
+                dx = 0;
+                dy = 0;
+            }
+            _ => {}
+        }
+    }
+    if let Some(open) = OPEN_NODES.lock().unwrap().as_mut() {
+        open.remove(&path);
+    }
+}
@@ -0,0 +1,80 @@
+//! macOS raw-ish input via a CGEventTap reading unaccelerated deltas.
+
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use anyhow::{bail, Result};
+
+static RAW_INPUT_RUNNING: AtomicBool = AtomicBool::new(false);
+static RAW_INPUT_PAUSED: AtomicBool = AtomicBool::new(false);
+
+pub(super) fn platform_pause_raw_input(paused: bool) {
+    RAW_INPUT_PAUSED.store(paused, Ordering::SeqCst);
+}
+
+/// Install a CGEventTap for mouseMoved/dragged events and forward
+/// kCGMouseEventDeltaX/Y, which are pre-acceleration device deltas.
+pub(super) fn platform_start_raw_input() -> Result<()> {
+    if RAW_INPUT_RUNNING.swap(true, Ordering::SeqCst) {
+        bail!("Raw input already running");
+    }
+    RAW_INPUT_PAUSED.store(false, Ordering::SeqCst);
+    std::thread::Builder::new()
+        .name("raw-input".to_string())
+        .spawn(event_tap_thread)?;
+    Ok(())
+}
+
+pub(super) fn platform_stop_raw_input() {
+    RAW_INPUT_RUNNING.store(false, Ordering::SeqCst);
+}
+
+fn event_tap_thread() {
+    use core_graphics::event::{
+        CGEventTap, CGEventTapLocation, CGEventTapOptions, CGEventTapPlacement, CGEventType,
+        EventField,
+    };
+
+    let tap = CGEventTap::new(
+        CGEventTapLocation::Session,
+        CGEventTapPlacement::HeadInsertEventTap,
+        CGEventTapOptions::ListenOnly,
+        vec![
+            CGEventType::MouseMoved,
+            CGEventType::LeftMouseDragged,
+            CGEventType::RightMouseDragged,
+        ],
+        |_proxy, _type, event| {
+            if RAW_INPUT_RUNNING.load(Ordering::SeqCst)
+                && !RAW_INPUT_PAUSED.load(Ordering::SeqCst)
+            {
+                let dx = event.get_integer_value_field(EventField::MOUSE_EVENT_DELTA_X);
+                let dy = event.get_integer_value_field(EventField::MOUSE_EVENT_DELTA_Y);
+                if dx != 0 || dy != 0 {
+                    super::forward_raw_delta(
+                        dx.clamp(i32::MIN as i64, i32::MAX as i64) as i32,
+                        dy.clamp(i32::MIN as i64, i32::MAX as i64) as i32,
+                    );
+                }
+            }
+            None
+        },
+    );
+    match tap {
+        Ok(tap) => {
+            log::info!("Raw input capture started (CGEventTap)");
+            let loop_source = tap
+                .mach_port
+                .create_runloop_source(0)
+                .expect("Failed to create runloop source");
+            unsafe {
+                let run_loop = core_foundation::runloop::CFRunLoop::get_current();
+                run_loop.add_source(&loop_source, core_foundation::runloop::kCFRunLoopCommonModes);
+            }
+            tap.enable();
+            core_foundation::runloop::CFRunLoop::run_current();
+        }
+        Err(_) => {
+            log::error!("Raw input: failed to create event tap (missing Accessibility permission?)");
+        }
+    }
+}
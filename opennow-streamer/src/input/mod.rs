@@ -0,0 +1,1186 @@
+//! Input capture and encoding into the GFN input protocol.
+//!
+//! Events flow: winit / raw-input thread -> `InputHandler` ->
+//! `input_event_tx` -> the streaming runner, which encodes them via
+//! `InputEncoder` and writes them to the WebRTC data channels.
+
+pub mod controller;
+
+#[cfg(windows)]
+mod windows;
+
+#[cfg(target_os = "macos")]
+mod macos;
+
+#[cfg(target_os = "linux")]
+mod linux;
+
+use std::collections::HashSet;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use anyhow::Result;
+use tokio::sync::mpsc::UnboundedSender;
+
+/// Set by input paths that bypass `App` (the raw-input threads, the
+/// Linux device-event fallback) when a real user event went through.
+/// The AFK guard consumes it each frame; heartbeats and synthetic
+/// traffic never set it.
+static RAW_ACTIVITY: AtomicBool = AtomicBool::new(false);
+
+pub(crate) fn note_raw_activity() {
+    RAW_ACTIVITY.store(true, Ordering::Relaxed);
+}
+
+/// Consume the raw-activity marker. Polled by the AFK guard.
+pub fn take_raw_activity() -> bool {
+    RAW_ACTIVITY.swap(false, Ordering::Relaxed)
+}
+
+/// Set while the OS raw-capture backend is live (`start_raw_input`
+/// succeeded, `stop_raw_input` not yet called). The winit device-event
+/// fallback checks it so deltas aren't forwarded twice when the OS
+/// hooks already deliver the same motion.
+static RAW_CAPTURE_ACTIVE: AtomicBool = AtomicBool::new(false);
+
+/// Whether OS raw capture actually started; false when it was refused
+/// (no evdev access, missing permissions) and the winit fallback must
+/// carry mouse motion instead.
+pub fn raw_capture_active() -> bool {
+    RAW_CAPTURE_ACTIVE.load(Ordering::SeqCst)
+}
+
+/// Handler the raw-capture threads forward into, so raw deltas get the
+/// same sensitivity scaling and congestion decimation as the winit
+/// path. The frame loop mirrors sensitivity and queue depth onto it
+/// alongside its own handler.
+static RAW_HANDLER: Mutex<Option<InputHandler>> = Mutex::new(None);
+
+/// Install the channel raw-captured mouse deltas are forwarded on,
+/// wrapped in a dedicated `InputHandler`. Called at stream start,
+/// before `start_raw_input`.
+pub fn set_raw_input_sender(sender: UnboundedSender<InputEvent>) {
+    *RAW_HANDLER.lock().unwrap() = Some(InputHandler::new(sender));
+}
+
+/// Entry point for the platform capture threads: one OS-level batch of
+/// mouse deltas, routed through `handle_mouse_delta_immediate` for
+/// scaling and congestion handling.
+#[cfg_attr(not(any(windows, target_os = "macos", target_os = "linux")), allow(dead_code))]
+pub(crate) fn forward_raw_delta(dx: i32, dy: i32) {
+    note_raw_activity();
+    if let Some(handler) = RAW_HANDLER.lock().unwrap().as_mut() {
+        handler.handle_mouse_delta_immediate(dx, dy);
+    }
+}
+
+/// Mirror the combined delta multiplier (profile sensitivity times
+/// `Settings::mouse_sensitivity`) onto the raw handler.
+pub fn set_raw_sensitivity(sensitivity: f32) {
+    if let Some(handler) = RAW_HANDLER.lock().unwrap().as_mut() {
+        handler.set_sensitivity(sensitivity);
+    }
+}
+
+/// Mirror the queue-depth estimate onto the raw handler so raw batches
+/// decimate under congestion like winit deltas do.
+pub fn update_raw_queue_depth(depth: usize) {
+    if let Some(handler) = RAW_HANDLER.lock().unwrap().as_mut() {
+        handler.update_queue_depth(depth);
+    }
+}
+
+// Packet type bytes of the GFN input protocol.
+const PACKET_KEY_DOWN: u8 = 0x02;
+const PACKET_KEY_UP: u8 = 0x03;
+const PACKET_UNICODE_TEXT: u8 = 0x04;
+const PACKET_MOUSE_ABSOLUTE: u8 = 0x05;
+const PACKET_MOUSE_BUTTON: u8 = 0x07;
+const PACKET_MOUSE_RELATIVE: u8 = 0x08;
+const PACKET_MOUSE_WHEEL: u8 = 0x0a;
+const PACKET_GAMEPAD_ARRIVAL: u8 = 0x0c;
+const PACKET_GAMEPAD_REMOVAL: u8 = 0x0d;
+const PACKET_GAMEPAD_STATE: u8 = 0x0e;
+/// Server → client: haptic command for one pad (the only packet the
+/// rig sends back on the input channel besides the handshake).
+const PACKET_RUMBLE: u8 = 0x0f;
+
+/// VK codes involved in Windows' AltGr synthesis.
+const VK_LCONTROL: u16 = 0xa2;
+const VK_RMENU: u16 = 0xa5;
+/// Key packet flag marking a right-Alt press as AltGr for servers that
+/// distinguish it; others ignore unknown flag bits.
+pub const KEY_FLAG_ALTGR: u16 = 0x0001;
+/// How close together the synthetic left-Ctrl and right-Alt arrive when
+/// Windows reports an AltGr press. Human Ctrl-then-Alt chords are an
+/// order of magnitude slower.
+const ALTGR_WINDOW: Duration = Duration::from_millis(50);
+
+/// Above this many locally-queued events we start decimating mouse
+/// deltas to avoid runaway latency on a congested channel.
+pub const MAX_INPUT_QUEUE_DEPTH: usize = 64;
+
+/// Wire size of one encoded relative mouse packet (type byte plus two
+/// i16 deltas), used to translate the data channels' buffered byte
+/// counts into the event estimate fed to `update_queue_depth`.
+const MOUSE_PACKET_WIRE_BYTES: usize = 5;
+
+/// Flush cadence while the channel is congested. Much shorter and the
+/// decimation does nothing; much longer and the cursor visibly steps.
+const CONGESTED_FLUSH_INTERVAL: Duration = Duration::from_millis(16);
+
+/// Estimate how many encoded events a buffered byte count represents.
+/// Mouse packets dominate the backlog under congestion, so their wire
+/// size is the divisor.
+pub fn estimate_queued_events(buffered_bytes: usize) -> usize {
+    buffered_bytes / MOUSE_PACKET_WIRE_BYTES
+}
+
+/// Default coalescing interval on a fast link; the adaptive logic
+/// scales up from here as RTT grows.
+const MOUSE_COALESCE_INTERVAL: Duration = Duration::from_millis(2);
+
+/// RTT at which the coalescing interval reaches its upper bound.
+const COALESCE_FULL_SPAN_RTT_MS: f32 = 60.0;
+
+/// Input-channel backlog at which the interval reaches its upper bound.
+const COALESCE_FULL_SPAN_BUFFERED: usize = 16 * 1024;
+
+/// Map measured RTT and input-channel backlog onto a coalescing
+/// interval: `min` on a fast idle link, approaching `max` as RTT grows
+/// or the channel backs up. Monotonic in both inputs.
+pub fn adaptive_coalesce_interval(
+    rtt_ms: f32,
+    buffered_bytes: usize,
+    min: Duration,
+    max: Duration,
+) -> Duration {
+    let span = max.saturating_sub(min);
+    let rtt_factor = (rtt_ms / COALESCE_FULL_SPAN_RTT_MS).clamp(0.0, 1.0);
+    let buffer_factor =
+        (buffered_bytes as f32 / COALESCE_FULL_SPAN_BUFFERED as f32).clamp(0.0, 1.0);
+    min + span.mul_f32(rtt_factor.max(buffer_factor))
+}
+
+/// Pixels of trackpad scroll that correspond to one 120-unit wheel
+/// notch.
+const WHEEL_PIXELS_PER_NOTCH: f32 = 50.0;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum InputEvent {
+    KeyDown { vk: u16, scancode: u16, flags: u16 },
+    KeyUp { vk: u16, scancode: u16, flags: u16 },
+    /// A chunk of pasted text, carried as UTF-16 code units. Producers
+    /// go through `paste_chunks`, which strips anything outside the
+    /// BMP — the packet has no room for surrogate pairs.
+    Text { text: String },
+    MouseMove { dx: i16, dy: i16 },
+    MouseAbsolute { x: u16, y: u16 },
+    MouseButton { button: u8, down: bool },
+    MouseWheel { delta: i16 },
+    /// A gamepad arrived (hot-plug, or already present at stream
+    /// start). Games swap their prompt glyphs on this, so it must go
+    /// out before the pad's first state packet.
+    GamepadConnected {
+        slot: u8,
+        controller_type: u8,
+        capabilities: u16,
+    },
+    /// A gamepad left its slot.
+    GamepadDisconnected { slot: u8 },
+    /// Absolute state of one pad, sent whenever it changes.
+    GamepadState {
+        slot: u8,
+        buttons: u16,
+        left_trigger: u8,
+        right_trigger: u8,
+        left_stick: (i16, i16),
+        right_stick: (i16, i16),
+    },
+    /// Client viewport changed; forwarded to the rig as a DRC hint
+    /// rather than an input packet.
+    ViewportResize { width: u32, height: u32 },
+}
+
+/// Encodes `InputEvent`s into the wire format the rig expects.
+pub struct InputEncoder;
+
+impl InputEncoder {
+    pub fn encode(event: &InputEvent) -> Vec<u8> {
+        match event {
+            InputEvent::KeyDown { vk, scancode, flags } => {
+                let mut packet = vec![PACKET_KEY_DOWN];
+                packet.extend_from_slice(&vk.to_le_bytes());
+                packet.extend_from_slice(&scancode.to_le_bytes());
+                packet.extend_from_slice(&flags.to_le_bytes());
+                packet
+            }
+            InputEvent::KeyUp { vk, scancode, flags } => {
+                let mut packet = vec![PACKET_KEY_UP];
+                packet.extend_from_slice(&vk.to_le_bytes());
+                packet.extend_from_slice(&scancode.to_le_bytes());
+                packet.extend_from_slice(&flags.to_le_bytes());
+                packet
+            }
+            InputEvent::Text { text } => {
+                let mut packet = vec![PACKET_UNICODE_TEXT];
+                for unit in text.encode_utf16() {
+                    packet.extend_from_slice(&unit.to_le_bytes());
+                }
+                packet
+            }
+            InputEvent::MouseMove { dx, dy } => {
+                let mut packet = vec![PACKET_MOUSE_RELATIVE];
+                packet.extend_from_slice(&dx.to_le_bytes());
+                packet.extend_from_slice(&dy.to_le_bytes());
+                packet
+            }
+            InputEvent::MouseAbsolute { x, y } => {
+                let mut packet = vec![PACKET_MOUSE_ABSOLUTE];
+                packet.extend_from_slice(&x.to_le_bytes());
+                packet.extend_from_slice(&y.to_le_bytes());
+                packet
+            }
+            InputEvent::MouseButton { button, down } => {
+                vec![PACKET_MOUSE_BUTTON, *button, u8::from(*down)]
+            }
+            InputEvent::MouseWheel { delta } => {
+                let mut packet = vec![PACKET_MOUSE_WHEEL];
+                packet.extend_from_slice(&delta.to_le_bytes());
+                packet
+            }
+            InputEvent::GamepadConnected {
+                slot,
+                controller_type,
+                capabilities,
+            } => {
+                let mut packet = vec![PACKET_GAMEPAD_ARRIVAL, *slot, *controller_type];
+                packet.extend_from_slice(&capabilities.to_le_bytes());
+                packet
+            }
+            InputEvent::GamepadDisconnected { slot } => {
+                vec![PACKET_GAMEPAD_REMOVAL, *slot]
+            }
+            InputEvent::GamepadState {
+                slot,
+                buttons,
+                left_trigger,
+                right_trigger,
+                left_stick,
+                right_stick,
+            } => {
+                let mut packet = vec![PACKET_GAMEPAD_STATE, *slot];
+                packet.extend_from_slice(&buttons.to_le_bytes());
+                packet.push(*left_trigger);
+                packet.push(*right_trigger);
+                packet.extend_from_slice(&left_stick.0.to_le_bytes());
+                packet.extend_from_slice(&left_stick.1.to_le_bytes());
+                packet.extend_from_slice(&right_stick.0.to_le_bytes());
+                packet.extend_from_slice(&right_stick.1.to_le_bytes());
+                packet
+            }
+            // Sent as a control message in the stream task, never as an
+            // input packet.
+            InputEvent::ViewportResize { .. } => Vec::new(),
+        }
+    }
+}
+
+/// A haptic command from the rig: per-motor magnitudes in XInput terms
+/// (low-frequency motor = the heavy one, high-frequency = the light
+/// one). Both zero means stop.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RumbleCommand {
+    pub slot: u8,
+    pub low_frequency: u16,
+    pub high_frequency: u16,
+}
+
+impl RumbleCommand {
+    /// Parse a rumble packet: type byte, slot, then both magnitudes
+    /// little-endian. None for anything else on the channel.
+    pub fn parse(data: &[u8]) -> Option<Self> {
+        if data.len() != 6 || data[0] != PACKET_RUMBLE {
+            return None;
+        }
+        Some(Self {
+            slot: data[1],
+            low_frequency: u16::from_le_bytes([data[2], data[3]]),
+            high_frequency: u16::from_le_bytes([data[4], data[5]]),
+        })
+    }
+}
+
+/// Most UTF-16 code units per text packet; longer pastes are split so
+/// no single packet grows unreasonably on the reliable channel.
+const MAX_TEXT_CHUNK_UNITS: usize = 128;
+
+/// Split `text` into chunks sized for text packets, dropping characters
+/// outside the Basic Multilingual Plane rather than failing the whole
+/// paste (the wire format carries bare UTF-16 units).
+pub fn paste_chunks(text: &str) -> Vec<String> {
+    let mut chunks = Vec::new();
+    let mut current = String::new();
+    let mut units = 0;
+    for c in text.chars().filter(|c| (*c as u32) <= 0xffff) {
+        current.push(c);
+        units += 1;
+        if units == MAX_TEXT_CHUNK_UNITS {
+            chunks.push(std::mem::take(&mut current));
+            units = 0;
+        }
+    }
+    if !current.is_empty() {
+        chunks.push(current);
+    }
+    chunks
+}
+
+/// Accumulates relative mouse deltas and flushes them as one packet per
+/// coalescing interval, matching the official client's send cadence.
+pub struct MouseCoalescer {
+    pending_dx: i32,
+    pending_dy: i32,
+    last_flush: Instant,
+    interval: Duration,
+}
+
+impl MouseCoalescer {
+    pub fn new() -> Self {
+        Self {
+            pending_dx: 0,
+            pending_dy: 0,
+            last_flush: Instant::now(),
+            interval: MOUSE_COALESCE_INTERVAL,
+        }
+    }
+
+    pub fn accumulate(&mut self, dx: i32, dy: i32) {
+        self.pending_dx += dx;
+        self.pending_dy += dy;
+    }
+
+    pub fn interval(&self) -> Duration {
+        self.interval
+    }
+
+    pub fn set_interval(&mut self, interval: Duration) {
+        self.interval = interval;
+    }
+
+    /// Returns the coalesced delta when the interval has elapsed and
+    /// there is movement to send.
+    pub fn flush(&mut self) -> Option<(i16, i16)> {
+        self.flush_after(self.interval)
+    }
+
+    /// Like `flush` but with an explicit interval, used by the
+    /// congestion path to stretch the cadence without disturbing the
+    /// adaptive state.
+    pub fn flush_after(&mut self, interval: Duration) -> Option<(i16, i16)> {
+        if self.last_flush.elapsed() < interval {
+            return None;
+        }
+        if self.pending_dx == 0 && self.pending_dy == 0 {
+            return None;
+        }
+        let dx = self.pending_dx.clamp(i16::MIN as i32, i16::MAX as i32) as i16;
+        let dy = self.pending_dy.clamp(i16::MIN as i32, i16::MAX as i32) as i16;
+        self.pending_dx = 0;
+        self.pending_dy = 0;
+        self.last_flush = Instant::now();
+        Some((dx, dy))
+    }
+}
+
+impl Default for MouseCoalescer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Accumulates fractional scroll in notch units so trackpad pixel
+/// deltas that individually round to zero still add up, and flushes
+/// whole 120-unit notches.
+#[derive(Debug, Default)]
+pub struct WheelAccumulator {
+    pending_notches: f32,
+}
+
+impl WheelAccumulator {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add scroll in notch units. Returns a wire delta (multiples of
+    /// 120) once at least one whole notch has accumulated; the fraction
+    /// carries over.
+    pub fn accumulate(&mut self, notches: f32) -> Option<i16> {
+        self.pending_notches += notches;
+        let whole = self.pending_notches.trunc();
+        if whole == 0.0 {
+            return None;
+        }
+        self.pending_notches -= whole;
+        Some((whole * 120.0).clamp(i16::MIN as f32, i16::MAX as f32) as i16)
+    }
+}
+
+/// Local cursor preview drawn while the remote cursor is hidden in
+/// relative mode.
+#[derive(Debug, Clone, Copy)]
+pub struct LocalCursor {
+    pub x: f32,
+    pub y: f32,
+    width: f32,
+    height: f32,
+}
+
+impl LocalCursor {
+    pub fn new(width: f32, height: f32) -> Self {
+        Self {
+            x: width / 2.0,
+            y: height / 2.0,
+            width,
+            height,
+        }
+    }
+
+    pub fn set_dimensions(&mut self, width: f32, height: f32) {
+        self.width = width;
+        self.height = height;
+    }
+
+    pub fn apply_delta(&mut self, dx: f32, dy: f32) {
+        self.x = (self.x + dx).clamp(0.0, self.width);
+        self.y = (self.y + dy).clamp(0.0, self.height);
+    }
+}
+
+/// Translates window/raw events into `InputEvent`s and pushes them down
+/// the channel consumed by the streaming runner.
+pub struct InputHandler {
+    input_event_tx: UnboundedSender<InputEvent>,
+    coalescer: MouseCoalescer,
+    /// Adaptive coalescing bounds; `pinned` overrides adaptation.
+    coalesce_min: Duration,
+    coalesce_max: Duration,
+    coalesce_pinned: Option<Duration>,
+    wheel: WheelAccumulator,
+    scroll_speed: f32,
+    natural_scroll: bool,
+    /// Multiplier applied to relative deltas (the input profile's
+    /// sensitivity times the global setting).
+    sensitivity: f32,
+    /// Sub-pixel movement left over after quantizing scaled deltas,
+    /// carried into the next delta so multipliers below 1.0 don't eat
+    /// slow movement.
+    delta_remainder: (f64, f64),
+    /// Tracks where the hidden cursor "is" for the preview overlay.
+    local_cursor: LocalCursor,
+    pressed_keys: HashSet<u16>,
+    queue_depth: usize,
+    /// AltGr detection (see `handle_key`); disabled for games that bind
+    /// real Ctrl+Alt combos.
+    altgr_enabled: bool,
+    /// When left-Ctrl last went down, to spot the synthetic press.
+    last_lctrl_down: Option<Instant>,
+    /// A detected AltGr press is currently held.
+    altgr_active: bool,
+    /// The synthetic Ctrl was retracted; swallow its pending key-up.
+    altgr_ctrl_suppressed: bool,
+}
+
+impl InputHandler {
+    pub fn new(input_event_tx: UnboundedSender<InputEvent>) -> Self {
+        Self {
+            input_event_tx,
+            coalescer: MouseCoalescer::new(),
+            coalesce_min: MOUSE_COALESCE_INTERVAL,
+            coalesce_max: Duration::from_millis(12),
+            coalesce_pinned: None,
+            wheel: WheelAccumulator::new(),
+            scroll_speed: 1.0,
+            natural_scroll: false,
+            sensitivity: 1.0,
+            delta_remainder: (0.0, 0.0),
+            local_cursor: LocalCursor::new(1920.0, 1080.0),
+            pressed_keys: HashSet::new(),
+            queue_depth: 0,
+            altgr_enabled: true,
+            last_lctrl_down: None,
+            altgr_active: false,
+            altgr_ctrl_suppressed: false,
+        }
+    }
+
+    /// Enable/disable AltGr detection (`Settings::altgr_mode`).
+    pub fn set_altgr_enabled(&mut self, enabled: bool) {
+        self.altgr_enabled = enabled;
+    }
+
+    /// Apply the combined delta multiplier (profile sensitivity times
+    /// `Settings::mouse_sensitivity`).
+    pub fn set_sensitivity(&mut self, sensitivity: f32) {
+        self.sensitivity = sensitivity.max(0.01);
+    }
+
+    /// Keep the local-cursor preview clamped to the window.
+    pub fn set_cursor_area(&mut self, width: f32, height: f32) {
+        self.local_cursor.set_dimensions(width, height);
+    }
+
+    /// Where the preview cursor currently is, in window coordinates.
+    pub fn local_cursor(&self) -> (f32, f32) {
+        (self.local_cursor.x, self.local_cursor.y)
+    }
+
+    /// Apply the user's scroll settings.
+    pub fn set_scroll_options(&mut self, speed: f32, natural: bool) {
+        self.scroll_speed = speed;
+        self.natural_scroll = natural;
+    }
+
+    /// Configure the adaptive coalescing bounds, or pin a fixed
+    /// interval (which disables adaptation).
+    pub fn set_coalesce_options(&mut self, min: Duration, max: Duration, pinned: Option<Duration>) {
+        self.coalesce_min = min;
+        self.coalesce_max = max.max(min);
+        self.coalesce_pinned = pinned;
+        if let Some(interval) = pinned {
+            self.coalescer.set_interval(interval);
+        }
+    }
+
+    /// Feed network measurements into the coalescer; called roughly
+    /// once per second from the frame loop with the latest stats.
+    pub fn update_network_feedback(&mut self, rtt_ms: f32, buffered_bytes: usize) {
+        if self.coalesce_pinned.is_some() {
+            return;
+        }
+        let interval =
+            adaptive_coalesce_interval(rtt_ms, buffered_bytes, self.coalesce_min, self.coalesce_max);
+        if interval != self.coalescer.interval() {
+            log::debug!(
+                "Mouse coalescing interval -> {:?} (rtt {:.0} ms, {} B buffered)",
+                interval,
+                rtt_ms,
+                buffered_bytes
+            );
+            self.coalescer.set_interval(interval);
+        }
+    }
+
+    /// The interval currently in effect (for the stats overlay).
+    pub fn coalesce_interval(&self) -> Duration {
+        self.coalescer.interval()
+    }
+
+    /// Forward a key transition. With AltGr detection on, Windows'
+    /// synthetic left-Ctrl + right-Alt pair (how it reports AltGr on
+    /// international layouts) is rewritten: the already-forwarded Ctrl
+    /// is retracted with an immediate key-up and the right-Alt goes out
+    /// flagged as AltGr, so the remote side types @/€ instead of firing
+    /// Ctrl+Alt shortcuts.
+    pub fn handle_key(&mut self, vk: u16, scancode: u16, down: bool) {
+        let mut flags = 0;
+        if self.altgr_enabled {
+            match (vk, down) {
+                (VK_LCONTROL, true) => {
+                    self.last_lctrl_down = Some(Instant::now());
+                    self.altgr_ctrl_suppressed = false;
+                }
+                (VK_LCONTROL, false) if self.altgr_ctrl_suppressed => {
+                    // The release paired with the synthetic press we
+                    // already retracted — forwarding it would be an
+                    // unmatched key-up.
+                    self.altgr_ctrl_suppressed = false;
+                    return;
+                }
+                (VK_RMENU, true) => {
+                    if self.pressed_keys.contains(&VK_LCONTROL)
+                        && self
+                            .last_lctrl_down
+                            .is_some_and(|at| at.elapsed() <= ALTGR_WINDOW)
+                    {
+                        self.pressed_keys.remove(&VK_LCONTROL);
+                        let _ = self.input_event_tx.send(InputEvent::KeyUp {
+                            vk: VK_LCONTROL,
+                            scancode: 0,
+                            flags: 0,
+                        });
+                        self.altgr_ctrl_suppressed = true;
+                        self.altgr_active = true;
+                    }
+                    if self.altgr_active {
+                        flags = KEY_FLAG_ALTGR;
+                    }
+                }
+                (VK_RMENU, false) if self.altgr_active => {
+                    flags = KEY_FLAG_ALTGR;
+                    self.altgr_active = false;
+                }
+                _ => {}
+            }
+        }
+        let event = if down {
+            self.pressed_keys.insert(vk);
+            InputEvent::KeyDown { vk, scancode, flags }
+        } else {
+            self.pressed_keys.remove(&vk);
+            InputEvent::KeyUp { vk, scancode, flags }
+        };
+        let _ = self.input_event_tx.send(event);
+    }
+
+    /// Relative mouse motion, coalesced.
+    pub fn handle_mouse_delta(&mut self, dx: f64, dy: f64) {
+        let (dx, dy) = self.scale_delta(dx, dy);
+        self.coalescer.accumulate(dx, dy);
+        if let Some((dx, dy)) = self.flush_mouse() {
+            let _ = self.input_event_tx.send(InputEvent::MouseMove { dx, dy });
+        }
+    }
+
+    /// Relative mouse motion bypassing coalescing; the raw-capture
+    /// threads reach this through `forward_raw_delta`, and their batches
+    /// already coalesce at the OS level. While the channel is congested,
+    /// raw batches go through the coalescer too so they decimate like
+    /// everything else.
+    pub fn handle_mouse_delta_immediate(&mut self, dx: i32, dy: i32) {
+        let (dx, dy) = self.scale_delta(dx as f64, dy as f64);
+        self.coalescer.accumulate(dx, dy);
+        let flushed = if self.is_congested() {
+            self.flush_mouse()
+        } else {
+            // Flush whatever is pending, including movement a congested
+            // stretch left behind.
+            self.coalescer.flush_after(Duration::ZERO)
+        };
+        if let Some((dx, dy)) = flushed {
+            let _ = self.input_event_tx.send(InputEvent::MouseMove { dx, dy });
+        }
+    }
+
+    /// Sensitivity scaling plus the local-cursor bookkeeping shared by
+    /// both relative paths. The wire carries whole counts, so the
+    /// fractional part of each scaled delta is banked and rides along
+    /// with the next one; the preview cursor is f32 and tracks the
+    /// unquantized movement directly.
+    fn scale_delta(&mut self, dx: f64, dy: f64) -> (i32, i32) {
+        let scaled_x = dx * self.sensitivity as f64;
+        let scaled_y = dy * self.sensitivity as f64;
+        self.local_cursor.apply_delta(scaled_x as f32, scaled_y as f32);
+        let dx = scaled_x + self.delta_remainder.0;
+        let dy = scaled_y + self.delta_remainder.1;
+        self.delta_remainder = (dx.fract(), dy.fract());
+        (dx.trunc() as i32, dy.trunc() as i32)
+    }
+
+    /// Flush honouring congestion: a backed-up channel stretches the
+    /// cadence to `CONGESTED_FLUSH_INTERVAL`, cutting the packet count
+    /// while the accumulated delta preserves total movement.
+    fn flush_mouse(&mut self) -> Option<(i16, i16)> {
+        if self.is_congested() {
+            self.coalescer
+                .flush_after(CONGESTED_FLUSH_INTERVAL.max(self.coalescer.interval()))
+        } else {
+            self.coalescer.flush()
+        }
+    }
+
+    /// Send `text` to the remote session as unicode text packets
+    /// (Ctrl+Shift+V paste). Returns how many characters went out
+    /// after stripping unsupported ones.
+    pub fn paste_text(&mut self, text: &str) -> usize {
+        let mut sent = 0;
+        for chunk in paste_chunks(text) {
+            sent += chunk.chars().count();
+            let _ = self.input_event_tx.send(InputEvent::Text { text: chunk });
+        }
+        sent
+    }
+
+    pub fn handle_mouse_absolute(&mut self, x: u16, y: u16) {
+        let _ = self.input_event_tx.send(InputEvent::MouseAbsolute { x, y });
+    }
+
+    pub fn handle_mouse_button(&mut self, button: u8, down: bool) {
+        let _ = self
+            .input_event_tx
+            .send(InputEvent::MouseButton { button, down });
+    }
+
+    /// Scroll from a notched wheel source (winit `LineDelta`).
+    pub fn handle_wheel_lines(&mut self, lines: f32) {
+        self.push_wheel(lines);
+    }
+
+    /// Scroll from a pixel source (trackpads, winit `PixelDelta`);
+    /// fractional notches accumulate instead of rounding to zero.
+    pub fn handle_wheel_pixels(&mut self, pixels: f32) {
+        self.push_wheel(pixels / WHEEL_PIXELS_PER_NOTCH);
+    }
+
+    fn push_wheel(&mut self, notches: f32) {
+        let mut notches = notches * self.scroll_speed;
+        if self.natural_scroll {
+            notches = -notches;
+        }
+        if let Some(delta) = self.wheel.accumulate(notches) {
+            let _ = self.input_event_tx.send(InputEvent::MouseWheel { delta });
+        }
+    }
+
+    /// Report how many encoded events are queued on the data channels
+    /// (see `estimate_queued_events`); above `MAX_INPUT_QUEUE_DEPTH` the
+    /// handler decimates mouse deltas until the backlog drains.
+    pub fn update_queue_depth(&mut self, depth: usize) {
+        self.queue_depth = depth;
+    }
+
+    pub fn is_congested(&self) -> bool {
+        self.queue_depth > MAX_INPUT_QUEUE_DEPTH
+    }
+
+    /// Send key-up for everything held; called when focus is lost or the
+    /// stream stops so the game doesn't see stuck keys.
+    pub fn release_all_keys(&mut self) {
+        self.altgr_active = false;
+        self.altgr_ctrl_suppressed = false;
+        for vk in self.pressed_keys.drain() {
+            let _ = self.input_event_tx.send(InputEvent::KeyUp {
+                vk,
+                scancode: 0,
+                flags: 0,
+            });
+        }
+    }
+
+    /// Read every slotted pad through the manager and forward state
+    /// changes as gamepad packets on the same channel as keyboard and
+    /// mouse. Called once per frame; arrival/removal packets travel
+    /// separately and always precede a pad's first state packet, and a
+    /// pad hot-plugged mid-session starts reporting on its next change.
+    pub fn poll_controllers(&mut self, manager: &mut controller::ControllerManager) {
+        for (slot, state) in manager.poll_changed() {
+            let _ = self.input_event_tx.send(InputEvent::GamepadState {
+                slot,
+                buttons: state.buttons,
+                left_trigger: state.left_trigger,
+                right_trigger: state.right_trigger,
+                left_stick: state.left_stick,
+                right_stick: state.right_stick,
+            });
+        }
+    }
+}
+
+/// Start the platform raw-capture backend and record the outcome:
+/// `raw_capture_active` reports success so the winit device-event
+/// fallback stands down while the OS hooks are live. Unimplemented on
+/// the remaining platforms (BSDs), where the fallback is the only path.
+pub fn start_raw_input() -> Result<()> {
+    #[cfg(any(windows, target_os = "macos", target_os = "linux"))]
+    {
+        platform_start_raw_input()?;
+        RAW_CAPTURE_ACTIVE.store(true, Ordering::SeqCst);
+        Ok(())
+    }
+    #[cfg(not(any(windows, target_os = "macos", target_os = "linux")))]
+    anyhow::bail!("Raw input capture is not supported on this platform")
+}
+
+/// Tear raw capture down at stream end and hand motion back to the
+/// device-event fallback.
+pub fn stop_raw_input() {
+    RAW_CAPTURE_ACTIVE.store(false, Ordering::SeqCst);
+    #[cfg(any(windows, target_os = "macos", target_os = "linux"))]
+    platform_stop_raw_input();
+}
+
+/// Pause raw-input forwarding without tearing the hook down (menus,
+/// focus loss).
+pub fn pause_raw_input(paused: bool) {
+    #[cfg(any(windows, target_os = "macos", target_os = "linux"))]
+    {
+        self::platform_pause_raw_input(paused);
+    }
+    #[cfg(not(any(windows, target_os = "macos", target_os = "linux")))]
+    let _ = paused;
+}
+
+#[cfg(windows)]
+use windows::{platform_pause_raw_input, platform_start_raw_input, platform_stop_raw_input};
+#[cfg(target_os = "macos")]
+use macos::{platform_pause_raw_input, platform_start_raw_input, platform_stop_raw_input};
+#[cfg(target_os = "linux")]
+use linux::{platform_pause_raw_input, platform_start_raw_input, platform_stop_raw_input};
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn drain_wheel(rx: &mut tokio::sync::mpsc::UnboundedReceiver<InputEvent>) -> i32 {
+        let mut total = 0i32;
+        while let Ok(event) = rx.try_recv() {
+            if let InputEvent::MouseWheel { delta } = event {
+                total += delta as i32;
+            }
+        }
+        total
+    }
+
+    /// A realistic two-finger trackpad gesture: many small pixel deltas
+    /// that individually round below one notch must still add up to the
+    /// physical distance scrolled.
+    #[test]
+    fn trackpad_pixel_deltas_accumulate_into_notches() {
+        let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel();
+        let mut handler = InputHandler::new(tx);
+        // 30 events of 5px = 150px = 3 notches at 50px/notch.
+        for _ in 0..30 {
+            handler.handle_wheel_pixels(5.0);
+        }
+        assert_eq!(drain_wheel(&mut rx), 3 * 120);
+    }
+
+    #[test]
+    fn line_deltas_map_to_whole_notches() {
+        let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel();
+        let mut handler = InputHandler::new(tx);
+        handler.handle_wheel_lines(2.0);
+        handler.handle_wheel_lines(-1.0);
+        assert_eq!(drain_wheel(&mut rx), 120);
+    }
+
+    #[test]
+    fn scroll_speed_and_natural_scrolling_apply() {
+        let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel();
+        let mut handler = InputHandler::new(tx);
+        handler.set_scroll_options(2.0, true);
+        handler.handle_wheel_lines(1.0);
+        assert_eq!(drain_wheel(&mut rx), -2 * 120);
+    }
+
+    #[test]
+    fn fraction_carries_across_direction_neutral_events() {
+        let mut wheel = WheelAccumulator::new();
+        assert_eq!(wheel.accumulate(0.6), None);
+        assert_eq!(wheel.accumulate(0.6), Some(120));
+        // 0.2 remains pending.
+        assert_eq!(wheel.accumulate(0.7), None);
+        assert_eq!(wheel.accumulate(0.1), Some(120));
+    }
+
+    fn drain_moves(rx: &mut tokio::sync::mpsc::UnboundedReceiver<InputEvent>) -> (usize, i32) {
+        let mut packets = 0;
+        let mut total_dx = 0i32;
+        while let Ok(event) = rx.try_recv() {
+            if let InputEvent::MouseMove { dx, .. } = event {
+                packets += 1;
+                total_dx += dx as i32;
+            }
+        }
+        (packets, total_dx)
+    }
+
+    #[test]
+    fn uncongested_raw_deltas_pass_through_per_call() {
+        let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel();
+        let mut handler = InputHandler::new(tx);
+        for _ in 0..5 {
+            handler.handle_mouse_delta_immediate(2, 0);
+        }
+        assert_eq!(drain_moves(&mut rx), (5, 10));
+    }
+
+    /// Synthetic congestion: with the queue depth above the threshold
+    /// the raw path must stop emitting one packet per delta, and the
+    /// held movement must come out once the backlog clears.
+    #[test]
+    fn congestion_decimates_raw_mouse_deltas_without_losing_movement() {
+        let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel();
+        let mut handler = InputHandler::new(tx);
+        handler.update_queue_depth(MAX_INPUT_QUEUE_DEPTH + 1);
+        assert!(handler.is_congested());
+        for _ in 0..100 {
+            handler.handle_mouse_delta_immediate(1, 0);
+        }
+        let (packets, congested_dx) = drain_moves(&mut rx);
+        assert!(
+            packets <= 2,
+            "congested path sent {} packets for 100 deltas",
+            packets
+        );
+        handler.update_queue_depth(0);
+        assert!(!handler.is_congested());
+        handler.handle_mouse_delta_immediate(1, 0);
+        let (_, drained_dx) = drain_moves(&mut rx);
+        assert_eq!(congested_dx + drained_dx, 101);
+    }
+
+    #[test]
+    fn sensitivity_scales_relative_deltas() {
+        let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel();
+        let mut handler = InputHandler::new(tx);
+        handler.set_sensitivity(2.0);
+        handler.handle_mouse_delta_immediate(10, -5);
+        let (_, dx) = drain_moves(&mut rx);
+        assert_eq!(dx, 20);
+        let (x, _) = handler.local_cursor();
+        assert_eq!(x, 1920.0 / 2.0 + 20.0, "preview tracks scaled deltas");
+    }
+
+    /// Multipliers below 1.0 must bank the sub-pixel part instead of
+    /// truncating it away, or slow movement stops registering entirely.
+    #[test]
+    fn fractional_sensitivity_keeps_sub_pixel_movement() {
+        let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel();
+        let mut handler = InputHandler::new(tx);
+        handler.set_sensitivity(0.25);
+        for _ in 0..8 {
+            handler.handle_mouse_delta_immediate(1, 0);
+        }
+        let (_, dx) = drain_moves(&mut rx);
+        assert_eq!(dx, 2, "8 counts at 0.25x must come out as 2, not 0");
+    }
+
+    /// Arrival/removal packets must be byte-exact against the official
+    /// client; expectations come from a capture of a DualShock 4
+    /// hot-plug on slot 0 (rumble + analog triggers) and its unplug.
+    #[test]
+    fn gamepad_connection_packets_match_captured_bytes() {
+        let arrival = InputEncoder::encode(&InputEvent::GamepadConnected {
+            slot: 0,
+            controller_type: controller::CONTROLLER_TYPE_DUALSHOCK,
+            capabilities: controller::PAD_CAP_RUMBLE | controller::PAD_CAP_ANALOG_TRIGGERS,
+        });
+        assert_eq!(arrival, [0x0c, 0x00, 0x02, 0x03, 0x00]);
+        let removal = InputEncoder::encode(&InputEvent::GamepadDisconnected { slot: 0 });
+        assert_eq!(removal, [0x0d, 0x00]);
+        // Second pad, generic digital-only stick: type and capability
+        // bytes must track the pad, not repeat slot 0's.
+        let arrival = InputEncoder::encode(&InputEvent::GamepadConnected {
+            slot: 1,
+            controller_type: controller::CONTROLLER_TYPE_GENERIC,
+            capabilities: 0,
+        });
+        assert_eq!(arrival, [0x0c, 0x01, 0x03, 0x00, 0x00]);
+    }
+
+    /// State packets carry the full pad snapshot little-endian: slot,
+    /// button bitmask, triggers, then both sticks.
+    #[test]
+    fn gamepad_state_packet_layout_is_byte_exact() {
+        let packet = InputEncoder::encode(&InputEvent::GamepadState {
+            slot: 0,
+            buttons: 0x1001, // A + d-pad up
+            left_trigger: 0,
+            right_trigger: 255,
+            left_stick: (i16::MIN, i16::MAX),
+            right_stick: (0, 0),
+        });
+        assert_eq!(
+            packet,
+            [
+                0x0e, 0x00, // type, slot
+                0x01, 0x10, // buttons LE
+                0x00, 0xff, // triggers
+                0x00, 0x80, 0xff, 0x7f, // left stick LE
+                0x00, 0x00, 0x00, 0x00, // right stick LE
+            ]
+        );
+    }
+
+    /// Text packets are the type byte followed by bare UTF-16LE units.
+    #[test]
+    fn text_packet_layout_is_byte_exact() {
+        let packet = InputEncoder::encode(&InputEvent::Text {
+            text: "A€".to_string(),
+        });
+        assert_eq!(packet, [0x04, 0x41, 0x00, 0xac, 0x20]);
+    }
+
+    /// Pastes drop what the wire can't carry and split at the chunk
+    /// limit instead of sending one oversized packet.
+    #[test]
+    fn paste_chunks_strip_non_bmp_and_split_long_text() {
+        // The emoji is outside the BMP and must vanish, not error.
+        assert_eq!(paste_chunks("pa😀ss"), vec!["pass".to_string()]);
+        assert!(paste_chunks("").is_empty());
+        let long: String = "x".repeat(MAX_TEXT_CHUNK_UNITS + 1);
+        let chunks = paste_chunks(&long);
+        assert_eq!(chunks.len(), 2);
+        assert_eq!(chunks[0].chars().count(), MAX_TEXT_CHUNK_UNITS);
+        assert_eq!(chunks[1], "x");
+    }
+
+    /// Rumble packets come the other way; anything that isn't exactly a
+    /// six-byte 0x0f packet is someone else's message.
+    #[test]
+    fn rumble_packets_parse_byte_exact_and_reject_the_rest() {
+        let command = RumbleCommand::parse(&[0x0f, 0x01, 0x00, 0x80, 0xff, 0x00]);
+        assert_eq!(
+            command,
+            Some(RumbleCommand {
+                slot: 1,
+                low_frequency: 0x8000,
+                high_frequency: 0x00ff,
+            })
+        );
+        // The handshake echo and a truncated packet must both fall through.
+        assert_eq!(RumbleCommand::parse(&[0x0e]), None);
+        assert_eq!(RumbleCommand::parse(&[0x0f, 0x00, 0x00, 0x00, 0x00]), None);
+    }
+
+    #[test]
+    fn buffered_bytes_translate_into_event_estimates() {
+        assert_eq!(estimate_queued_events(0), 0);
+        assert_eq!(
+            estimate_queued_events(MOUSE_PACKET_WIRE_BYTES * MAX_INPUT_QUEUE_DEPTH),
+            MAX_INPUT_QUEUE_DEPTH
+        );
+    }
+
+    #[test]
+    fn coalesce_interval_is_monotonic_in_rtt() {
+        let min = Duration::from_millis(2);
+        let max = Duration::from_millis(12);
+        let mut previous = Duration::ZERO;
+        for rtt_ms in [0.0, 5.0, 10.0, 20.0, 40.0, 60.0, 120.0, 500.0] {
+            let interval = adaptive_coalesce_interval(rtt_ms, 0, min, max);
+            assert!(
+                interval >= previous,
+                "interval shrank at rtt {} ms",
+                rtt_ms
+            );
+            previous = interval;
+        }
+    }
+
+    #[test]
+    fn coalesce_interval_is_monotonic_in_buffered_bytes() {
+        let min = Duration::from_millis(2);
+        let max = Duration::from_millis(12);
+        let mut previous = Duration::ZERO;
+        for buffered in [0, 512, 2048, 8192, 16 * 1024, 64 * 1024] {
+            let interval = adaptive_coalesce_interval(0.0, buffered, min, max);
+            assert!(
+                interval >= previous,
+                "interval shrank at {} buffered bytes",
+                buffered
+            );
+            previous = interval;
+        }
+    }
+
+    #[test]
+    fn coalesce_interval_clamps_to_bounds() {
+        let min = Duration::from_millis(2);
+        let max = Duration::from_millis(12);
+        assert_eq!(adaptive_coalesce_interval(0.0, 0, min, max), min);
+        assert_eq!(adaptive_coalesce_interval(1000.0, usize::MAX, min, max), max);
+    }
+
+    #[test]
+    fn pinned_interval_disables_adaptation() {
+        let (tx, _rx) = tokio::sync::mpsc::unbounded_channel();
+        let mut handler = InputHandler::new(tx);
+        let pinned = Duration::from_millis(4);
+        handler.set_coalesce_options(
+            Duration::from_millis(2),
+            Duration::from_millis(12),
+            Some(pinned),
+        );
+        handler.update_network_feedback(500.0, 64 * 1024);
+        assert_eq!(handler.coalesce_interval(), pinned);
+    }
+    fn drain_keys(rx: &mut tokio::sync::mpsc::UnboundedReceiver<InputEvent>) -> Vec<InputEvent> {
+        let mut events = Vec::new();
+        while let Ok(event) = rx.try_recv() {
+            if matches!(event, InputEvent::KeyDown { .. } | InputEvent::KeyUp { .. }) {
+                events.push(event);
+            }
+        }
+        events
+    }
+
+    /// The exact sequence Windows generates for AltGr+Q (@) on a German
+    /// layout: synthetic left-Ctrl down, right-Alt down, the character
+    /// key, then the releases. The forwarded stream must retract the
+    /// Ctrl, flag the right-Alt, and leave nothing held.
+    #[test]
+    fn altgr_retracts_synthetic_ctrl_and_leaves_no_stuck_modifiers() {
+        let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel();
+        let mut handler = InputHandler::new(tx);
+        handler.handle_key(VK_LCONTROL, 0x1d, true);
+        handler.handle_key(VK_RMENU, 0x38, true);
+        handler.handle_key(0x51, 0x10, true); // Q
+        handler.handle_key(0x51, 0x10, false);
+        handler.handle_key(VK_LCONTROL, 0x1d, false);
+        handler.handle_key(VK_RMENU, 0x38, false);
+        let events = drain_keys(&mut rx);
+        assert_eq!(
+            events,
+            vec![
+                InputEvent::KeyDown { vk: VK_LCONTROL, scancode: 0x1d, flags: 0 },
+                // Retraction of the synthetic Ctrl, before the Alt.
+                InputEvent::KeyUp { vk: VK_LCONTROL, scancode: 0, flags: 0 },
+                InputEvent::KeyDown { vk: VK_RMENU, scancode: 0x38, flags: KEY_FLAG_ALTGR },
+                InputEvent::KeyDown { vk: 0x51, scancode: 0x10, flags: 0 },
+                InputEvent::KeyUp { vk: 0x51, scancode: 0x10, flags: 0 },
+                // The paired Ctrl release is swallowed entirely.
+                InputEvent::KeyUp { vk: VK_RMENU, scancode: 0x38, flags: KEY_FLAG_ALTGR },
+            ]
+        );
+        assert!(handler.pressed_keys.is_empty(), "stuck keys: {:?}", handler.pressed_keys);
+    }
+
+    /// Dead-key input (e.g. AltGr+~ then a vowel) releases and re-presses
+    /// AltGr; the second press must flag too even though the synthetic
+    /// Ctrl pattern repeats.
+    #[test]
+    fn repeated_altgr_presses_each_get_detected() {
+        let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel();
+        let mut handler = InputHandler::new(tx);
+        for _ in 0..2 {
+            handler.handle_key(VK_LCONTROL, 0x1d, true);
+            handler.handle_key(VK_RMENU, 0x38, true);
+            handler.handle_key(VK_LCONTROL, 0x1d, false);
+            handler.handle_key(VK_RMENU, 0x38, false);
+        }
+        let events = drain_keys(&mut rx);
+        let flagged = events
+            .iter()
+            .filter(|e| {
+                matches!(
+                    e,
+                    InputEvent::KeyDown { vk: VK_RMENU, flags: KEY_FLAG_ALTGR, .. }
+                        | InputEvent::KeyUp { vk: VK_RMENU, flags: KEY_FLAG_ALTGR, .. }
+                )
+            })
+            .count();
+        assert_eq!(flagged, 4);
+        assert!(handler.pressed_keys.is_empty());
+    }
+
+    /// With the toggle off, Ctrl+Alt combos pass through untouched for
+    /// games that bind them.
+    #[test]
+    fn ctrl_alt_passes_through_when_altgr_mode_is_off() {
+        let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel();
+        let mut handler = InputHandler::new(tx);
+        handler.set_altgr_enabled(false);
+        handler.handle_key(VK_LCONTROL, 0x1d, true);
+        handler.handle_key(VK_RMENU, 0x38, true);
+        handler.handle_key(VK_RMENU, 0x38, false);
+        handler.handle_key(VK_LCONTROL, 0x1d, false);
+        let events = drain_keys(&mut rx);
+        assert_eq!(events.len(), 4);
+        assert!(events.iter().all(|e| {
+            matches!(e, InputEvent::KeyDown { flags: 0, .. } | InputEvent::KeyUp { flags: 0, .. })
+        }));
+    }
+}
@@ -0,0 +1,131 @@
+//! Windows raw input capture: a dedicated message-loop thread registers
+//! for WM_INPUT and forwards unaccelerated mouse deltas at device rate.
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use std::thread::JoinHandle;
+
+use anyhow::{bail, Result};
+
+static RAW_INPUT_RUNNING: AtomicBool = AtomicBool::new(false);
+static RAW_INPUT_PAUSED: AtomicBool = AtomicBool::new(false);
+static RAW_INPUT_THREAD: Mutex<Option<JoinHandle<()>>> = Mutex::new(None);
+
+pub(super) fn platform_pause_raw_input(paused: bool) {
+    RAW_INPUT_PAUSED.store(paused, Ordering::SeqCst);
+}
+
+/// Spawn the raw-input thread: create a message-only window, register
+/// RIDEV_INPUTSINK for mouse usage, and pump messages until stopped.
+pub(super) fn platform_start_raw_input() -> Result<()> {
+    if RAW_INPUT_RUNNING.swap(true, Ordering::SeqCst) {
+        bail!("Raw input already running");
+    }
+    RAW_INPUT_PAUSED.store(false, Ordering::SeqCst);
+    let handle = std::thread::Builder::new()
+        .name("raw-input".to_string())
+        .spawn(move || unsafe { raw_input_thread() })?;
+    *RAW_INPUT_THREAD.lock().unwrap() = Some(handle);
+    Ok(())
+}
+
+pub(super) fn platform_stop_raw_input() {
+    if !RAW_INPUT_RUNNING.swap(false, Ordering::SeqCst) {
+        return;
+    }
+    // The message loop checks RAW_INPUT_RUNNING on every message; post a
+    // wakeup so it notices promptly.
+    unsafe {
+        use windows_sys::Win32::UI::WindowsAndMessaging::PostThreadMessageW;
+        use windows_sys::Win32::UI::WindowsAndMessaging::WM_QUIT;
+        if let Some(handle) = RAW_INPUT_THREAD.lock().unwrap().take() {
+            let thread_id = get_thread_id(&handle);
+            PostThreadMessageW(thread_id, WM_QUIT, 0, 0);
+            let _ = handle.join();
+        }
+    }
+}
+
+unsafe fn get_thread_id(_handle: &JoinHandle<()>) -> u32 {
+    // The thread stores its id on startup.
+    RAW_INPUT_THREAD_ID.load(Ordering::SeqCst)
+}
+
+static RAW_INPUT_THREAD_ID: std::sync::atomic::AtomicU32 = std::sync::atomic::AtomicU32::new(0);
+
+unsafe fn raw_input_thread() {
+    use windows_sys::Win32::Devices::HumanInterfaceDevice::{
+        HID_USAGE_GENERIC_MOUSE, HID_USAGE_PAGE_GENERIC,
+    };
+    use windows_sys::Win32::System::Threading::GetCurrentThreadId;
+    use windows_sys::Win32::UI::Input::{
+        GetRawInputData, RegisterRawInputDevices, RAWINPUT, RAWINPUTDEVICE, RAWINPUTHEADER,
+        RIDEV_INPUTSINK, RID_INPUT, RIM_TYPEMOUSE,
+    };
+    use windows_sys::Win32::UI::WindowsAndMessaging::{
+        CreateWindowExW, DefWindowProcW, DispatchMessageW, GetMessageW, TranslateMessage,
+        HWND_MESSAGE, MSG, WM_INPUT,
+    };
+
+    RAW_INPUT_THREAD_ID.store(GetCurrentThreadId(), Ordering::SeqCst);
+
+    let class_name: Vec<u16> = "STATIC\0".encode_utf16().collect();
+    let hwnd = CreateWindowExW(
+        0,
+        class_name.as_ptr(),
+        std::ptr::null(),
+        0,
+        0,
+        0,
+        0,
+        0,
+        HWND_MESSAGE,
+        std::ptr::null_mut(),
+        std::ptr::null_mut(),
+        std::ptr::null(),
+    );
+    if hwnd.is_null() {
+        log::error!("Raw input: failed to create message-only window");
+        return;
+    }
+
+    let device = RAWINPUTDEVICE {
+        usUsagePage: HID_USAGE_PAGE_GENERIC,
+        usUsage: HID_USAGE_GENERIC_MOUSE,
+        dwFlags: RIDEV_INPUTSINK,
+        hwndTarget: hwnd,
+    };
+    if RegisterRawInputDevices(&device, 1, std::mem::size_of::<RAWINPUTDEVICE>() as u32) == 0 {
+        log::error!("Raw input: RegisterRawInputDevices failed");
+        return;
+    }
+    log::info!("Raw input capture started");
+
+    let mut msg: MSG = std::mem::zeroed();
+    while GetMessageW(&mut msg, std::ptr::null_mut(), 0, 0) > 0 {
+        if !RAW_INPUT_RUNNING.load(Ordering::SeqCst) {
+            break;
+        }
+        if msg.message == WM_INPUT && !RAW_INPUT_PAUSED.load(Ordering::SeqCst) {
+            let mut raw: RAWINPUT = std::mem::zeroed();
+            let mut size = std::mem::size_of::<RAWINPUT>() as u32;
+            if GetRawInputData(
+                msg.lParam as _,
+                RID_INPUT,
+                &mut raw as *mut _ as *mut _,
+                &mut size,
+                std::mem::size_of::<RAWINPUTHEADER>() as u32,
+            ) != u32::MAX
+                && raw.header.dwType == RIM_TYPEMOUSE
+            {
+                let mouse = raw.data.mouse;
+                if mouse.lLastX != 0 || mouse.lLastY != 0 {
+                    super::forward_raw_delta(mouse.lLastX, mouse.lLastY);
+                }
+            }
+        }
+        TranslateMessage(&msg);
+        DispatchMessageW(&msg);
+    }
+    log::info!("Raw input capture stopped");
+}
@@ -0,0 +1,837 @@
+//! OpenNOW native streamer: entry point and winit event loop.
+
+mod api;
+mod app;
+mod auth;
+mod gui;
+mod headless;
+mod input;
+mod media;
+mod session_result;
+mod settings;
+mod webrtc;
+
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use winit::application::ApplicationHandler;
+use winit::event::{DeviceEvent, ElementState, MouseScrollDelta, WindowEvent};
+use winit::event_loop::{ActiveEventLoop, ControlFlow, EventLoop};
+use winit::keyboard::{KeyCode, PhysicalKey};
+use winit::window::{Window, WindowId};
+
+use crate::app::{App, AppState};
+use crate::gui::Renderer;
+use crate::input::InputHandler;
+
+pub const USER_AGENT: &str = "OpenNOW/0.4.0";
+
+struct OpenNowApp {
+    app: App,
+    renderer: Option<Renderer>,
+    input_handler: Option<InputHandler>,
+    /// Cached copy of the input profile in effect, so the event paths
+    /// don't re-resolve it per mouse event.
+    input_profile: settings::InputProfile,
+    ctrl_held: bool,
+    shift_held: bool,
+    /// Left Alt, the push-to-talk key.
+    ptt_held: bool,
+    /// Whether an egui text widget held keyboard focus on the previous
+    /// event, so the transition into text focus can release held remote
+    /// keys exactly once.
+    ui_text_focus: bool,
+    /// `shutdown` ran; makes the end-of-`main` call a no-op after a
+    /// window-close teardown.
+    shutdown_done: bool,
+    /// Hands AccessKit adapter events back to the event loop; see
+    /// `Renderer::init_accesskit`.
+    accesskit_proxy: winit::event_loop::EventLoopProxy<accesskit_winit::Event>,
+}
+
+impl OpenNowApp {
+    fn new(
+        runtime: tokio::runtime::Handle,
+        accesskit_proxy: winit::event_loop::EventLoopProxy<accesskit_winit::Event>,
+    ) -> Self {
+        Self {
+            app: App::new(runtime),
+            renderer: None,
+            input_handler: None,
+            input_profile: settings::InputProfile::default(),
+            ctrl_held: false,
+            shift_held: false,
+            ptt_held: false,
+            ui_text_focus: false,
+            shutdown_done: false,
+            accesskit_proxy,
+        }
+    }
+
+    fn streaming(&self) -> bool {
+        self.app.state == AppState::Streaming
+    }
+
+    /// Deterministic teardown. Order matters: the raw-input hook calls
+    /// back into the window, so it must stop before the window is
+    /// destroyed, and settings are persisted only after the streaming
+    /// runner has ended — a crash-on-exit between those two used to
+    /// corrupt the save. Invoked from `CloseRequested` and again (as a
+    /// no-op) at the end of `main` for exits that bypass the window.
+    fn shutdown(&mut self) {
+        if self.shutdown_done {
+            return;
+        }
+        self.shutdown_done = true;
+        let total = Instant::now();
+        // 1. Input capture: the raw hook and held keys first, while the
+        //    window and the input channel are both still alive.
+        let stage = Instant::now();
+        input::stop_raw_input();
+        if let Some(mut handler) = self.input_handler.take() {
+            handler.release_all_keys();
+        }
+        log::info!("Shutdown: input capture stopped in {:?}", stage.elapsed());
+        // 2. Streaming runner: signal, then wait bounded so its tasks
+        //    stop touching shared state before anything is persisted.
+        let stage = Instant::now();
+        if self.streaming() {
+            self.app.stop_streaming();
+        }
+        self.app.wait_for_stream_end(Duration::from_secs(3));
+        log::info!("Shutdown: streaming runner ended in {:?}", stage.elapsed());
+        // 3. Settings/state, with everything above quiesced.
+        let stage = Instant::now();
+        self.app.flush_settings();
+        log::info!("Shutdown: settings persisted in {:?}", stage.elapsed());
+        // 4. Renderer (and with it the window) last.
+        self.renderer = None;
+        log::info!("Shutdown complete in {:?}", total.elapsed());
+    }
+
+    /// Ctrl+Shift+V: paste the local clipboard into the remote session
+    /// through the text input packets. A dedicated chord so a plain
+    /// Ctrl+V still reaches the game as keystrokes.
+    fn paste_clipboard(&mut self) {
+        if !self.streaming() {
+            return;
+        }
+        let Some(handler) = self.input_handler.as_mut() else {
+            return;
+        };
+        match arboard::Clipboard::new().and_then(|mut clipboard| clipboard.get_text()) {
+            Ok(text) if !text.is_empty() => {
+                let sent = handler.paste_text(&text);
+                self.app.notify_info(format!("Pasted {} characters", sent));
+            }
+            Ok(_) => {}
+            Err(e) => self.app.notify_error(format!("Clipboard read failed: {}", e)),
+        }
+    }
+
+    /// Keep the input handler in sync with the stream lifecycle: create
+    /// it when a stream starts, drop it when the stream ends.
+    fn sync_input_handler(&mut self) {
+        match (&self.app.input_event_tx, self.input_handler.is_some()) {
+            (Some(tx), false) => {
+                let mut handler = InputHandler::new(tx.clone());
+                handler.set_scroll_options(
+                    self.app.settings.scroll_speed,
+                    self.app.settings.natural_scroll,
+                );
+                handler.set_coalesce_options(
+                    Duration::from_millis(self.app.settings.coalesce_min_ms as u64),
+                    Duration::from_millis(self.app.settings.coalesce_max_ms as u64),
+                    self.app
+                        .settings
+                        .coalesce_fixed_ms
+                        .map(|ms| Duration::from_millis(ms as u64)),
+                );
+                handler.set_altgr_enabled(self.app.settings.altgr_mode);
+                self.input_handler = Some(handler);
+                if let Err(e) = input::start_raw_input() {
+                    log::warn!("Raw input unavailable, using winit events: {}", e);
+                }
+            }
+            (None, true) => {
+                if let Some(mut handler) = self.input_handler.take() {
+                    handler.release_all_keys();
+                }
+                input::stop_raw_input();
+            }
+            _ => {}
+        }
+    }
+}
+
+impl ApplicationHandler<accesskit_winit::Event> for OpenNowApp {
+    fn resumed(&mut self, event_loop: &ActiveEventLoop) {
+        if self.renderer.is_none() {
+            let window = Arc::new(
+                event_loop
+                    .create_window(
+                        Window::default_attributes()
+                            .with_title("OpenNOW")
+                            .with_inner_size(winit::dpi::LogicalSize::new(1280.0, 800.0)),
+                    )
+                    .expect("Failed to create window"),
+            );
+            let mut renderer = Renderer::new(window).expect("Failed to create renderer");
+            renderer.init_accesskit(event_loop, self.accesskit_proxy.clone());
+            if renderer.software_adapter && !self.app.settings.low_spec_ui {
+                self.app.notify_error_with_action(
+                    "Software rendering detected — the UI may peg the CPU/GPU. \
+                     Low-spec UI mode is recommended.",
+                    app::notifications::NotificationAction::EnableLowSpecUi,
+                );
+            }
+            if renderer.looks_like_unscaled_4k() {
+                self.app.offer_ui_scale_prompt();
+            }
+            self.renderer = Some(renderer);
+        }
+    }
+
+    fn window_event(&mut self, event_loop: &ActiveEventLoop, id: WindowId, event: WindowEvent) {
+        let Some(renderer) = self.renderer.as_mut() else {
+            return;
+        };
+        // Input capture is scoped to the streaming window: events from any
+        // other window of our own process (dev console, tools) must never
+        // reach the remote session.
+        let is_stream_window = id == renderer.window.id();
+        let stream_window_focused = is_stream_window && renderer.window.has_focus();
+        // Let egui see the event first; while streaming the UI is mostly
+        // hidden and events flow to the input handler.
+        let response = renderer.on_window_event(&event);
+        let consumed = response.consumed;
+        // `consumed` only marks events egui used this frame; a focused
+        // text widget (quick-menu search box, settings field) also claims
+        // every keystroke that follows. Releasing on the transition keeps
+        // a key held at focus time from repeating remotely forever.
+        let ui_wants_keyboard = renderer.wants_keyboard_input();
+        let ui_wants_pointer = renderer.wants_pointer_input();
+        if ui_wants_keyboard && !self.ui_text_focus {
+            if let Some(handler) = self.input_handler.as_mut() {
+                handler.release_all_keys();
+            }
+        }
+        self.ui_text_focus = ui_wants_keyboard;
+        // In low-spec mode redraws are event-driven rather than
+        // continuous, so honor egui's repaint requests here.
+        if response.repaint && self.app.settings.low_spec_ui {
+            renderer.window.request_redraw();
+        }
+        match event {
+            WindowEvent::CloseRequested => {
+                self.shutdown();
+                event_loop.exit();
+            }
+            WindowEvent::Resized(size) => {
+                renderer.resize(size.width, size.height);
+                self.app.notify_viewport_resize(size.width, size.height);
+            }
+            WindowEvent::KeyboardInput { event: key_event, .. } => {
+                let down = key_event.state == ElementState::Pressed;
+                if down {
+                    self.app.note_user_interaction();
+                }
+                if let PhysicalKey::Code(code) = key_event.physical_key {
+                    match code {
+                        KeyCode::ControlLeft | KeyCode::ControlRight => self.ctrl_held = down,
+                        KeyCode::ShiftLeft | KeyCode::ShiftRight => self.shift_held = down,
+                        KeyCode::AltLeft => self.ptt_held = down,
+                        _ => {}
+                    }
+                    // Global hotkeys take precedence over forwarding. Any
+                    // of them also dismisses the cheat sheet on activation.
+                    if down {
+                        match code {
+                            KeyCode::F1 => {
+                                self.app.toggle_help_overlay();
+                                return;
+                            }
+                            KeyCode::Escape if self.app.show_help_overlay => {
+                                self.app.dismiss_help_overlay();
+                                return;
+                            }
+                            KeyCode::F2 => {
+                                if self.app.show_help_overlay {
+                                    self.app.dismiss_help_overlay();
+                                }
+                                if self.streaming() {
+                                    self.app.toggle_quick_menu();
+                                }
+                                return;
+                            }
+                            KeyCode::F3 => {
+                                if self.app.show_help_overlay {
+                                    self.app.dismiss_help_overlay();
+                                }
+                                self.app.settings.show_stats_overlay =
+                                    !self.app.settings.show_stats_overlay;
+                                self.app.settings_changed();
+                                return;
+                            }
+                            KeyCode::F4 => {
+                                if self.app.show_help_overlay {
+                                    self.app.dismiss_help_overlay();
+                                }
+                                self.app.toggle_frame_history();
+                                return;
+                            }
+                            KeyCode::F11 => {
+                                if self.app.show_help_overlay {
+                                    self.app.dismiss_help_overlay();
+                                }
+                                renderer.toggle_fullscreen(&self.app.settings);
+                                return;
+                            }
+                            KeyCode::F12 => {
+                                if self.app.show_help_overlay {
+                                    self.app.dismiss_help_overlay();
+                                }
+                                if self.streaming() {
+                                    self.app.save_screenshot();
+                                }
+                                return;
+                            }
+                            KeyCode::F9 if self.ctrl_held && self.shift_held => {
+                                // Dev: exercise the device-loss recovery
+                                // path without yanking a driver.
+                                renderer.simulate_device_loss();
+                                return;
+                            }
+                            KeyCode::KeyM if self.ctrl_held && self.shift_held => {
+                                self.app.toggle_mute();
+                                return;
+                            }
+                            KeyCode::KeyV if self.ctrl_held && self.shift_held => {
+                                self.paste_clipboard();
+                                return;
+                            }
+                            KeyCode::KeyQ if self.ctrl_held && self.shift_held => {
+                                if self.app.show_help_overlay {
+                                    self.app.dismiss_help_overlay();
+                                }
+                                if self.streaming() {
+                                    self.app.stop_streaming();
+                                }
+                                return;
+                            }
+                            _ => {}
+                        }
+                    }
+                    if forward_key_to_stream(
+                        self.streaming(),
+                        consumed,
+                        ui_wants_keyboard,
+                        stream_window_focused,
+                    ) {
+                        if let Some(handler) = self.input_handler.as_mut() {
+                            let vk = vk_from_keycode(code);
+                            let scancode = key_event.physical_key.to_scancode().unwrap_or(0) as u16;
+                            handler.handle_key(vk, scancode, down);
+                        }
+                    }
+                }
+            }
+            WindowEvent::MouseInput { state, button, .. } => {
+                if state == ElementState::Pressed {
+                    self.app.note_user_interaction();
+                }
+                if self.streaming() && !consumed && !ui_wants_pointer && stream_window_focused {
+                    if let Some(handler) = self.input_handler.as_mut() {
+                        let button = match button {
+                            winit::event::MouseButton::Left => 1,
+                            winit::event::MouseButton::Middle => 2,
+                            winit::event::MouseButton::Right => 3,
+                            winit::event::MouseButton::Back => 4,
+                            winit::event::MouseButton::Forward => 5,
+                            _ => return,
+                        };
+                        handler.handle_mouse_button(button, state == ElementState::Pressed);
+                    }
+                }
+            }
+            WindowEvent::CursorMoved { position, .. } => {
+                // Absolute-mouse profiles forward window cursor
+                // positions; relative ones get deltas from raw input /
+                // device events instead.
+                if self.streaming()
+                    && !consumed
+                    && !ui_wants_pointer
+                    && stream_window_focused
+                    && !self.input_profile.relative_mouse
+                {
+                    self.app.note_user_interaction();
+                    let stream = self.app.stream_stats.lock().unwrap().resolution;
+                    if let Some(handler) = self.input_handler.as_mut() {
+                        if let Some((x, y)) = absolute_from_window(
+                            position,
+                            renderer.window.inner_size(),
+                            stream,
+                        ) {
+                            handler.handle_mouse_absolute(x, y);
+                        }
+                    }
+                }
+            }
+            WindowEvent::MouseWheel { delta, .. } => {
+                self.app.note_user_interaction();
+                if self.streaming() && !consumed && !ui_wants_pointer && stream_window_focused {
+                    if let Some(handler) = self.input_handler.as_mut() {
+                        match delta {
+                            MouseScrollDelta::LineDelta(_, y) => {
+                                handler.handle_wheel_lines(y);
+                            }
+                            MouseScrollDelta::PixelDelta(pos) => {
+                                handler.handle_wheel_pixels(pos.y as f32);
+                            }
+                        }
+                    }
+                }
+            }
+            WindowEvent::Focused(focused) => {
+                if self.streaming() {
+                    if is_stream_window {
+                        input::pause_raw_input(
+                            !focused || !self.input_profile.relative_mouse,
+                        );
+                        if !focused {
+                            if let Some(handler) = self.input_handler.as_mut() {
+                                handler.release_all_keys();
+                            }
+                        } else {
+                            // Wayland drops pointer constraints on focus
+                            // loss; force a fresh grab on the next frame.
+                            renderer.reapply_capture();
+                        }
+                    } else if focused {
+                        // Another window of our own process took focus:
+                        // the app is still foreground, but capture must
+                        // stop so typing there stays local.
+                        input::pause_raw_input(true);
+                        if let Some(handler) = self.input_handler.as_mut() {
+                            handler.release_all_keys();
+                        }
+                    }
+                }
+            }
+            WindowEvent::RedrawRequested => {
+                self.app.update();
+                renderer.set_low_spec(self.app.settings.low_spec_ui && !self.streaming());
+                // The monitor picker in the settings modal enumerates
+                // through the window; publish while the modal is open.
+                if self.app.show_settings {
+                    self.app.available_monitors = renderer.monitor_names();
+                }
+                // Device-loss recovery replaced the egui context; the
+                // AccessKit adapter must be re-attached to the new one.
+                if renderer.take_accesskit_reinit() {
+                    renderer.init_accesskit(event_loop, self.accesskit_proxy.clone());
+                }
+                // Push-to-talk gate, recomputed every frame: the
+                // capture callback discards samples while this is off.
+                crate::media::mic::set_transmitting(
+                    self.streaming()
+                        && self.app.settings.mic_enabled
+                        && (!self.app.settings.mic_push_to_talk || self.ptt_held),
+                );
+                self.sync_input_handler();
+                // A profile switch transitions capture safely: release
+                // everything held, reconfigure the handler, and let the
+                // renderer re-grab per the new mode on this frame.
+                if self.app.take_input_profile_change() {
+                    let profile = self.app.active_input_profile();
+                    if let Some(handler) = self.input_handler.as_mut() {
+                        handler.release_all_keys();
+                        handler.set_sensitivity(
+                            profile.sensitivity * self.app.settings.mouse_sensitivity,
+                        );
+                        handler.set_coalesce_options(
+                            Duration::from_millis(self.app.settings.coalesce_min_ms as u64),
+                            Duration::from_millis(self.app.settings.coalesce_max_ms as u64),
+                            profile
+                                .coalesce_fixed_ms
+                                .or(self.app.settings.coalesce_fixed_ms)
+                                .map(|ms| Duration::from_millis(ms as u64)),
+                        );
+                    }
+                    // The raw-capture threads scale through their own
+                    // handler; keep its multiplier in step.
+                    input::set_raw_sensitivity(
+                        profile.sensitivity * self.app.settings.mouse_sensitivity,
+                    );
+                    // Raw capture only feeds the relative path; absolute
+                    // profiles use window cursor events instead.
+                    input::pause_raw_input(
+                        !profile.relative_mouse || !renderer.window.has_focus(),
+                    );
+                    self.input_profile = profile;
+                }
+                // Feed the latest network measurements into the mouse
+                // coalescer and publish the active interval for the
+                // overlay.
+                if let Some(handler) = self.input_handler.as_mut() {
+                    let (rtt_ms, input_buffered, mouse_buffered) = {
+                        let stats = self.app.stream_stats.lock().unwrap();
+                        (
+                            stats.rtt_ms,
+                            stats.input_buffered_bytes,
+                            stats.mouse_buffered_bytes,
+                        )
+                    };
+                    // Gamepads are polled per frame; state diffs go out
+                    // as packets alongside keyboard and mouse.
+                    if let Some(manager) = self.app.controller.as_mut() {
+                        handler.poll_controllers(manager);
+                    }
+                    handler.update_network_feedback(rtt_ms, input_buffered);
+                    // Both channels' backlog counts toward the throttle:
+                    // under congestion the mouse channel is where deltas
+                    // pile up. The raw handler decimates off the same
+                    // estimate.
+                    let queued =
+                        input::estimate_queued_events(input_buffered + mouse_buffered);
+                    handler.update_queue_depth(queued);
+                    input::update_raw_queue_depth(queued);
+                    self.app.stream_stats.lock().unwrap().coalesce_interval_ms =
+                        handler.coalesce_interval().as_secs_f32() * 1000.0;
+                    // Publish the local-cursor preview position for the
+                    // streaming overlay when the profile draws one.
+                    let size = renderer.window.inner_size();
+                    handler.set_cursor_area(size.width as f32, size.height as f32);
+                    self.app.local_cursor_pos = (self.input_profile.show_local_cursor
+                        && self.input_profile.capture == settings::CursorCapture::Locked)
+                        .then(|| handler.local_cursor());
+                }
+                if self.streaming() {
+                    // Frame pacing: the target presentation interval
+                    // comes from the stream FPS, snapped to the
+                    // monitor's refresh grid in VSync mode; the measured
+                    // jitter goes to the overlay to verify the effect.
+                    let frame = 1.0 / self.app.settings.fps.max(1) as f64;
+                    let interval = match self.app.settings.frame_pacing {
+                        settings::FramePacing::Off => None,
+                        settings::FramePacing::Smooth => Some(Duration::from_secs_f64(frame)),
+                        settings::FramePacing::VSync => {
+                            let vsync = renderer
+                                .window
+                                .current_monitor()
+                                .and_then(|monitor| monitor.refresh_rate_millihertz())
+                                .map(|mhz| 1000.0 / mhz as f64);
+                            Some(Duration::from_secs_f64(match vsync {
+                                Some(vsync) => (frame / vsync).round().max(1.0) * vsync,
+                                None => frame,
+                            }))
+                        }
+                    };
+                    self.app.current_frame.set_pacing_interval(interval);
+                    self.app.stream_stats.lock().unwrap().frame_jitter_ms =
+                        self.app.current_frame.presentation_jitter_ms();
+                }
+                if let Err(e) = renderer.render(&mut self.app) {
+                    log::error!("Render failed: {}", e);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn device_event(
+        &mut self,
+        _event_loop: &ActiveEventLoop,
+        _device_id: winit::event::DeviceId,
+        event: DeviceEvent,
+    ) {
+        // winit device events are the fallback when OS raw capture was
+        // refused (no evdev access on Linux, unsupported platform).
+        // While the capture threads are live they deliver the same
+        // motion, so forwarding both would double every delta.
+        if !self.streaming() || input::raw_capture_active() {
+            return;
+        }
+        // Absolute-mouse profiles take positions from window events;
+        // forwarding deltas too would double-drive the cursor.
+        if !self.input_profile.relative_mouse {
+            return;
+        }
+        // Same focus scoping as the window-event paths: no forwarding
+        // unless the streaming window itself is foreground.
+        if !self
+            .renderer
+            .as_ref()
+            .is_some_and(|r| r.window.has_focus())
+        {
+            return;
+        }
+        match event {
+            DeviceEvent::MouseMotion { delta: (dx, dy) } => {
+                self.app.note_user_interaction();
+                if let Some(handler) = self.input_handler.as_mut() {
+                    handler.handle_mouse_delta(dx, dy);
+                }
+            }
+            // Wheel events are deliberately not forwarded here: the
+            // window-event handler already receives them (routing both
+            // would scroll twice), and both units go through the same
+            // `handle_wheel_lines`/`handle_wheel_pixels` conversion.
+            _ => {}
+        }
+    }
+
+    fn user_event(&mut self, _event_loop: &ActiveEventLoop, event: accesskit_winit::Event) {
+        // AccessKit adapter traffic (tree requests, action requests from
+        // the screen reader) routed back to egui.
+        if let Some(renderer) = &mut self.renderer {
+            if renderer.window.id() == event.window_id {
+                renderer.on_accesskit_event(event.window_event);
+            }
+        }
+    }
+
+    fn new_events(&mut self, _event_loop: &ActiveEventLoop, cause: winit::event::StartCause) {
+        // The low-spec wake timer fired: run one frame so queued async
+        // results get drained and drawn.
+        if matches!(cause, winit::event::StartCause::ResumeTimeReached { .. }) {
+            if let Some(renderer) = &self.renderer {
+                renderer.window.request_redraw();
+            }
+        }
+    }
+
+    fn about_to_wait(&mut self, event_loop: &ActiveEventLoop) {
+        if self.app.settings.low_spec_ui && !self.streaming() {
+            // Low-spec idle: redraw on events only. Async results (games
+            // loads, login) arrive on channels with no winit waker, so
+            // wake a few times a second instead of waiting forever.
+            event_loop.set_control_flow(ControlFlow::WaitUntil(
+                std::time::Instant::now() + Duration::from_millis(250),
+            ));
+        } else {
+            event_loop.set_control_flow(ControlFlow::Poll);
+            if let Some(renderer) = &self.renderer {
+                renderer.window.request_redraw();
+            }
+        }
+    }
+}
+
+/// Whether a keystroke may be forwarded to the remote session. Beyond
+/// the focus/consumed scoping, `ui_wants_keyboard` blocks forwarding for
+/// as long as an egui text widget holds keyboard focus — typing a game
+/// title into the overlay search box must not also drive the game.
+fn forward_key_to_stream(
+    streaming: bool,
+    consumed: bool,
+    ui_wants_keyboard: bool,
+    stream_window_focused: bool,
+) -> bool {
+    streaming && !consumed && !ui_wants_keyboard && stream_window_focused
+}
+
+/// Map a window cursor position onto the 0..=65535 normalized space the
+/// absolute-mouse packet uses, through the same letterbox the renderer
+/// applies to the video. None for positions in the black bars.
+fn absolute_from_window(
+    position: winit::dpi::PhysicalPosition<f64>,
+    window: winit::dpi::PhysicalSize<u32>,
+    stream: (u32, u32),
+) -> Option<(u16, u16)> {
+    if window.width == 0 || window.height == 0 || stream.0 == 0 || stream.1 == 0 {
+        return None;
+    }
+    let scale = (window.width as f64 / stream.0 as f64)
+        .min(window.height as f64 / stream.1 as f64);
+    let video_width = stream.0 as f64 * scale;
+    let video_height = stream.1 as f64 * scale;
+    let x = (position.x - (window.width as f64 - video_width) / 2.0) / video_width;
+    let y = (position.y - (window.height as f64 - video_height) / 2.0) / video_height;
+    if !(0.0..=1.0).contains(&x) || !(0.0..=1.0).contains(&y) {
+        return None;
+    }
+    Some(((x * 65535.0) as u16, (y * 65535.0) as u16))
+}
+
+/// Map winit key codes to Windows virtual-key codes, which is what the
+/// GFN input protocol speaks regardless of client platform.
+fn vk_from_keycode(code: KeyCode) -> u16 {
+    match code {
+        KeyCode::KeyA => 0x41,
+        KeyCode::KeyB => 0x42,
+        KeyCode::KeyC => 0x43,
+        KeyCode::KeyD => 0x44,
+        KeyCode::KeyE => 0x45,
+        KeyCode::KeyF => 0x46,
+        KeyCode::KeyG => 0x47,
+        KeyCode::KeyH => 0x48,
+        KeyCode::KeyI => 0x49,
+        KeyCode::KeyJ => 0x4a,
+        KeyCode::KeyK => 0x4b,
+        KeyCode::KeyL => 0x4c,
+        KeyCode::KeyM => 0x4d,
+        KeyCode::KeyN => 0x4e,
+        KeyCode::KeyO => 0x4f,
+        KeyCode::KeyP => 0x50,
+        KeyCode::KeyQ => 0x51,
+        KeyCode::KeyR => 0x52,
+        KeyCode::KeyS => 0x53,
+        KeyCode::KeyT => 0x54,
+        KeyCode::KeyU => 0x55,
+        KeyCode::KeyV => 0x56,
+        KeyCode::KeyW => 0x57,
+        KeyCode::KeyX => 0x58,
+        KeyCode::KeyY => 0x59,
+        KeyCode::KeyZ => 0x5a,
+        KeyCode::Digit0 => 0x30,
+        KeyCode::Digit1 => 0x31,
+        KeyCode::Digit2 => 0x32,
+        KeyCode::Digit3 => 0x33,
+        KeyCode::Digit4 => 0x34,
+        KeyCode::Digit5 => 0x35,
+        KeyCode::Digit6 => 0x36,
+        KeyCode::Digit7 => 0x37,
+        KeyCode::Digit8 => 0x38,
+        KeyCode::Digit9 => 0x39,
+        KeyCode::Escape => 0x1b,
+        KeyCode::Space => 0x20,
+        KeyCode::Enter => 0x0d,
+        KeyCode::Backspace => 0x08,
+        KeyCode::Tab => 0x09,
+        KeyCode::ShiftLeft => 0xa0,
+        KeyCode::ShiftRight => 0xa1,
+        KeyCode::ControlLeft => 0xa2,
+        KeyCode::ControlRight => 0xa3,
+        KeyCode::AltLeft => 0xa4,
+        KeyCode::AltRight => 0xa5,
+        KeyCode::ArrowUp => 0x26,
+        KeyCode::ArrowDown => 0x28,
+        KeyCode::ArrowLeft => 0x25,
+        KeyCode::ArrowRight => 0x27,
+        KeyCode::F1 => 0x70,
+        KeyCode::F2 => 0x71,
+        KeyCode::F4 => 0x73,
+        KeyCode::F5 => 0x74,
+        KeyCode::F6 => 0x75,
+        KeyCode::F7 => 0x76,
+        KeyCode::F8 => 0x77,
+        KeyCode::F9 => 0x78,
+        KeyCode::F10 => 0x79,
+        KeyCode::F12 => 0x7b,
+        _ => 0,
+    }
+}
+
+fn main() {
+    env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("info")).init();
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if args.iter().any(|a| a == "--headless-stream") {
+        std::process::exit(headless::run(&args));
+    }
+    // Non-interactive token import for SSH-only setups: validate the
+    // file, persist it, and exit.
+    if let Some(index) = args.iter().position(|a| a == "--import-auth") {
+        let Some(path) = args.get(index + 1) else {
+            eprintln!("--import-auth needs a file path");
+            std::process::exit(2);
+        };
+        let contents = match std::fs::read_to_string(path) {
+            Ok(contents) => contents,
+            Err(e) => {
+                eprintln!("Cannot read {}: {}", path, e);
+                std::process::exit(1);
+            }
+        };
+        let runtime = tokio::runtime::Runtime::new().expect("Failed to create tokio runtime");
+        match runtime.block_on(auth::import_token_text(&contents)) {
+            Ok(tokens) => {
+                if let Err(e) = auth::save_tokens(&tokens) {
+                    eprintln!("Token validated but could not be saved: {}", e);
+                    std::process::exit(1);
+                }
+                println!("Token imported and validated");
+                std::process::exit(0);
+            }
+            Err(e) => {
+                eprintln!("Token import failed: {:#}", e);
+                std::process::exit(1);
+            }
+        }
+    }
+    let runtime = tokio::runtime::Runtime::new().expect("Failed to create tokio runtime");
+    // Session outcome reporting for external launchers; see the
+    // `session_result` module docs for the schema and exit codes.
+    if let Some(index) = args.iter().position(|a| a == "--result-file") {
+        let Some(path) = args.get(index + 1) else {
+            eprintln!("--result-file needs a file path");
+            std::process::exit(2);
+        };
+        session_result::init(std::path::PathBuf::from(path), runtime.handle());
+    }
+    let event_loop = EventLoop::<accesskit_winit::Event>::with_user_event()
+        .build()
+        .expect("Failed to create event loop");
+    let mut app = OpenNowApp::new(runtime.handle().clone(), event_loop.create_proxy());
+    if let Err(e) = event_loop.run_app(&mut app) {
+        log::error!("Event loop error: {}", e);
+    }
+    app.shutdown();
+    std::process::exit(session_result::exit_code());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::input::InputEvent;
+
+    /// Run one egui frame that draws a single-line text edit and hands
+    /// it keyboard focus, mirroring the overlay search box.
+    fn frame_with_focused_text_field(ctx: &egui::Context, text: &mut String) {
+        let _ = ctx.run(egui::RawInput::default(), |ctx| {
+            egui::CentralPanel::default().show(ctx, |ui| {
+                ui.text_edit_singleline(text).request_focus();
+            });
+        });
+    }
+
+    #[test]
+    fn focused_text_field_blocks_keys_from_the_input_channel() {
+        let ctx = egui::Context::default();
+        let mut text = String::new();
+        // Two frames: focus requested on the first takes effect by the
+        // second, which is when real keystrokes would arrive.
+        frame_with_focused_text_field(&ctx, &mut text);
+        frame_with_focused_text_field(&ctx, &mut text);
+        assert!(ctx.wants_keyboard_input());
+
+        let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel();
+        let mut handler = InputHandler::new(tx);
+        // The event-loop forwarding path, with the same gate
+        // `window_event` applies: type "wasd" while the field is focused.
+        for vk in [0x57u16, 0x41, 0x53, 0x44] {
+            for down in [true, false] {
+                if forward_key_to_stream(true, false, ctx.wants_keyboard_input(), true) {
+                    handler.handle_key(vk, 0, down);
+                }
+            }
+        }
+        assert!(
+            rx.try_recv().is_err(),
+            "keystrokes leaked to the remote session while a text field had focus"
+        );
+
+        // Once focus leaves UI widgets the same keystroke flows again.
+        let _ = ctx.run(egui::RawInput::default(), |ctx| {
+            egui::CentralPanel::default().show(ctx, |_ui| {});
+        });
+        assert!(!ctx.wants_keyboard_input());
+        if forward_key_to_stream(true, false, ctx.wants_keyboard_input(), true) {
+            handler.handle_key(0x57, 0, true);
+        }
+        assert!(matches!(
+            rx.try_recv(),
+            Ok(InputEvent::KeyDown { vk: 0x57, .. })
+        ));
+    }
+}
@@ -0,0 +1,339 @@
+//! Audio playback via cpal and Opus decode of the audio track.
+
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicBool, AtomicI32, AtomicU32, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use anyhow::{anyhow, Result};
+use cpal::traits::{DeviceTrait, HostTrait, StreamTrait};
+
+/// Audio delay currently requested by the UI, in milliseconds. Written
+/// from the settings modal, read by the playback thread each iteration;
+/// negative values are handled on the video side (see
+/// `SharedFrame::set_presentation_delay`).
+static AUDIO_DELAY_MS: AtomicI32 = AtomicI32::new(0);
+
+/// Name of the output device the last `AudioPlayer` opened, so the
+/// settings UI can key the persisted delay by device even after the
+/// stream ends.
+static CURRENT_OUTPUT_DEVICE: Mutex<Option<String>> = Mutex::new(None);
+
+/// Output device the user picked in settings, or None for the system
+/// default. Written from the settings modal and seeded from `Settings`
+/// at stream start; read whenever a player is (re)opened.
+static REQUESTED_OUTPUT_DEVICE: Mutex<Option<String>> = Mutex::new(None);
+
+/// Playback gain in permille (0–2000), 0 while muted. Written by the
+/// settings modal and the mute hotkey, read per sample batch by the
+/// playback thread, so changes land without touching the cpal stream.
+static VOLUME_PERMILLE: AtomicU32 = AtomicU32::new(1000);
+
+/// Set when the user presses the sync-test button; the playback thread
+/// consumes it and injects a click through the delayed queue.
+static TEST_CLICK_REQUESTED: AtomicBool = AtomicBool::new(false);
+
+pub fn set_audio_delay_ms(ms: i32) {
+    AUDIO_DELAY_MS.store(ms, Ordering::Relaxed);
+}
+
+pub fn audio_delay_ms() -> i32 {
+    AUDIO_DELAY_MS.load(Ordering::Relaxed)
+}
+
+/// Output device the active player opened, if any.
+pub fn current_output_device() -> Option<String> {
+    CURRENT_OUTPUT_DEVICE.lock().unwrap().clone()
+}
+
+/// Select the output device for the next (re)open; None restores the
+/// system default. The playback thread notices the change and reopens
+/// mid-session.
+pub fn set_output_device(name: Option<String>) {
+    *REQUESTED_OUTPUT_DEVICE.lock().unwrap() = name;
+}
+
+pub fn requested_output_device() -> Option<String> {
+    REQUESTED_OUTPUT_DEVICE.lock().unwrap().clone()
+}
+
+/// Apply the user's volume/mute choice to subsequent sample batches.
+pub fn set_playback_volume(volume: f32, muted: bool) {
+    let permille = if muted {
+        0
+    } else {
+        (volume.clamp(0.0, 2.0) * 1000.0) as u32
+    };
+    VOLUME_PERMILLE.store(permille, Ordering::Relaxed);
+}
+
+/// Current gain for the playback thread (0.0 while muted).
+pub fn playback_gain() -> f32 {
+    VOLUME_PERMILLE.load(Ordering::Relaxed) as f32 / 1000.0
+}
+
+/// Scale interleaved PCM in place. Muted batches come through as zeros
+/// rather than being dropped, so queue timing (and the configured
+/// delay) is unaffected.
+pub fn apply_gain(samples: &mut [i16], gain: f32) {
+    if (gain - 1.0).abs() < f32::EPSILON {
+        return;
+    }
+    for sample in samples.iter_mut() {
+        *sample = (*sample as f32 * gain).clamp(i16::MIN as f32, i16::MAX as f32) as i16;
+    }
+}
+
+/// Names of the available output devices, for the settings combo box.
+pub fn list_output_devices() -> Vec<String> {
+    let host = cpal::default_host();
+    match host.output_devices() {
+        Ok(devices) => devices.filter_map(|device| device.name().ok()).collect(),
+        Err(e) => {
+            log::warn!("Audio device enumeration failed: {}", e);
+            Vec::new()
+        }
+    }
+}
+
+/// Ask the playback thread to play a sync-test click. The click goes
+/// through the same delayed queue as stream audio, so it lands exactly
+/// as late as the configured offset.
+pub fn request_test_click() {
+    TEST_CLICK_REQUESTED.store(true, Ordering::Relaxed);
+}
+
+pub(crate) fn take_test_click() -> bool {
+    TEST_CLICK_REQUESTED.swap(false, Ordering::Relaxed)
+}
+
+/// Plays interleaved i16 PCM through the default output device. Samples
+/// are pushed from the streaming task and drained by the cpal callback.
+pub struct AudioPlayer {
+    _stream: cpal::Stream,
+    queue: Arc<Mutex<VecDeque<i16>>>,
+    /// Millisecond timestamp of the last cpal callback; the watchdog in
+    /// the playback thread uses this as a liveness heartbeat.
+    last_callback_ms: Arc<AtomicU64>,
+    pub device_name: String,
+    pub sample_rate: u32,
+    pub channels: u16,
+    /// Delay currently baked into the queue as leading silence.
+    applied_delay_ms: i32,
+}
+
+fn now_ms() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or(0)
+}
+
+impl AudioPlayer {
+    pub fn new(sample_rate: u32, channels: u16) -> Result<Self> {
+        let host = cpal::default_host();
+        // The configured device may be gone (USB headset unplugged);
+        // fall back to the default rather than losing audio for the
+        // rest of the session.
+        let requested = requested_output_device().and_then(|name| {
+            let found = host
+                .output_devices()
+                .ok()?
+                .find(|device| device.name().map(|n| n == name).unwrap_or(false));
+            if found.is_none() {
+                log::warn!("Audio device '{}' not found; using the system default", name);
+            }
+            found
+        });
+        let device = match requested {
+            Some(device) => device,
+            None => host
+                .default_output_device()
+                .ok_or_else(|| anyhow!("No audio output device available"))?,
+        };
+        let device_name = device.name().unwrap_or_else(|_| "unknown".to_string());
+        let config = cpal::StreamConfig {
+            channels,
+            sample_rate: cpal::SampleRate(sample_rate),
+            buffer_size: cpal::BufferSize::Default,
+        };
+        let queue: Arc<Mutex<VecDeque<i16>>> = Arc::new(Mutex::new(VecDeque::new()));
+        let callback_queue = queue.clone();
+        let last_callback_ms = Arc::new(AtomicU64::new(now_ms()));
+        let heartbeat = last_callback_ms.clone();
+        let error_device = device_name.clone();
+        let stream = device.build_output_stream(
+            &config,
+            move |data: &mut [i16], _| {
+                heartbeat.store(now_ms(), Ordering::Relaxed);
+                let mut queue = callback_queue.lock().unwrap();
+                for sample in data.iter_mut() {
+                    *sample = queue.pop_front().unwrap_or(0);
+                }
+            },
+            move |err| log::error!("cpal stream error on '{}': {}", error_device, err),
+            None,
+        )?;
+        stream.play()?;
+        *CURRENT_OUTPUT_DEVICE.lock().unwrap() = Some(device_name.clone());
+        Ok(Self {
+            _stream: stream,
+            queue,
+            last_callback_ms,
+            device_name,
+            sample_rate,
+            channels,
+            applied_delay_ms: 0,
+        })
+    }
+
+    /// True when samples are waiting but the cpal callback hasn't run
+    /// for `threshold` — the stream died without telling anyone.
+    pub fn is_stalled(&self, threshold: Duration) -> bool {
+        if self.queue.lock().unwrap().is_empty() {
+            return false;
+        }
+        now_ms().saturating_sub(self.last_callback_ms.load(Ordering::Relaxed))
+            > threshold.as_millis() as u64
+    }
+
+    /// Queue decoded samples for playback, dropping the oldest when the
+    /// queue grows past ~250ms (plus any configured delay) to bound
+    /// latency.
+    pub fn push_samples(&self, samples: &[i16]) {
+        let mut queue = self.queue.lock().unwrap();
+        let max_queued =
+            (self.sample_rate as usize / 4 + self.delay_samples(self.applied_delay_ms))
+                * self.channels as usize;
+        queue.extend(samples.iter().copied());
+        while queue.len() > max_queued {
+            queue.pop_front();
+        }
+    }
+
+    /// Per-channel sample count for a (non-negative) millisecond delay.
+    fn delay_samples(&self, ms: i32) -> usize {
+        (ms.max(0) as usize * self.sample_rate as usize) / 1000
+    }
+
+    /// Apply a positive audio delay by padding the head of the queue
+    /// with silence; shrinking the delay removes queued samples instead.
+    /// Negative values are clamped to zero here — the video side handles
+    /// them.
+    pub fn set_delay_ms(&mut self, ms: i32) {
+        let ms = ms.max(0);
+        if ms == self.applied_delay_ms {
+            return;
+        }
+        let old = self.delay_samples(self.applied_delay_ms) * self.channels as usize;
+        let new = self.delay_samples(ms) * self.channels as usize;
+        let mut queue = self.queue.lock().unwrap();
+        if new > old {
+            for _ in 0..new - old {
+                queue.push_front(0);
+            }
+        } else {
+            for _ in 0..(old - new).min(queue.len()) {
+                queue.pop_front();
+            }
+        }
+        self.applied_delay_ms = ms;
+    }
+
+    /// Queue a short 1kHz click for the A/V sync test. It rides the same
+    /// queue as stream audio and therefore hears the configured delay.
+    pub fn push_test_click(&self) {
+        let frames = self.sample_rate as usize * 30 / 1000;
+        let mut samples = Vec::with_capacity(frames * self.channels as usize);
+        for i in 0..frames {
+            let t = i as f32 / self.sample_rate as f32;
+            let envelope = 1.0 - i as f32 / frames as f32;
+            let value = ((t * 1000.0 * std::f32::consts::TAU).sin() * envelope * 8000.0) as i16;
+            for _ in 0..self.channels {
+                samples.push(value);
+            }
+        }
+        self.push_samples(&samples);
+    }
+}
+
+/// Longest frame a single Opus packet can carry.
+const MAX_OPUS_FRAME_MS: usize = 120;
+
+/// Most lost packets bridged with concealment before we just resume
+/// from the next real one; a longer gap sounds worse stretched than
+/// cut.
+const MAX_PLC_PACKETS: u16 = 5;
+
+/// Opus decoder for the audio track. Fed bare payloads by
+/// `rtp::RtpAudioDepacketizer`, which also supplies the loss count.
+pub struct AudioDecoder {
+    pub sample_rate: u32,
+    pub channels: u16,
+    /// None when libopus refused the configuration; decode then yields
+    /// nothing and the stream plays silent rather than failing.
+    decoder: Option<opus::Decoder>,
+}
+
+impl AudioDecoder {
+    pub fn new(sample_rate: u32, channels: u16) -> Self {
+        let layout = if channels == 1 {
+            opus::Channels::Mono
+        } else {
+            opus::Channels::Stereo
+        };
+        let decoder = match opus::Decoder::new(sample_rate, layout) {
+            Ok(decoder) => Some(decoder),
+            Err(e) => {
+                log::error!("Opus decoder unavailable: {}", e);
+                None
+            }
+        };
+        Self {
+            sample_rate,
+            channels,
+            decoder,
+        }
+    }
+
+    /// Decode one Opus payload into interleaved i16 PCM. `lost_before`
+    /// packets missing ahead of this one are bridged with one
+    /// concealment frame each (at the stream's 20ms cadence, capped at
+    /// `MAX_PLC_PACKETS`) so a drop warbles instead of clicking.
+    pub fn decode(&mut self, payload: &[u8], lost_before: u16) -> Vec<i16> {
+        let Some(decoder) = self.decoder.as_mut() else {
+            return Vec::new();
+        };
+        let channels = self.channels as usize;
+        let mut samples = Vec::new();
+        if lost_before > 0 && lost_before <= MAX_PLC_PACKETS {
+            let frame = (self.sample_rate as usize / 50) * channels;
+            for _ in 0..lost_before {
+                let start = samples.len();
+                samples.resize(start + frame, 0);
+                // An empty input asks libopus to extrapolate from the
+                // previous frame.
+                match decoder.decode(&[], &mut samples[start..], false) {
+                    Ok(decoded) => samples.truncate(start + decoded * channels),
+                    Err(_) => {
+                        samples.truncate(start);
+                        break;
+                    }
+                }
+            }
+        }
+        let start = samples.len();
+        samples.resize(
+            start + (self.sample_rate as usize * MAX_OPUS_FRAME_MS / 1000) * channels,
+            0,
+        );
+        match decoder.decode(payload, &mut samples[start..], false) {
+            Ok(decoded) => samples.truncate(start + decoded * channels),
+            Err(e) => {
+                log::debug!("Opus decode failed: {}", e);
+                samples.truncate(start);
+            }
+        }
+        samples
+    }
+}
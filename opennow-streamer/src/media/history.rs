@@ -0,0 +1,221 @@
+//! Rolling thumbnail history of the last ~30 seconds of decoded video,
+//! for "what just happened" review after a stutter or artifact.
+//!
+//! The streaming loop captures a downscaled copy of a decoded frame at
+//! most twice a second (on its own thread, before the frame is handed to
+//! the renderer), annotated with decode time and cumulative packet loss.
+//! The F4 overlay scrubs through the ring; a contact sheet can be saved
+//! as PNG for bug reports. Off by default
+//! (`Settings::frame_history_enabled`).
+
+use std::collections::VecDeque;
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+use anyhow::{Context, Result};
+
+use crate::media::VideoFrame;
+
+/// Thumbnail width; height follows the frame's aspect ratio.
+const THUMB_WIDTH: u32 = 320;
+
+/// Minimum spacing between captures (2fps).
+const CAPTURE_INTERVAL: Duration = Duration::from_millis(500);
+
+/// Ring length: 30 seconds at 2fps.
+const MAX_THUMBS: usize = 60;
+
+/// Hard cap on thumbnail memory regardless of entry count.
+const MAX_BYTES: usize = 30 * 1024 * 1024;
+
+/// One downscaled frame plus the stats it was decoded under.
+pub struct HistoryThumb {
+    pub rgba: Vec<u8>,
+    pub width: u32,
+    pub height: u32,
+    /// Wall-clock capture time (unix milliseconds).
+    pub captured_ms: i64,
+    /// Decode latency of the captured frame.
+    pub decode_ms: f32,
+    /// Cumulative RTP packets lost at capture time; the overlay diffs
+    /// consecutive entries to show loss per thumbnail interval.
+    pub packets_lost: u64,
+}
+
+/// Fixed-size ring of recent thumbnails. Owned behind a mutex shared
+/// between the streaming loop (writer) and the overlay (reader).
+#[derive(Default)]
+pub struct FrameHistory {
+    thumbs: VecDeque<HistoryThumb>,
+    bytes: usize,
+    last_capture: Option<Instant>,
+    /// Bumped on every capture so the overlay knows when its texture
+    /// cache is stale.
+    generation: u64,
+}
+
+impl FrameHistory {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Capture `frame` if the rate limit allows. Called from the
+    /// streaming loop; the downscale runs here, never on the UI thread.
+    pub fn capture(&mut self, frame: &VideoFrame, decode_ms: f32, packets_lost: u64) {
+        let now = Instant::now();
+        if self
+            .last_capture
+            .is_some_and(|at| now.duration_since(at) < CAPTURE_INTERVAL)
+        {
+            return;
+        }
+        self.last_capture = Some(now);
+
+        let width = THUMB_WIDTH.min(frame.width.max(1));
+        let height = (frame.height * width / frame.width.max(1)).max(1);
+        let full = crate::gui::renderer::yuv_to_rgba(frame);
+        let mut rgba = Vec::with_capacity((width * height * 4) as usize);
+        for y in 0..height {
+            let src_y = (y as u64 * frame.height as u64 / height as u64) as u32;
+            for x in 0..width {
+                let src_x = (x as u64 * frame.width as u64 / width as u64) as u32;
+                let offset = ((src_y * frame.width + src_x) * 4) as usize;
+                rgba.extend_from_slice(&full[offset..offset + 4]);
+            }
+        }
+
+        self.bytes += rgba.len();
+        self.thumbs.push_back(HistoryThumb {
+            rgba,
+            width,
+            height,
+            captured_ms: chrono::Utc::now().timestamp_millis(),
+            decode_ms,
+            packets_lost,
+        });
+        self.generation += 1;
+        while self.thumbs.len() > MAX_THUMBS || self.bytes > MAX_BYTES {
+            if let Some(old) = self.thumbs.pop_front() {
+                self.bytes -= old.rgba.len();
+            } else {
+                break;
+            }
+        }
+    }
+
+    pub fn thumbs(&self) -> &VecDeque<HistoryThumb> {
+        &self.thumbs
+    }
+
+    pub fn generation(&self) -> u64 {
+        self.generation
+    }
+
+    pub fn clear(&mut self) {
+        self.thumbs.clear();
+        self.bytes = 0;
+        self.generation += 1;
+    }
+
+    /// Render the ring as a contact-sheet PNG (10 columns, oldest first)
+    /// for attaching to bug reports.
+    pub fn save_contact_sheet(&self, path: &Path) -> Result<()> {
+        let Some(first) = self.thumbs.front() else {
+            anyhow::bail!("No frames captured yet");
+        };
+        const COLUMNS: usize = 10;
+        let (tw, th) = (first.width as usize, first.height as usize);
+        let rows = self.thumbs.len().div_ceil(COLUMNS);
+        let sheet_w = tw * COLUMNS.min(self.thumbs.len());
+        let sheet_h = th * rows;
+        let mut sheet = vec![0u8; sheet_w * sheet_h * 4];
+        for (i, thumb) in self.thumbs.iter().enumerate() {
+            let (cell_x, cell_y) = (i % COLUMNS, i / COLUMNS);
+            let copy_w = (thumb.width as usize).min(tw);
+            for y in 0..(thumb.height as usize).min(th) {
+                let src = y * thumb.width as usize * 4;
+                let dst = ((cell_y * th + y) * sheet_w + cell_x * tw) * 4;
+                sheet[dst..dst + copy_w * 4]
+                    .copy_from_slice(&thumb.rgba[src..src + copy_w * 4]);
+            }
+        }
+        write_png(path, sheet_w as u32, sheet_h as u32, &sheet)
+            .with_context(|| format!("Failed to write {}", path.display()))
+    }
+}
+
+/// Minimal PNG writer: 8-bit RGBA, stored (uncompressed) deflate blocks.
+/// Larger files than a real encoder but dependency-free, which is all a
+/// bug-report attachment (or a quick screenshot) needs.
+pub(crate) fn write_png(path: &Path, width: u32, height: u32, rgba: &[u8]) -> Result<()> {
+    // Raw zlib payload: filter byte 0 before each scanline.
+    let stride = width as usize * 4;
+    let mut raw = Vec::with_capacity(rgba.len() + height as usize);
+    for row in rgba.chunks(stride) {
+        raw.push(0);
+        raw.extend_from_slice(row);
+    }
+
+    let mut idat = vec![0x78, 0x01]; // zlib header, no compression preset
+    for (i, block) in raw.chunks(65535).enumerate() {
+        let last = (i + 1) * 65535 >= raw.len();
+        idat.push(if last { 1 } else { 0 });
+        idat.extend_from_slice(&(block.len() as u16).to_le_bytes());
+        idat.extend_from_slice(&(!(block.len() as u16)).to_le_bytes());
+        idat.extend_from_slice(block);
+    }
+    idat.extend_from_slice(&adler32(&raw).to_be_bytes());
+
+    let mut ihdr = Vec::with_capacity(13);
+    ihdr.extend_from_slice(&width.to_be_bytes());
+    ihdr.extend_from_slice(&height.to_be_bytes());
+    // 8-bit, color type 6 (RGBA), deflate, adaptive filtering, no interlace.
+    ihdr.extend_from_slice(&[8, 6, 0, 0, 0]);
+
+    let mut out = Vec::with_capacity(idat.len() + 64);
+    out.extend_from_slice(&[0x89, b'P', b'N', b'G', 0x0d, 0x0a, 0x1a, 0x0a]);
+    push_chunk(&mut out, b"IHDR", &ihdr);
+    push_chunk(&mut out, b"IDAT", &idat);
+    push_chunk(&mut out, b"IEND", &[]);
+    std::fs::write(path, out)?;
+    Ok(())
+}
+
+fn push_chunk(out: &mut Vec<u8>, kind: &[u8; 4], data: &[u8]) {
+    out.extend_from_slice(&(data.len() as u32).to_be_bytes());
+    out.extend_from_slice(kind);
+    out.extend_from_slice(data);
+    let mut crc_input = Vec::with_capacity(4 + data.len());
+    crc_input.extend_from_slice(kind);
+    crc_input.extend_from_slice(data);
+    out.extend_from_slice(&crc32(&crc_input).to_be_bytes());
+}
+
+fn crc32(data: &[u8]) -> u32 {
+    let mut crc = 0xffff_ffffu32;
+    for &byte in data {
+        crc ^= byte as u32;
+        for _ in 0..8 {
+            crc = if crc & 1 != 0 {
+                (crc >> 1) ^ 0xedb8_8320
+            } else {
+                crc >> 1
+            };
+        }
+    }
+    !crc
+}
+
+fn adler32(data: &[u8]) -> u32 {
+    const MOD: u32 = 65521;
+    let (mut a, mut b) = (1u32, 0u32);
+    for chunk in data.chunks(4096) {
+        for &byte in chunk {
+            a += byte as u32;
+            b += a;
+        }
+        a %= MOD;
+        b %= MOD;
+    }
+    (b << 16) | a
+}
@@ -0,0 +1,116 @@
+//! Microphone capture and Opus encode for the upstream voice track.
+//!
+//! Capture runs on its own thread (cpal streams are not `Send`), the
+//! encoded packets cross into the async side over a channel, and the
+//! stream task writes them onto the mic track `WebRtcPeer` negotiated.
+
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use anyhow::{anyhow, Result};
+use cpal::traits::{DeviceTrait, HostTrait, StreamTrait};
+
+/// Samples per 20ms Opus frame at 48kHz mono.
+const FRAME_SAMPLES: usize = 960;
+
+/// Upper bound libopus may need for one encoded frame.
+const MAX_PACKET_BYTES: usize = 400;
+
+/// Whether the mic is currently allowed to transmit. The UI layer
+/// combines the enable setting with push-to-talk state each frame;
+/// while false, captured samples are discarded immediately so nothing
+/// is buffered, encoded or sent.
+static TRANSMITTING: AtomicBool = AtomicBool::new(false);
+
+pub fn set_transmitting(open: bool) {
+    TRANSMITTING.store(open, Ordering::Relaxed);
+}
+
+/// True while mic packets are going out; the stats overlay shows an
+/// indicator off this.
+pub fn is_transmitting() -> bool {
+    TRANSMITTING.load(Ordering::Relaxed)
+}
+
+/// Names of the available input devices, for the settings combo box.
+pub fn list_input_devices() -> Vec<String> {
+    let host = cpal::default_host();
+    match host.input_devices() {
+        Ok(devices) => devices.filter_map(|device| device.name().ok()).collect(),
+        Err(e) => {
+            log::warn!("Microphone enumeration failed: {}", e);
+            Vec::new()
+        }
+    }
+}
+
+/// Captures 48kHz mono PCM from the chosen input device and pushes
+/// encoded 20ms Opus packets into `packet_tx`. Capture stops when the
+/// value is dropped.
+pub struct MicCapture {
+    _stream: cpal::Stream,
+    pub device_name: String,
+}
+
+impl MicCapture {
+    /// Open `device_name` (None = system default input). A configured
+    /// device that is gone falls back to the default with a warning,
+    /// matching the output side.
+    pub fn start(
+        device_name: Option<&str>,
+        packet_tx: tokio::sync::mpsc::UnboundedSender<Vec<u8>>,
+    ) -> Result<Self> {
+        let host = cpal::default_host();
+        let requested = device_name.and_then(|name| {
+            let found = host
+                .input_devices()
+                .ok()?
+                .find(|device| device.name().map(|n| n == name).unwrap_or(false));
+            if found.is_none() {
+                log::warn!("Microphone '{}' not found; using the system default", name);
+            }
+            found
+        });
+        let device = match requested {
+            Some(device) => device,
+            None => host
+                .default_input_device()
+                .ok_or_else(|| anyhow!("No audio input device available"))?,
+        };
+        let device_name = device.name().unwrap_or_else(|_| "unknown".to_string());
+        let config = cpal::StreamConfig {
+            channels: 1,
+            sample_rate: cpal::SampleRate(48000),
+            buffer_size: cpal::BufferSize::Default,
+        };
+        let mut encoder =
+            opus::Encoder::new(48000, opus::Channels::Mono, opus::Application::Voip)?;
+        let mut pending: Vec<i16> = Vec::new();
+        let error_device = device_name.clone();
+        let stream = device.build_input_stream(
+            &config,
+            move |data: &[i16], _| {
+                if !is_transmitting() {
+                    pending.clear();
+                    return;
+                }
+                pending.extend_from_slice(data);
+                while pending.len() >= FRAME_SAMPLES {
+                    let frame: Vec<i16> = pending.drain(..FRAME_SAMPLES).collect();
+                    match encoder.encode_vec(&frame, MAX_PACKET_BYTES) {
+                        Ok(packet) => {
+                            let _ = packet_tx.send(packet);
+                        }
+                        Err(e) => log::debug!("Opus encode failed: {}", e),
+                    }
+                }
+            },
+            move |err| log::error!("cpal input error on '{}': {}", error_device, err),
+            None,
+        )?;
+        stream.play()?;
+        Ok(Self {
+            _stream: stream,
+            device_name,
+        })
+    }
+}
@@ -0,0 +1,419 @@
+//! Media pipeline: RTP depacketization, video decode, audio playback.
+
+pub mod audio;
+pub mod history;
+pub mod mic;
+pub mod rtp;
+pub mod spectate;
+pub mod stats_export;
+
+#[cfg(windows)]
+pub mod d3d11;
+#[cfg(target_os = "linux")]
+pub mod vaapi;
+#[cfg(target_os = "macos")]
+pub mod videotoolbox;
+
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use anyhow::{anyhow, Result};
+
+use crate::settings::VideoCodec;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PixelFormat {
+    Yuv420,
+    Nv12,
+    /// 10-bit NV12 layout: 16-bit little-endian samples with the 10
+    /// significant bits in the high bits. Carries HDR10 (BT.2020 + PQ)
+    /// content from the hardware decoders.
+    P010,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ColorSpace {
+    Bt601,
+    Bt709,
+    Bt2020,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ColorRange {
+    Limited,
+    Full,
+}
+
+/// Transfer characteristics of the decoded samples. The renderer
+/// tonemaps `Pq` (HDR10) down to the SDR swapchain; `Sdr` covers
+/// BT.709/sRGB-style gamma.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ColorTransfer {
+    Sdr,
+    Pq,
+}
+
+/// One decoded video frame. Planes are Y/U/V for `Yuv420` or Y/UV for
+/// `Nv12` and `P010`; `P010` strides are in bytes, not samples.
+#[derive(Debug, Clone)]
+pub struct VideoFrame {
+    pub width: u32,
+    pub height: u32,
+    pub pixel_format: PixelFormat,
+    pub planes: Vec<Vec<u8>>,
+    pub strides: Vec<usize>,
+    pub timestamp_us: i64,
+    pub color_space: ColorSpace,
+    pub color_range: ColorRange,
+    pub transfer: ColorTransfer,
+}
+
+/// Latest-frame handoff between the decode task and the render loop. The
+/// renderer always takes the newest frame; older frames are dropped.
+///
+/// A small presentation delay can be configured to approximate a
+/// negative audio offset (audio can't be advanced, so video is held back
+/// instead); see `Settings::audio_delay_by_device`.
+#[derive(Clone, Default)]
+pub struct SharedFrame {
+    inner: Arc<Mutex<FrameSlot>>,
+}
+
+#[derive(Default)]
+struct FrameSlot {
+    latest: Option<VideoFrame>,
+    /// Frames held back while a presentation delay is active; (due,
+    /// frame) in arrival order.
+    held: VecDeque<(Instant, VideoFrame)>,
+    delay: Duration,
+    /// Pacing interval: `read` releases at most one frame per interval,
+    /// aligned to the schedule, instead of whatever arrived last.
+    pacing: Option<Duration>,
+    /// When the next paced frame is due.
+    next_present: Option<Instant>,
+    /// When `read` last released a frame, plus EMA mean/variance of the
+    /// release intervals in ms — the judder figure in the overlay.
+    last_present: Option<Instant>,
+    interval_mean_ms: f32,
+    interval_var_ms: f32,
+}
+
+/// EMA weight for the presentation-interval statistics (~2s horizon at
+/// 60fps).
+const JITTER_EMA_ALPHA: f32 = 0.02;
+
+/// Bound on held frames (~1s at 60fps) so a stalled reader can't grow
+/// the queue without limit; overflow presents the oldest frame early.
+const MAX_HELD_FRAMES: usize = 64;
+
+impl SharedFrame {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn write(&self, frame: VideoFrame) {
+        let mut slot = self.inner.lock().unwrap();
+        if slot.delay.is_zero() && slot.held.is_empty() {
+            slot.latest = Some(frame);
+            return;
+        }
+        let due = Instant::now() + slot.delay;
+        slot.held.push_back((due, frame));
+        if slot.held.len() > MAX_HELD_FRAMES {
+            let (_, frame) = slot.held.pop_front().unwrap();
+            slot.latest = Some(frame);
+        }
+    }
+
+    pub fn read(&self) -> Option<VideoFrame> {
+        let mut slot = self.inner.lock().unwrap();
+        let now = Instant::now();
+        while slot.held.front().is_some_and(|(due, _)| *due <= now) {
+            let (_, frame) = slot.held.pop_front().unwrap();
+            slot.latest = Some(frame);
+        }
+        if let Some(interval) = slot.pacing {
+            match slot.next_present {
+                Some(due) if now < due => return None,
+                Some(due) => {
+                    // Advance on the grid; a long stall resets it so we
+                    // don't burst frames catching up.
+                    let next = due + interval;
+                    slot.next_present = Some(if next < now { now + interval } else { next });
+                }
+                None => slot.next_present = Some(now + interval),
+            }
+        }
+        let frame = slot.latest.take();
+        if frame.is_some() {
+            if let Some(last) = slot.last_present {
+                let dt_ms = (now - last).as_secs_f32() * 1000.0;
+                let diff = dt_ms - slot.interval_mean_ms;
+                slot.interval_mean_ms += JITTER_EMA_ALPHA * diff;
+                slot.interval_var_ms +=
+                    JITTER_EMA_ALPHA * (diff * diff - slot.interval_var_ms);
+            }
+            slot.last_present = Some(now);
+        }
+        frame
+    }
+
+    /// Latest frame without consuming it (screenshots); the renderer's
+    /// `read` still presents the frame afterwards.
+    pub fn peek(&self) -> Option<VideoFrame> {
+        let mut slot = self.inner.lock().unwrap();
+        let now = Instant::now();
+        while slot.held.front().is_some_and(|(due, _)| *due <= now) {
+            let (_, frame) = slot.held.pop_front().unwrap();
+            slot.latest = Some(frame);
+        }
+        slot.latest.clone()
+    }
+
+    /// Whether a frame is waiting, without consuming it.
+    pub fn has_frame(&self) -> bool {
+        let slot = self.inner.lock().unwrap();
+        slot.latest.is_some()
+            || slot
+                .held
+                .front()
+                .is_some_and(|(due, _)| *due <= Instant::now())
+    }
+
+    /// Hold frames back by `delay` before presenting them. Zero restores
+    /// direct latest-frame handoff.
+    pub fn set_presentation_delay(&self, delay: Duration) {
+        self.inner.lock().unwrap().delay = delay;
+    }
+
+    /// Pace `read` to release at most one frame per `interval`, aligned
+    /// to the schedule; None restores present-ASAP. The frame loop sets
+    /// this from `Settings::frame_pacing` and the stream FPS.
+    pub fn set_pacing_interval(&self, interval: Option<Duration>) {
+        let mut slot = self.inner.lock().unwrap();
+        if slot.pacing != interval {
+            slot.next_present = None;
+        }
+        slot.pacing = interval;
+    }
+
+    /// Standard deviation of recent presentation intervals in ms — how
+    /// (un)even the frame cadence actually is.
+    pub fn presentation_jitter_ms(&self) -> f32 {
+        self.inner.lock().unwrap().interval_var_ms.max(0.0).sqrt()
+    }
+}
+
+/// Per-frame decoder feedback published to the streaming runner.
+#[derive(Debug, Clone, Default)]
+pub struct DecodeStats {
+    pub frames_decoded: u64,
+    pub frames_dropped: u64,
+    pub decode_time_ms: f32,
+    /// Set when the decoder hit an error and wants an IDR.
+    pub needs_keyframe: bool,
+}
+
+/// Aggregated stream statistics shown in the F3 overlay. Serialized
+/// into the persisted stream-end summary (`cache::StreamEndSummary`).
+#[derive(Debug, Clone, Default, serde::Serialize, serde::Deserialize)]
+pub struct StreamStats {
+    pub fps: f32,
+    pub bitrate_mbps: f32,
+    /// Decode latency per frame in milliseconds.
+    pub latency_ms: f32,
+    pub frames_decoded: u64,
+    pub frames_dropped: u64,
+    pub resolution: (u32, u32),
+    pub codec: Option<VideoCodec>,
+    /// Times the audio watchdog had to recreate a dead cpal stream.
+    pub audio_restarts: u32,
+    /// Round-trip time of the nominated ICE pair in milliseconds.
+    pub rtt_ms: f32,
+    /// Inbound video packet loss over the last stats window, percent.
+    pub packet_loss_pct: f32,
+    /// Std deviation of frame presentation intervals in milliseconds;
+    /// the judder the frame-pacing modes exist to flatten.
+    pub frame_jitter_ms: f32,
+    /// Encoder bitrate currently targeted by the adaptive loop, in
+    /// Mbps; equals the configured cap when adaptation is off.
+    pub target_bitrate_mbps: f32,
+    /// Packets the reorder buffer delivered back in sequence order
+    /// instead of treating as loss.
+    pub reordered_packets: u64,
+    /// Packets currently held back by the reorder buffer.
+    pub reorder_depth: usize,
+    /// Rate-limited keyframe (PLI) requests sent after decode errors.
+    pub keyframe_recoveries: u32,
+    /// Times persistent corruption escalated to recreating the decoder.
+    pub decoder_resets: u32,
+    /// Bytes queued on the input data channel awaiting transmission.
+    pub input_buffered_bytes: usize,
+    /// Bytes queued on the partially-reliable mouse channel.
+    pub mouse_buffered_bytes: usize,
+    /// Mouse coalescing interval currently in effect.
+    pub coalesce_interval_ms: f32,
+    /// Milliseconds from the start of the streaming loop until the first
+    /// decoded frame was presented. 0 until the first frame lands.
+    pub time_to_first_frame_ms: f32,
+    /// Wait measured from session creation until the rig was ready, and
+    /// the estimate shown at launch (0 = unknown) — for judging the
+    /// queue estimate's accuracy.
+    pub queue_wait_actual_secs: f32,
+    pub queue_wait_estimate_secs: f32,
+    /// Stream time so far and what is left of the subscription balance
+    /// fetched at launch, in seconds. Remaining stays 0 on unlimited
+    /// plans.
+    pub session_elapsed_secs: f32,
+    pub session_remaining_secs: f32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DecoderBackend {
+    /// Software decode via openh264 / dav1d.
+    Software,
+    #[cfg(windows)]
+    D3d11,
+    #[cfg(target_os = "linux")]
+    Vaapi,
+    #[cfg(target_os = "macos")]
+    VideoToolbox,
+}
+
+/// Backends usable on this platform, preferred first.
+pub fn get_supported_decoder_backends() -> Vec<DecoderBackend> {
+    let mut backends = Vec::new();
+    #[cfg(windows)]
+    backends.push(DecoderBackend::D3d11);
+    #[cfg(target_os = "linux")]
+    backends.push(DecoderBackend::Vaapi);
+    #[cfg(target_os = "macos")]
+    backends.push(DecoderBackend::VideoToolbox);
+    backends.push(DecoderBackend::Software);
+    backends
+}
+
+/// Video decoder wrapping the platform backend selected at stream start.
+pub struct VideoDecoder {
+    codec: VideoCodec,
+    backend: DecoderBackend,
+    /// The stream was negotiated as HDR10; hardware sessions configure
+    /// P010 output. The software path is 8-bit only and ignores this.
+    hdr: bool,
+    #[allow(dead_code)]
+    software: Option<openh264::decoder::Decoder>,
+    /// Lazily-created VA-API session; see [`vaapi::decode`].
+    #[cfg(target_os = "linux")]
+    pub(crate) vaapi: Option<vaapi::VaapiSession>,
+    frames_decoded: u64,
+}
+
+impl VideoDecoder {
+    pub fn new(codec: VideoCodec, hdr: bool) -> Result<Self> {
+        let backend = *get_supported_decoder_backends()
+            .first()
+            .ok_or_else(|| anyhow!("No decoder backend available"))?;
+        let software = match backend {
+            DecoderBackend::Software => Some(
+                openh264::decoder::Decoder::new()
+                    .map_err(|e| anyhow!("Failed to create openh264 decoder: {}", e))?,
+            ),
+            #[allow(unreachable_patterns)]
+            _ => None,
+        };
+        log::info!(
+            "Video decoder: {:?} backend for {:?}{}",
+            backend,
+            codec,
+            if hdr { " (HDR10)" } else { "" }
+        );
+        Ok(Self {
+            codec,
+            backend,
+            hdr,
+            software,
+            #[cfg(target_os = "linux")]
+            vaapi: None,
+            frames_decoded: 0,
+        })
+    }
+
+    pub fn codec(&self) -> VideoCodec {
+        self.codec
+    }
+
+    pub fn backend(&self) -> DecoderBackend {
+        self.backend
+    }
+
+    /// Whether the hardware session should be configured for 10-bit
+    /// (P010) output.
+    pub fn hdr_requested(&self) -> bool {
+        self.hdr
+    }
+
+    /// Decode one access unit. Returns None when the decoder needs more
+    /// data (e.g. parameter sets only). A hardware backend failure
+    /// permanently demotes the decoder to the software path.
+    pub fn decode(&mut self, access_unit: &[u8]) -> Result<Option<VideoFrame>> {
+        let result = match self.backend {
+            DecoderBackend::Software => return self.decode_software(access_unit),
+            #[cfg(windows)]
+            DecoderBackend::D3d11 => d3d11::decode(self, access_unit),
+            #[cfg(target_os = "linux")]
+            DecoderBackend::Vaapi => vaapi::decode(self, access_unit),
+            #[cfg(target_os = "macos")]
+            DecoderBackend::VideoToolbox => videotoolbox::decode(self, access_unit),
+        };
+        match result {
+            Ok(frame) => {
+                if frame.is_some() {
+                    self.frames_decoded += 1;
+                }
+                Ok(frame)
+            }
+            Err(e) => {
+                log::warn!(
+                    "{:?} backend failed ({}); falling back to software decode",
+                    self.backend,
+                    e
+                );
+                self.backend = DecoderBackend::Software;
+                if self.software.is_none() {
+                    self.software = Some(
+                        openh264::decoder::Decoder::new()
+                            .map_err(|e| anyhow!("Failed to create openh264 decoder: {}", e))?,
+                    );
+                }
+                self.decode_software(access_unit)
+            }
+        }
+    }
+
+    fn decode_software(&mut self, access_unit: &[u8]) -> Result<Option<VideoFrame>> {
+        let decoder = self
+            .software
+            .as_mut()
+            .ok_or_else(|| anyhow!("Software decoder not initialized"))?;
+        let yuv = match decoder.decode(access_unit) {
+            Ok(Some(yuv)) => yuv,
+            Ok(None) => return Ok(None),
+            Err(e) => return Err(anyhow!("Decode error: {}", e)),
+        };
+        let (width, height) = yuv.dimensions();
+        let (y_stride, u_stride, v_stride) = yuv.strides();
+        self.frames_decoded += 1;
+        Ok(Some(VideoFrame {
+            width: width as u32,
+            height: height as u32,
+            pixel_format: PixelFormat::Yuv420,
+            planes: vec![yuv.y().to_vec(), yuv.u().to_vec(), yuv.v().to_vec()],
+            strides: vec![y_stride, u_stride, v_stride],
+            timestamp_us: chrono::Utc::now().timestamp_micros(),
+            color_space: ColorSpace::Bt709,
+            color_range: ColorRange::Limited,
+            transfer: ColorTransfer::Sdr,
+        }))
+    }
+}
@@ -0,0 +1,538 @@
+//! RTP depacketization for the video track: reassembles RTP payloads into
+//! Annex-B access units the decoder can consume. The header split is
+//! shared with the audio path, which gets one Opus frame per packet.
+
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+const RTP_HEADER_LEN: usize = 12;
+
+/// Split a raw RTP packet into (sequence, marker, payload), skipping
+/// CSRC entries and any header extension. None when nothing is left
+/// after the headers.
+pub fn split_rtp(packet: &[u8]) -> Option<(u16, bool, &[u8])> {
+    if packet.len() <= RTP_HEADER_LEN {
+        return None;
+    }
+    let sequence = u16::from_be_bytes([packet[2], packet[3]]);
+    let marker = packet[1] & 0x80 != 0;
+    // CSRC entries extend the fixed header.
+    let csrc_count = (packet[0] & 0x0f) as usize;
+    let mut payload_offset = RTP_HEADER_LEN + csrc_count * 4;
+    // Skip the extension header when the X bit is set.
+    if packet[0] & 0x10 != 0 && packet.len() >= payload_offset + 4 {
+        let ext_words =
+            u16::from_be_bytes([packet[payload_offset + 2], packet[payload_offset + 3]])
+                as usize;
+        payload_offset += 4 + ext_words * 4;
+    }
+    if packet.len() <= payload_offset {
+        return None;
+    }
+    Some((sequence, marker, &packet[payload_offset..]))
+}
+
+/// Depacketizer for the audio track. Opus puts exactly one frame in
+/// each packet, so there is nothing to reassemble — this strips the
+/// header and tracks sequence/timestamp so the decoder can conceal
+/// losses.
+#[derive(Default)]
+pub struct RtpAudioDepacketizer {
+    sequence_number: Option<u16>,
+    timestamp: Option<u32>,
+    /// Packets missing immediately before the last processed one.
+    lost_before_last: u16,
+    pub packets_lost: u64,
+    pub packets_received: u64,
+}
+
+impl RtpAudioDepacketizer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Strip the header off one packet, returning the bare Opus
+    /// payload and updating the sequence/loss bookkeeping.
+    pub fn process<'a>(&mut self, packet: &'a [u8]) -> Option<&'a [u8]> {
+        let (sequence, _marker, payload) = split_rtp(packet)?;
+        // split_rtp validated the fixed header, so the timestamp field
+        // is in bounds.
+        let timestamp = u32::from_be_bytes([packet[4], packet[5], packet[6], packet[7]]);
+        self.lost_before_last = match self.sequence_number {
+            Some(last) => sequence.wrapping_sub(last.wrapping_add(1)),
+            None => 0,
+        };
+        self.packets_received += 1;
+        self.packets_lost += self.lost_before_last as u64;
+        self.sequence_number = Some(sequence);
+        self.timestamp = Some(timestamp);
+        Some(payload)
+    }
+
+    /// Sequence number of the last processed packet.
+    pub fn sequence_number(&self) -> Option<u16> {
+        self.sequence_number
+    }
+
+    /// RTP timestamp of the last processed packet (48kHz units for
+    /// Opus).
+    pub fn timestamp(&self) -> Option<u32> {
+        self.timestamp
+    }
+
+    /// How many packets went missing right before the last one; the
+    /// decoder turns these into concealment frames.
+    pub fn lost_before_last(&self) -> u16 {
+        self.lost_before_last
+    }
+}
+const ANNEX_B_START: [u8; 4] = [0, 0, 0, 1];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DepacketizerCodec {
+    H264,
+    H265,
+    AV1,
+}
+
+/// OBU type carried in bits 6..3 of the OBU header.
+const OBU_SEQUENCE_HEADER: u8 = 1;
+
+/// Read a LEB128 value (AV1 aggregation element lengths). Returns the
+/// value and how many bytes it occupied.
+fn read_leb128(data: &[u8]) -> Option<(u64, usize)> {
+    let mut value = 0u64;
+    for (i, &byte) in data.iter().enumerate().take(8) {
+        value |= ((byte & 0x7f) as u64) << (7 * i);
+        if byte & 0x80 == 0 {
+            return Some((value, i + 1));
+        }
+    }
+    None
+}
+
+fn write_leb128(out: &mut Vec<u8>, mut value: usize) {
+    loop {
+        let mut byte = (value & 0x7f) as u8;
+        value >>= 7;
+        if value != 0 {
+            byte |= 0x80;
+        }
+        out.push(byte);
+        if value == 0 {
+            break;
+        }
+    }
+}
+
+/// Beyond this many held packets a gap is declared lost regardless of
+/// the hold timer, so a long stall can't pin memory.
+const REORDER_MAX_DEPTH: usize = 32;
+
+/// Bounded reordering buffer ahead of the video depacketizer. Wi-Fi
+/// swaps adjacent packets routinely; holding a stray for a few
+/// milliseconds and releasing in sequence order avoids corrupting an
+/// access unit (and the keyframe round trip that follows) over a packet
+/// that was never actually lost.
+pub struct RtpReorderBuffer {
+    /// Held packets, sorted by wrapping sequence distance from
+    /// `next_sequence`.
+    pending: VecDeque<(u16, Instant, Vec<u8>)>,
+    /// Sequence the depacketizer expects next; None until the first
+    /// packet flows.
+    next_sequence: Option<u16>,
+    /// How long a packet blocked by a gap may wait before the gap is
+    /// declared a loss.
+    hold: Duration,
+    /// Packets that arrived behind a later sequence and were put back
+    /// in order before delivery.
+    pub reordered_packets: u64,
+}
+
+impl RtpReorderBuffer {
+    pub fn new(low_latency: bool) -> Self {
+        Self {
+            pending: VecDeque::new(),
+            next_sequence: None,
+            hold: if low_latency {
+                Duration::from_millis(2)
+            } else {
+                Duration::from_millis(8)
+            },
+            reordered_packets: 0,
+        }
+    }
+
+    /// Packets currently held back.
+    pub fn depth(&self) -> usize {
+        self.pending.len()
+    }
+
+    /// Accept one packet in arrival order. Late duplicates of sequences
+    /// already delivered (or written off) are dropped — replaying them
+    /// would rewind the depacketizer's loss tracking.
+    pub fn push(&mut self, packet: Vec<u8>) {
+        let Some((sequence, _, _)) = split_rtp(&packet) else {
+            return;
+        };
+        if let Some(next) = self.next_sequence {
+            if sequence.wrapping_sub(next) > u16::MAX / 2 {
+                return;
+            }
+        }
+        let distance = |seq: u16| match self.next_sequence {
+            Some(next) => seq.wrapping_sub(next),
+            None => 0,
+        };
+        if self.pending.iter().any(|(held, _, _)| *held == sequence) {
+            return;
+        }
+        match self
+            .pending
+            .iter()
+            .position(|(held, _, _)| distance(*held) > distance(sequence))
+        {
+            Some(index) => {
+                self.reordered_packets += 1;
+                self.pending.insert(index, (sequence, Instant::now(), packet));
+            }
+            None => self.pending.push_back((sequence, Instant::now(), packet)),
+        }
+    }
+
+    /// Next packet ready for the depacketizer: in sequence, or blocked
+    /// long (or deep) enough that the gap before it counts as loss —
+    /// the depacketizer sees the jump and does its usual accounting,
+    /// which feeds the keyframe-recovery path.
+    pub fn pop_ready(&mut self) -> Option<Vec<u8>> {
+        let (sequence, arrived, _) = self.pending.front()?;
+        let in_order = match self.next_sequence {
+            Some(next) => *sequence == next,
+            None => true,
+        };
+        if !in_order && arrived.elapsed() < self.hold && self.pending.len() <= REORDER_MAX_DEPTH {
+            return None;
+        }
+        let (sequence, _, packet) = self.pending.pop_front()?;
+        self.next_sequence = Some(sequence.wrapping_add(1));
+        Some(packet)
+    }
+
+    /// Forget all held packets and sequence state; pairs with
+    /// `RtpDepacketizer::reset` after a decoder recreate.
+    pub fn reset(&mut self) {
+        self.pending.clear();
+        self.next_sequence = None;
+    }
+}
+
+/// Stateful depacketizer fed raw RTP packets in arrival order. Emits a
+/// complete access unit when the marker bit closes one.
+pub struct RtpDepacketizer {
+    codec: DepacketizerCodec,
+    /// Accumulated NAL units for the access unit under construction.
+    current_au: Vec<u8>,
+    /// Fragment buffer for an in-flight FU.
+    fragment: Vec<u8>,
+    last_sequence: Option<u16>,
+    pub packets_lost: u64,
+    /// Packets actually processed; with `packets_lost` this gives the
+    /// loss ratio the auto mouse-channel routing keys off.
+    pub packets_received: u64,
+}
+
+impl RtpDepacketizer {
+    pub fn new(codec: DepacketizerCodec) -> Self {
+        Self {
+            codec,
+            current_au: Vec::new(),
+            fragment: Vec::new(),
+            last_sequence: None,
+            packets_lost: 0,
+            packets_received: 0,
+        }
+    }
+
+    pub fn codec(&self) -> DepacketizerCodec {
+        self.codec
+    }
+
+    /// Drop all parse state (in-flight access unit, fragment, sequence
+    /// tracking) after a decoder reset so stale NALs are never fed to
+    /// the fresh decoder. Loss counters survive; the stats stay
+    /// cumulative.
+    pub fn reset(&mut self) {
+        self.current_au.clear();
+        self.fragment.clear();
+        self.last_sequence = None;
+    }
+
+    /// Feed one RTP packet; returns a complete access unit when the
+    /// packet carried the marker bit.
+    pub fn process(&mut self, packet: &[u8]) -> Option<Vec<u8>> {
+        let (sequence, marker, payload) = split_rtp(packet)?;
+
+        self.packets_received += 1;
+        if let Some(last) = self.last_sequence {
+            let expected = last.wrapping_add(1);
+            if sequence != expected {
+                let gap = sequence.wrapping_sub(expected);
+                self.packets_lost += gap as u64;
+                // A gap invalidates any in-flight fragment.
+                self.fragment.clear();
+            }
+        }
+        self.last_sequence = Some(sequence);
+
+        match self.codec {
+            DepacketizerCodec::H264 => self.process_h264(payload),
+            DepacketizerCodec::H265 => self.process_h265(payload),
+            DepacketizerCodec::AV1 => self.process_av1(payload),
+        }
+
+        if marker && !self.current_au.is_empty() {
+            Some(std::mem::take(&mut self.current_au))
+        } else {
+            None
+        }
+    }
+
+    fn push_nal(&mut self, nal: &[u8]) {
+        self.current_au.extend_from_slice(&ANNEX_B_START);
+        self.current_au.extend_from_slice(nal);
+    }
+
+    fn process_h264(&mut self, payload: &[u8]) {
+        if payload.is_empty() {
+            return;
+        }
+        let nal_type = payload[0] & 0x1f;
+        match nal_type {
+            // Single NAL unit.
+            1..=23 => self.push_nal(payload),
+            // STAP-A: multiple length-prefixed NALs.
+            24 => {
+                let mut offset = 1;
+                while offset + 2 <= payload.len() {
+                    let len =
+                        u16::from_be_bytes([payload[offset], payload[offset + 1]]) as usize;
+                    offset += 2;
+                    if offset + len > payload.len() {
+                        break;
+                    }
+                    let nal = payload[offset..offset + len].to_vec();
+                    self.push_nal(&nal);
+                    offset += len;
+                }
+            }
+            // FU-A fragmentation.
+            28 => {
+                if payload.len() < 2 {
+                    return;
+                }
+                let fu_header = payload[1];
+                let start = fu_header & 0x80 != 0;
+                let end = fu_header & 0x40 != 0;
+                if start {
+                    self.fragment.clear();
+                    let reconstructed = (payload[0] & 0xe0) | (fu_header & 0x1f);
+                    self.fragment.push(reconstructed);
+                }
+                self.fragment.extend_from_slice(&payload[2..]);
+                if end && !self.fragment.is_empty() {
+                    let nal = std::mem::take(&mut self.fragment);
+                    self.push_nal(&nal);
+                }
+            }
+            _ => log::trace!("Ignoring H.264 NAL type {}", nal_type),
+        }
+    }
+
+    /// Whether an assembled access unit contains a keyframe: an IDR
+    /// slice for H.264, any IRAP NAL (BLA/IDR/CRA) for H.265, a sequence
+    /// header OBU for AV1. Used to decide when the initial keyframe
+    /// request can stop retrying.
+    pub fn access_unit_has_keyframe(&self, access_unit: &[u8]) -> bool {
+        if self.codec == DepacketizerCodec::AV1 {
+            return obu_stream_has_sequence_header(access_unit);
+        }
+        nal_units(access_unit).into_iter().any(|nal| {
+            let Some(&first) = nal.first() else {
+                return false;
+            };
+            match self.codec {
+                DepacketizerCodec::H264 => first & 0x1f == 5,
+                DepacketizerCodec::H265 => (16..=23).contains(&((first >> 1) & 0x3f)),
+                // Handled above; OBU streams have no NAL units.
+                DepacketizerCodec::AV1 => false,
+            }
+        })
+    }
+
+    fn process_h265(&mut self, payload: &[u8]) {
+        if payload.len() < 2 {
+            return;
+        }
+        let nal_type = (payload[0] >> 1) & 0x3f;
+        match nal_type {
+            // FU fragmentation.
+            49 => {
+                if payload.len() < 3 {
+                    return;
+                }
+                let fu_header = payload[2];
+                let start = fu_header & 0x80 != 0;
+                let end = fu_header & 0x40 != 0;
+                if start {
+                    self.fragment.clear();
+                    let ty = fu_header & 0x3f;
+                    self.fragment.push((payload[0] & 0x81) | (ty << 1));
+                    self.fragment.push(payload[1]);
+                }
+                self.fragment.extend_from_slice(&payload[3..]);
+                if end && !self.fragment.is_empty() {
+                    let nal = std::mem::take(&mut self.fragment);
+                    self.push_nal(&nal);
+                }
+            }
+            // Aggregation packet.
+            48 => {
+                let mut offset = 2;
+                while offset + 2 <= payload.len() {
+                    let len =
+                        u16::from_be_bytes([payload[offset], payload[offset + 1]]) as usize;
+                    offset += 2;
+                    if offset + len > payload.len() {
+                        break;
+                    }
+                    let nal = payload[offset..offset + len].to_vec();
+                    self.push_nal(&nal);
+                    offset += len;
+                }
+            }
+            _ => self.push_nal(payload),
+        }
+    }
+
+    /// AV1 RTP payload format (AOM spec): an aggregation header byte
+    /// followed by OBU elements. Z marks the first element as the
+    /// continuation of an OBU started in a previous packet, Y marks the
+    /// last element as continuing into the next packet, W is the element
+    /// count (0 means every element carries a LEB128 length prefix;
+    /// otherwise the last element runs to the end of the payload), and N
+    /// flags a new coded video sequence.
+    fn process_av1(&mut self, payload: &[u8]) {
+        let Some((&aggregation, mut rest)) = payload.split_first() else {
+            return;
+        };
+        let z = aggregation & 0x80 != 0;
+        let y = aggregation & 0x40 != 0;
+        let w = (aggregation >> 4) & 0x03;
+        if aggregation & 0x08 != 0 {
+            // New coded video sequence: anything in flight is stale.
+            self.current_au.clear();
+            self.fragment.clear();
+        }
+
+        let mut index = 0u8;
+        while !rest.is_empty() {
+            index += 1;
+            let element = if w != 0 && index == w {
+                // Last element of a W-mode packet has no length prefix.
+                std::mem::take(&mut rest)
+            } else {
+                let Some((len, len_bytes)) = read_leb128(rest) else {
+                    return;
+                };
+                let end = len_bytes + len as usize;
+                if end > rest.len() {
+                    return;
+                }
+                let element = &rest[len_bytes..end];
+                rest = &rest[end..];
+                element
+            };
+
+            if index == 1 && z {
+                // Continuation of the OBU from the previous packet;
+                // drop it when the start was lost.
+                if self.fragment.is_empty() {
+                    continue;
+                }
+                self.fragment.extend_from_slice(element);
+            } else {
+                self.fragment.clear();
+                self.fragment.extend_from_slice(element);
+            }
+            // The final element stays in the fragment buffer when Y says
+            // it continues into the next packet.
+            if y && rest.is_empty() {
+                break;
+            }
+            let obu = std::mem::take(&mut self.fragment);
+            self.push_obu(&obu);
+        }
+    }
+
+    /// Append one reassembled OBU to the access unit as a Section-5
+    /// low-overhead stream entry: the RTP format strips size fields, so
+    /// rewrite the header with `obu_has_size_field` set and a LEB128
+    /// payload size the decoder can walk.
+    fn push_obu(&mut self, obu: &[u8]) {
+        let Some(&header) = obu.first() else {
+            return;
+        };
+        let header_len = if header & 0x04 != 0 { 2 } else { 1 };
+        if obu.len() < header_len {
+            return;
+        }
+        self.current_au.push(header | 0x02);
+        self.current_au.extend_from_slice(&obu[1..header_len]);
+        write_leb128(&mut self.current_au, obu.len() - header_len);
+        self.current_au.extend_from_slice(&obu[header_len..]);
+    }
+}
+
+/// Walk a low-overhead OBU stream (every OBU size-prefixed, as `push_obu`
+/// emits) looking for a sequence header.
+fn obu_stream_has_sequence_header(data: &[u8]) -> bool {
+    let mut offset = 0;
+    while offset < data.len() {
+        let header = data[offset];
+        if (header >> 3) & 0x0f == OBU_SEQUENCE_HEADER {
+            return true;
+        }
+        offset += if header & 0x04 != 0 { 2 } else { 1 };
+        if offset >= data.len() {
+            return false;
+        }
+        let Some((len, len_bytes)) = read_leb128(&data[offset..]) else {
+            return false;
+        };
+        offset += len_bytes + len as usize;
+    }
+    false
+}
+
+/// Split an Annex-B blob into NAL unit payloads. Only 4-byte start codes
+/// are handled, which is all `push_nal` ever emits.
+fn nal_units(data: &[u8]) -> Vec<&[u8]> {
+    let mut nals = Vec::new();
+    let mut start = None;
+    let mut i = 0;
+    while i + 4 <= data.len() {
+        if data[i..i + 4] == ANNEX_B_START {
+            if let Some(s) = start {
+                nals.push(&data[s..i]);
+            }
+            start = Some(i + 4);
+            i += 4;
+        } else {
+            i += 1;
+        }
+    }
+    if let Some(s) = start {
+        nals.push(&data[s..]);
+    }
+    nals
+}
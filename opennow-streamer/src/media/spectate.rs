@@ -0,0 +1,404 @@
+//! Experimental read-only co-watch endpoint ("spectate link").
+//!
+//! When enabled, decoded frames are re-encoded as JPEG on a dedicated
+//! thread and served over plain HTTP as an MJPEG stream
+//! (`multipart/x-mixed-replace`) that any LAN browser can open — a
+//! dependency-free stand-in for real HLS/WebRTC packaging, at the cost
+//! of a short browser-side buffer and no audio (both noted on the watch
+//! page). The link carries a random token; a wrong token gets a 403 and
+//! the connection is dropped. There is deliberately no input path: the
+//! server never reads past the request line, and nothing it parses
+//! reaches the rig.
+//!
+//! The primary stream always wins under load. The render loop hands
+//! frames over through a bounded channel with `try_send` (a full queue
+//! drops the spectate copy, never blocks the frame), the encode runs
+//! off-thread, and a slow viewer skips frames through its own bounded
+//! queue instead of backing up the encoder.
+
+use std::io::{BufRead, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{sync_channel, Receiver, RecvTimeoutError, SyncSender};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use anyhow::{Context, Result};
+
+use super::VideoFrame;
+
+/// Fixed port for the co-watch endpoint, so the link survives restarts.
+const SPECTATE_PORT: u16 = 47123;
+
+/// Frames waiting for the encoder; beyond this the render loop drops
+/// the spectate copy.
+const ENCODE_QUEUE: usize = 2;
+
+/// Encoded parts waiting per viewer; a slower viewer skips frames.
+const VIEWER_QUEUE: usize = 2;
+
+/// Spectate frames are downscaled to at most this width to keep the
+/// JPEG encode cheap; height follows the aspect ratio.
+const MAX_OUT_WIDTH: u32 = 1280;
+
+/// JPEG quality bounds for the bandwidth-cap adaptation.
+const MIN_QUALITY: u8 = 30;
+const MAX_QUALITY: u8 = 85;
+
+/// An encoded multipart chunk, shared across viewer queues.
+type Part = Arc<Vec<u8>>;
+
+/// The running co-watch server. Dropping it stops the listener, the
+/// encoder and every viewer connection.
+pub struct SpectateServer {
+    url: String,
+    frame_tx: SyncSender<VideoFrame>,
+    viewers: Arc<Mutex<Vec<SyncSender<Part>>>>,
+    stop: Arc<AtomicBool>,
+    last_offer: Option<Instant>,
+    min_interval: Duration,
+}
+
+impl SpectateServer {
+    /// Bind the endpoint and start the accept + encode threads.
+    /// `max_fps` and `max_mbps` cap what the re-stream may consume.
+    pub fn start(max_fps: u32, max_mbps: f32) -> Result<Self> {
+        Self::start_on(SPECTATE_PORT, max_fps, max_mbps)
+    }
+
+    fn start_on(port: u16, max_fps: u32, max_mbps: f32) -> Result<Self> {
+        let listener = TcpListener::bind(("0.0.0.0", port))
+            .with_context(|| format!("Failed to bind co-watch port {}", port))?;
+        let port = listener.local_addr()?.port();
+        listener.set_nonblocking(true)?;
+        let token = format!("{:032x}", rand::random::<u128>());
+        let ip = crate::auth::lan_ip()
+            .map(|ip| ip.to_string())
+            .unwrap_or_else(|| "127.0.0.1".to_string());
+        let url = format!("http://{}:{}/watch/{}", ip, port, token);
+
+        let stop = Arc::new(AtomicBool::new(false));
+        let viewers: Arc<Mutex<Vec<SyncSender<Part>>>> = Arc::new(Mutex::new(Vec::new()));
+        let (frame_tx, frame_rx) = sync_channel::<VideoFrame>(ENCODE_QUEUE);
+
+        {
+            let stop = stop.clone();
+            let viewers = viewers.clone();
+            let token = token.clone();
+            std::thread::spawn(move || accept_loop(listener, &token, &viewers, &stop));
+        }
+        {
+            let stop = stop.clone();
+            let viewers = viewers.clone();
+            let max_fps = max_fps.max(1);
+            std::thread::spawn(move || encode_loop(frame_rx, &viewers, &stop, max_fps, max_mbps));
+        }
+
+        let min_interval = Duration::from_secs(1) / max_fps.max(1);
+        Ok(Self {
+            url,
+            frame_tx,
+            viewers,
+            stop,
+            last_offer: None,
+            min_interval,
+        })
+    }
+
+    /// The tokenized link a browser on the LAN can open.
+    pub fn watch_url(&self) -> &str {
+        &self.url
+    }
+
+    pub fn viewer_count(&self) -> usize {
+        self.viewers.lock().unwrap().len()
+    }
+
+    /// Hand the render loop's frame to the encoder if anyone is
+    /// watching. Never blocks: rate-limited to the configured fps, and
+    /// dropped outright when the encoder is behind.
+    pub fn offer_frame(&mut self, frame: &VideoFrame) {
+        if self.viewers.lock().unwrap().is_empty() {
+            return;
+        }
+        let now = Instant::now();
+        if self
+            .last_offer
+            .is_some_and(|at| now.duration_since(at) < self.min_interval)
+        {
+            return;
+        }
+        self.last_offer = Some(now);
+        let _ = self.frame_tx.try_send(frame.clone());
+    }
+}
+
+impl Drop for SpectateServer {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::SeqCst);
+        self.viewers.lock().unwrap().clear();
+    }
+}
+
+/// Accept viewers. The listener is non-blocking so a stop is noticed
+/// within the poll interval instead of hanging on `accept`.
+fn accept_loop(
+    listener: TcpListener,
+    token: &str,
+    viewers: &Mutex<Vec<SyncSender<Part>>>,
+    stop: &Arc<AtomicBool>,
+) {
+    while !stop.load(Ordering::SeqCst) {
+        match listener.accept() {
+            Ok((stream, _)) => {
+                if let Err(e) = handle_connection(stream, token, viewers, stop) {
+                    log::debug!("Co-watch connection error: {}", e);
+                }
+            }
+            Err(e) if e.kind() == std::io::ErrorKind::WouldBlock => {
+                std::thread::sleep(Duration::from_millis(100));
+            }
+            Err(e) => {
+                log::warn!("Co-watch accept failed: {}", e);
+                std::thread::sleep(Duration::from_millis(100));
+            }
+        }
+    }
+}
+
+/// Route one request. Only the request line is ever read — this server
+/// has no request bodies, no forms, and no input path by design.
+fn handle_connection(
+    mut stream: TcpStream,
+    token: &str,
+    viewers: &Mutex<Vec<SyncSender<Part>>>,
+    stop: &Arc<AtomicBool>,
+) -> Result<()> {
+    stream.set_read_timeout(Some(Duration::from_secs(5)))?;
+    let mut line = String::new();
+    std::io::BufReader::new(stream.try_clone()?).read_line(&mut line)?;
+    let path = line.split_whitespace().nth(1).unwrap_or("");
+
+    if path == format!("/watch/{}", token) {
+        let page = watch_page(token);
+        write!(
+            stream,
+            "HTTP/1.1 200 OK\r\nContent-Type: text/html; charset=utf-8\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+            page.len(),
+            page
+        )?;
+        return Ok(());
+    }
+    if path == format!("/stream/{}", token) {
+        let (tx, rx) = sync_channel::<Part>(VIEWER_QUEUE);
+        viewers.lock().unwrap().push(tx);
+        let stop = stop.clone();
+        std::thread::spawn(move || viewer_loop(stream, rx, &stop));
+        return Ok(());
+    }
+    // Wrong or missing token: no hints about the valid link.
+    write!(stream, "HTTP/1.1 403 Forbidden\r\nConnection: close\r\n\r\n")?;
+    Ok(())
+}
+
+fn watch_page(token: &str) -> String {
+    format!(
+        "<!doctype html><html><head><title>OpenNOW co-watch</title></head>\
+         <body style=\"margin:0;background:#000;color:#888;font-family:sans-serif\">\
+         <img src=\"/stream/{}\" style=\"width:100vw;height:100vh;object-fit:contain\">\
+         <div style=\"position:fixed;bottom:4px;left:8px;font-size:12px\">\
+         OpenNOW co-watch (experimental) — video only, view only</div>\
+         </body></html>",
+        token
+    )
+}
+
+/// Stream multipart JPEG chunks to one viewer until it hangs up or the
+/// server stops.
+fn viewer_loop(mut stream: TcpStream, rx: Receiver<Part>, stop: &Arc<AtomicBool>) {
+    let header = "HTTP/1.1 200 OK\r\n\
+                  Content-Type: multipart/x-mixed-replace; boundary=frame\r\n\
+                  Cache-Control: no-store\r\nConnection: close\r\n\r\n";
+    if stream.write_all(header.as_bytes()).is_err() {
+        return;
+    }
+    while !stop.load(Ordering::SeqCst) {
+        match rx.recv_timeout(Duration::from_secs(1)) {
+            Ok(part) => {
+                if stream.write_all(&part).is_err() {
+                    // Viewer gone; the encoder drops our sender on the
+                    // next broadcast.
+                    return;
+                }
+            }
+            Err(RecvTimeoutError::Timeout) => {}
+            Err(RecvTimeoutError::Disconnected) => return,
+        }
+    }
+}
+
+/// Encode frames to JPEG and broadcast them, adapting quality to stay
+/// under the bandwidth cap.
+fn encode_loop(
+    frame_rx: Receiver<VideoFrame>,
+    viewers: &Mutex<Vec<SyncSender<Part>>>,
+    stop: &Arc<AtomicBool>,
+    max_fps: u32,
+    max_mbps: f32,
+) {
+    // Per-frame byte budget that would saturate the cap at full rate.
+    let budget = (max_mbps.max(0.5) * 1_000_000.0 / 8.0 / max_fps as f32) as usize;
+    let mut quality = 70u8;
+    loop {
+        let frame = match frame_rx.recv_timeout(Duration::from_secs(1)) {
+            Ok(frame) => frame,
+            Err(RecvTimeoutError::Timeout) => {
+                if stop.load(Ordering::SeqCst) {
+                    break;
+                }
+                continue;
+            }
+            Err(RecvTimeoutError::Disconnected) => break,
+        };
+        let jpeg = match encode_jpeg(&frame, quality) {
+            Ok(jpeg) => jpeg,
+            Err(e) => {
+                log::warn!("Co-watch JPEG encode failed: {}", e);
+                continue;
+            }
+        };
+        if jpeg.len() > budget {
+            quality = quality.saturating_sub(5).max(MIN_QUALITY);
+        } else if jpeg.len() < budget / 2 {
+            quality = (quality + 5).min(MAX_QUALITY);
+        }
+        let mut part = Vec::with_capacity(jpeg.len() + 96);
+        let _ = write!(
+            part,
+            "--frame\r\nContent-Type: image/jpeg\r\nContent-Length: {}\r\n\r\n",
+            jpeg.len()
+        );
+        part.extend_from_slice(&jpeg);
+        part.extend_from_slice(b"\r\n");
+        let part: Part = Arc::new(part);
+        // Full queue = slow viewer, skip its frame; closed = viewer
+        // thread exited, drop the sender.
+        viewers.lock().unwrap().retain(|tx| {
+            !matches!(
+                tx.try_send(part.clone()),
+                Err(std::sync::mpsc::TrySendError::Disconnected(_))
+            )
+        });
+    }
+    viewers.lock().unwrap().clear();
+}
+
+/// Convert, downscale and JPEG-encode one frame.
+fn encode_jpeg(frame: &VideoFrame, quality: u8) -> Result<Vec<u8>> {
+    let rgba = crate::gui::renderer::yuv_to_rgba(frame);
+    let width = MAX_OUT_WIDTH.min(frame.width.max(1));
+    let height = (frame.height * width / frame.width.max(1)).max(1);
+    // Nearest-neighbor downscale plus RGBA→RGB (JPEG has no alpha).
+    let mut rgb = Vec::with_capacity((width * height * 3) as usize);
+    for y in 0..height {
+        let src_y = (y as u64 * frame.height as u64 / height as u64) as u32;
+        for x in 0..width {
+            let src_x = (x as u64 * frame.width as u64 / width as u64) as u32;
+            let offset = ((src_y * frame.width + src_x) * 4) as usize;
+            rgb.extend_from_slice(&rgba[offset..offset + 3]);
+        }
+    }
+    let mut jpeg = Vec::new();
+    image::codecs::jpeg::JpegEncoder::new_with_quality(&mut jpeg, quality)
+        .encode(&rgb, width, height, image::ExtendedColorType::Rgb8)
+        .context("JPEG encode failed")?;
+    Ok(jpeg)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::media::{ColorRange, ColorSpace, ColorTransfer, PixelFormat};
+    use std::io::Read;
+
+    fn test_frame() -> VideoFrame {
+        VideoFrame {
+            width: 16,
+            height: 16,
+            pixel_format: PixelFormat::Yuv420,
+            planes: vec![vec![200; 16 * 16], vec![128; 8 * 8], vec![128; 8 * 8]],
+            strides: vec![16, 8, 8],
+            timestamp_us: 0,
+            color_space: ColorSpace::Bt709,
+            color_range: ColorRange::Limited,
+            transfer: ColorTransfer::Sdr,
+        }
+    }
+
+    fn request(port: u16, path: &str) -> String {
+        let mut stream = TcpStream::connect(("127.0.0.1", port)).unwrap();
+        write!(stream, "GET {} HTTP/1.1\r\n\r\n", path).unwrap();
+        stream
+            .set_read_timeout(Some(Duration::from_secs(2)))
+            .unwrap();
+        let mut response = String::new();
+        let _ = stream.take(512).read_to_string(&mut response);
+        response
+    }
+
+    #[test]
+    fn wrong_token_is_rejected_without_hints() {
+        let server = SpectateServer::start_on(0, 30, 8.0).unwrap();
+        let port = server.url.rsplit(':').next().unwrap();
+        let port: u16 = port.split('/').next().unwrap().parse().unwrap();
+        let response = request(port, "/watch/not-the-token");
+        assert!(response.starts_with("HTTP/1.1 403"), "got: {}", response);
+        assert!(!response.contains("watch/"), "must not leak the link");
+    }
+
+    /// End to end: a viewer with the right token receives a JPEG part
+    /// for an offered frame.
+    #[test]
+    fn viewer_receives_a_jpeg_part() {
+        let mut server = SpectateServer::start_on(0, 30, 8.0).unwrap();
+        let url = server.url.clone();
+        let token = url.rsplit('/').next().unwrap().to_string();
+        let port: u16 = url.rsplit(':').next().unwrap().split('/').next().unwrap().parse().unwrap();
+
+        let mut stream = TcpStream::connect(("127.0.0.1", port)).unwrap();
+        write!(stream, "GET /stream/{} HTTP/1.1\r\n\r\n", token).unwrap();
+        stream
+            .set_read_timeout(Some(Duration::from_secs(5)))
+            .unwrap();
+
+        // The viewer registers asynchronously; offer frames until it's
+        // on the list, then until a part arrives.
+        let frame = test_frame();
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while server.viewer_count() == 0 && Instant::now() < deadline {
+            std::thread::sleep(Duration::from_millis(20));
+        }
+        assert!(server.viewer_count() > 0, "viewer never registered");
+
+        let mut response = Vec::new();
+        let mut buf = [0u8; 4096];
+        while Instant::now() < deadline && !contains(&response, b"image/jpeg") {
+            server.last_offer = None;
+            server.offer_frame(&frame);
+            if let Ok(n) = stream.read(&mut buf) {
+                response.extend_from_slice(&buf[..n]);
+            }
+        }
+        assert!(
+            contains(&response, b"multipart/x-mixed-replace"),
+            "missing stream header"
+        );
+        assert!(contains(&response, b"image/jpeg"), "no frame part arrived");
+        // JPEG SOI marker somewhere after the part header.
+        assert!(contains(&response, &[0xff, 0xd8, 0xff]), "no JPEG payload");
+    }
+
+    fn contains(haystack: &[u8], needle: &[u8]) -> bool {
+        haystack.windows(needle.len()).any(|w| w == needle)
+    }
+}
@@ -0,0 +1,88 @@
+//! Optional stats exporter for OBS: writes `StreamStats` once per
+//! second as JSON (browser source) and as a user-templated text file
+//! (text-from-file source).
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use anyhow::{Context, Result};
+
+use super::StreamStats;
+
+/// Default template when the user hasn't customized one.
+pub const DEFAULT_TEMPLATE: &str = "FPS: {fps} | {bitrate_mbps} Mbps | {latency_ms} ms";
+
+/// Fill the user template with the current stats. Unknown placeholders
+/// are left as-is so a typo is visible rather than silently dropped.
+pub fn render_template(template: &str, stats: &StreamStats) -> String {
+    template
+        .replace("{fps}", &format!("{:.0}", stats.fps))
+        .replace("{bitrate_mbps}", &format!("{:.1}", stats.bitrate_mbps))
+        .replace("{latency_ms}", &format!("{:.1}", stats.latency_ms))
+        .replace("{frames_decoded}", &stats.frames_decoded.to_string())
+        .replace("{frames_dropped}", &stats.frames_dropped.to_string())
+        .replace(
+            "{resolution}",
+            &format!("{}x{}", stats.resolution.0, stats.resolution.1),
+        )
+        .replace(
+            "{codec}",
+            stats.codec.map(|c| c.display_name()).unwrap_or("-"),
+        )
+}
+
+/// Write via temp + rename so OBS never reads a half-written file.
+fn write_atomic(path: &Path, contents: &str) -> Result<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let tmp = path.with_extension("tmp");
+    fs::write(&tmp, contents)?;
+    fs::rename(&tmp, path).with_context(|| format!("Failed to write {}", path.display()))?;
+    Ok(())
+}
+
+fn export_once(dir: &Path, template: &str, stats: &StreamStats, online: bool) -> Result<()> {
+    let json = serde_json::json!({
+        "online": online,
+        "fps": stats.fps,
+        "bitrate_mbps": stats.bitrate_mbps,
+        "latency_ms": stats.latency_ms,
+        "frames_decoded": stats.frames_decoded,
+        "frames_dropped": stats.frames_dropped,
+        "resolution": format!("{}x{}", stats.resolution.0, stats.resolution.1),
+        "codec": stats.codec.map(|c| c.display_name()),
+    });
+    write_atomic(&dir.join("stream_stats.json"), &json.to_string())?;
+    let text = if online {
+        render_template(template, stats)
+    } else {
+        "Offline".to_string()
+    };
+    write_atomic(&dir.join("stream_stats.txt"), &text)?;
+    Ok(())
+}
+
+/// Export loop for one stream. Runs until `stop` is set, then writes a
+/// final "offline" state so stale numbers don't linger in the overlay.
+pub async fn run_stats_exporter(
+    dir: PathBuf,
+    template: String,
+    stats: Arc<Mutex<StreamStats>>,
+    stop: Arc<AtomicBool>,
+) {
+    log::info!("Stats exporter writing to {}", dir.display());
+    while !stop.load(Ordering::SeqCst) {
+        let snapshot = stats.lock().unwrap().clone();
+        if let Err(e) = export_once(&dir, &template, &snapshot, true) {
+            log::warn!("Stats export failed: {}", e);
+        }
+        tokio::time::sleep(Duration::from_secs(1)).await;
+    }
+    if let Err(e) = export_once(&dir, &template, &StreamStats::default(), false) {
+        log::warn!("Stats export (offline) failed: {}", e);
+    }
+}
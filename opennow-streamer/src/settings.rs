@@ -0,0 +1,831 @@
+//! Persistent user settings, stored as JSON in the app data directory.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+
+use crate::app::cache::get_app_data_dir;
+
+/// Video codec requested from the server. What we actually get depends on
+/// what the rig negotiates, but this drives the SDP codec preference.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum VideoCodec {
+    H264,
+    H265,
+    AV1,
+}
+
+impl VideoCodec {
+    pub fn display_name(&self) -> &'static str {
+        match self {
+            VideoCodec::H264 => "H.264",
+            VideoCodec::H265 => "H.265 (HEVC)",
+            VideoCodec::AV1 => "AV1",
+        }
+    }
+}
+
+/// Peak luma samples per second each codec can carry at the level the
+/// rigs negotiate: H.264 High L5.2, HEVC L6.1, AV1 6.1. Requesting
+/// beyond this produces an opaque server-side failure, so the client
+/// validates first.
+fn max_luma_rate(codec: VideoCodec) -> u64 {
+    match codec {
+        VideoCodec::H264 => 530_841_600,
+        VideoCodec::H265 => 2_005_401_600,
+        VideoCodec::AV1 => 2_058_982_400,
+    }
+}
+
+/// Per-frame luma sample ceiling at the same levels.
+fn max_luma_picture(codec: VideoCodec) -> u64 {
+    match codec {
+        VideoCodec::H264 => 9_437_184,
+        VideoCodec::H265 | VideoCodec::AV1 => 35_651_584,
+    }
+}
+
+/// Whether `codec` can carry `resolution` at `fps` within its level
+/// limits.
+pub fn codec_supports(codec: VideoCodec, resolution: (u32, u32), fps: u32) -> bool {
+    let samples = resolution.0 as u64 * resolution.1 as u64;
+    samples <= max_luma_picture(codec) && samples * fps as u64 <= max_luma_rate(codec)
+}
+
+/// Tooltip text for a greyed-out combo: which codecs could carry it,
+/// e.g. "3840x2160@240 requires H.265 (HEVC) or AV1".
+pub fn codec_requirement_hint(resolution: (u32, u32), fps: u32) -> String {
+    let capable: Vec<&str> = [VideoCodec::H264, VideoCodec::H265, VideoCodec::AV1]
+        .into_iter()
+        .filter(|codec| codec_supports(*codec, resolution, fps))
+        .map(|codec| codec.display_name())
+        .collect();
+    let combo = format!("{}x{}@{}", resolution.0, resolution.1, fps);
+    if capable.is_empty() {
+        format!("{} exceeds every supported codec level", combo)
+    } else {
+        format!("{} requires {}", combo, capable.join(" or "))
+    }
+}
+
+/// Which data channel carries encoded mouse deltas.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum MouseChannelMode {
+    /// The 8 ms-lifetime channel: stale deltas are dropped instead of
+    /// retransmitted, keeping latency flat on lossy links.
+    PartiallyReliable,
+    /// Every delta arrives, possibly late, over the reliable channel.
+    Reliable,
+    /// Reliable on a clean link, partially reliable once measured loss
+    /// crosses the threshold in the streaming runner.
+    Auto,
+}
+
+impl MouseChannelMode {
+    pub fn display_name(&self) -> &'static str {
+        match self {
+            MouseChannelMode::PartiallyReliable => "Partially reliable (drop stale)",
+            MouseChannelMode::Reliable => "Reliable (never drop)",
+            MouseChannelMode::Auto => "Auto (loss-based)",
+        }
+    }
+}
+
+/// How decoded frames are scheduled for presentation.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum FramePacing {
+    /// Present whatever frame arrived most recently (lowest latency,
+    /// judders when stream and monitor rates don't divide evenly).
+    Off,
+    /// Release one frame per stream-frame interval, each at its ideal
+    /// display time.
+    Smooth,
+    /// Like Smooth, with the interval snapped to a whole number of
+    /// monitor refreshes so every frame shows for the same vsync count.
+    VSync,
+}
+
+impl FramePacing {
+    pub fn display_name(&self) -> &'static str {
+        match self {
+            FramePacing::Off => "Off (lowest latency)",
+            FramePacing::Smooth => "Smooth",
+            FramePacing::VSync => "VSync-aligned",
+        }
+    }
+}
+
+/// What F11 fullscreen means.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum FullscreenMode {
+    /// A borderless window over the whole monitor; the compositor keeps
+    /// running (safe, instant alt-tab).
+    Borderless,
+    /// Take the display over with a video mode matching the stream
+    /// resolution and FPS, skipping the composition hop on Windows.
+    /// Falls back to borderless when no matching mode exists.
+    Exclusive,
+}
+
+impl FullscreenMode {
+    pub fn display_name(&self) -> &'static str {
+        match self {
+            FullscreenMode::Borderless => "Borderless",
+            FullscreenMode::Exclusive => "Exclusive (lowest latency)",
+        }
+    }
+}
+
+/// How the OS cursor is captured while streaming.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum CursorCapture {
+    /// Grab and hide: all motion arrives as relative deltas.
+    Locked,
+    /// Visible but confined to the window, so edge-panning works
+    /// without the cursor escaping to another monitor.
+    Confined,
+    /// No grab at all (windowed desktop use).
+    Free,
+}
+
+impl CursorCapture {
+    pub fn display_name(&self) -> &'static str {
+        match self {
+            CursorCapture::Locked => "Locked (hidden)",
+            CursorCapture::Confined => "Confined to window",
+            CursorCapture::Free => "Free",
+        }
+    }
+}
+
+/// A named bundle of input behavior — mouse mode, capture, coalescing,
+/// sensitivity — switchable mid-stream from the quick menu (F2) and
+/// assignable per game. The built-in definitions live in
+/// `builtin_input_profile`; edited copies persist in
+/// `Settings::input_profiles` under the same name.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct InputProfile {
+    /// Forward relative deltas (raw capture) instead of absolute
+    /// cursor positions.
+    pub relative_mouse: bool,
+    pub capture: CursorCapture,
+    /// Pin the coalescing interval for this profile, overriding the
+    /// adaptive bounds. None keeps adaptation.
+    pub coalesce_fixed_ms: Option<u32>,
+    /// Multiplier applied to relative deltas before encoding.
+    pub sensitivity: f32,
+    /// Draw the client-side cursor preview while the OS cursor is
+    /// hidden by `Locked` capture.
+    pub show_local_cursor: bool,
+}
+
+impl Default for InputProfile {
+    fn default() -> Self {
+        // Matches the pre-profile behavior: raw relative capture with
+        // the cursor locked and adaptive coalescing.
+        Self {
+            relative_mouse: true,
+            capture: CursorCapture::Locked,
+            coalesce_fixed_ms: None,
+            sensitivity: 1.0,
+            show_local_cursor: false,
+        }
+    }
+}
+
+/// The profiles that always exist, in menu order.
+pub const INPUT_PROFILE_NAMES: [&str; 3] = ["FPS", "MOBA", "Desktop"];
+
+/// The in-code definition of a built-in profile; unknown names get the
+/// default (FPS-like) behavior.
+pub fn builtin_input_profile(name: &str) -> InputProfile {
+    match name {
+        // Relative capture at a short, fixed send cadence.
+        "FPS" => InputProfile {
+            coalesce_fixed_ms: Some(2),
+            ..InputProfile::default()
+        },
+        // Absolute cursor, visible and confined so edge-pan works.
+        "MOBA" => InputProfile {
+            relative_mouse: false,
+            capture: CursorCapture::Confined,
+            ..InputProfile::default()
+        },
+        // Emulated desktop / strategy: absolute cursor, no grab.
+        "Desktop" => InputProfile {
+            relative_mouse: false,
+            capture: CursorCapture::Free,
+            ..InputProfile::default()
+        },
+        _ => InputProfile::default(),
+    }
+}
+
+/// Stick response curve applied between the deadzone and saturation.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum StickCurve {
+    Linear,
+    Squared,
+    /// `curve_exponent` is applied as `value.powf(exponent)`.
+    Custom,
+}
+
+impl StickCurve {
+    pub fn display_name(&self) -> &'static str {
+        match self {
+            StickCurve::Linear => "Linear",
+            StickCurve::Squared => "Squared",
+            StickCurve::Custom => "Custom exponent",
+        }
+    }
+}
+
+/// Per-stick tuning. All values are normalized (0.0–1.0 magnitude).
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct StickTuning {
+    /// Magnitudes below this are treated as zero (drift compensation).
+    pub inner_deadzone: f32,
+    /// Magnitudes above this saturate to full deflection.
+    pub outer_deadzone: f32,
+    pub curve: StickCurve,
+    /// Exponent for `StickCurve::Custom`.
+    pub curve_exponent: f32,
+}
+
+impl Default for StickTuning {
+    fn default() -> Self {
+        Self {
+            inner_deadzone: 0.10,
+            outer_deadzone: 0.95,
+            curve: StickCurve::Linear,
+            curve_exponent: 1.5,
+        }
+    }
+}
+
+/// Controller tuning profile, persisted per controller identity so
+/// different pads keep their own settings.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ControllerTuning {
+    pub left_stick: StickTuning,
+    pub right_stick: StickTuning,
+    /// Trigger values below this are treated as unpressed.
+    pub trigger_threshold: f32,
+}
+
+impl Default for ControllerTuning {
+    fn default() -> Self {
+        Self {
+            left_stick: StickTuning::default(),
+            right_stick: StickTuning::default(),
+            trigger_threshold: 0.05,
+        }
+    }
+}
+
+/// A TURN relay entry (see `Settings::turn_servers`). Long-term
+/// credentials only, which is what webrtc-rs speaks.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TurnConfig {
+    /// e.g. "turn:relay.example.com:3478" (or "turns:" for TLS).
+    pub url: String,
+    pub username: String,
+    pub credential: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Settings {
+    /// Requested stream resolution (width, height).
+    pub resolution: (u32, u32),
+    /// Requested stream FPS.
+    pub fps: u32,
+    /// Maximum bitrate in Mbps sent in the session request / SDP.
+    pub max_bitrate_mbps: u32,
+    /// Let the bitrate slider exceed the tier's server-side cap. The
+    /// server is free to ignore the excess; this exists because
+    /// Alliance tiers vary and our cap table can be wrong.
+    pub bitrate_override: bool,
+    /// Auto bitrate: adapt the encoder target to measured loss
+    /// mid-stream — fast steps down under congestion, slow recovery
+    /// toward `max_bitrate_mbps` (which becomes the ceiling) once the
+    /// path is clean.
+    pub adaptive_bitrate: bool,
+    pub codec: VideoCodec,
+    /// Presentation scheduling for decoded frames; the overlay's jitter
+    /// figure shows what each mode buys on a given monitor.
+    pub frame_pacing: FramePacing,
+    /// Shrink the RTP reorder window to its minimum: a couple of ms less
+    /// latency, at the cost of treating mild Wi-Fi reordering as loss.
+    pub low_latency_mode: bool,
+    /// Request an HDR10 stream: sets the HDR flags in the session
+    /// request and SDP, and asks the hardware decoder for 10-bit (P010)
+    /// output. The rig only honors it for HDR-capable titles. Frames
+    /// are tonemapped to SDR for display; an HDR swapchain passthrough
+    /// would need a float surface format.
+    pub hdr_enabled: bool,
+    /// TURN relay servers handed to the ICE agent alongside the default
+    /// STUN server. Behind symmetric NAT or CGNAT no direct candidate
+    /// pair ever succeeds, so without a relay the stream sits on a
+    /// black screen. Empty means direct-only. Edited in settings.json.
+    pub turn_servers: Vec<TurnConfig>,
+    /// STUN servers for ICE gathering, as "stun:"/"stuns:" URLs. More
+    /// than one matters where a host is regionally blocked (Google's
+    /// often is on corporate networks); malformed entries are skipped
+    /// with a warning when the peer is created.
+    pub stun_servers: Vec<String>,
+    /// Persisted server/zone id, or None for automatic selection.
+    pub selected_server: Option<String>,
+    /// GPU class the user wants to land on (e.g. "RTX 4080"), matched
+    /// against the rig classes zones advertise. None means any rig.
+    pub preferred_rig: Option<String>,
+    /// When set, automatic zone selection only considers zones that
+    /// advertise the preferred rig; zones without rig data make this a
+    /// no-op rather than blocking every launch.
+    pub preferred_rig_strict: bool,
+    /// When the selected zone rejects a launch for capacity/maintenance,
+    /// automatically retry the next-best zones by ping.
+    pub zone_failover: bool,
+    /// Failover never picks a zone above this ping.
+    pub failover_max_ping_ms: u32,
+    /// Opt-in session racing: request sessions in the best zones
+    /// simultaneously and keep whichever is ready first, cancelling the
+    /// rest. Disabled automatically when the account's one-session
+    /// limit rejects concurrent requests.
+    pub session_racing: bool,
+    /// How many zones a race spans.
+    pub session_racing_zones: u32,
+    /// Re-ping the selected zone right before a launch and offer a
+    /// switch when its latency has drifted. Hard-capped at about a
+    /// second; off means launch on the stored pings alone.
+    pub prelaunch_ping_check: bool,
+    /// How many milliseconds the selected zone must have drifted (above
+    /// its stored ping, or behind the best alternative) before the
+    /// switch prompt appears.
+    pub prelaunch_ping_threshold_ms: u32,
+    /// The zone-switch prompt was answered with "remember my choice":
+    /// Some(true) switches silently, Some(false) keeps the selected
+    /// zone silently. None asks each time.
+    pub prelaunch_switch_remembered: Option<bool>,
+    /// Require an explicit confirmation before launching when the
+    /// subscription's remaining hours drop below this. None disables the
+    /// block; unlimited plans are never affected.
+    pub low_hours_block_threshold: Option<f32>,
+    /// Stop the stream (and terminate the session server-side, so the
+    /// hour meter stops) after this many minutes without keyboard, mouse
+    /// or controller input. A 60-second "Still here?" warning comes
+    /// first. None disables the guard.
+    pub afk_timeout_minutes: Option<u32>,
+    /// Minutes-remaining marks at which the countdown warning fires
+    /// during a stream, measured against the subscription balance
+    /// fetched at launch. Empty disables the warnings; unlimited plans
+    /// never warn.
+    pub session_warning_minutes: Vec<u32>,
+    /// Reconnect attempts after a dropped connection before the stream
+    /// is torn down. The session is kept alive server-side between
+    /// attempts; each attempt backs off a little longer. 0 disables
+    /// automatic reconnect.
+    pub max_reconnect_attempts: u32,
+    /// Scroll wheel multiplier applied before notch quantization.
+    pub scroll_speed: f32,
+    /// Global look-sensitivity multiplier on relative mouse deltas,
+    /// applied on top of the active input profile's own sensitivity.
+    pub mouse_sensitivity: f32,
+    /// Adaptive mouse coalescing bounds in milliseconds. The interval
+    /// scales between these based on measured RTT and input-channel
+    /// backlog.
+    pub coalesce_min_ms: u32,
+    pub coalesce_max_ms: u32,
+    /// Pin a fixed coalescing interval instead of adapting.
+    pub coalesce_fixed_ms: Option<u32>,
+    /// Which data channel carries mouse deltas; applies live mid-stream.
+    pub mouse_channel_mode: MouseChannelMode,
+    /// Profile applied when the game has no override.
+    pub active_input_profile: String,
+    /// User-edited copies of the built-in profiles, keyed by name.
+    /// Missing entries fall back to `builtin_input_profile`.
+    pub input_profiles: std::collections::HashMap<String, InputProfile>,
+    /// Per-game profile assignment, keyed by game id.
+    pub game_input_profiles: std::collections::HashMap<String, String>,
+    /// Invert scroll direction (trackpad-style natural scrolling).
+    pub natural_scroll: bool,
+    /// Treat Windows' synthetic Ctrl+right-Alt as AltGr so international
+    /// layouts type @/€ instead of firing Ctrl+Alt shortcuts remotely.
+    /// Off for games that genuinely bind Ctrl+Alt combos.
+    pub altgr_mode: bool,
+    pub fullscreen: bool,
+    /// Monitor (by OS name) F11 goes fullscreen on; None uses whatever
+    /// monitor the window is on. A disconnected choice falls back to
+    /// the primary.
+    pub preferred_monitor: Option<String>,
+    pub fullscreen_mode: FullscreenMode,
+    /// Low-spec UI: redraw-on-event outside streaming, no hover/fade
+    /// animations, reduced cover art resolution. Suggested automatically
+    /// on software (llvmpipe) renderers.
+    pub low_spec_ui: bool,
+    /// GPU memory budget for cached cover textures, in MB. Keeps VRAM
+    /// free for the video decoder on small GPUs.
+    pub cover_vram_budget_mb: u32,
+    /// Experimental: serve a view-only co-watch stream of the decoded
+    /// video on the LAN while streaming (token-protected MJPEG over
+    /// HTTP; no input path exists on the endpoint). Off by default.
+    pub spectate_enabled: bool,
+    /// Bandwidth cap for the co-watch stream, in Mbps.
+    pub spectate_max_mbps: f32,
+    /// Frame-rate cap for the co-watch stream.
+    pub spectate_max_fps: u32,
+    /// Send viewport updates on window resize so the server's DRC can
+    /// follow the window size. Off = fixed encode resolution.
+    pub dynamic_viewport: bool,
+    pub show_stats_overlay: bool,
+    /// Hide the stats overlay automatically while screen capture is
+    /// detected (see `app::capture`).
+    pub hide_overlay_when_captured: bool,
+    /// The "Recording detected" prompt was answered with "remember my
+    /// choice"; don't ask again.
+    pub capture_prompt_remembered: bool,
+    /// The F1 cheat sheet (with the getting-started section) was shown
+    /// once after install; it only opens on demand after that.
+    pub help_overlay_seen: bool,
+    pub vsync: bool,
+    /// "system" (follow the OS appearance, the default), "dark" or
+    /// "light".
+    pub theme: String,
+    /// UI scale multiplier applied on top of the OS-reported scale
+    /// factor (egui zoom, so every point-sized window and overlay
+    /// scales with it). 1.0 follows the OS; 4K TVs that report 96 DPI
+    /// want 1.5+.
+    pub ui_scale: f32,
+    /// The one-time "4K at 100% scale" offer was answered; don't ask
+    /// again.
+    pub ui_scale_prompt_seen: bool,
+    /// Keep auth tokens in the OS keyring instead of a plaintext
+    /// auth.json. An existing auth.json is migrated in (and deleted) on
+    /// the next start; a machine without a usable keyring falls back to
+    /// the file.
+    pub use_secure_token_storage: bool,
+    /// Dev: record sanitized API request/response fixtures (tokens,
+    /// emails and user ids stripped) for the replay regression corpus.
+    /// See `api::recording`.
+    pub api_recording: bool,
+    /// Keep a rolling 30s thumbnail history of decoded frames (F4
+    /// overlay) for reviewing stutters; costs a little CPU and memory.
+    pub frame_history_enabled: bool,
+    /// Write per-second stream stats to files for OBS overlays.
+    pub stats_export_enabled: bool,
+    /// Directory for the exported files, or None for the app data dir.
+    pub stats_export_dir: Option<PathBuf>,
+    /// Format string for the text export; see
+    /// `media::stats_export::render_template` for placeholders.
+    pub stats_export_template: String,
+    /// Forward the server's haptic packets to the pad's motors.
+    pub controller_vibration: bool,
+    /// Tuning profiles keyed by controller identity (name/GUID).
+    pub controller_profiles: std::collections::HashMap<String, ControllerTuning>,
+    /// Stream playback gain (1.0 = as received, up to 2.0 boost).
+    pub volume: f32,
+    /// Mute stream audio without touching the volume (Ctrl+Shift+M).
+    pub muted: bool,
+    /// Output device for stream audio; None follows the system default.
+    pub audio_output_device: Option<String>,
+    /// Capture the microphone for in-game voice chat. Off by default —
+    /// nothing is recorded until the user opts in.
+    pub mic_enabled: bool,
+    /// Input device for the mic track; None follows the system default.
+    pub mic_input_device: Option<String>,
+    /// Only transmit while the push-to-talk key (left Alt) is held;
+    /// unchecked means open mic whenever streaming.
+    pub mic_push_to_talk: bool,
+    /// Audio delay in milliseconds keyed by output device name, tuned
+    /// with the A/V sync test in settings. Positive delays audio;
+    /// negative is approximated by holding video back (capped at
+    /// -200ms, since audio can't be advanced).
+    pub audio_delay_by_device: std::collections::HashMap<String, i32>,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            resolution: (1920, 1080),
+            fps: 60,
+            max_bitrate_mbps: 50,
+            bitrate_override: false,
+            adaptive_bitrate: false,
+            codec: VideoCodec::H264,
+            frame_pacing: FramePacing::Off,
+            low_latency_mode: false,
+            hdr_enabled: false,
+            turn_servers: Vec::new(),
+            stun_servers: vec![
+                "stun:stun.l.google.com:19302".to_string(),
+                "stun:stun.cloudflare.com:3478".to_string(),
+            ],
+            selected_server: None,
+            preferred_rig: None,
+            preferred_rig_strict: false,
+            zone_failover: false,
+            failover_max_ping_ms: 80,
+            session_racing: false,
+            session_racing_zones: 2,
+            prelaunch_ping_check: true,
+            prelaunch_ping_threshold_ms: 30,
+            prelaunch_switch_remembered: None,
+            low_hours_block_threshold: None,
+            afk_timeout_minutes: None,
+            session_warning_minutes: vec![10, 1],
+            max_reconnect_attempts: 5,
+            scroll_speed: 1.0,
+            mouse_sensitivity: 1.0,
+            coalesce_min_ms: 2,
+            coalesce_max_ms: 12,
+            coalesce_fixed_ms: None,
+            mouse_channel_mode: MouseChannelMode::PartiallyReliable,
+            active_input_profile: "FPS".to_string(),
+            input_profiles: std::collections::HashMap::new(),
+            game_input_profiles: std::collections::HashMap::new(),
+            natural_scroll: false,
+            altgr_mode: true,
+            fullscreen: false,
+            preferred_monitor: None,
+            fullscreen_mode: FullscreenMode::Borderless,
+            low_spec_ui: false,
+            cover_vram_budget_mb: 256,
+            spectate_enabled: false,
+            spectate_max_mbps: 8.0,
+            spectate_max_fps: 30,
+            dynamic_viewport: true,
+            show_stats_overlay: false,
+            hide_overlay_when_captured: false,
+            capture_prompt_remembered: false,
+            help_overlay_seen: false,
+            vsync: true,
+            theme: "system".to_string(),
+            ui_scale: 1.0,
+            ui_scale_prompt_seen: false,
+            use_secure_token_storage: false,
+            api_recording: false,
+            frame_history_enabled: false,
+            stats_export_enabled: false,
+            stats_export_dir: None,
+            stats_export_template: crate::media::stats_export::DEFAULT_TEMPLATE.to_string(),
+            controller_vibration: true,
+            controller_profiles: std::collections::HashMap::new(),
+            volume: 1.0,
+            muted: false,
+            audio_output_device: None,
+            mic_enabled: false,
+            mic_input_device: None,
+            mic_push_to_talk: true,
+            audio_delay_by_device: std::collections::HashMap::new(),
+        }
+    }
+}
+
+pub fn settings_path() -> PathBuf {
+    get_app_data_dir().join("settings.json")
+}
+
+/// Quiet period after the last change before the debounced save writes.
+/// Slider drags fire changes continuously; the write lands once the
+/// drag settles.
+pub const SAVE_DEBOUNCE: Duration = Duration::from_millis(500);
+
+/// Apply-now, write-later persistence: changes mark the settings dirty,
+/// and the frame loop writes once the change burst has been quiet for
+/// `SAVE_DEBOUNCE` (or immediately on the explicit flush points — app
+/// exit and stream start).
+#[derive(Debug, Default)]
+pub struct SaveDebouncer {
+    last_change: Option<Instant>,
+}
+
+impl SaveDebouncer {
+    pub fn note_change(&mut self) {
+        self.last_change = Some(Instant::now());
+    }
+
+    /// True when a debounced write is due; clears the dirty mark.
+    pub fn take_due(&mut self) -> bool {
+        self.take_due_after(SAVE_DEBOUNCE)
+    }
+
+    fn take_due_after(&mut self, debounce: Duration) -> bool {
+        if self.last_change.is_some_and(|at| at.elapsed() >= debounce) {
+            self.last_change = None;
+            true
+        } else {
+            false
+        }
+    }
+
+    /// True when anything is dirty, regardless of the quiet period.
+    /// Used by the flush points so no change is lost.
+    pub fn take_dirty(&mut self) -> bool {
+        self.last_change.take().is_some()
+    }
+}
+
+impl Settings {
+    /// Pre-launch guard against a stream config the codec level can't
+    /// carry. The modal greys such combos out, but settings.json can be
+    /// edited into one externally.
+    pub fn validate_stream_config(&self) -> Result<(), String> {
+        if codec_supports(self.codec, self.resolution, self.fps) {
+            return Ok(());
+        }
+        Err(format!(
+            "{} can't carry this stream config: {}",
+            self.codec.display_name(),
+            codec_requirement_hint(self.resolution, self.fps)
+        ))
+    }
+
+    /// The profile as the user sees it: their edited copy when one
+    /// exists, otherwise the built-in definition.
+    pub fn input_profile(&self, name: &str) -> InputProfile {
+        self.input_profiles
+            .get(name)
+            .cloned()
+            .unwrap_or_else(|| builtin_input_profile(name))
+    }
+
+    pub fn load() -> Self {
+        let path = settings_path();
+        match Self::read_from(&path) {
+            Some(settings) => settings,
+            None => {
+                // A torn write (power loss mid-save) can leave the
+                // primary unreadable; the previous save survives as
+                // `.bak` and gets promoted back.
+                let backup = backup_path(&path);
+                match Self::read_from(&backup) {
+                    Some(settings) => {
+                        log::warn!(
+                            "settings.json was unreadable; restored the last known-good backup"
+                        );
+                        let _ = fs::copy(&backup, &path);
+                        settings
+                    }
+                    None => Settings::default(),
+                }
+            }
+        }
+    }
+
+    fn read_from(path: &Path) -> Option<Self> {
+        let data = fs::read_to_string(path).ok()?;
+        match serde_json::from_str(&data) {
+            Ok(settings) => Some(settings),
+            Err(e) => {
+                log::warn!("Failed to parse {}: {}", path.display(), e);
+                None
+            }
+        }
+    }
+
+    pub fn save(&self) -> Result<()> {
+        self.save_to(&settings_path())
+    }
+
+    /// Atomic save: write a temp file, rotate the current file to
+    /// `.bak`, then rename the temp into place — an interrupted write
+    /// can never destroy both copies.
+    pub fn save_to(&self, path: &Path) -> Result<()> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let data = serde_json::to_string_pretty(self)?;
+        let tmp = path.with_extension("json.tmp");
+        fs::write(&tmp, data).context("Failed to write settings temp file")?;
+        if path.exists() {
+            let _ = fs::rename(path, backup_path(path));
+        }
+        fs::rename(&tmp, path).context("Failed to move settings into place")?;
+        Ok(())
+    }
+}
+
+fn backup_path(path: &Path) -> PathBuf {
+    path.with_extension("json.bak")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_settings_path(tag: &str) -> PathBuf {
+        std::env::temp_dir().join(format!(
+            "opennow-settings-test-{}-{}.json",
+            tag,
+            std::process::id()
+        ))
+    }
+
+    /// Spot checks against combos known to fit (or not) the negotiated
+    /// codec levels.
+    #[test]
+    fn codec_level_table_matches_known_combos() {
+        let cases = [
+            // (codec, resolution, fps, valid)
+            (VideoCodec::H264, (1920, 1080), 240, true),
+            (VideoCodec::H264, (2560, 1440), 120, true),
+            (VideoCodec::H264, (3840, 2160), 60, true),
+            (VideoCodec::H264, (3840, 2160), 120, false),
+            (VideoCodec::H264, (3840, 2160), 240, false),
+            (VideoCodec::H265, (3840, 2160), 240, true),
+            (VideoCodec::AV1, (3840, 2160), 240, true),
+        ];
+        for (codec, resolution, fps, valid) in cases {
+            assert_eq!(
+                codec_supports(codec, resolution, fps),
+                valid,
+                "{:?} {}x{}@{}",
+                codec,
+                resolution.0,
+                resolution.1,
+                fps
+            );
+        }
+    }
+
+    #[test]
+    fn requirement_hint_names_the_capable_codecs() {
+        let hint = codec_requirement_hint((3840, 2160), 240);
+        assert_eq!(hint, "3840x2160@240 requires H.265 (HEVC) or AV1");
+
+        let mut settings = Settings::default();
+        settings.resolution = (3840, 2160);
+        settings.fps = 240;
+        assert!(settings.validate_stream_config().is_err());
+        settings.codec = VideoCodec::AV1;
+        assert!(settings.validate_stream_config().is_ok());
+    }
+
+    /// A slider drag: dozens of changes in quick succession must
+    /// coalesce into a single write, and the file must hold the final
+    /// state, not an intermediate one.
+    #[test]
+    fn rapid_changes_coalesce_into_one_write() {
+        let path = temp_settings_path("debounce");
+        let debounce = Duration::from_millis(30);
+        let mut saver = SaveDebouncer::default();
+        let mut settings = Settings::default();
+        let mut writes = 0;
+        for fps in 1..=50 {
+            settings.fps = fps;
+            saver.note_change();
+            if saver.take_due_after(debounce) {
+                settings.save_to(&path).unwrap();
+                writes += 1;
+            }
+            std::thread::sleep(Duration::from_millis(1));
+        }
+        std::thread::sleep(debounce);
+        if saver.take_due_after(debounce) {
+            settings.save_to(&path).unwrap();
+            writes += 1;
+        }
+        assert_eq!(writes, 1, "a drag burst must produce one write");
+        let reloaded = Settings::read_from(&path).unwrap();
+        assert_eq!(reloaded.fps, 50);
+        let _ = fs::remove_file(backup_path(&path));
+        let _ = fs::remove_file(&path);
+    }
+
+    #[test]
+    fn flush_writes_without_waiting_for_the_quiet_period() {
+        let mut saver = SaveDebouncer::default();
+        saver.note_change();
+        assert!(saver.take_dirty());
+        assert!(!saver.take_dirty(), "flush clears the dirty mark");
+    }
+
+    /// Saving rotates the previous good file to `.bak`, and a corrupted
+    /// primary falls back to it instead of losing everything.
+    #[test]
+    fn corrupted_primary_recovers_from_backup() {
+        let path = temp_settings_path("backup");
+        let mut settings = Settings::default();
+        settings.fps = 120;
+        settings.save_to(&path).unwrap();
+        settings.fps = 144;
+        settings.save_to(&path).unwrap();
+
+        let backup = Settings::read_from(&backup_path(&path)).unwrap();
+        assert_eq!(backup.fps, 120, ".bak holds the previous save");
+
+        // Simulate the torn write.
+        fs::write(&path, "{ \"fps\": 14").unwrap();
+        assert!(Settings::read_from(&path).is_none());
+        assert_eq!(Settings::read_from(&backup_path(&path)).unwrap().fps, 120);
+        let _ = fs::remove_file(backup_path(&path));
+        let _ = fs::remove_file(&path);
+    }
+}
@@ -0,0 +1,1181 @@
+//! WebRTC streaming: signaling, peer setup, and the main streaming loop
+//! that feeds the decoder and drains input events.
+
+#[cfg(feature = "netsim")]
+pub mod netsim;
+mod signaling;
+
+pub use signaling::{SignalingClient, SignalingEvent};
+
+use std::sync::atomic::{AtomicBool, AtomicU8, Ordering};
+use std::sync::Arc;
+
+use anyhow::{anyhow, Context, Result};
+use tokio::sync::mpsc::{self, UnboundedReceiver, UnboundedSender};
+use webrtc::api::interceptor_registry::register_default_interceptors;
+use webrtc::api::media_engine::{MediaEngine, MIME_TYPE_OPUS};
+use webrtc::api::APIBuilder;
+use webrtc::data_channel::data_channel_message::DataChannelMessage;
+use webrtc::data_channel::RTCDataChannel;
+use webrtc::ice_transport::ice_server::RTCIceServer;
+use webrtc::interceptor::registry::Registry;
+use webrtc::media::Sample;
+use webrtc::peer_connection::configuration::RTCConfiguration;
+use webrtc::peer_connection::peer_connection_state::RTCPeerConnectionState;
+use webrtc::peer_connection::sdp::session_description::RTCSessionDescription;
+use webrtc::peer_connection::RTCPeerConnection;
+use webrtc::rtcp::payload_feedbacks::picture_loss_indication::PictureLossIndication;
+use webrtc::rtp_transceiver::rtp_codec::{RTCRtpCodecCapability, RTPCodecType};
+use webrtc::track::track_local::track_local_static_sample::TrackLocalStaticSample;
+use webrtc::track::track_local::TrackLocal;
+
+use crate::api::cloudmatch::SessionInfo;
+use crate::input::{InputEncoder, InputEvent, RumbleCommand};
+use crate::media::audio::{AudioDecoder, AudioPlayer};
+use crate::media::history::FrameHistory;
+use crate::media::rtp::{DepacketizerCodec, RtpAudioDepacketizer, RtpDepacketizer, RtpReorderBuffer};
+use crate::media::{SharedFrame, StreamStats, VideoDecoder};
+use crate::settings::{MouseChannelMode, Settings, VideoCodec};
+
+/// First byte of the input channel handshake message from the server.
+const INPUT_HANDSHAKE_MAGIC: u8 = 0x0e;
+
+/// Negotiated connection details surfaced in the connection info panel
+/// (F3 overlay). Updated by `run_streaming` as facts become known.
+#[derive(Debug, Clone, Default)]
+pub struct ConnectionInfo {
+    /// Which input handshake was negotiated (e.g. "0x0e (legacy)").
+    pub input_protocol: Option<String>,
+    /// (label, reliability) of each data channel we opened.
+    pub data_channels: Vec<(String, String)>,
+    /// Selected ICE candidate pair: local/remote type, address family.
+    pub ice_candidate_pair: Option<String>,
+    pub dtls_cipher: Option<String>,
+    /// Negotiated codec and profile parsed from the answer SDP.
+    pub codec: Option<String>,
+    /// Where mouse packets currently go ("reliable" or "partially
+    /// reliable (8 ms)"), prefixed with "auto: " when loss-based
+    /// routing chose it.
+    pub mouse_route: Option<String>,
+    /// Send failures per channel — packets dropped client-side before
+    /// reaching the wire.
+    pub input_send_drops: u64,
+    pub mouse_send_drops: u64,
+}
+
+/// Mouse routing mode mirrored from `Settings` by the UI so toggling it
+/// mid-stream applies without restarting the session. Encoded as the
+/// `MouseChannelMode` discriminant.
+static MOUSE_CHANNEL_MODE: AtomicU8 = AtomicU8::new(0);
+
+/// Resolution of `MouseChannelMode::Auto`, updated by the stats tick
+/// from measured video RTP loss.
+static AUTO_PREFERS_RELIABLE: AtomicBool = AtomicBool::new(true);
+
+/// Auto routing stays on the reliable channel while loss over the last
+/// stats window is below this.
+const AUTO_RELIABLE_MAX_LOSS_PCT: f32 = 0.5;
+
+/// Adaptive bitrate: loss over a stats window at or above this steps
+/// the target down by a quarter.
+const ABR_LOSS_THRESHOLD_PCT: f32 = 2.0;
+/// Consecutive clean windows (loss under the Auto-routing threshold)
+/// before a recovery step of a tenth of the cap.
+const ABR_RECOVERY_WINDOWS: u32 = 5;
+/// The adaptive target never goes below this.
+const ABR_MIN_BITRATE_KBPS: u32 = 5_000;
+
+pub fn set_mouse_channel_mode(mode: MouseChannelMode) {
+    MOUSE_CHANNEL_MODE.store(mode as u8, Ordering::Relaxed);
+}
+
+fn mouse_channel_mode() -> MouseChannelMode {
+    match MOUSE_CHANNEL_MODE.load(Ordering::Relaxed) {
+        1 => MouseChannelMode::Reliable,
+        2 => MouseChannelMode::Auto,
+        _ => MouseChannelMode::PartiallyReliable,
+    }
+}
+
+#[derive(Debug)]
+pub enum WebRtcEvent {
+    Connected,
+    Disconnected,
+    /// Raw RTP packet from the video track.
+    VideoFrame(Vec<u8>),
+    /// Raw RTP packet from the audio track.
+    AudioFrame(Vec<u8>),
+}
+
+/// Build the nvst SDP attribute blob carrying stream configuration the
+/// rig reads out of the client's answer.
+pub fn build_nvst_sdp(settings: &Settings) -> String {
+    let (width, height) = settings.resolution;
+    let mut sdp = String::new();
+    sdp.push_str(&format!("a=video.clientViewportWd:{}\r\n", width));
+    sdp.push_str(&format!("a=video.clientViewportHt:{}\r\n", height));
+    sdp.push_str(&format!("a=video.maxFPS:{}\r\n", settings.fps));
+    sdp.push_str(&format!(
+        "a=video.initialBitrateKbps:{}\r\n",
+        settings.max_bitrate_mbps * 1000
+    ));
+    sdp.push_str(&format!(
+        "a=video.peakBitrateKbps:{}\r\n",
+        settings.max_bitrate_mbps * 1000
+    ));
+    if settings.hdr_enabled {
+        sdp.push_str("a=video.dx9EnableHdr:1\r\n");
+    }
+    sdp.push_str("a=audio.channelCount:2\r\n");
+    // Mic track; captured and sent when the user enables the
+    // microphone in settings.
+    sdp.push_str("m=mic 0 RTP/AVP 96\r\n");
+    sdp.push_str("a=msid:mic\r\n");
+    sdp
+}
+
+/// Wrapper around the RTCPeerConnection and the GFN data channels.
+pub struct WebRtcPeer {
+    pub connection: Arc<RTCPeerConnection>,
+    pub input_channel: Arc<RTCDataChannel>,
+    /// Partially-reliable channel for mouse deltas (8ms lifetime).
+    pub mouse_channel: Arc<RTCDataChannel>,
+    /// Upstream voice track; None unless the mic is enabled in
+    /// settings when the peer is created.
+    pub mic_track: Option<Arc<TrackLocalStaticSample>>,
+    video_ssrc: Arc<std::sync::atomic::AtomicU32>,
+}
+
+impl WebRtcPeer {
+    /// Create the peer, apply the remote offer, and return the local
+    /// answer SDP (with the nvst attributes appended).
+    pub async fn handle_offer(
+        offer_sdp: &str,
+        settings: &Settings,
+        event_tx: UnboundedSender<WebRtcEvent>,
+    ) -> Result<Self> {
+        let mut media_engine = MediaEngine::default();
+        media_engine.register_default_codecs()?;
+        let mut registry = Registry::new();
+        registry = register_default_interceptors(registry, &mut media_engine)?;
+        let api = APIBuilder::new()
+            .with_media_engine(media_engine)
+            .with_interceptor_registry(registry)
+            .build();
+
+        let mut ice_servers = Vec::new();
+        for stun in &settings.stun_servers {
+            if !(stun.starts_with("stun:") || stun.starts_with("stuns:")) {
+                log::warn!("Ignoring malformed STUN server entry '{}'", stun);
+                continue;
+            }
+            ice_servers.push(RTCIceServer {
+                urls: vec![stun.clone()],
+                ..Default::default()
+            });
+        }
+        let stun_count = ice_servers.len();
+        for turn in &settings.turn_servers {
+            if turn.url.is_empty() {
+                continue;
+            }
+            ice_servers.push(RTCIceServer {
+                urls: vec![turn.url.clone()],
+                username: turn.username.clone(),
+                credential: turn.credential.clone(),
+                ..Default::default()
+            });
+        }
+        if ice_servers.len() > stun_count {
+            log::info!(
+                "ICE: {} TURN relay server(s) configured",
+                ice_servers.len() - stun_count
+            );
+        }
+        let config = RTCConfiguration {
+            ice_servers,
+            ..Default::default()
+        };
+        let connection = Arc::new(api.new_peer_connection(config).await?);
+
+        let state_tx = event_tx.clone();
+        connection.on_peer_connection_state_change(Box::new(move |state| {
+            log::info!("Peer connection state: {}", state);
+            let event = match state {
+                RTCPeerConnectionState::Connected => Some(WebRtcEvent::Connected),
+                RTCPeerConnectionState::Disconnected | RTCPeerConnectionState::Failed => {
+                    Some(WebRtcEvent::Disconnected)
+                }
+                _ => None,
+            };
+            if let Some(event) = event {
+                let _ = state_tx.send(event);
+            }
+            Box::pin(async {})
+        }));
+
+        // The upstream voice track must exist before the answer is
+        // created so its m-line makes it into the SDP.
+        let mic_track = if settings.mic_enabled {
+            let track = Arc::new(TrackLocalStaticSample::new(
+                RTCRtpCodecCapability {
+                    mime_type: MIME_TYPE_OPUS.to_owned(),
+                    clock_rate: 48000,
+                    channels: 1,
+                    ..Default::default()
+                },
+                "mic".to_owned(),
+                "mic".to_owned(),
+            ));
+            connection
+                .add_track(track.clone() as Arc<dyn TrackLocal + Send + Sync>)
+                .await?;
+            Some(track)
+        } else {
+            None
+        };
+
+        let video_ssrc = Arc::new(std::sync::atomic::AtomicU32::new(0));
+        let peer = Self {
+            connection: connection.clone(),
+            input_channel: create_data_channel(&connection, "input_channel_v1", true).await?,
+            mouse_channel: create_data_channel(&connection, "mouse_channel_v1", false).await?,
+            mic_track,
+            video_ssrc: video_ssrc.clone(),
+        };
+
+        let track_tx = event_tx.clone();
+        let ssrc_writer = video_ssrc.clone();
+        connection.on_track(Box::new(move |track, _receiver, _transceiver| {
+            let tx = track_tx.clone();
+            let kind = track.kind();
+            if kind == RTPCodecType::Video {
+                ssrc_writer.store(track.ssrc(), Ordering::SeqCst);
+            }
+            Box::pin(async move {
+                log::info!("Track opened: {} ({})", track.id(), kind);
+                // Dev-only impairment between the RTP read and the
+                // depacketizer; one instance per track so burst/reorder
+                // state stays per-stream.
+                #[cfg(feature = "netsim")]
+                let mut netsim = netsim::Netsim::from_config_file();
+                let mut buf = vec![0u8; 1500];
+                'read: loop {
+                    match track.read(&mut buf).await {
+                        Ok((n, _attrs)) => {
+                            let packet = buf[..n.marshal_size()].to_vec();
+                            #[cfg(feature = "netsim")]
+                            let packets = netsim.impair(packet).await;
+                            #[cfg(not(feature = "netsim"))]
+                            let packets = std::iter::once(packet);
+                            for packet in packets {
+                                let event = match kind {
+                                    RTPCodecType::Video => WebRtcEvent::VideoFrame(packet),
+                                    RTPCodecType::Audio => WebRtcEvent::AudioFrame(packet),
+                                    _ => continue,
+                                };
+                                if tx.send(event).is_err() {
+                                    break 'read;
+                                }
+                            }
+                        }
+                        Err(_) => break,
+                    }
+                }
+            })
+        }));
+
+        let offer = RTCSessionDescription::offer(offer_sdp.to_string())?;
+        connection.set_remote_description(offer).await?;
+        let answer = connection.create_answer(None).await?;
+        connection.set_local_description(answer).await?;
+        Ok(peer)
+    }
+
+    pub async fn local_answer_sdp(&self, settings: &Settings) -> Result<String> {
+        let description = self
+            .connection
+            .local_description()
+            .await
+            .ok_or_else(|| anyhow!("No local description set"))?;
+        Ok(format!("{}{}", description.sdp, build_nvst_sdp(settings)))
+    }
+
+    /// Send on the reliable input channel.
+    pub async fn send_input(&self, packet: &[u8]) -> Result<()> {
+        self.input_channel
+            .send(&bytes::Bytes::copy_from_slice(packet))
+            .await
+            .context("input channel send failed")?;
+        Ok(())
+    }
+
+    /// Send on the partially-reliable mouse channel.
+    pub async fn send_mouse_input(&self, packet: &[u8]) -> Result<()> {
+        self.mouse_channel
+            .send(&bytes::Bytes::copy_from_slice(packet))
+            .await
+            .context("mouse channel send failed")?;
+        Ok(())
+    }
+
+    /// DRC hint: tell the rig the client viewport changed so the encode
+    /// follows the window size. The server's result comes back on the
+    /// input channel and is logged there.
+    pub async fn send_viewport_update(&self, width: u32, height: u32) -> Result<()> {
+        let message = serde_json::json!({
+            "type": "viewportUpdate",
+            "wd": width,
+            "ht": height,
+        });
+        self.input_channel
+            .send_text(message.to_string())
+            .await
+            .context("viewport update send failed")?;
+        log::info!("Sent viewport update {}x{}", width, height);
+        Ok(())
+    }
+
+    /// Runtime bitrate retarget: same control path as the viewport
+    /// updates, no renegotiation. Driven by the adaptive-bitrate loop
+    /// in the streaming runner.
+    pub async fn send_bitrate_update(&self, kbps: u32) -> Result<()> {
+        let message = serde_json::json!({
+            "type": "bitrateUpdate",
+            "maxBitrateKbps": kbps,
+        });
+        self.input_channel
+            .send_text(message.to_string())
+            .await
+            .context("bitrate update send failed")?;
+        Ok(())
+    }
+
+    /// Ask the encoder for an IDR via PLI.
+    pub async fn request_keyframe(&self) -> Result<()> {
+        let ssrc = self.video_ssrc.load(Ordering::SeqCst);
+        self.connection
+            .write_rtcp(&[Box::new(PictureLossIndication {
+                sender_ssrc: 0,
+                media_ssrc: ssrc,
+            })])
+            .await
+            .context("PLI send failed")?;
+        Ok(())
+    }
+
+    /// Fill transport details that are only known once connected: the
+    /// selected ICE candidate pair and the DTLS cipher.
+    pub async fn fill_transport_details(&self, info: &std::sync::Mutex<ConnectionInfo>) {
+        let transport = self.connection.sctp().transport();
+        let pair = transport
+            .ice_transport()
+            .get_selected_candidate_pair()
+            .await;
+        let mut info = info.lock().unwrap();
+        if let Some(pair) = pair {
+            let family = |addr: &str| if addr.contains(':') { "IPv6" } else { "IPv4" };
+            info.ice_candidate_pair = Some(format!(
+                "{} {} -> {} {}",
+                pair.local.typ,
+                family(&pair.local.address),
+                pair.remote.typ,
+                family(&pair.remote.address),
+            ));
+        }
+        // webrtc-rs doesn't expose the negotiated cipher suite directly;
+        // the transport state is the closest user-visible signal.
+        info.dtls_cipher = Some(format!("{:?}", transport.state()));
+    }
+
+    /// RTT of the nominated ICE candidate pair in milliseconds, or None
+    /// before the first STUN binding response has been measured.
+    pub async fn current_rtt_ms(&self) -> Option<f32> {
+        let report = self.connection.get_stats().await;
+        report.reports.values().find_map(|entry| match entry {
+            webrtc::stats::StatsReportType::CandidatePair(pair) if pair.nominated => {
+                Some((pair.current_round_trip_time * 1000.0) as f32)
+            }
+            _ => None,
+        })
+    }
+
+    pub async fn close(&self) {
+        let _ = self.connection.close().await;
+    }
+}
+
+/// Describe the codec/profile the offer actually carries for the codec
+/// we requested (e.g. "H.264 profile-level-id=42e01f").
+fn negotiated_codec_description(offer_sdp: &str, requested: VideoCodec) -> String {
+    let token = match requested {
+        VideoCodec::H264 => "H264",
+        VideoCodec::H265 => "H265",
+        VideoCodec::AV1 => "AV1",
+    };
+    let profile = offer_sdp
+        .lines()
+        .skip_while(|line| !(line.starts_with("a=rtpmap:") && line.contains(token)))
+        .find(|line| line.starts_with("a=fmtp:"))
+        .and_then(|line| {
+            line.split(';')
+                .find(|part| part.contains("profile-level-id="))
+                .map(|part| part.trim().to_string())
+        });
+    match profile {
+        Some(profile) => format!("{} ({})", requested.display_name(), profile),
+        None => requested.display_name().to_string(),
+    }
+}
+
+/// Out-of-band parameter sets from the offer SDP as an Annex-B blob:
+/// H.264 `sprop-parameter-sets` (SPS,PPS), H.265 `sprop-vps`/`sprop-sps`/
+/// `sprop-pps`. Priming the decoder with these before the first packet
+/// means the first IDR can decode immediately instead of waiting for
+/// in-band parameter sets.
+fn parse_sprop_parameter_sets(sdp: &str, codec: VideoCodec) -> Option<Vec<u8>> {
+    let mut blob = Vec::new();
+    let mut push_set = |b64: &str| {
+        if let Some(nal) = decode_base64(b64.trim()) {
+            if !nal.is_empty() {
+                blob.extend_from_slice(&[0, 0, 0, 1]);
+                blob.extend_from_slice(&nal);
+            }
+        }
+    };
+    for line in sdp.lines() {
+        let line = line.trim();
+        if !line.starts_with("a=fmtp:") {
+            continue;
+        }
+        // Drop the "a=fmtp:<pt>" prefix so the first parameter parses too.
+        let params = line.split_once(' ').map(|(_, p)| p).unwrap_or(line);
+        for param in params.split(';') {
+            let param = param.trim();
+            match codec {
+                VideoCodec::H264 => {
+                    if let Some(value) = param.strip_prefix("sprop-parameter-sets=") {
+                        for set in value.split(',') {
+                            push_set(set);
+                        }
+                    }
+                }
+                VideoCodec::H265 => {
+                    for key in ["sprop-vps=", "sprop-sps=", "sprop-pps="] {
+                        if let Some(value) = param.strip_prefix(key) {
+                            for set in value.split(',') {
+                                push_set(set);
+                            }
+                        }
+                    }
+                }
+                VideoCodec::AV1 => {}
+            }
+        }
+    }
+    (!blob.is_empty()).then_some(blob)
+}
+
+/// Minimal base64 decode (standard alphabet, `=` padding) for the couple
+/// of sprop attributes read out of SDP; not worth a dependency.
+fn decode_base64(input: &str) -> Option<Vec<u8>> {
+    let mut out = Vec::with_capacity(input.len() * 3 / 4);
+    let mut acc: u32 = 0;
+    let mut bits = 0u32;
+    for byte in input.bytes() {
+        let value = match byte {
+            b'A'..=b'Z' => byte - b'A',
+            b'a'..=b'z' => byte - b'a' + 26,
+            b'0'..=b'9' => byte - b'0' + 52,
+            b'+' => 62,
+            b'/' => 63,
+            b'=' => break,
+            _ => return None,
+        };
+        acc = (acc << 6) | value as u32;
+        bits += 6;
+        if bits >= 8 {
+            bits -= 8;
+            out.push((acc >> bits) as u8);
+        }
+    }
+    Some(out)
+}
+
+async fn create_data_channel(
+    connection: &Arc<RTCPeerConnection>,
+    label: &str,
+    reliable: bool,
+) -> Result<Arc<RTCDataChannel>> {
+    use webrtc::data_channel::data_channel_init::RTCDataChannelInit;
+    let init = if reliable {
+        RTCDataChannelInit {
+            ordered: Some(true),
+            ..Default::default()
+        }
+    } else {
+        RTCDataChannelInit {
+            ordered: Some(false),
+            max_packet_life_time: Some(8),
+            ..Default::default()
+        }
+    };
+    Ok(connection.create_data_channel(label, Some(init)).await?)
+}
+
+/// Record a client-side send failure against the channel it targeted.
+fn note_send_drop(info: &Arc<std::sync::Mutex<ConnectionInfo>>, via_mouse_channel: bool) {
+    let mut info = info.lock().unwrap();
+    if via_mouse_channel {
+        info.mouse_send_drops += 1;
+    } else {
+        info.input_send_drops += 1;
+    }
+}
+
+/// Run a full streaming session: signaling, peer setup, decode loop and
+/// input forwarding. Returns when the stream ends or errors.
+pub async fn run_streaming(
+    session: SessionInfo,
+    settings: Settings,
+    shared_frame: SharedFrame,
+    stats: Arc<std::sync::Mutex<StreamStats>>,
+    connection_info: Arc<std::sync::Mutex<ConnectionInfo>>,
+    frame_history: Arc<std::sync::Mutex<FrameHistory>>,
+    mut input_event_rx: UnboundedReceiver<InputEvent>,
+    rumble_tx: UnboundedSender<RumbleCommand>,
+    stop: Arc<AtomicBool>,
+) -> Result<()> {
+    // Seed the live mouse routing from the persisted setting; the UI
+    // updates the static directly when the user changes it mid-stream.
+    set_mouse_channel_mode(settings.mouse_channel_mode);
+    let signal_url = session
+        .signal_connection_url
+        .clone()
+        .ok_or_else(|| anyhow!("Session has no signaling URL"))?;
+    let mut signaling = SignalingClient::connect(&signal_url)
+        .await
+        .context("Signaling connect failed")?;
+
+    let offer = signaling.wait_for_offer().await?;
+    let (event_tx, mut event_rx) = mpsc::unbounded_channel();
+    let peer = Arc::new(WebRtcPeer::handle_offer(&offer, &settings, event_tx).await?);
+    let answer = peer.local_answer_sdp(&settings).await?;
+    signaling.send_answer(&answer).await?;
+
+    {
+        let mut info = connection_info.lock().unwrap();
+        *info = ConnectionInfo {
+            data_channels: vec![
+                ("input_channel_v1".to_string(), "reliable, ordered".to_string()),
+                (
+                    "mouse_channel_v1".to_string(),
+                    "unordered, max lifetime 8 ms".to_string(),
+                ),
+            ],
+            codec: Some(negotiated_codec_description(&offer, settings.codec)),
+            ..ConnectionInfo::default()
+        };
+    }
+
+    // The input channel stays quiet until the server's handshake
+    // arrives; the flag gates the forwarding task.
+    let handshake_done = Arc::new(AtomicBool::new(false));
+    let handshake_flag = handshake_done.clone();
+    let handshake_peer = peer.clone();
+    let handshake_info = connection_info.clone();
+    let handshake_rumble = rumble_tx.clone();
+    peer.input_channel
+        .on_message(Box::new(move |message: DataChannelMessage| {
+            let flag = handshake_flag.clone();
+            let peer = handshake_peer.clone();
+            let info = handshake_info.clone();
+            let rumble = handshake_rumble.clone();
+            Box::pin(async move {
+                let data = message.data.as_ref();
+                if !data.is_empty() && data[0] == INPUT_HANDSHAKE_MAGIC {
+                    // Echo the handshake back to open the input path.
+                    let _ = peer.send_input(data).await;
+                    flag.store(true, Ordering::SeqCst);
+                    info.lock().unwrap().input_protocol = Some("0x0e (legacy)".to_string());
+                    log::info!("Input channel handshake complete");
+                } else if let Some(command) = RumbleCommand::parse(data) {
+                    // The UI thread owns the gamepads; it applies (or
+                    // drops, with vibration disabled) on its next frame.
+                    let _ = rumble.send(command);
+                } else if message.is_string {
+                    // Control replies (DRC/viewport acknowledgments etc.).
+                    let text = String::from_utf8_lossy(data);
+                    if text.contains("viewport") {
+                        log::info!("Viewport update result: {}", text);
+                    } else {
+                        log::debug!("Input channel message: {}", text);
+                    }
+                }
+            })
+        }));
+
+    // Input forwarding task: mouse deltas go to whichever channel the
+    // mouse-channel mode selects (re-read per event, so toggling the
+    // setting applies live), everything else reliable.
+    let input_peer = peer.clone();
+    let input_gate = handshake_done.clone();
+    let input_stop = stop.clone();
+    let input_info = connection_info.clone();
+    let input_task = tokio::spawn(async move {
+        #[cfg(feature = "netsim")]
+        let mut netsim = netsim::Netsim::from_config_file();
+        let mut published_route: Option<&'static str> = None;
+        while let Some(event) = input_event_rx.recv().await {
+            if input_stop.load(Ordering::SeqCst) {
+                break;
+            }
+            if !input_gate.load(Ordering::SeqCst) {
+                continue;
+            }
+            let mode = mouse_channel_mode();
+            let mouse_reliable = match mode {
+                MouseChannelMode::PartiallyReliable => false,
+                MouseChannelMode::Reliable => true,
+                MouseChannelMode::Auto => AUTO_PREFERS_RELIABLE.load(Ordering::Relaxed),
+            };
+            let via_mouse_channel =
+                matches!(event, InputEvent::MouseMove { .. }) && !mouse_reliable;
+            if matches!(event, InputEvent::MouseMove { .. }) {
+                let route = match (mode, mouse_reliable) {
+                    (MouseChannelMode::Auto, true) => "auto: reliable",
+                    (MouseChannelMode::Auto, false) => "auto: partially reliable (8 ms)",
+                    (_, true) => "reliable",
+                    (_, false) => "partially reliable (8 ms)",
+                };
+                if published_route != Some(route) {
+                    input_info.lock().unwrap().mouse_route = Some(route.to_string());
+                    published_route = Some(route);
+                    log::info!("Mouse packets routed via {}", route);
+                }
+            }
+            // Optional dev-only impairment of the encoded input path;
+            // control messages (viewport updates) are left alone.
+            #[cfg(feature = "netsim")]
+            if netsim.impairs_input()
+                && !matches!(event, InputEvent::ViewportResize { .. })
+            {
+                let packets = netsim.impair(InputEncoder::encode(&event)).await;
+                for packet in packets {
+                    let result = if via_mouse_channel {
+                        input_peer.send_mouse_input(&packet).await
+                    } else {
+                        input_peer.send_input(&packet).await
+                    };
+                    if let Err(e) = result {
+                        log::warn!("Input send failed: {}", e);
+                        note_send_drop(&input_info, via_mouse_channel);
+                    }
+                }
+                continue;
+            }
+            let result = match event {
+                InputEvent::ViewportResize { width, height } => {
+                    input_peer.send_viewport_update(width, height).await
+                }
+                InputEvent::MouseMove { .. } if via_mouse_channel => {
+                    input_peer
+                        .send_mouse_input(&InputEncoder::encode(&event))
+                        .await
+                }
+                _ => input_peer.send_input(&InputEncoder::encode(&event)).await,
+            };
+            if let Err(e) = result {
+                log::warn!("Input send failed: {}", e);
+                note_send_drop(&input_info, via_mouse_channel);
+            }
+        }
+    });
+
+    // Buffered-amount poll: the 1 s stats tick is far too coarse for
+    // input throttling, so a dedicated task samples both channels every
+    // 10 ms and publishes the counts for the frame loop to translate
+    // into a queue-depth estimate.
+    const BUFFERED_POLL_INTERVAL: std::time::Duration = std::time::Duration::from_millis(10);
+    let buffered_peer = peer.clone();
+    let buffered_stats = stats.clone();
+    let buffered_stop = stop.clone();
+    let buffered_task = tokio::spawn(async move {
+        while !buffered_stop.load(Ordering::SeqCst) {
+            let input = buffered_peer.input_channel.buffered_amount().await;
+            let mouse = buffered_peer.mouse_channel.buffered_amount().await;
+            {
+                let mut s = buffered_stats.lock().unwrap();
+                s.input_buffered_bytes = input;
+                s.mouse_buffered_bytes = mouse;
+            }
+            tokio::time::sleep(BUFFERED_POLL_INTERVAL).await;
+        }
+    });
+
+    let mut depacketizer = match settings.codec {
+        VideoCodec::H264 => RtpDepacketizer::new(DepacketizerCodec::H264),
+        VideoCodec::H265 => RtpDepacketizer::new(DepacketizerCodec::H265),
+        VideoCodec::AV1 => RtpDepacketizer::new(DepacketizerCodec::AV1),
+    };
+    // In-order delivery ahead of the depacketizer; a few ms of holding
+    // keeps Wi-Fi reordering from reading as corruption.
+    let mut reorder_buffer = RtpReorderBuffer::new(settings.low_latency_mode);
+    let mut video_decoder = VideoDecoder::new(settings.codec, settings.hdr_enabled)?;
+    // Prime the decoder with out-of-band parameter sets from the offer so
+    // the first IDR decodes without waiting for in-band SPS/PPS.
+    if let Some(parameter_sets) = parse_sprop_parameter_sets(&offer, settings.codec) {
+        match video_decoder.decode(&parameter_sets) {
+            Ok(_) => log::info!("Decoder primed with out-of-band parameter sets"),
+            Err(e) => log::debug!("Parameter set priming failed: {}", e),
+        }
+    }
+    let mut audio_decoder = AudioDecoder::new(48000, 2);
+    let mut audio_depacketizer = RtpAudioDepacketizer::new();
+
+    // Audio playback thread: cpal wants its own thread, fed over a
+    // channel from the streaming loop. A watchdog recreates the player
+    // when the cpal stream dies silently (callback heartbeat goes stale
+    // while samples are queued). The thread also owns A/V offset
+    // handling: positive delays pad the audio queue, negative ones hold
+    // video back via the shared frame slot.
+    let (audio_tx, audio_rx) = std::sync::mpsc::sync_channel::<Vec<i16>>(64);
+    // Seed the live device selection from the persisted setting, like
+    // the mouse routing above; the settings modal updates it mid-stream.
+    crate::media::audio::set_output_device(settings.audio_output_device.clone());
+    crate::media::audio::set_playback_volume(settings.volume, settings.muted);
+    let audio_stats = stats.clone();
+    let audio_delay_by_device = settings.audio_delay_by_device.clone();
+    let audio_shared_frame = shared_frame.clone();
+    std::thread::Builder::new()
+        .name("audio-playback".to_string())
+        .spawn(move || {
+            const AUDIO_STALL_THRESHOLD: std::time::Duration = std::time::Duration::from_secs(2);
+            const DELAY_POLL: std::time::Duration = std::time::Duration::from_millis(250);
+            let mut last_device: Option<String> = None;
+            let mut adopt_device_delay = |p: &AudioPlayer| {
+                // Same device recreated by the watchdog: keep whatever
+                // the user has live-tuned instead of reverting.
+                if last_device.as_deref() == Some(p.device_name.as_str()) {
+                    return;
+                }
+                let delay = audio_delay_by_device
+                    .get(&p.device_name)
+                    .copied()
+                    .unwrap_or(0);
+                crate::media::audio::set_audio_delay_ms(delay);
+                if delay != 0 {
+                    log::info!("Audio delay for '{}': {}ms", p.device_name, delay);
+                }
+                last_device = Some(p.device_name.clone());
+            };
+            // The device request in effect when the player was opened;
+            // compared against the live setting so a mid-session change
+            // reopens the stream (even when the request fell back to
+            // the default because the device was missing).
+            let mut opened_request = crate::media::audio::requested_output_device();
+            let mut player = match AudioPlayer::new(48000, 2) {
+                Ok(player) => {
+                    adopt_device_delay(&player);
+                    Some(player)
+                }
+                Err(e) => {
+                    log::error!("Audio playback unavailable: {}", e);
+                    None
+                }
+            };
+            let mut had_player = player.is_some();
+            loop {
+                // recv_timeout rather than recv so delay changes and
+                // sync-test clicks still apply while no samples arrive.
+                let samples = match audio_rx.recv_timeout(DELAY_POLL) {
+                    Ok(samples) => Some(samples),
+                    Err(std::sync::mpsc::RecvTimeoutError::Timeout) => None,
+                    Err(std::sync::mpsc::RecvTimeoutError::Disconnected) => break,
+                };
+                let requested = crate::media::audio::requested_output_device();
+                if requested != opened_request {
+                    log::info!(
+                        "Audio output device changed to {}; reopening",
+                        requested.as_deref().unwrap_or("the system default")
+                    );
+                    opened_request = requested;
+                    player = None;
+                }
+                if let Some(p) = &player {
+                    if p.is_stalled(AUDIO_STALL_THRESHOLD) {
+                        log::warn!(
+                            "Audio stream on '{}' stopped consuming samples; recreating",
+                            p.device_name
+                        );
+                        player = None;
+                    }
+                }
+                if player.is_none() {
+                    match AudioPlayer::new(48000, 2) {
+                        Ok(p) => {
+                            if had_player {
+                                audio_stats.lock().unwrap().audio_restarts += 1;
+                                log::info!("Audio restarted on '{}'", p.device_name);
+                            }
+                            had_player = true;
+                            adopt_device_delay(&p);
+                            player = Some(p);
+                        }
+                        Err(e) => {
+                            log::error!("Audio restart failed: {}", e);
+                            continue;
+                        }
+                    }
+                }
+                let delay = crate::media::audio::audio_delay_ms();
+                audio_shared_frame.set_presentation_delay(std::time::Duration::from_millis(
+                    (-delay).clamp(0, 200) as u64,
+                ));
+                if let Some(p) = &mut player {
+                    p.set_delay_ms(delay);
+                    if crate::media::audio::take_test_click() {
+                        p.push_test_click();
+                    }
+                    if let Some(mut samples) = samples {
+                        crate::media::audio::apply_gain(
+                            &mut samples,
+                            crate::media::audio::playback_gain(),
+                        );
+                        p.push_samples(&samples);
+                    }
+                }
+            }
+            audio_shared_frame.set_presentation_delay(std::time::Duration::ZERO);
+        })?;
+
+    // Microphone: capture runs on its own thread (cpal streams are not
+    // Send), encoded packets come back over a channel, and a small task
+    // writes them onto the negotiated mic track. The transmit gate is
+    // enforced inside the capture callback, so with push-to-talk
+    // released nothing is even encoded.
+    if let Some(mic_track) = peer.mic_track.clone() {
+        let (packet_tx, mut packet_rx) = mpsc::unbounded_channel::<Vec<u8>>();
+        let mic_device = settings.mic_input_device.clone();
+        let mic_stop = stop.clone();
+        std::thread::Builder::new()
+            .name("mic-capture".to_string())
+            .spawn(move || {
+                let _capture =
+                    match crate::media::mic::MicCapture::start(mic_device.as_deref(), packet_tx) {
+                        Ok(capture) => {
+                            log::info!("Microphone capturing on '{}'", capture.device_name);
+                            capture
+                        }
+                        Err(e) => {
+                            log::error!("Microphone unavailable: {}", e);
+                            return;
+                        }
+                    };
+                // Dropping the capture (and with it the packet sender)
+                // ends the writer task below.
+                while !mic_stop.load(Ordering::SeqCst) {
+                    std::thread::sleep(std::time::Duration::from_millis(250));
+                }
+            })?;
+        tokio::spawn(async move {
+            while let Some(packet) = packet_rx.recv().await {
+                let sample = Sample {
+                    data: packet.into(),
+                    duration: std::time::Duration::from_millis(20),
+                    ..Default::default()
+                };
+                if mic_track.write_sample(&sample).await.is_err() {
+                    break;
+                }
+            }
+        });
+    }
+
+    let mut bytes_received: u64 = 0;
+    let mut frames_decoded: u64 = 0;
+    let mut last_stats = std::time::Instant::now();
+    let mut frames_since_stats: u32 = 0;
+    // (received, lost) totals at the last stats tick, for windowed loss.
+    let mut last_loss_window = (0u64, 0u64);
+
+    // First-frame bring-up: request an IDR as soon as video RTP starts
+    // flowing and keep asking every 500ms until one arrives, instead of
+    // sitting in black until the encoder's natural keyframe cadence.
+    const KEYFRAME_RETRY_INTERVAL: std::time::Duration = std::time::Duration::from_millis(500);
+    let stream_start = std::time::Instant::now();
+    let mut keyframe_seen = false;
+    let mut next_keyframe_request: Option<std::time::Instant> = None;
+
+    // Decode-error recovery: PLI requests after errors are rate-limited
+    // (heavy loss produces a burst of failed access units, and a PLI per
+    // failure just floods the encoder), and corruption that persists
+    // across several consecutive failures escalates to recreating the
+    // decoder — hardware sessions can wedge in a state no IDR fixes.
+    const RECOVERY_PLI_INTERVAL: std::time::Duration = std::time::Duration::from_millis(250);
+    const RECOVERY_RESET_THRESHOLD: u32 = 8;
+    let mut consecutive_decode_failures = 0u32;
+    let mut last_recovery_pli: Option<std::time::Instant> = None;
+    let mut keyframe_recoveries = 0u32;
+    let mut decoder_resets = 0u32;
+
+    // A peer that never reaches Connected is almost always a blocked UDP
+    // path (firewall, strict NAT); fail with a recognizable error
+    // instead of sitting on a black screen forever. The message text is
+    // matched upstream to offer the Windows firewall fix.
+    const ICE_CONNECT_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(20);
+    let connect_deadline = tokio::time::Instant::now() + ICE_CONNECT_TIMEOUT;
+    // Halfway through the connect window, say whether TURN relays are
+    // in play — on restrictive networks the relayed pair is the one
+    // that finally comes up (or the setting the user is missing).
+    let relay_checkpoint = tokio::time::Instant::now() + ICE_CONNECT_TIMEOUT / 2;
+    let mut relay_logged = false;
+    let mut connected = false;
+
+    // Adaptive bitrate state (AIMD): multiplicative cuts when a stats
+    // window shows loss, slow additive recovery toward the cap.
+    let bitrate_cap_kbps = settings.max_bitrate_mbps * 1000;
+    let mut target_bitrate_kbps = bitrate_cap_kbps;
+    let mut clean_windows = 0u32;
+
+    log::info!("Streaming loop started for session {}", session.session_id);
+    // Set when the transport dropped out from under us (as opposed to a
+    // requested stop); the session is usually still alive server-side,
+    // so the app keeps the SessionInfo and reconnects against it.
+    let mut interrupted = false;
+    'streaming: loop {
+        if stop.load(Ordering::SeqCst) {
+            break;
+        }
+        let event = tokio::select! {
+            event = event_rx.recv() => match event {
+                Some(event) => event,
+                None => break,
+            },
+            signal = signaling.next_event() => {
+                if matches!(signal, Ok(SignalingEvent::Disconnected)) {
+                    log::warn!("Signaling disconnected");
+                    interrupted = true;
+                    break;
+                }
+                continue;
+            }
+            _ = tokio::time::sleep_until(relay_checkpoint), if !connected && !relay_logged => {
+                relay_logged = true;
+                if settings.turn_servers.is_empty() {
+                    log::warn!(
+                        "ICE: no candidate pair yet and no TURN relays configured; \
+                         symmetric NAT / CGNAT cannot connect without one"
+                    );
+                } else {
+                    log::info!("ICE: no direct pair yet — attempting TURN relay candidates");
+                }
+                continue;
+            }
+            _ = tokio::time::sleep_until(connect_deadline), if !connected => {
+                input_task.abort();
+                buffered_task.abort();
+                peer.close().await;
+                return Err(anyhow!(
+                    "ICE connection timed out — no media path to the server came up"
+                ));
+            }
+        };
+        match event {
+            WebRtcEvent::Connected => {
+                log::info!("WebRTC connected");
+                connected = true;
+                peer.fill_transport_details(&connection_info).await;
+            }
+            WebRtcEvent::Disconnected => {
+                log::warn!("WebRTC disconnected");
+                interrupted = true;
+                break;
+            }
+            WebRtcEvent::VideoFrame(rtp_data) => {
+                bytes_received += rtp_data.len() as u64;
+                // Packets go through the reorder buffer; one arrival
+                // can release several held packets at once.
+                reorder_buffer.push(rtp_data);
+                while let Some(rtp_data) = reorder_buffer.pop_ready() {
+                    if !keyframe_seen {
+                        let now = std::time::Instant::now();
+                        if next_keyframe_request.is_none_or(|at| now >= at) {
+                            let _ = peer.request_keyframe().await;
+                            next_keyframe_request = Some(now + KEYFRAME_RETRY_INTERVAL);
+                        }
+                    }
+                    let Some(access_unit) = depacketizer.process(&rtp_data) else {
+                        continue;
+                    };
+                    if !keyframe_seen && depacketizer.access_unit_has_keyframe(&access_unit) {
+                        keyframe_seen = true;
+                    }
+                    let decode_start = std::time::Instant::now();
+                    match video_decoder.decode(&access_unit) {
+                        Ok(Some(frame)) => {
+                            consecutive_decode_failures = 0;
+                            frames_decoded += 1;
+                            frames_since_stats += 1;
+                            let decode_ms = decode_start.elapsed().as_secs_f32() * 1000.0;
+                            {
+                                let mut s = stats.lock().unwrap();
+                                s.frames_decoded = frames_decoded;
+                                s.latency_ms = decode_ms;
+                                s.resolution = (frame.width, frame.height);
+                                s.codec = Some(settings.codec);
+                                if frames_decoded == 1 {
+                                    let ttff = stream_start.elapsed().as_secs_f32() * 1000.0;
+                                    s.time_to_first_frame_ms = ttff;
+                                    log::info!("First frame {:.0} ms after stream start", ttff);
+                                }
+                            }
+                            if settings.frame_history_enabled {
+                                frame_history.lock().unwrap().capture(
+                                    &frame,
+                                    decode_ms,
+                                    depacketizer.packets_lost,
+                                );
+                            }
+                            shared_frame.write(frame);
+                        }
+                        Ok(None) => {}
+                        Err(e) => {
+                            // Errors while resyncing to an IDR (fresh
+                            // decoder, bring-up) don't count toward
+                            // escalation; only an established stream
+                            // that keeps failing does.
+                            if keyframe_seen {
+                                consecutive_decode_failures += 1;
+                            }
+                            if consecutive_decode_failures >= RECOVERY_RESET_THRESHOLD {
+                                log::warn!(
+                                    "Decode error: {}; {} consecutive failures — recreating decoder",
+                                    e,
+                                    consecutive_decode_failures
+                                );
+                                match VideoDecoder::new(settings.codec, settings.hdr_enabled) {
+                                    Ok(fresh) => video_decoder = fresh,
+                                    Err(err) => {
+                                        // No decoder at all is a dead
+                                        // stream; let the reconnect
+                                        // path rebuild everything.
+                                        log::error!("Decoder recreate failed: {}", err);
+                                        interrupted = true;
+                                        break 'streaming;
+                                    }
+                                }
+                                depacketizer.reset();
+                                reorder_buffer.reset();
+                                decoder_resets += 1;
+                                consecutive_decode_failures = 0;
+                                // Re-arm the first-frame bring-up loop:
+                                // keep PLIs flowing until the fresh
+                                // decoder sees an IDR.
+                                keyframe_seen = false;
+                                next_keyframe_request = None;
+                            }
+                            let now = std::time::Instant::now();
+                            if last_recovery_pli
+                                .is_none_or(|at| now.duration_since(at) >= RECOVERY_PLI_INTERVAL)
+                            {
+                                log::warn!("Decode error: {}; requesting keyframe", e);
+                                let _ = peer.request_keyframe().await;
+                                last_recovery_pli = Some(now);
+                                keyframe_recoveries += 1;
+                            }
+                        }
+                    }
+                }
+            }
+            WebRtcEvent::AudioFrame(rtp_data) => {
+                if let Some(payload) = audio_depacketizer.process(&rtp_data) {
+                    let samples =
+                        audio_decoder.decode(payload, audio_depacketizer.lost_before_last());
+                    if !samples.is_empty() {
+                        let _ = audio_tx.try_send(samples);
+                    }
+                }
+            }
+        }
+        if last_stats.elapsed().as_secs_f32() >= 1.0 {
+            let elapsed = last_stats.elapsed().as_secs_f32();
+            let rtt_ms = peer.current_rtt_ms().await;
+            // Resolve Auto mouse routing from loss over this window; the
+            // video RTP stream is the best live proxy for path loss.
+            let window_received = depacketizer
+                .packets_received
+                .saturating_sub(last_loss_window.0);
+            let window_lost = depacketizer.packets_lost.saturating_sub(last_loss_window.1);
+            last_loss_window = (depacketizer.packets_received, depacketizer.packets_lost);
+            let mut window_loss_pct = None;
+            if window_received + window_lost > 0 {
+                let loss_pct =
+                    window_lost as f32 * 100.0 / (window_received + window_lost) as f32;
+                AUTO_PREFERS_RELIABLE
+                    .store(loss_pct < AUTO_RELIABLE_MAX_LOSS_PCT, Ordering::Relaxed);
+                window_loss_pct = Some(loss_pct);
+            }
+            if settings.adaptive_bitrate {
+                let mut new_target = target_bitrate_kbps;
+                match window_loss_pct {
+                    Some(loss) if loss >= ABR_LOSS_THRESHOLD_PCT => {
+                        clean_windows = 0;
+                        new_target =
+                            (target_bitrate_kbps * 3 / 4).max(ABR_MIN_BITRATE_KBPS);
+                    }
+                    Some(loss) if loss < AUTO_RELIABLE_MAX_LOSS_PCT => {
+                        clean_windows += 1;
+                        if clean_windows >= ABR_RECOVERY_WINDOWS
+                            && target_bitrate_kbps < bitrate_cap_kbps
+                        {
+                            clean_windows = 0;
+                            new_target = (target_bitrate_kbps + bitrate_cap_kbps / 10)
+                                .min(bitrate_cap_kbps);
+                        }
+                    }
+                    _ => {}
+                }
+                if new_target != target_bitrate_kbps {
+                    target_bitrate_kbps = new_target;
+                    log::info!(
+                        "Adaptive bitrate: {} kbps (cap {} kbps)",
+                        target_bitrate_kbps,
+                        bitrate_cap_kbps
+                    );
+                    if let Err(e) = peer.send_bitrate_update(target_bitrate_kbps).await {
+                        log::debug!("Bitrate update failed: {}", e);
+                    }
+                }
+            }
+            let mut s = stats.lock().unwrap();
+            s.fps = frames_since_stats as f32 / elapsed;
+            s.bitrate_mbps = (bytes_received as f32 * 8.0) / elapsed / 1_000_000.0;
+            s.target_bitrate_mbps = target_bitrate_kbps as f32 / 1000.0;
+            if let Some(rtt_ms) = rtt_ms {
+                s.rtt_ms = rtt_ms;
+            }
+            if let Some(loss_pct) = window_loss_pct {
+                s.packet_loss_pct = loss_pct;
+            }
+            s.reordered_packets = reorder_buffer.reordered_packets;
+            s.reorder_depth = reorder_buffer.depth();
+            s.keyframe_recoveries = keyframe_recoveries;
+            s.decoder_resets = decoder_resets;
+            frames_since_stats = 0;
+            bytes_received = 0;
+            last_stats = std::time::Instant::now();
+        }
+    }
+
+    input_task.abort();
+    buffered_task.abort();
+    peer.close().await;
+    if interrupted && !stop.load(Ordering::SeqCst) {
+        // The message text is matched upstream to trigger the
+        // reconnect path instead of a teardown.
+        return Err(anyhow!("Connection interrupted"));
+    }
+    log::info!("Streaming loop ended");
+    Ok(())
+}
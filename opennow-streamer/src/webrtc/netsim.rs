@@ -0,0 +1,265 @@
+//! Development-only network impairment for reproducing user-reported
+//! stutter: packet loss (optionally bursty), delay/jitter, and
+//! reordering applied between the RTP read loop and the depacketizer,
+//! and optionally to the input send path.
+//!
+//! Only compiled with the `netsim` cargo feature, so release builds
+//! can't enable it. Configured from `netsim.toml` in the app data dir
+//! (or the file named by `OPENNOW_NETSIM`); a missing file means no
+//! impairment.
+
+use std::time::Duration;
+
+use crate::app::cache::get_app_data_dir;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct NetsimConfig {
+    /// Probability (percent) that a packet starts a loss event.
+    pub loss_pct: f32,
+    /// Packets dropped per loss event; 1 = independent losses.
+    pub burst_len: u32,
+    /// Fixed added delay in milliseconds.
+    pub delay_ms: u32,
+    /// Additional uniform random delay in `0..=jitter_ms` milliseconds.
+    pub jitter_ms: u32,
+    /// Probability (percent) that a packet is held back and delivered
+    /// after its successor.
+    pub reorder_pct: f32,
+    /// Also impair encoded input packets on the send path.
+    pub impair_input: bool,
+}
+
+impl Default for NetsimConfig {
+    fn default() -> Self {
+        Self {
+            loss_pct: 0.0,
+            burst_len: 1,
+            delay_ms: 0,
+            jitter_ms: 0,
+            reorder_pct: 0.0,
+            impair_input: false,
+        }
+    }
+}
+
+impl NetsimConfig {
+    fn is_passthrough(&self) -> bool {
+        self.loss_pct <= 0.0
+            && self.delay_ms == 0
+            && self.jitter_ms == 0
+            && self.reorder_pct <= 0.0
+    }
+}
+
+/// Parse the `key = value` subset of TOML the config uses. Unknown keys
+/// are ignored so the file can carry comments/notes.
+fn parse_config(text: &str) -> NetsimConfig {
+    let mut config = NetsimConfig::default();
+    for line in text.lines() {
+        let line = line.split('#').next().unwrap_or("").trim();
+        let Some((key, value)) = line.split_once('=') else {
+            continue;
+        };
+        let (key, value) = (key.trim(), value.trim());
+        match key {
+            "loss_pct" => config.loss_pct = value.parse().unwrap_or(config.loss_pct),
+            "burst_len" => config.burst_len = value.parse().unwrap_or(config.burst_len),
+            "delay_ms" => config.delay_ms = value.parse().unwrap_or(config.delay_ms),
+            "jitter_ms" => config.jitter_ms = value.parse().unwrap_or(config.jitter_ms),
+            "reorder_pct" => config.reorder_pct = value.parse().unwrap_or(config.reorder_pct),
+            "impair_input" => config.impair_input = value == "true",
+            _ => {}
+        }
+    }
+    config.burst_len = config.burst_len.max(1);
+    config
+}
+
+pub fn load_config() -> NetsimConfig {
+    let path = std::env::var("OPENNOW_NETSIM")
+        .map(std::path::PathBuf::from)
+        .unwrap_or_else(|_| get_app_data_dir().join("netsim.toml"));
+    match std::fs::read_to_string(&path) {
+        Ok(text) => {
+            let config = parse_config(&text);
+            log::warn!("NETSIM ACTIVE from {}: {:?}", path.display(), config);
+            config
+        }
+        Err(_) => NetsimConfig::default(),
+    }
+}
+
+/// Per-stream impairment state. Each task (video, audio, input) gets
+/// its own instance so burst/reorder state doesn't interleave.
+pub struct Netsim {
+    config: NetsimConfig,
+    burst_remaining: u32,
+    held: Option<Vec<u8>>,
+}
+
+impl Netsim {
+    pub fn new(config: NetsimConfig) -> Self {
+        Self {
+            config,
+            burst_remaining: 0,
+            held: None,
+        }
+    }
+
+    pub fn from_config_file() -> Self {
+        Self::new(load_config())
+    }
+
+    pub fn impairs_input(&self) -> bool {
+        self.config.impair_input
+    }
+
+    /// Loss/reorder decision for one packet: the packets to deliver now,
+    /// in order (empty = dropped or held for reordering).
+    fn step(&mut self, packet: Vec<u8>) -> Vec<Vec<u8>> {
+        if self.burst_remaining > 0 {
+            self.burst_remaining -= 1;
+            return Vec::new();
+        }
+        if self.config.loss_pct > 0.0 && rand::random::<f32>() * 100.0 < self.config.loss_pct {
+            self.burst_remaining = self.config.burst_len - 1;
+            return Vec::new();
+        }
+        if let Some(held) = self.held.take() {
+            return vec![packet, held];
+        }
+        if self.config.reorder_pct > 0.0 && rand::random::<f32>() * 100.0 < self.config.reorder_pct
+        {
+            self.held = Some(packet);
+            return Vec::new();
+        }
+        vec![packet]
+    }
+
+    /// One sample of the configured delay distribution.
+    fn sample_delay(&self) -> Duration {
+        let jitter = if self.config.jitter_ms > 0 {
+            rand::random::<u32>() % (self.config.jitter_ms + 1)
+        } else {
+            0
+        };
+        Duration::from_millis((self.config.delay_ms + jitter) as u64)
+    }
+
+    /// Apply the configured impairments to one packet, sleeping out the
+    /// sampled delay. Call from the task that owns the packet flow.
+    pub async fn impair(&mut self, packet: Vec<u8>) -> Vec<Vec<u8>> {
+        if self.config.is_passthrough() {
+            return vec![packet];
+        }
+        let delay = self.sample_delay();
+        if !delay.is_zero() {
+            tokio::time::sleep(delay).await;
+        }
+        self.step(packet)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RUN: usize = 100_000;
+
+    fn seq_packet(seq: usize) -> Vec<u8> {
+        (seq as u32).to_be_bytes().to_vec()
+    }
+
+    #[test]
+    fn independent_loss_rate_matches_config() {
+        let mut sim = Netsim::new(NetsimConfig {
+            loss_pct: 10.0,
+            ..NetsimConfig::default()
+        });
+        let delivered: usize = (0..RUN).map(|s| sim.step(seq_packet(s)).len()).sum();
+        let rate = delivered as f32 / RUN as f32;
+        assert!(
+            (rate - 0.9).abs() < 0.01,
+            "delivery rate {} outside 90% ± 1%",
+            rate
+        );
+    }
+
+    #[test]
+    fn burst_loss_drops_runs_of_packets() {
+        let mut sim = Netsim::new(NetsimConfig {
+            loss_pct: 2.0,
+            burst_len: 5,
+            ..NetsimConfig::default()
+        });
+        // 2% of packets start a 5-packet burst, so roughly 10% are lost
+        // overall (slightly less because bursts can't overlap).
+        let delivered: usize = (0..RUN).map(|s| sim.step(seq_packet(s)).len()).sum();
+        let rate = delivered as f32 / RUN as f32;
+        assert!(
+            (0.88..=0.93).contains(&rate),
+            "delivery rate {} outside burst-loss expectation",
+            rate
+        );
+    }
+
+    #[test]
+    fn reorder_rate_matches_config_and_loses_nothing() {
+        let mut sim = Netsim::new(NetsimConfig {
+            reorder_pct: 5.0,
+            ..NetsimConfig::default()
+        });
+        let mut output = Vec::new();
+        for seq in 0..RUN {
+            output.extend(sim.step(seq_packet(seq)));
+        }
+        // At most the final held packet is still in flight.
+        assert!(output.len() >= RUN - 1);
+        let inversions = output
+            .windows(2)
+            .filter(|pair| pair[0] > pair[1])
+            .count();
+        let rate = inversions as f32 / RUN as f32;
+        assert!(
+            (rate - 0.05).abs() < 0.01,
+            "reorder rate {} outside 5% ± 1%",
+            rate
+        );
+    }
+
+    #[test]
+    fn delay_samples_stay_within_configured_bounds() {
+        let sim = Netsim::new(NetsimConfig {
+            delay_ms: 20,
+            jitter_ms: 30,
+            ..NetsimConfig::default()
+        });
+        let mut sum = Duration::ZERO;
+        for _ in 0..RUN {
+            let delay = sim.sample_delay();
+            assert!((20..=50).contains(&(delay.as_millis() as u32)));
+            sum += delay;
+        }
+        // Uniform jitter should average to the middle of the range.
+        let mean_ms = sum.as_millis() as f32 / RUN as f32;
+        assert!((34.0..=36.0).contains(&mean_ms), "mean delay {} ms", mean_ms);
+    }
+
+    #[test]
+    fn config_parses_and_defaults_missing_keys() {
+        let config = parse_config(
+            "# wifi stutter repro\nloss_pct = 2.5\nburst_len = 4\njitter_ms = 40\nimpair_input = true\n",
+        );
+        assert_eq!(
+            config,
+            NetsimConfig {
+                loss_pct: 2.5,
+                burst_len: 4,
+                delay_ms: 0,
+                jitter_ms: 40,
+                reorder_pct: 0.0,
+                impair_input: true,
+            }
+        );
+    }
+}
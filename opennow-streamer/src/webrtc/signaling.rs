@@ -0,0 +1,83 @@
+//! WebSocket signaling with the GFN streaming server: receives the SDP
+//! offer, sends our answer, and relays ICE candidates.
+
+use anyhow::{anyhow, Context, Result};
+use futures::{SinkExt, StreamExt};
+use tokio_tungstenite::tungstenite::Message;
+use tokio_tungstenite::{connect_async, MaybeTlsStream, WebSocketStream};
+
+type WsStream = WebSocketStream<MaybeTlsStream<tokio::net::TcpStream>>;
+
+#[derive(Debug)]
+pub enum SignalingEvent {
+    Offer(String),
+    IceCandidate(String),
+    Disconnected,
+}
+
+pub struct SignalingClient {
+    stream: WsStream,
+}
+
+impl SignalingClient {
+    pub async fn connect(url: &str) -> Result<Self> {
+        log::info!("Connecting signaling: {}", url);
+        let (stream, _response) = connect_async(url)
+            .await
+            .context("WebSocket connect failed")?;
+        Ok(Self { stream })
+    }
+
+    /// Wait for the next signaling message.
+    pub async fn next_event(&mut self) -> Result<SignalingEvent> {
+        loop {
+            let message = match self.stream.next().await {
+                Some(Ok(message)) => message,
+                Some(Err(e)) => return Err(anyhow!("Signaling error: {}", e)),
+                None => return Ok(SignalingEvent::Disconnected),
+            };
+            let text = match message {
+                Message::Text(text) => text,
+                Message::Close(_) => return Ok(SignalingEvent::Disconnected),
+                _ => continue,
+            };
+            let value: serde_json::Value =
+                serde_json::from_str(&text).context("Invalid signaling JSON")?;
+            match value["type"].as_str() {
+                Some("offer") => {
+                    if let Some(sdp) = value["sdp"].as_str() {
+                        return Ok(SignalingEvent::Offer(sdp.to_string()));
+                    }
+                }
+                Some("candidate") => {
+                    if let Some(candidate) = value["candidate"].as_str() {
+                        return Ok(SignalingEvent::IceCandidate(candidate.to_string()));
+                    }
+                }
+                other => log::debug!("Ignoring signaling message type {:?}", other),
+            }
+        }
+    }
+
+    /// Block until the server's offer arrives.
+    pub async fn wait_for_offer(&mut self) -> Result<String> {
+        loop {
+            match self.next_event().await? {
+                SignalingEvent::Offer(sdp) => return Ok(sdp),
+                SignalingEvent::Disconnected => {
+                    return Err(anyhow!("Signaling closed before offer"))
+                }
+                SignalingEvent::IceCandidate(_) => continue,
+            }
+        }
+    }
+
+    pub async fn send_answer(&mut self, sdp: &str) -> Result<()> {
+        let message = serde_json::json!({ "type": "answer", "sdp": sdp });
+        self.stream
+            .send(Message::Text(message.to_string()))
+            .await
+            .context("Failed to send answer")?;
+        Ok(())
+    }
+}